@@ -0,0 +1,30 @@
+//! Criterion benchmarks of the wake-up latency of all compiled-in backends.
+//!
+//! Each iteration interrupts a sleeping thread and measures the delay between
+//! the [`Waker::wake`] call and the actual awakening of the sleeper. Run with
+//! `cargo bench`, optionally with the features of further backends.
+//!
+//! [`Waker::wake`]: benchmark_interruptible_sleep::Waker::wake
+
+use benchmark_interruptible_sleep::{backends, runner};
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::time::Duration;
+
+/// Time between the begin of the sleep and the wake-up.
+const WAKE_OFFSET: Duration = Duration::from_micros(500);
+
+fn wake_latency(c: &mut Criterion) {
+    let mut group = c.benchmark_group("wake_latency");
+    for backend in backends::available() {
+        group.bench_function(backend.name, |b| {
+            b.iter_custom(|iters| {
+                let (sleeper, waker) = backend.new_pair();
+                runner::total_wake_delay(iters, sleeper, waker, WAKE_OFFSET)
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, wake_latency);
+criterion_main!(benches);
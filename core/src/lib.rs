@@ -0,0 +1,321 @@
+//! The core of `benchmark-interruptible-sleep` without the std library: the
+//! [`Sleeper`] and [`Waker`] traits and the measurement of a single sleep, see
+//! [`measure`].
+//!
+//! The traits are generic over the [`Timestamp`], so that embedded and RTOS
+//! projects can implement them with their own clocks and primitives. With the
+//! `std` feature, [`std::time::Instant`] is a [`Timestamp`].
+
+#![no_std]
+#![deny(
+    clippy::all,
+    clippy::cargo,
+    clippy::nursery,
+    clippy::must_use_candidate,
+    clippy::perf
+    // clippy::restriction,
+    // clippy::pedantic
+)]
+#![deny(missing_debug_implementations)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+use core::time::Duration;
+
+/// A point in time of a monotonic clock.
+pub trait Timestamp: Copy + Ord {
+    /// Returns the current point in time.
+    fn now() -> Self;
+
+    /// Returns the time that passed from `earlier` to `self`, or zero if
+    /// `earlier` is later.
+    fn saturating_duration_since(self, earlier: Self) -> Duration;
+}
+
+#[cfg(feature = "std")]
+impl Timestamp for std::time::Instant {
+    fn now() -> Self {
+        Self::now()
+    }
+
+    fn saturating_duration_since(self, earlier: Self) -> Duration {
+        Self::saturating_duration_since(&self, earlier)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum WakeupReason<T> {
+    Timeout,
+    Interrupted { wake_call_instant: T },
+}
+
+/// A sleeper that puts the executing thread context into an interruptible
+/// sleep.
+pub trait Sleeper<T: Timestamp> {
+    /// Puts the thread into sleep that is interruptible.
+    fn sleep_interruptible(&self, sleep_duration: Duration) -> WakeupReason<T>;
+
+    /// Like [`Self::sleep_interruptible`] but with an absolute deadline.
+    ///
+    /// The default implementation converts the deadline into a relative
+    /// timeout. Backends whose OS primitive supports absolute timeouts
+    /// override this: the timeout then doesn't drift by the time between the
+    /// conversion and entering the kernel.
+    fn sleep_until(&self, deadline: T) -> WakeupReason<T> {
+        self.sleep_interruptible(deadline.saturating_duration_since(T::now()))
+    }
+
+    /// Returns when the last interrupted sleep observed the wake-up call,
+    /// i.e., when the primitive returned, before the return path of the
+    /// backend, e.g., acknowledging the wake-up or releasing a lock.
+    ///
+    /// Returns `None` if the backend doesn't record it, which is the default.
+    fn wake_observed(&self) -> Option<T> {
+        None
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Timestamp, S: Sleeper<T> + ?Sized> Sleeper<T> for alloc::boxed::Box<S> {
+    fn sleep_interruptible(&self, sleep_duration: Duration) -> WakeupReason<T> {
+        (**self).sleep_interruptible(sleep_duration)
+    }
+
+    fn sleep_until(&self, deadline: T) -> WakeupReason<T> {
+        (**self).sleep_until(deadline)
+    }
+
+    fn wake_observed(&self) -> Option<T> {
+        (**self).wake_observed()
+    }
+}
+
+/// How a [`Waker`] and its [`Sleeper`] wait for each other in the ACK
+/// handshake of [`Waker::wake`], see [`Waker::set_handshake`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Handshake {
+    /// Whether the waiting thread yields the CPU instead of spinning, e.g.,
+    /// because both threads are pinned to the same CPU, where the spinning
+    /// one keeps the other one from arriving.
+    pub yielding: bool,
+    /// How long the waiting thread waits for the other one before it gives
+    /// up, e.g., because the [`Sleeper`] ignored the wake-up. `None` waits
+    /// forever.
+    pub timeout: Option<Duration>,
+}
+
+/// A waker for a [`Sleeper`].
+pub trait Waker {
+    /// Wakes the corresponding [`Sleeper`].
+    ///
+    /// The implementation is supposed to send the current [`Timestamp`] to
+    /// the [`Sleeper`] so that it can report it in
+    /// [`WakeupReason::Interrupted`].
+    ///
+    /// This function waits for the [`Sleeper`] to acknowledge the wake-up
+    /// call. The main motivation of this property is to facilitate
+    /// unit-testing and prevent race conditions. This synchronization should
+    /// add as little delay as possible, e.g., by busy-waiting. It gives up
+    /// after [`Handshake::timeout`].
+    fn wake(&self);
+
+    /// Wakes the corresponding [`Sleeper`] without a reason, i.e., injects a
+    /// spurious wake-up. The [`Sleeper`] must go back to sleep until its
+    /// timeout or a real wake-up.
+    ///
+    /// Doesn't wait for the [`Sleeper`]. Returns `false` if the backend can't
+    /// inject spurious wake-ups, which is the default.
+    fn wake_spurious(&self) -> bool {
+        false
+    }
+
+    /// Like [`Self::wake`] but doesn't wait for the [`Sleeper`] to
+    /// acknowledge the wake-up, so it can be called several times for a
+    /// single sleep. The [`Sleeper`] doesn't acknowledge such wake-ups.
+    ///
+    /// Whether several wake-ups are coalesced into one depends on the backend.
+    /// Returns `false` if the backend doesn't support detached wake-ups,
+    /// which is the default.
+    fn wake_detached(&self) -> bool {
+        false
+    }
+
+    /// Configures the ACK handshake of [`Self::wake`] for both the waker and
+    /// its [`Sleeper`]. Backends without a handshake ignore it, which is the
+    /// default.
+    fn set_handshake(&self, _handshake: Handshake) {}
+}
+
+#[cfg(feature = "alloc")]
+impl<W: Waker + ?Sized> Waker for alloc::boxed::Box<W> {
+    fn wake(&self) {
+        (**self).wake();
+    }
+
+    fn wake_spurious(&self) -> bool {
+        (**self).wake_spurious()
+    }
+
+    fn wake_detached(&self) -> bool {
+        (**self).wake_detached()
+    }
+
+    fn set_handshake(&self, handshake: Handshake) {
+        (**self).set_handshake(handshake);
+    }
+}
+
+/// The durations of a single sleep, see [`measure`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timing {
+    /// The ideal duration of the sleep: until the wake-up call or the
+    /// timeout.
+    pub expected_duration: Duration,
+    /// The duration of the sleep, including all overhead.
+    pub actual_duration: Duration,
+    /// The difference of both: how late the sleeper ran again. Zero if the
+    /// sleep returned early, see [`Self::signed_delay`].
+    pub delay: Duration,
+}
+
+impl Timing {
+    /// Returns the delay including its sign: negative if the sleep returned
+    /// before its expected duration.
+    #[must_use]
+    pub fn signed_delay(&self) -> SignedDelay {
+        SignedDelay::between(self.expected_duration, self.actual_duration)
+    }
+}
+
+/// The delay of a sleep, negative if the sleep returned before its expected
+/// duration, e.g., because a timer fired early or because of a clock
+/// anomaly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SignedDelay {
+    /// The sleep returned this much before its expected duration.
+    Early(Duration),
+    /// The sleep returned this much after its expected duration, or on time.
+    Late(Duration),
+}
+
+impl SignedDelay {
+    /// Returns the delay of a sleep of `actual` instead of `expected`.
+    #[must_use]
+    pub fn between(expected: Duration, actual: Duration) -> Self {
+        actual
+            .checked_sub(expected)
+            .map_or_else(|| Self::Early(expected - actual), Self::Late)
+    }
+
+    #[must_use]
+    pub const fn is_negative(self) -> bool {
+        matches!(self, Self::Early(_))
+    }
+
+    /// Returns the delay, or zero if it is negative.
+    #[must_use]
+    pub const fn saturated(self) -> Duration {
+        match self {
+            Self::Early(_) => Duration::ZERO,
+            Self::Late(delay) => delay,
+        }
+    }
+
+    #[must_use]
+    pub const fn as_nanos(self) -> i128 {
+        match self {
+            Self::Early(early) => -(early.as_nanos() as i128),
+            Self::Late(delay) => delay.as_nanos() as i128,
+        }
+    }
+}
+
+/// Measures a sleep with the given timeout from `begin` to `end` that
+/// returned `reason`.
+///
+/// Returns `None` if the wake-up call was issued before the sleep began, as
+/// there is no meaningful expected duration.
+#[must_use]
+pub fn measure<T: Timestamp>(
+    reason: &WakeupReason<T>,
+    begin: T,
+    end: T,
+    timeout: Duration,
+) -> Option<Timing> {
+    let expected_duration = match *reason {
+        WakeupReason::Interrupted { wake_call_instant } if wake_call_instant < begin => {
+            return None;
+        }
+        WakeupReason::Interrupted { wake_call_instant } => {
+            wake_call_instant.saturating_duration_since(begin)
+        }
+        WakeupReason::Timeout => timeout,
+    };
+    let actual_duration = end.saturating_duration_since(begin);
+    Some(Timing {
+        expected_duration,
+        actual_duration,
+        delay: SignedDelay::between(expected_duration, actual_duration).saturated(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert2::check;
+
+    /// Ticks of a millisecond clock, as on a microcontroller.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    struct Ticks(u64);
+
+    impl Timestamp for Ticks {
+        fn now() -> Self {
+            Self(0)
+        }
+
+        fn saturating_duration_since(self, earlier: Self) -> Duration {
+            Duration::from_millis(self.0.saturating_sub(earlier.0))
+        }
+    }
+
+    #[test]
+    fn test_measure() {
+        let timeout = Duration::from_millis(10);
+        let ms = Duration::from_millis;
+
+        let timing = measure(&WakeupReason::Timeout, Ticks(100), Ticks(112), timeout);
+        check!(
+            timing
+                == Some(Timing {
+                    expected_duration: ms(10),
+                    actual_duration: ms(12),
+                    delay: ms(2),
+                })
+        );
+
+        let interrupted = WakeupReason::Interrupted {
+            wake_call_instant: Ticks(104),
+        };
+        let timing = measure(&interrupted, Ticks(100), Ticks(105), timeout).unwrap();
+        check!(timing.expected_duration == ms(4));
+        check!(timing.delay == ms(1));
+
+        // The wake-up call was issued before the sleep.
+        check!(measure(&interrupted, Ticks(106), Ticks(107), timeout).is_none());
+
+        // The timeout fired early.
+        let timing = measure(&WakeupReason::Timeout, Ticks(100), Ticks(108), timeout).unwrap();
+        check!(timing.delay == Duration::ZERO);
+        check!(timing.signed_delay() == SignedDelay::Early(ms(2)));
+        check!(timing.signed_delay().as_nanos() == -2_000_000);
+        check!(
+            !measure(&WakeupReason::Timeout, Ticks(100), Ticks(110), timeout)
+                .unwrap()
+                .signed_delay()
+                .is_negative()
+        );
+    }
+}
@@ -0,0 +1,350 @@
+//! Statistics of delays in fixed-size memory. See [`DelayAccumulator`].
+//!
+//! Runs with tens of millions of rounds don't fit into memory as
+//! [`Measurement`]s. Recorded runs and the analysis of recordings accumulate
+//! their delays one at a time instead.
+//!
+//! [`Measurement`]: crate::Measurement
+
+use crate::stats::{
+    Estimate, HistogramBucket, Outliers, Summary, bucket_high, bucket_index, bucket_low,
+};
+use std::time::Duration;
+
+/// Each power of two of the delay in ns is split into `2^SUB_BUCKET_BITS`
+/// buckets, so that a percentile is off by less than 1%.
+const SUB_BUCKET_BITS: u32 = 7;
+
+const SUB_BUCKETS: u64 = 1 << SUB_BUCKET_BITS;
+
+/// Number of buckets, enough for any delay.
+const BUCKETS: usize = (u64::BITS - SUB_BUCKET_BITS + 1) as usize * SUB_BUCKETS as usize;
+
+/// Quantile of the standard normal distribution for the two-sided
+/// [`CONFIDENCE_LEVEL`](crate::stats::CONFIDENCE_LEVEL).
+const Z: f64 = 1.959_963_984_540_054;
+
+/// Delays accumulated in fixed-size memory, no matter how many were added.
+///
+/// The mean, the standard deviation, and the jitter are exact. Percentiles
+/// come from a log-linear histogram and are off by less than 1%. The
+/// confidence intervals are analytic instead of bootstrapped, see
+/// [`Self::summary`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DelayAccumulator {
+    count: u64,
+    /// In ns.
+    sum: u128,
+    /// In ns².
+    sum_of_squares: u128,
+    /// Sum of the absolute differences of successive delays in ns, see
+    /// [`jitter`](crate::stats::jitter).
+    jitter_sum: u128,
+    last: Option<u64>,
+    min: u64,
+    max: u64,
+    buckets: Box<[u64]>,
+}
+
+impl Default for DelayAccumulator {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            sum: 0,
+            sum_of_squares: 0,
+            jitter_sum: 0,
+            last: None,
+            min: u64::MAX,
+            max: 0,
+            buckets: vec![0; BUCKETS].into_boxed_slice(),
+        }
+    }
+}
+
+impl DelayAccumulator {
+    /// Adds a delay, in the order of the measurements.
+    pub fn add(&mut self, delay: Duration) {
+        let nanos = u64::try_from(delay.as_nanos()).unwrap_or(u64::MAX);
+        self.count += 1;
+        self.sum += u128::from(nanos);
+        self.sum_of_squares = self
+            .sum_of_squares
+            .saturating_add(u128::from(nanos) * u128::from(nanos));
+        if let Some(last) = self.last {
+            self.jitter_sum += u128::from(last.abs_diff(nanos));
+        }
+        self.last = Some(nanos);
+        self.min = self.min.min(nanos);
+        self.max = self.max.max(nanos);
+        self.buckets[index(nanos)] += 1;
+    }
+
+    #[must_use]
+    pub const fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the mean delay or zero if there is none.
+    #[must_use]
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            Duration::ZERO
+        } else {
+            nanos(self.sum / u128::from(self.count))
+        }
+    }
+
+    /// Returns the sample standard deviation, see
+    /// [`stddev`](crate::stats::stddev).
+    #[must_use]
+    pub fn stddev(&self) -> Duration {
+        if self.count < 2 {
+            return Duration::ZERO;
+        }
+        let n = u128::from(self.count);
+        let spread = (n.saturating_mul(self.sum_of_squares))
+            .saturating_sub(self.sum.saturating_mul(self.sum));
+        Duration::from_secs_f64((spread as f64 / (n * (n - 1)) as f64).sqrt() / 1e9)
+    }
+
+    /// Returns the jitter, see [`jitter`](crate::stats::jitter).
+    #[must_use]
+    pub fn jitter(&self) -> Duration {
+        if self.count < 2 {
+            Duration::ZERO
+        } else {
+            nanos(self.jitter_sum / u128::from(self.count - 1))
+        }
+    }
+
+    /// Returns the `p`-th percentile (`0.0..=100.0`) with the nearest-rank
+    /// method, like [`percentile`](crate::stats::percentile), or zero if
+    /// there is no delay.
+    #[must_use]
+    pub fn percentile(&self, p: f64) -> Duration {
+        let rank = (p / 100.0 * self.count as f64).ceil() as u64;
+        self.at_rank(rank)
+    }
+
+    /// Returns the delay of the given rank (1-based) in sorted order, with
+    /// the resolution of the histogram. The smallest and the largest delay
+    /// are exact.
+    fn at_rank(&self, rank: u64) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+        let rank = rank.clamp(1, self.count);
+        if rank == 1 {
+            return nanos(u128::from(self.min));
+        }
+        if rank == self.count {
+            return nanos(u128::from(self.max));
+        }
+        let mut seen = 0;
+        for (index, &count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return self.representative(index);
+            }
+        }
+        nanos(u128::from(self.max))
+    }
+
+    /// Returns the center of the bucket, within the observed delays.
+    fn representative(&self, index: usize) -> Duration {
+        let (low, high) = bounds(index);
+        nanos(u128::from(
+            (low + (high - low) / 2).clamp(self.min, self.max),
+        ))
+    }
+
+    /// Estimates the `p`-th percentile and its confidence interval from the
+    /// ranks of the binomial distribution of the sample.
+    fn percentile_estimate(&self, p: f64) -> Estimate {
+        let n = self.count as f64;
+        let q = p / 100.0;
+        let half = Z * (n * q * (1.0 - q)).sqrt();
+        Estimate {
+            value: self.percentile(p),
+            ci_low: self.at_rank((n * q - half).floor() as u64),
+            ci_high: self.at_rank((n * q + half).ceil() as u64),
+        }
+    }
+
+    /// Summarizes the delays like [`summarize`], or `None` if there are none.
+    ///
+    /// The confidence interval of the mean comes from the normal
+    /// approximation, those of the percentiles from the ranks of the
+    /// binomial distribution, since a bootstrap needs all delays.
+    ///
+    /// [`summarize`]: crate::stats::summarize
+    #[must_use]
+    pub fn summary(&self) -> Option<Summary> {
+        if self.count == 0 {
+            return None;
+        }
+        let mean = self.mean();
+        let half = self.stddev().mul_f64(Z / (self.count as f64).sqrt());
+        Some(Summary {
+            count: usize::try_from(self.count).unwrap_or(usize::MAX),
+            mean: Estimate {
+                value: mean,
+                ci_low: mean.saturating_sub(half),
+                ci_high: mean + half,
+            },
+            p50: self.percentile_estimate(50.0),
+            p99: self.percentile_estimate(99.0),
+            stddev: self.stddev(),
+            jitter: self.jitter(),
+        })
+    }
+
+    /// Counts the delays above Tukey's upper fence, see
+    /// [`outliers`](crate::stats::outliers). Delays in the bucket of the
+    /// fence count by the center of the bucket.
+    #[must_use]
+    pub fn outliers(&self) -> Outliers {
+        let q1 = self.percentile(25.0);
+        let q3 = self.percentile(75.0);
+        let upper_fence = q3 + (q3 - q1) * 3 / 2;
+        let count = self
+            .buckets
+            .iter()
+            .enumerate()
+            .filter(|&(index, &count)| count > 0 && self.representative(index) > upper_fence)
+            .map(|(_, &count)| count)
+            .sum::<u64>();
+        Outliers {
+            upper_fence,
+            count: usize::try_from(count).unwrap_or(usize::MAX),
+        }
+    }
+
+    /// Returns the buckets of [`histogram`](crate::stats::histogram). Delays
+    /// count by the center of their bucket of the accumulator.
+    #[must_use]
+    pub fn histogram(&self) -> Vec<HistogramBucket> {
+        let mut counts = Vec::<usize>::new();
+        for (index, &count) in self.buckets.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let bucket = bucket_index(self.representative(index));
+            if counts.len() <= bucket {
+                counts.resize(bucket + 1, 0);
+            }
+            counts[bucket] += usize::try_from(count).unwrap_or(usize::MAX);
+        }
+        let first = counts.iter().position(|&count| count > 0).unwrap_or(0);
+        (first..counts.len())
+            .map(|index| HistogramBucket {
+                low: bucket_low(index),
+                high: bucket_high(index),
+                count: counts[index],
+            })
+            .collect()
+    }
+}
+
+fn nanos(nanos: u128) -> Duration {
+    Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX))
+}
+
+/// Returns the bucket of a delay in ns. Delays below `2 * SUB_BUCKETS` have
+/// a bucket of their own, above, the buckets are log-linear.
+const fn index(nanos: u64) -> usize {
+    if nanos < 2 * SUB_BUCKETS {
+        nanos as usize
+    } else {
+        let shift = nanos.ilog2() - SUB_BUCKET_BITS;
+        (shift as u64 * SUB_BUCKETS + (nanos >> shift)) as usize
+    }
+}
+
+/// Returns the smallest and the largest delay in ns of the bucket.
+const fn bounds(index: usize) -> (u64, u64) {
+    let index = index as u64;
+    if index < 2 * SUB_BUCKETS {
+        (index, index)
+    } else {
+        let shift = index / SUB_BUCKETS - 1;
+        let low = (index - shift * SUB_BUCKETS) << shift;
+        (low, low + ((1 << shift) - 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::{histogram, jitter, outliers, percentile, stddev};
+    use assert2::check;
+
+    #[test]
+    fn test_buckets() {
+        for nanos in [0, 1, 255, 256, 257, 1000, 123_456_789, u64::MAX] {
+            let (low, high) = bounds(index(nanos));
+            check!(low <= nanos);
+            check!(nanos <= high);
+        }
+        check!(index(u64::MAX) == BUCKETS - 1);
+        // Adjacent buckets don't overlap or leave gaps.
+        for index in 1..BUCKETS {
+            check!(bounds(index - 1).1 + 1 == bounds(index).0);
+        }
+    }
+
+    #[test]
+    fn test_matches_exact_statistics() {
+        let mut rng = fastrand::Rng::with_seed(7);
+        let delays = (0..10_000)
+            .map(|_| Duration::from_nanos(rng.u64(1_000..5_000_000)))
+            .collect::<Vec<_>>();
+        let mut accumulator = DelayAccumulator::default();
+        for &delay in &delays {
+            accumulator.add(delay);
+        }
+        let mut sorted = delays.clone();
+        sorted.sort_unstable();
+
+        check!(accumulator.count() == 10_000);
+        check!(accumulator.mean() == delays.iter().sum::<Duration>() / 10_000);
+        check!(accumulator.jitter() == jitter(&delays));
+        check!(accumulator.stddev().abs_diff(stddev(&delays)) < Duration::from_nanos(10));
+        for p in [0.0, 25.0, 50.0, 99.0, 99.9, 100.0] {
+            let exact = percentile(&sorted, p);
+            check!(
+                accumulator.percentile(p).abs_diff(exact) <= exact / 100,
+                "p{p}"
+            );
+        }
+        check!(accumulator.percentile(100.0) == sorted[9_999]);
+        let approximate = accumulator.outliers();
+        let exact = outliers(&sorted);
+        check!(approximate.upper_fence.abs_diff(exact.upper_fence) <= exact.upper_fence / 50);
+        let total = accumulator
+            .histogram()
+            .iter()
+            .map(|b| b.count)
+            .sum::<usize>();
+        check!(total == 10_000);
+        check!(accumulator.histogram().len() == histogram(&delays).len());
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut accumulator = DelayAccumulator::default();
+        check!(accumulator.summary().is_none());
+        for us in [10, 20, 30, 40] {
+            accumulator.add(Duration::from_micros(us));
+        }
+        let summary = accumulator.summary().unwrap();
+        check!(summary.count == 4);
+        check!(summary.mean.value == Duration::from_micros(25));
+        check!(summary.mean.ci_low < summary.mean.value);
+        check!(summary.mean.value < summary.mean.ci_high);
+        check!(summary.p50.ci_low <= summary.p50.value);
+        check!(summary.p50.value <= summary.p50.ci_high);
+        check!(summary.p99.ci_high == Duration::from_micros(40));
+        check!(summary.jitter == Duration::from_micros(10));
+    }
+}
@@ -0,0 +1,1041 @@
+//! Detailed offline analysis of raw measurements. See [`render`].
+//!
+//! Collecting measurements is expensive, analyzing them is cheap: this module
+//! works on [`RawRun`]s, e.g., read back with [`report::read_raw`]. Each run
+//! is accumulated one measurement at a time in fixed-size memory, see
+//! [`RunAnalysis`], so recordings that don't fit into memory can be analyzed
+//! as well. Percentiles are therefore off by less than 1%, see
+//! [`DelayAccumulator`].
+//!
+//! [`report::read_raw`]: crate::report::read_raw
+
+use crate::accumulator::DelayAccumulator;
+use crate::backends::BASELINE_NAME;
+use crate::recording::{FromRecords, RunHeader};
+use crate::report::{RawMeasurement, RawRun};
+use crate::runner::MeasurementSink;
+use crate::stats::CONFIDENCE_LEVEL;
+use crate::threads::ThreadIds;
+use crate::units::format_duration;
+use crate::{Measurement, Measurements};
+use std::fmt::{self, Display, Formatter, Write};
+use std::ops::ControlFlow;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Width of the longest bar of the histogram.
+const BAR_WIDTH: usize = 40;
+
+/// Percentiles of the delays in the analysis.
+const PERCENTILES: [(f64, &str); 5] = [
+    (50.0, "p50"),
+    (90.0, "p90"),
+    (99.0, "p99"),
+    (99.9, "p99.9"),
+    (100.0, "max"),
+];
+
+/// Why a sleeper woke up. Like [`WakeupReason`] but without any data.
+///
+/// [`WakeupReason`]: crate::WakeupReason
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    Interrupted,
+    Timeouted,
+}
+
+impl Reason {
+    pub const ALL: [Self; 2] = [Self::Interrupted, Self::Timeouted];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Interrupted => "interrupted",
+            Self::Timeouted => "timeouted",
+        }
+    }
+}
+
+impl Display for Reason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Reason {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|reason| reason.name() == s)
+            .ok_or_else(|| format!("unknown reason `{s}`, expected interrupted or timeouted"))
+    }
+}
+
+/// Selects the measurements to analyze. `None` matches everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filter {
+    pub backend: Option<String>,
+    pub timeslice: Option<Duration>,
+    pub reason: Option<Reason>,
+}
+
+impl Filter {
+    /// Returns whether the run matches the backend and the timeslice.
+    #[must_use]
+    pub fn matches_run(&self, run: &RunHeader) -> bool {
+        self.backend.as_ref().is_none_or(|b| *b == run.backend)
+            && self.timeslice.is_none_or(|t| t == run.timeslice)
+    }
+
+    /// Returns whether the reason matches.
+    #[must_use]
+    pub fn matches_reason(&self, reason: Reason) -> bool {
+        self.reason.is_none_or(|r| r == reason)
+    }
+}
+
+/// Wake-ups that involved a migration of the sleeper thread to another CPU,
+/// compared with wake-ups on the same CPU.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Migrations {
+    /// Number of measurements with a known CPU.
+    pub known: usize,
+    pub migrated: usize,
+    migrated_sum: Duration,
+    local_sum: Duration,
+}
+
+impl Migrations {
+    /// Returns `None` if the CPU of no measurement is known.
+    pub fn of<'a>(measurements: impl IntoIterator<Item = &'a RawMeasurement>) -> Option<Self> {
+        let mut migrations = Self::default();
+        for m in measurements {
+            migrations.add(m);
+        }
+        (migrations.known > 0).then_some(migrations)
+    }
+
+    /// Adds a measurement. Measurements without known CPUs are ignored.
+    pub fn add(&mut self, m: &RawMeasurement) {
+        match m.migrated() {
+            Some(true) => {
+                self.migrated += 1;
+                self.migrated_sum += m.delay;
+            }
+            Some(false) => self.local_sum += m.delay,
+            None => return,
+        }
+        self.known += 1;
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        self.known += other.known;
+        self.migrated += other.migrated;
+        self.migrated_sum += other.migrated_sum;
+        self.local_sum += other.local_sum;
+    }
+
+    /// Mean delay of the migrated wake-ups, `None` if there are none.
+    #[must_use]
+    pub fn migrated_mean(&self) -> Option<Duration> {
+        (self.migrated > 0).then(|| mean_of(self.migrated_sum, self.migrated))
+    }
+
+    /// Mean delay of the wake-ups on the same CPU, `None` if there are none.
+    #[must_use]
+    pub fn local_mean(&self) -> Option<Duration> {
+        let local = self.known - self.migrated;
+        (local > 0).then(|| mean_of(self.local_sum, local))
+    }
+
+    /// Returns the fraction of the migrated wake-ups in percent.
+    #[must_use]
+    pub fn percent(&self) -> f64 {
+        self.migrated as f64 / self.known as f64 * 100.0
+    }
+}
+
+impl Display for Migrations {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.1}% ({}/{})",
+            self.percent(),
+            self.migrated,
+            self.known
+        )?;
+        if let (Some(migrated), Some(local)) = (self.migrated_mean(), self.local_mean()) {
+            write!(
+                f,
+                ", mean delay {} vs {} on the same CPU",
+                format_duration(migrated),
+                format_duration(local)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The mean delay split into the time until the sleeper thread was made
+/// runnable and the time it waited for a CPU afterward.
+///
+/// The former depends on the backend, the latter on the scheduler. See
+/// [`WakeupContext::delay_breakdown`].
+///
+/// [`WakeupContext::delay_breakdown`]: crate::WakeupContext::delay_breakdown
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Breakdown {
+    /// Number of measurements with a known run delay.
+    pub known: usize,
+    until_runnable_sum: Duration,
+    queueing_sum: Duration,
+}
+
+impl Breakdown {
+    /// Returns `None` if the run delay of no measurement is known.
+    pub fn of<'a>(measurements: impl IntoIterator<Item = &'a RawMeasurement>) -> Option<Self> {
+        let mut breakdown = Self::default();
+        for m in measurements {
+            breakdown.add(m);
+        }
+        (breakdown.known > 0).then_some(breakdown)
+    }
+
+    /// Adds a measurement. Measurements without a run delay are ignored.
+    pub fn add(&mut self, m: &RawMeasurement) {
+        if let Some((runnable, queued)) = m.delay_breakdown() {
+            self.known += 1;
+            self.until_runnable_sum += runnable;
+            self.queueing_sum += queued;
+        }
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        self.known += other.known;
+        self.until_runnable_sum += other.until_runnable_sum;
+        self.queueing_sum += other.queueing_sum;
+    }
+
+    /// Mean time until the sleeper thread was runnable.
+    #[must_use]
+    pub const fn until_runnable(&self) -> Duration {
+        mean_of(self.until_runnable_sum, self.known)
+    }
+
+    /// Mean time the runnable sleeper thread waited for a CPU.
+    #[must_use]
+    pub const fn queueing(&self) -> Duration {
+        mean_of(self.queueing_sum, self.known)
+    }
+}
+
+impl Display for Breakdown {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} until runnable + {} queueing (mean)",
+            format_duration(self.until_runnable()),
+            format_duration(self.queueing())
+        )
+    }
+}
+
+/// The mean delay of the interrupted wake-ups attributed to the notification
+/// latency, the scheduling latency, and the return path of the backend. See
+/// [`Attribution`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DelayAttribution {
+    /// Number of measurements whose backend recorded when it observed the
+    /// wake-up.
+    pub known: usize,
+    notification_sum: Duration,
+    /// `None` if the run delay of no measurement is known.
+    scheduling_sum: Option<Duration>,
+    return_path_sum: Duration,
+}
+
+impl DelayAttribution {
+    /// Returns `None` if the attribution of no measurement is known.
+    pub fn of<'a>(measurements: impl IntoIterator<Item = &'a RawMeasurement>) -> Option<Self> {
+        let mut attribution = Self::default();
+        for m in measurements {
+            attribution.add(m);
+        }
+        (attribution.known > 0).then_some(attribution)
+    }
+
+    /// Adds a measurement. Measurements without an attribution are ignored.
+    pub fn add(&mut self, m: &RawMeasurement) {
+        if let Some(attribution) = m.attribution() {
+            self.known += 1;
+            self.notification_sum += attribution.notification;
+            if let Some(scheduling) = attribution.scheduling {
+                *self.scheduling_sum.get_or_insert_default() += scheduling;
+            }
+            self.return_path_sum += attribution.return_path;
+        }
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        self.known += other.known;
+        self.notification_sum += other.notification_sum;
+        if let Some(scheduling) = other.scheduling_sum {
+            *self.scheduling_sum.get_or_insert_default() += scheduling;
+        }
+        self.return_path_sum += other.return_path_sum;
+    }
+
+    #[must_use]
+    pub const fn notification(&self) -> Duration {
+        mean_of(self.notification_sum, self.known)
+    }
+
+    /// `None` if the run delay of no measurement is known. Measurements
+    /// without count as zero.
+    #[must_use]
+    pub fn scheduling(&self) -> Option<Duration> {
+        self.scheduling_sum.map(|sum| mean_of(sum, self.known))
+    }
+
+    #[must_use]
+    pub const fn return_path(&self) -> Duration {
+        mean_of(self.return_path_sum, self.known)
+    }
+}
+
+impl Display for DelayAttribution {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} notification", format_duration(self.notification()))?;
+        match self.scheduling() {
+            Some(scheduling) => write!(f, " + {} scheduling", format_duration(scheduling))?,
+            None => write!(f, " (incl. scheduling)")?,
+        }
+        write!(
+            f,
+            " + {} return path (mean)",
+            format_duration(self.return_path())
+        )
+    }
+}
+
+/// Returns `sum / count`, or zero if `count` is zero.
+const fn mean_of(sum: Duration, count: usize) -> Duration {
+    if count == 0 {
+        Duration::ZERO
+    } else {
+        Duration::from_nanos((sum.as_nanos() / count as u128) as u64)
+    }
+}
+
+/// Where in the timeslice an interrupted wake-up was issued.
+///
+/// Some backends behave differently if the wake-up lands close to the
+/// timeout, which the statistics of all interrupted wake-ups together hide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WakeOffset {
+    /// In the first third of the timeslice.
+    Early,
+    Middle,
+    /// In the last fifth of the timeslice.
+    NearDeadline,
+}
+
+impl WakeOffset {
+    pub const ALL: [Self; 3] = [Self::Early, Self::Middle, Self::NearDeadline];
+
+    /// Wake-ups from this percentage of the timeslice on are not early.
+    const MIDDLE_PERCENT: u128 = 33;
+    /// Wake-ups from this percentage of the timeslice on are near the
+    /// deadline.
+    const NEAR_DEADLINE_PERCENT: u128 = 80;
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Early => "early",
+            Self::Middle => "middle",
+            Self::NearDeadline => "near-deadline",
+        }
+    }
+
+    /// The range of the bucket in percent of the timeslice.
+    #[must_use]
+    pub const fn range(self) -> &'static str {
+        match self {
+            Self::Early => "0-33%",
+            Self::Middle => "33-80%",
+            Self::NearDeadline => "80-100%",
+        }
+    }
+
+    /// Returns the bucket of a wake-up issued `offset` after the begin of a
+    /// sleep with the given timeslice.
+    #[must_use]
+    pub fn of(offset: Duration, timeslice: Duration) -> Self {
+        let percent = offset.as_nanos() * 100 / timeslice.as_nanos().max(1);
+        if percent < Self::MIDDLE_PERCENT {
+            Self::Early
+        } else if percent < Self::NEAR_DEADLINE_PERCENT {
+            Self::Middle
+        } else {
+            Self::NearDeadline
+        }
+    }
+}
+
+/// The delays of the interrupted wake-ups in one [`WakeOffset`] bucket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OffsetBucket {
+    pub offset: WakeOffset,
+    pub delays: DelayAccumulator,
+}
+
+impl OffsetBucket {
+    /// Returns the non-empty buckets of the interrupted wake-ups, in the
+    /// order of [`WakeOffset::ALL`]. The offset of a wake-up is its expected
+    /// duration.
+    pub fn of<'a>(
+        measurements: impl IntoIterator<Item = &'a RawMeasurement>,
+        timeslice: Duration,
+    ) -> Vec<Self> {
+        let mut buckets = Self::all();
+        for m in measurements {
+            Self::add(&mut buckets, m, timeslice);
+        }
+        Self::non_empty(&buckets)
+    }
+
+    fn all() -> [Self; 3] {
+        WakeOffset::ALL.map(|offset| Self {
+            offset,
+            delays: DelayAccumulator::default(),
+        })
+    }
+
+    /// Adds the measurement to its bucket if it was interrupted.
+    fn add(buckets: &mut [Self; 3], m: &RawMeasurement, timeslice: Duration) {
+        if m.interrupted {
+            let offset = WakeOffset::of(m.expected_duration, timeslice);
+            buckets[offset as usize].delays.add(m.delay);
+        }
+    }
+
+    fn non_empty(buckets: &[Self; 3]) -> Vec<Self> {
+        buckets
+            .iter()
+            .filter(|bucket| bucket.delays.count() > 0)
+            .cloned()
+            .collect()
+    }
+
+    #[must_use]
+    pub fn mean(&self) -> Duration {
+        self.delays.mean()
+    }
+
+    #[must_use]
+    pub fn p99(&self) -> Duration {
+        self.delays.percentile(99.0)
+    }
+}
+
+/// The mean delays of the [`OffsetBucket`]s in one line, e.g.,
+/// "early 12µs, middle 15µs, near-deadline 40µs (mean)".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByWakeOffset<'a>(pub &'a [OffsetBucket]);
+
+impl Display for ByWakeOffset<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, bucket) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(
+                f,
+                "{} {}",
+                bucket.offset.name(),
+                format_duration(bucket.mean())
+            )?;
+        }
+        write!(f, " (mean)")
+    }
+}
+
+/// The analysis of a set of measurements, accumulated one measurement at a
+/// time in fixed-size memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Analysis {
+    timeslice: Duration,
+    pub delays: DelayAccumulator,
+    breakdown: Breakdown,
+    attribution: DelayAttribution,
+    migrations: Migrations,
+    /// In the order of [`WakeOffset::ALL`].
+    offsets: [OffsetBucket; 3],
+}
+
+impl Analysis {
+    /// Creates an empty analysis of measurements with the given timeslice.
+    #[must_use]
+    pub fn new(timeslice: Duration) -> Self {
+        Self {
+            timeslice,
+            delays: DelayAccumulator::default(),
+            breakdown: Breakdown::default(),
+            attribution: DelayAttribution::default(),
+            migrations: Migrations::default(),
+            offsets: OffsetBucket::all(),
+        }
+    }
+
+    /// Adds a measurement, in the order of the measurements.
+    pub fn add(&mut self, m: &RawMeasurement) {
+        self.delays.add(m.delay);
+        self.breakdown.add(m);
+        self.attribution.add(m);
+        self.migrations.add(m);
+        OffsetBucket::add(&mut self.offsets, m, self.timeslice);
+    }
+
+    /// See [`Breakdown::of`].
+    #[must_use]
+    pub fn breakdown(&self) -> Option<Breakdown> {
+        (self.breakdown.known > 0).then_some(self.breakdown)
+    }
+
+    /// See [`DelayAttribution::of`].
+    #[must_use]
+    pub fn attribution(&self) -> Option<DelayAttribution> {
+        (self.attribution.known > 0).then_some(self.attribution)
+    }
+
+    /// See [`Migrations::of`].
+    #[must_use]
+    pub fn migrations(&self) -> Option<Migrations> {
+        (self.migrations.known > 0).then_some(self.migrations)
+    }
+
+    /// See [`OffsetBucket::of`].
+    #[must_use]
+    pub fn offsets(&self) -> Vec<OffsetBucket> {
+        OffsetBucket::non_empty(&self.offsets)
+    }
+}
+
+/// A run accumulated for its analysis in fixed-size memory, by [`Reason`].
+///
+/// It is built while reading a recording, see [`RecordingReader::runs_as`],
+/// or while measuring, as [`MeasurementSink`].
+///
+/// [`RecordingReader::runs_as`]: crate::recording::RecordingReader::runs_as
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunAnalysis {
+    pub header: RunHeader,
+    pub rounds: usize,
+    pub skipped: usize,
+    /// Number of rounds whose sleep returned before its expected duration,
+    /// see [`RunReport::negative_delays`].
+    ///
+    /// [`RunReport::negative_delays`]: crate::report::RunReport::negative_delays
+    pub negative_delays: usize,
+    pub failure: Option<String>,
+    pub threads: Option<ThreadIds>,
+    pub interrupted: Analysis,
+    pub timeouted: Analysis,
+}
+
+impl RunAnalysis {
+    #[must_use]
+    pub fn new(header: RunHeader) -> Self {
+        Self {
+            rounds: 0,
+            skipped: 0,
+            negative_delays: 0,
+            failure: None,
+            threads: None,
+            interrupted: Analysis::new(header.timeslice),
+            timeouted: Analysis::new(header.timeslice),
+            header,
+        }
+    }
+
+    /// Accumulates a run that is in memory already.
+    #[must_use]
+    pub fn of(run: &RawRun) -> Self {
+        let mut analysis = Self::new(RunHeader::of(run));
+        for m in &run.measurements {
+            analysis.add(m);
+        }
+        analysis.skipped = run.skipped;
+        analysis
+    }
+
+    /// Accumulates measurements that are in memory already.
+    #[must_use]
+    pub fn of_measurements(header: RunHeader, measurements: &Measurements) -> Self {
+        let mut analysis = Self::new(header);
+        for m in measurements
+            .interrupted
+            .iter()
+            .chain(&measurements.timeouted)
+        {
+            analysis.add(&RawMeasurement::from(m));
+        }
+        analysis.skipped = measurements.skipped;
+        analysis.failure.clone_from(&measurements.failure);
+        analysis.threads = measurements.threads;
+        analysis
+    }
+
+    pub fn add(&mut self, m: &RawMeasurement) {
+        self.rounds += 1;
+        if m.signed_delay().is_negative() {
+            self.negative_delays += 1;
+        }
+        if m.interrupted {
+            self.interrupted.add(m);
+        } else {
+            self.timeouted.add(m);
+        }
+    }
+
+    #[must_use]
+    pub const fn reason(&self, reason: Reason) -> &Analysis {
+        match reason {
+            Reason::Interrupted => &self.interrupted,
+            Reason::Timeouted => &self.timeouted,
+        }
+    }
+
+    /// Like [`Analysis::breakdown`] but of all measurements.
+    #[must_use]
+    pub fn breakdown(&self) -> Option<Breakdown> {
+        let mut breakdown = self.interrupted.breakdown;
+        breakdown.merge(&self.timeouted.breakdown);
+        (breakdown.known > 0).then_some(breakdown)
+    }
+
+    /// Like [`Analysis::attribution`] but of all measurements.
+    #[must_use]
+    pub fn attribution(&self) -> Option<DelayAttribution> {
+        let mut attribution = self.interrupted.attribution;
+        attribution.merge(&self.timeouted.attribution);
+        (attribution.known > 0).then_some(attribution)
+    }
+
+    /// Like [`Analysis::migrations`] but of all measurements.
+    #[must_use]
+    pub fn migrations(&self) -> Option<Migrations> {
+        let mut migrations = self.interrupted.migrations;
+        migrations.merge(&self.timeouted.migrations);
+        (migrations.known > 0).then_some(migrations)
+    }
+}
+
+impl MeasurementSink for RunAnalysis {
+    fn record(&mut self, measurement: Measurement) -> ControlFlow<()> {
+        self.add(&RawMeasurement::from(&measurement));
+        ControlFlow::Continue(())
+    }
+
+    fn skip(&mut self) {
+        self.skipped += 1;
+    }
+
+    fn threads(&mut self, threads: ThreadIds) {
+        self.threads = Some(threads);
+    }
+
+    fn fail(&mut self, reason: String) {
+        self.failure = Some(reason);
+    }
+}
+
+impl FromRecords for RunAnalysis {
+    fn begin(header: RunHeader) -> Self {
+        Self::new(header)
+    }
+
+    fn record(&mut self, measurement: RawMeasurement) {
+        self.add(&measurement);
+    }
+
+    fn skip(&mut self) {
+        self.skipped += 1;
+    }
+}
+
+/// The mean delays of the timeouts of the [baseline](crate::backends::baseline)
+/// per timeslice and timeout mode.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Baselines(Vec<(Duration, String, Duration)>);
+
+impl Baselines {
+    /// Adds the run if it is the first run of the baseline with its
+    /// timeslice and timeout mode.
+    pub fn add(&mut self, run: &RunAnalysis) {
+        if run.header.backend == BASELINE_NAME && self.find(&run.header).is_none() {
+            self.0.push((
+                run.header.timeslice,
+                run.header.timeout_mode.clone(),
+                run.timeouted.delays.mean(),
+            ));
+        }
+    }
+
+    /// Returns the mean delay of the baseline with the same timeslice and
+    /// timeout mode as `run`, if any and if `run` is not the baseline itself.
+    #[must_use]
+    pub fn of(&self, run: &RunHeader) -> Option<Duration> {
+        (run.backend != BASELINE_NAME)
+            .then(|| self.find(run))
+            .flatten()
+    }
+
+    fn find(&self, run: &RunHeader) -> Option<Duration> {
+        self.0
+            .iter()
+            .find(|(timeslice, timeout_mode, _)| {
+                *timeslice == run.timeslice && *timeout_mode == run.timeout_mode
+            })
+            .map(|(_, _, mean)| *mean)
+    }
+}
+
+/// Renders the analysis of all runs and reasons that match the filter:
+/// summary with confidence intervals, percentiles, outliers, and a histogram
+/// of the delays.
+#[must_use]
+pub fn render(runs: &[RawRun], filter: &Filter) -> String {
+    let mut baselines = Baselines::default();
+    for run in runs.iter().filter(|run| run.backend == BASELINE_NAME) {
+        baselines.add(&RunAnalysis::of(run));
+    }
+    runs.iter()
+        .map(|run| render_run(&RunAnalysis::of(run), filter, &baselines))
+        .collect()
+}
+
+/// Renders the analysis of one run like [`render`]. Empty if the run doesn't
+/// match the filter.
+#[must_use]
+pub fn render_run(run: &RunAnalysis, filter: &Filter, baselines: &Baselines) -> String {
+    let mut out = String::new();
+    if filter.matches_run(&run.header) {
+        for reason in Reason::ALL {
+            if filter.matches_reason(reason) {
+                render_reason(&mut out, run, reason, baselines.of(&run.header));
+            }
+        }
+    }
+    out
+}
+
+fn render_reason(
+    out: &mut String,
+    run: &RunAnalysis,
+    reason: Reason,
+    baseline_mean: Option<Duration>,
+) {
+    let header = &run.header;
+    let analysis = run.reason(reason);
+    let delays = &analysis.delays;
+    let _ = writeln!(
+        out,
+        "RUN: {} Sleeper, scenario={}, timeout={}, timeslice={}, {reason} (#): {}",
+        header.backend,
+        header.scenario,
+        header.timeout_mode,
+        format_duration(header.timeslice),
+        delays.count()
+    );
+    let Some(summary) = delays.summary() else {
+        let _ = writeln!(out);
+        return;
+    };
+
+    let _ = writeln!(
+        out,
+        "  mean        : {} ({:.0}% CI: {}..{})",
+        format_duration(summary.mean.value),
+        CONFIDENCE_LEVEL * 100.0,
+        format_duration(summary.mean.ci_low),
+        format_duration(summary.mean.ci_high)
+    );
+    if let Some(baseline_mean) = baseline_mean.filter(|_| reason == Reason::Timeouted) {
+        let overshoot = summary.mean.value.as_nanos() as i128 - baseline_mean.as_nanos() as i128;
+        let _ = writeln!(
+            out,
+            "  vs baseline : {}{} (baseline mean: {})",
+            if overshoot < 0 { "-" } else { "+" },
+            format_duration(Duration::from_nanos(overshoot.unsigned_abs() as u64)),
+            format_duration(baseline_mean)
+        );
+    }
+    let _ = writeln!(out, "  stddev      : {}", format_duration(summary.stddev));
+    let _ = writeln!(out, "  jitter      : {}", format_duration(summary.jitter));
+    for (p, label) in PERCENTILES {
+        let _ = writeln!(
+            out,
+            "  {label:<12}: {}",
+            format_duration(delays.percentile(p))
+        );
+    }
+    let outliers = delays.outliers();
+    let _ = writeln!(
+        out,
+        "  outliers    : {} (> {})",
+        outliers.count,
+        format_duration(outliers.upper_fence)
+    );
+
+    if let Some(breakdown) = analysis.breakdown() {
+        let _ = writeln!(out, "  breakdown   : {breakdown}");
+    }
+    if let Some(attribution) = analysis.attribution() {
+        let _ = writeln!(out, "  attribution : {attribution}");
+    }
+    if let Some(migrations) = analysis.migrations() {
+        let _ = writeln!(out, "  migrations  : {migrations}");
+    }
+    let buckets = analysis.offsets();
+    if !buckets.is_empty() {
+        let _ = writeln!(out, "  wake offset :");
+    }
+    for bucket in &buckets {
+        let _ = writeln!(
+            out,
+            "    {:<13} {:>7}: {:>6} wake-ups, mean {}, p99 {}",
+            bucket.offset.name(),
+            bucket.offset.range(),
+            bucket.delays.count(),
+            format_duration(bucket.mean()),
+            format_duration(bucket.p99())
+        );
+    }
+
+    let buckets = delays.histogram();
+    let max_count = buckets.iter().map(|b| b.count).max().unwrap_or(0);
+    for bucket in buckets {
+        let bar = "#".repeat((bucket.count * BAR_WIDTH).div_ceil(max_count));
+        let _ = writeln!(
+            out,
+            "  {:>8}..{:<8} |{bar:<BAR_WIDTH$}| {}",
+            format_duration(bucket.low),
+            format_duration(bucket.high),
+            bucket.count
+        );
+    }
+    let _ = writeln!(out);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::priority::Priorities;
+    use crate::scenario::DEFAULT_INTERRUPT_PERCENT;
+    use assert2::check;
+
+    fn run(backend: &str, delays_us: &[u64]) -> RawRun {
+        RawRun {
+            backend: backend.to_string(),
+            scenario: "mixed".to_string(),
+            timeout_mode: "relative".to_string(),
+            timeslice: Duration::from_millis(10),
+            seed: 0,
+            repeat: 0,
+            interrupt_percent: DEFAULT_INTERRUPT_PERCENT,
+            detached_wakes: false,
+            cpu_dma_latency: None,
+            load: None,
+            placement: None,
+            cpus: None,
+            priorities: Priorities::default(),
+            skipped: 0,
+            measurements: delays_us
+                .iter()
+                .map(|&us| RawMeasurement {
+                    interrupted: true,
+                    expected_duration: Duration::ZERO,
+                    actual_duration: Duration::from_micros(us),
+                    delay: Duration::from_micros(us),
+                    cpu_before: None,
+                    cpu_after: None,
+                    run_delay: None,
+                    return_path: None,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_filter() {
+        let runs = [run("condvar", &[1, 2, 3]), run("futex", &[4, 5, 6])];
+        let filter = Filter {
+            backend: Some("futex".to_string()),
+            reason: Some(Reason::Interrupted),
+            ..Filter::default()
+        };
+        let out = render(&runs, &filter);
+        check!(out.contains("futex Sleeper"));
+        check!(!out.contains("condvar Sleeper"));
+        check!(!out.contains("timeouted"));
+
+        let filter = Filter {
+            timeslice: Some(Duration::from_millis(5)),
+            ..Filter::default()
+        };
+        check!(render(&runs, &filter).is_empty());
+    }
+
+    #[test]
+    fn test_baseline() {
+        let timeouts = |mut run: RawRun| {
+            for m in &mut run.measurements {
+                m.interrupted = false;
+            }
+            run
+        };
+        let runs = [
+            timeouts(run(BASELINE_NAME, &[50, 70])),
+            timeouts(run("condvar", &[100, 140])),
+        ];
+        let filter = Filter {
+            reason: Some(Reason::Timeouted),
+            ..Filter::default()
+        };
+        let out = render(&runs, &filter);
+        check!(out.contains("vs baseline : +60µs (baseline mean: 60µs)"));
+        // The baseline isn't compared with itself.
+        check!(out.matches("vs baseline").count() == 1);
+    }
+
+    #[test]
+    fn test_migrations() {
+        let measurement = |us, cpu_after| RawMeasurement {
+            interrupted: true,
+            expected_duration: Duration::ZERO,
+            actual_duration: Duration::from_micros(us),
+            delay: Duration::from_micros(us),
+            cpu_before: Some(0),
+            cpu_after,
+            run_delay: None,
+            return_path: None,
+        };
+        let measurements = [
+            measurement(10, Some(0)),
+            measurement(20, Some(0)),
+            measurement(100, Some(1)),
+            measurement(1000, None),
+        ];
+
+        let migrations = Migrations::of(&measurements).unwrap();
+        check!(migrations.known == 3);
+        check!(migrations.migrated == 1);
+        check!(migrations.migrated_mean() == Some(Duration::from_micros(100)));
+        check!(migrations.local_mean() == Some(Duration::from_micros(15)));
+        check!(Migrations::of(&measurements[3..]).is_none());
+    }
+
+    #[test]
+    fn test_breakdown() {
+        let measurement = |delay_us, run_delay_us: Option<u64>| RawMeasurement {
+            interrupted: true,
+            expected_duration: Duration::ZERO,
+            actual_duration: Duration::from_micros(delay_us),
+            delay: Duration::from_micros(delay_us),
+            cpu_before: None,
+            cpu_after: None,
+            run_delay: run_delay_us.map(Duration::from_micros),
+            return_path: None,
+        };
+        let measurements = [
+            measurement(100, Some(30)),
+            // Run delay before the sleep began: capped at the delay.
+            measurement(20, Some(50)),
+            measurement(1000, None),
+        ];
+
+        let breakdown = Breakdown::of(&measurements).unwrap();
+        check!(breakdown.known == 2);
+        check!(breakdown.until_runnable() == Duration::from_micros(35));
+        check!(breakdown.queueing() == Duration::from_micros(25));
+        check!(Breakdown::of(&measurements[2..]).is_none());
+    }
+
+    #[test]
+    fn test_delay_attribution() {
+        let measurement =
+            |delay_us, run_delay_us: Option<u64>, return_path_us: Option<u64>| RawMeasurement {
+                interrupted: true,
+                expected_duration: Duration::ZERO,
+                actual_duration: Duration::from_micros(delay_us),
+                delay: Duration::from_micros(delay_us),
+                cpu_before: None,
+                cpu_after: None,
+                run_delay: run_delay_us.map(Duration::from_micros),
+                return_path: return_path_us.map(Duration::from_micros),
+            };
+        let measurements = [
+            measurement(100, Some(30), Some(20)),
+            measurement(60, Some(10), Some(40)),
+            measurement(1000, Some(10), None),
+        ];
+
+        let attribution = DelayAttribution::of(&measurements).unwrap();
+        check!(attribution.known == 2);
+        check!(attribution.notification() == Duration::from_micros(30));
+        check!(attribution.scheduling() == Some(Duration::from_micros(20)));
+        check!(attribution.return_path() == Duration::from_micros(30));
+        check!(DelayAttribution::of(&measurements[2..]).is_none());
+
+        let attribution = DelayAttribution::of(&[measurement(100, None, Some(20))]).unwrap();
+        check!(attribution.notification() == Duration::from_micros(80));
+        check!(attribution.scheduling().is_none());
+        check!(
+            attribution.to_string()
+                == "80µs notification (incl. scheduling) + 20µs return path (mean)"
+        );
+    }
+
+    #[test]
+    fn test_wake_offset() {
+        let timeslice = Duration::from_millis(10);
+        check!(WakeOffset::of(Duration::ZERO, timeslice) == WakeOffset::Early);
+        check!(WakeOffset::of(Duration::from_millis(5), timeslice) == WakeOffset::Middle);
+        check!(WakeOffset::of(Duration::from_millis(8), timeslice) == WakeOffset::NearDeadline);
+
+        let measurement = |offset_ms, delay_us, interrupted| RawMeasurement {
+            interrupted,
+            expected_duration: Duration::from_millis(offset_ms),
+            actual_duration: Duration::from_millis(offset_ms) + Duration::from_micros(delay_us),
+            delay: Duration::from_micros(delay_us),
+            cpu_before: None,
+            cpu_after: None,
+            run_delay: None,
+            return_path: None,
+        };
+        let measurements = [
+            measurement(1, 10, true),
+            measurement(2, 30, true),
+            measurement(9, 100, true),
+            measurement(10, 1000, false),
+        ];
+        let buckets = OffsetBucket::of(&measurements, timeslice);
+        check!(buckets.len() == 2);
+        check!(buckets[0].offset == WakeOffset::Early);
+        check!(buckets[0].mean() == Duration::from_micros(20));
+        check!(buckets[1].offset == WakeOffset::NearDeadline);
+        check!(buckets[1].p99() == Duration::from_micros(100));
+        check!(ByWakeOffset(&buckets).to_string() == "early 20µs, near-deadline 100µs (mean)");
+    }
+
+    #[test]
+    fn test_reason_from_str() {
+        check!("interrupted".parse::<Reason>() == Ok(Reason::Interrupted));
+        check!("timeouted".parse::<Reason>() == Ok(Reason::Timeouted));
+        check!("foo".parse::<Reason>().is_err());
+    }
+}
@@ -0,0 +1,223 @@
+//! Bounds on the delays for CI, see [`Bounds`].
+//!
+//! With bounds, e.g., "the p99 delay of `futex` is below 50µs", the
+//! benchmark can gate merges directly: it exits with an error if a run
+//! exceeds them and writes the [`Violation`]s in a machine-readable form.
+
+use crate::report::RunReport;
+use crate::units::{format_duration, serialize_nanos, serialize_optional_nanos};
+use serde::Serialize;
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
+
+/// Upper bounds on the delays of the interrupted wake-ups.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bounds {
+    /// Only check the runs of this backend. All runs if `None`.
+    pub backend: Option<String>,
+    pub mean: Option<Duration>,
+    pub p99: Option<Duration>,
+}
+
+/// What a [`Violation`] is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Check {
+    /// The mean delay reached the bound.
+    Mean,
+    /// The p99 delay reached the bound.
+    P99,
+    /// The run was aborted, see [`RunReport::failure`].
+    Failed,
+}
+
+/// A run that doesn't satisfy the [`Bounds`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Violation {
+    pub backend: String,
+    pub scenario: String,
+    #[serde(rename = "timeslice_ns", serialize_with = "serialize_nanos")]
+    pub timeslice: Duration,
+    pub check: Check,
+    /// `None` for [`Check::Failed`].
+    #[serde(rename = "bound_ns", serialize_with = "serialize_optional_nanos")]
+    pub bound: Option<Duration>,
+    /// The measured mean or p99 delay, or `None` for [`Check::Failed`].
+    #[serde(rename = "value_ns", serialize_with = "serialize_optional_nanos")]
+    pub value: Option<Duration>,
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} Sleeper, scenario={}, timeslice={}: ",
+            self.backend,
+            self.scenario,
+            format_duration(self.timeslice)
+        )?;
+        let statistic = match self.check {
+            Check::Mean => "mean",
+            Check::P99 => "p99",
+            Check::Failed => return write!(f, "the run failed"),
+        };
+        write!(
+            f,
+            "{statistic} delay {} is not below {}",
+            format_duration(self.value.unwrap_or_default()),
+            format_duration(self.bound.unwrap_or_default())
+        )
+    }
+}
+
+/// The outcome of [`Bounds::check`], e.g., for a JSON summary.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Verdict {
+    /// Whether runs were checked and none violates the bounds.
+    pub passed: bool,
+    /// Number of runs that were checked.
+    pub checked: usize,
+    pub violations: Vec<Violation>,
+}
+
+impl Bounds {
+    /// Returns whether there is any bound to check.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.mean.is_none() && self.p99.is_none()
+    }
+
+    /// Checks the runs of the selected backend. Runs without interrupted
+    /// wake-ups, e.g., of the `timeout` scenario, are skipped, failed runs
+    /// always violate the bounds.
+    ///
+    /// The verdict doesn't pass if no run was checked, e.g., because the
+    /// selected backend didn't run.
+    #[must_use]
+    pub fn check(&self, reports: &[RunReport]) -> Verdict {
+        let mut verdict = Verdict {
+            passed: false,
+            checked: 0,
+            violations: Vec::new(),
+        };
+        let selected = reports.iter().filter(|report| {
+            self.backend
+                .as_ref()
+                .is_none_or(|backend| *backend == report.backend)
+        });
+        for report in selected {
+            let violation = |check, bound, value| Violation {
+                backend: report.backend.clone(),
+                scenario: report.scenario.clone(),
+                timeslice: report.timeslice,
+                check,
+                bound,
+                value,
+            };
+            if report.failure.is_some() {
+                verdict.checked += 1;
+                verdict
+                    .violations
+                    .push(violation(Check::Failed, None, None));
+                continue;
+            }
+            let Some(summary) = &report.interrupted else {
+                continue;
+            };
+            verdict.checked += 1;
+            for (check, bound, value) in [
+                (Check::Mean, self.mean, summary.mean.value),
+                (Check::P99, self.p99, summary.p99.value),
+            ] {
+                if let Some(bound) = bound.filter(|&bound| value >= bound) {
+                    verdict
+                        .violations
+                        .push(violation(check, Some(bound), Some(value)));
+                }
+            }
+        }
+        verdict.passed = verdict.checked > 0 && verdict.violations.is_empty();
+        verdict
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::priority::Priorities;
+    use crate::stats::{Estimate, Summary};
+    use assert2::check;
+
+    fn report(backend: &str, mean_us: u64, p99_us: u64) -> RunReport {
+        let estimate = |us| Estimate {
+            value: Duration::from_micros(us),
+            ci_low: Duration::from_micros(us),
+            ci_high: Duration::from_micros(us),
+        };
+        RunReport {
+            backend: backend.to_string(),
+            scenario: "mixed".to_string(),
+            interrupt_percent: 50,
+            timeout_mode: "relative".to_string(),
+            timeslice: Duration::from_millis(10),
+            seed: 0,
+            repeat: 0,
+            rounds: 10,
+            skipped: 0,
+            negative_delays: 0,
+            interrupted: Some(Summary {
+                count: 10,
+                mean: estimate(mean_us),
+                p50: estimate(mean_us),
+                p99: estimate(p99_us),
+                stddev: Duration::ZERO,
+                jitter: Duration::ZERO,
+            }),
+            timeouted: None,
+            cpu_dma_latency: None,
+            load: None,
+            energy: None,
+            detached_wakes: false,
+            failure: None,
+            placement: None,
+            cpus: None,
+            priorities: Priorities::default(),
+            threads: None,
+            batch_seeds: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let reports = [
+            report("futex", 10, 40),
+            report("futex", 12, 60),
+            report("condvar", 30, 90),
+            RunReport {
+                failure: Some("hung".to_string()),
+                ..report("futex", 0, 0)
+            },
+        ];
+        let bounds = Bounds {
+            backend: Some("futex".to_string()),
+            mean: None,
+            p99: Some(Duration::from_micros(50)),
+        };
+
+        let verdict = bounds.check(&reports);
+        check!(verdict.checked == 3);
+        check!(!verdict.passed);
+        check!(verdict.violations.len() == 2);
+        check!(verdict.violations[0].check == Check::P99);
+        check!(verdict.violations[0].value == Some(Duration::from_micros(60)));
+        check!(
+            verdict.violations[0].to_string()
+                == "futex Sleeper, scenario=mixed, timeslice=10ms: p99 delay 60µs is not below 50µs"
+        );
+        check!(verdict.violations[1].check == Check::Failed);
+
+        check!(bounds.check(&reports[..1]).passed);
+        // Nothing to check.
+        check!(!bounds.check(&reports[2..3]).passed);
+    }
+}
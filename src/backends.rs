@@ -0,0 +1,356 @@
+//! All [`Sleeper`] and [`Waker`] implementations and a registry of the ones
+//! compiled into this build. See [`available`].
+//!
+//! The backends based on the std library are always available. Every
+//! platform-specific or dependency-heavy backend is gated behind its own cargo
+//! feature of the same name:
+//!
+//! | Feature         | Backend                                           | Platforms  |
+//! |-----------------|---------------------------------------------------|------------|
+//! | `futex`         | raw `futex(2)` syscall                            | Linux      |
+//! | `io_uring`      | `io_uring` read on an `eventfd` + timeout         | Linux      |
+//! | `semaphore`     | POSIX `sem_post` + `sem_timedwait`                | Linux      |
+//! | `parking_lot`   | `parking_lot::Mutex` + `parking_lot::Condvar`     | all        |
+//! | `tokio`         | `tokio::sync::Notify` + `tokio::time::timeout`    | all        |
+//! | `oneshot`       | `futures` oneshot + `tokio::time::timeout`        | all        |
+//! | `async_channel` | `async_channel::bounded` + `tokio::time::timeout` | all        |
+//! | `smol`          | `event_listener::Event` + `smol::Timer`           | all        |
+//! | `kqueue`        | `kqueue` with an `EVFILT_USER` event              | macOS, BSD |
+//! | `windows`       | `WaitOnAddress` + `WakeByAddressSingle`           | Windows    |
+//! | `windows`       | `SRWLock` + `SleepConditionVariableSRW`           | Windows    |
+//!
+//! Enabling a feature on an unsupported platform is not an error; the backend
+//! is simply not compiled in.
+//!
+//! On Linux, the non-interruptible [`nanosleep`] sleeper serves as
+//! [`baseline`] for the timeouts. It is not part of [`available`].
+//!
+//! Further backends can be added at runtime with [`register`], e.g., from
+//! plugins (feature `plugins`).
+
+use crate::{Sleeper, Waker};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+#[cfg(feature = "async_channel")]
+pub mod async_channel;
+pub mod channel;
+pub mod condvar;
+#[cfg(all(feature = "futex", target_os = "linux"))]
+pub mod futex;
+#[cfg(all(feature = "io_uring", target_os = "linux"))]
+pub mod io_uring;
+#[cfg(all(
+    feature = "kqueue",
+    any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "dragonfly"
+    )
+))]
+pub mod kqueue;
+#[cfg(target_os = "linux")]
+mod monotonic;
+#[cfg(target_os = "linux")]
+pub mod nanosleep;
+#[cfg(feature = "oneshot")]
+pub mod oneshot;
+#[cfg(feature = "parking_lot")]
+pub mod parking_lot;
+#[cfg(all(feature = "semaphore", target_os = "linux"))]
+pub mod semaphore;
+#[cfg(feature = "smol")]
+pub mod smol;
+#[cfg(all(feature = "windows", windows))]
+pub mod srw;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+#[cfg(all(feature = "windows", windows))]
+pub mod wait_on_address;
+
+/// Type-erased [`Sleeper`] of a [`Backend`].
+pub type BoxedSleeper = Box<dyn Sleeper<Instant> + Send>;
+
+/// Type-erased [`Waker`] of a [`Backend`].
+pub type BoxedWaker = Box<dyn Waker + Send>;
+
+/// Type-erased [`Sleeper`]s on one shared primitive and their [`Waker`], see
+/// [`Backend::new_group`].
+pub type BoxedGroup = (Vec<BoxedSleeper>, BoxedWaker);
+
+/// A [`Sleeper`] and [`Waker`] implementation that is compiled into this
+/// build or [registered](register) at runtime.
+#[derive(Debug, Clone, Copy)]
+pub struct Backend {
+    /// Short unique name, for example, to select the backend on the command
+    /// line.
+    pub name: &'static str,
+    /// Human-readable description of the underlying primitives.
+    pub description: &'static str,
+    new_pair: fn() -> (BoxedSleeper, BoxedWaker),
+    new_group: Option<fn(usize) -> BoxedGroup>,
+}
+
+impl Backend {
+    /// Creates a backend, e.g., for [`register`]. `new_pair` creates a new
+    /// connected [`Sleeper`] and [`Waker`] pair.
+    #[must_use]
+    pub const fn new(
+        name: &'static str,
+        description: &'static str,
+        new_pair: fn() -> (BoxedSleeper, BoxedWaker),
+    ) -> Self {
+        Self {
+            name,
+            description,
+            new_pair,
+            new_group: None,
+        }
+    }
+
+    /// Adds a constructor of several [`Sleeper`]s that block on one shared
+    /// primitive, see [`Self::new_group`].
+    #[must_use]
+    pub const fn with_new_group(mut self, new_group: fn(usize) -> BoxedGroup) -> Self {
+        self.new_group = Some(new_group);
+        self
+    }
+
+    /// Creates a new connected [`Sleeper`] and [`Waker`] pair of this backend.
+    #[must_use]
+    pub fn new_pair(&self) -> (BoxedSleeper, BoxedWaker) {
+        (self.new_pair)()
+    }
+
+    /// Creates `sleepers` [`Sleeper`]s that block on one shared instance of
+    /// the primitive, and a [`Waker`] that wakes one of them, whichever the
+    /// primitive picks. One thread sleeps on each sleeper.
+    ///
+    /// Returns `None` if the backend doesn't support several sleepers.
+    #[must_use]
+    pub fn new_group(&self, sleepers: usize) -> Option<BoxedGroup> {
+        self.new_group.map(|new_group| new_group(sleepers))
+    }
+
+    /// Returns whether the backend supports several sleepers on one shared
+    /// primitive, see [`Self::new_group`].
+    #[must_use]
+    pub const fn supports_groups(&self) -> bool {
+        self.new_group.is_some()
+    }
+
+    /// Returns whether the waker supports [`Waker::wake_detached`]. Probes a
+    /// fresh pair, whose sleeper never sleeps.
+    #[must_use]
+    pub fn supports_detached_wakes(&self) -> bool {
+        let (_sleeper, waker) = self.new_pair();
+        waker.wake_detached()
+    }
+}
+
+fn boxed<S: Sleeper<Instant> + Send + 'static, W: Waker + Send + 'static>(
+    (sleeper, waker): (S, W),
+) -> (BoxedSleeper, BoxedWaker) {
+    (Box::new(sleeper), Box::new(waker))
+}
+
+fn boxed_group<S: Sleeper<Instant> + Send + 'static, W: Waker + Send + 'static>(
+    (sleepers, waker): (Vec<S>, W),
+) -> BoxedGroup {
+    let sleepers = sleepers
+        .into_iter()
+        .map(|sleeper| Box::new(sleeper) as BoxedSleeper)
+        .collect();
+    (sleepers, Box::new(waker))
+}
+
+const BACKENDS: &[Backend] = &[
+    Backend {
+        name: "condvar",
+        description: "std::sync::Mutex + std::sync::Condvar",
+        new_pair: || boxed(condvar::new_pair()),
+        new_group: Some(|sleepers| boxed_group(condvar::new_group(sleepers))),
+    },
+    Backend {
+        name: "channel",
+        description: "std::sync::mpsc::sync_channel",
+        new_pair: || boxed(channel::new_pair()),
+        new_group: None,
+    },
+    #[cfg(all(feature = "futex", target_os = "linux"))]
+    Backend {
+        name: "futex",
+        description: "Linux futex(2) syscall",
+        new_pair: || boxed(futex::new_pair()),
+        new_group: Some(|sleepers| boxed_group(futex::new_group(sleepers))),
+    },
+    #[cfg(all(feature = "io_uring", target_os = "linux"))]
+    Backend {
+        name: "io_uring",
+        description: "io_uring read on an eventfd with a linked timeout",
+        new_pair: || boxed(self::io_uring::new_pair()),
+        new_group: None,
+    },
+    #[cfg(all(feature = "semaphore", target_os = "linux"))]
+    Backend {
+        name: "semaphore",
+        description: "POSIX counting semaphore, sem_post + sem_timedwait",
+        new_pair: || boxed(semaphore::new_pair()),
+        new_group: None,
+    },
+    #[cfg(all(
+        feature = "kqueue",
+        any(
+            target_os = "macos",
+            target_os = "ios",
+            target_os = "freebsd",
+            target_os = "dragonfly"
+        )
+    ))]
+    Backend {
+        name: "kqueue",
+        description: "kqueue with an EVFILT_USER event",
+        new_pair: || boxed(kqueue::new_pair()),
+        new_group: None,
+    },
+    #[cfg(feature = "parking_lot")]
+    Backend {
+        name: "parking_lot",
+        description: "parking_lot::Mutex + parking_lot::Condvar",
+        new_pair: || boxed(self::parking_lot::new_pair()),
+        new_group: Some(|sleepers| boxed_group(self::parking_lot::new_group(sleepers))),
+    },
+    #[cfg(feature = "tokio")]
+    Backend {
+        name: "tokio",
+        description: "tokio::sync::Notify + tokio::time::timeout",
+        new_pair: || boxed(self::tokio::new_pair()),
+        new_group: None,
+    },
+    #[cfg(feature = "oneshot")]
+    Backend {
+        name: "oneshot",
+        description: "futures::channel::oneshot + tokio::time::timeout",
+        new_pair: || boxed(oneshot::new_pair()),
+        new_group: None,
+    },
+    #[cfg(feature = "async_channel")]
+    Backend {
+        name: "async_channel",
+        description: "async_channel::bounded + tokio::time::timeout",
+        new_pair: || boxed(self::async_channel::new_pair()),
+        new_group: None,
+    },
+    #[cfg(feature = "smol")]
+    Backend {
+        name: "smol",
+        description: "event_listener::Event + smol::Timer",
+        new_pair: || boxed(self::smol::new_pair()),
+        new_group: None,
+    },
+    #[cfg(all(feature = "windows", windows))]
+    Backend {
+        name: "wait_on_address",
+        description: "Windows WaitOnAddress + WakeByAddressSingle",
+        new_pair: || boxed(wait_on_address::new_pair()),
+        new_group: None,
+    },
+    #[cfg(all(feature = "windows", windows))]
+    Backend {
+        name: "srw",
+        description: "Windows SRWLock + SleepConditionVariableSRW",
+        new_pair: || boxed(srw::new_pair()),
+        new_group: None,
+    },
+];
+
+/// Name of the [`baseline`], also on platforms that don't support it, e.g., to
+/// find it in results.
+pub const BASELINE_NAME: &str = "nanosleep";
+
+#[cfg(target_os = "linux")]
+const BASELINE: Option<Backend> = Some(Backend {
+    name: BASELINE_NAME,
+    description: "clock_nanosleep with TIMER_ABSTIME, not interruptible",
+    new_pair: || boxed(nanosleep::new_pair()),
+    new_group: None,
+});
+#[cfg(not(target_os = "linux"))]
+const BASELINE: Option<Backend> = None;
+
+/// Returns the baseline for the timeouts of all backends: a sleeper that
+/// can't be interrupted but uses the most precise timer of the OS. Runs of it
+/// must use [`Scenario::Timeout`].
+///
+/// Returns `None` if the platform doesn't support it.
+///
+/// [`Scenario::Timeout`]: crate::scenario::Scenario::Timeout
+#[must_use]
+pub const fn baseline() -> Option<&'static Backend> {
+    match &BASELINE {
+        Some(backend) => Some(backend),
+        None => None,
+    }
+}
+
+/// The compiled-in backends followed by the registered ones, see
+/// [`register`].
+static REGISTERED: OnceLock<&'static [Backend]> = OnceLock::new();
+
+/// Adds backends to the compiled-in ones, e.g., the backends of plugins.
+/// Must be called at most once and before the backends are used.
+///
+/// # Errors
+///
+/// Fails if backends were already registered or if a name is already taken.
+pub fn register(backends: Vec<Backend>) -> Result<(), String> {
+    let all = with_compiled_in(backends)?;
+    REGISTERED
+        .set(all.leak())
+        .map_err(|_| "backends were already registered".to_string())
+}
+
+fn with_compiled_in(backends: Vec<Backend>) -> Result<Vec<Backend>, String> {
+    let mut all = BACKENDS.to_vec();
+    for backend in backends {
+        if backend.name == BASELINE_NAME || all.iter().any(|b| b.name == backend.name) {
+            return Err(format!("backend `{}` already exists", backend.name));
+        }
+        all.push(backend);
+    }
+    Ok(all)
+}
+
+/// Returns all backends compiled into this build and the registered ones.
+#[must_use]
+pub fn available() -> &'static [Backend] {
+    REGISTERED.get().copied().unwrap_or(BACKENDS)
+}
+
+/// Returns the compiled-in or registered backend with the given name, if any.
+#[must_use]
+pub fn find(name: &str) -> Option<&'static Backend> {
+    available().iter().find(|backend| backend.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert2::check;
+
+    #[test]
+    fn test_with_compiled_in() {
+        let backend = |name| Backend::new(name, "test", || boxed(condvar::new_pair()));
+        let all = with_compiled_in(vec![backend("custom")]).unwrap();
+        check!(all.len() == BACKENDS.len() + 1);
+        check!(all.last().unwrap().name == "custom");
+
+        check!(
+            with_compiled_in(vec![backend("condvar")]).unwrap_err()
+                == "backend `condvar` already exists"
+        );
+        check!(with_compiled_in(vec![backend("a"), backend("a")]).is_err());
+        check!(with_compiled_in(vec![backend(BASELINE_NAME)]).is_err());
+    }
+}
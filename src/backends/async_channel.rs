@@ -0,0 +1,94 @@
+//! Implements [`Sleeper`] and [`Waker`] using a bounded channel from the
+//! `async-channel` crate and [`tokio::time::timeout`], the async counterpart
+//! of the `channel` backend.
+//!
+//! Like the `tokio` backend, the sleeper drives its own current-thread
+//! runtime.
+
+use crate::synchronization::NoDelayBarrier;
+use crate::trace;
+use crate::{Handshake, Sleeper, Waker, WakeupReason};
+use async_channel::{Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::runtime::{Builder, Runtime};
+
+#[derive(Debug)]
+pub struct AsyncChannelSleeper {
+    runtime: Runtime,
+    receiver: Receiver<Instant>,
+    // Helper to synchronize sleep_interruptible() and wake()
+    synchronization_point: Arc<NoDelayBarrier>,
+}
+
+#[derive(Debug)]
+pub struct AsyncChannelWaker {
+    sender: Sender<Instant>,
+    // Helper to synchronize sleep_interruptible() and wake()
+    synchronization_point: Arc<NoDelayBarrier>,
+}
+
+#[must_use]
+pub fn new_pair() -> (AsyncChannelSleeper, AsyncChannelWaker) {
+    let runtime = Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .expect("should create tokio runtime");
+    let (sender, receiver) = async_channel::bounded(1);
+    let synchronization_point = Arc::new(NoDelayBarrier::new());
+
+    let sleeper = AsyncChannelSleeper {
+        runtime,
+        receiver,
+        synchronization_point: synchronization_point.clone(),
+    };
+    let waker = AsyncChannelWaker {
+        sender,
+        synchronization_point,
+    };
+
+    (sleeper, waker)
+}
+
+impl Sleeper<Instant> for AsyncChannelSleeper {
+    fn sleep_interruptible(&self, sleep_duration: Duration) -> WakeupReason {
+        // The timer must be created inside the runtime context.
+        let res = self
+            .runtime
+            .block_on(async { tokio::time::timeout(sleep_duration, self.receiver.recv()).await });
+        let wake_call_instant = match res {
+            Ok(res) => Some(res.expect("channel should not be closed")),
+            // If the wake-up raced with the timeout, the instant is already in
+            // the channel. We must still ACK it, otherwise Waker::wake() waits
+            // forever.
+            Err(_elapsed) => match self.receiver.try_recv() {
+                Ok(instant) => Some(instant),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Closed) => panic!("Channel closed"),
+            },
+        };
+
+        let Some(wake_call_instant) = wake_call_instant else {
+            return WakeupReason::Timeout;
+        };
+        // Unblock Waker::wake()
+        self.synchronization_point.wait();
+        trace::event!(backend = "async_channel", "ack sent");
+
+        WakeupReason::Interrupted { wake_call_instant }
+    }
+}
+
+impl Waker for AsyncChannelWaker {
+    fn wake(&self) {
+        self.sender.send_blocking(Instant::now()).unwrap();
+
+        // Wait for Sleeper to ACK
+        self.synchronization_point.wait();
+        trace::event!(backend = "async_channel", "ack received");
+    }
+
+    fn set_handshake(&self, handshake: Handshake) {
+        self.synchronization_point.set_handshake(handshake);
+    }
+}
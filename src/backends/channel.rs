@@ -0,0 +1,121 @@
+//! Implements [`Sleeper`] and [`Waker`] using a Rust channel from
+//! the std library.
+
+use crate::synchronization::NoDelayBarrier;
+use crate::trace;
+use crate::{Handshake, Sleeper, Waker, WakeupReason};
+use std::cell::Cell;
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, SyncSender, TryRecvError, sync_channel};
+use std::time::{Duration, Instant};
+
+/// Message of a wake-up call.
+#[derive(Debug)]
+struct WakeCall {
+    instant: Instant,
+    // Whether the wake-up came from wake_detached(), which isn't ACKed
+    detached: bool,
+}
+
+#[derive(Debug)]
+pub struct ChannelSleeper {
+    receiver: Receiver<WakeCall>,
+    // Barrier to synchronize sleep_interruptible() and wake()
+    synchronization_point: Arc<NoDelayBarrier>,
+    // When the last interrupted sleep observed the wake-up call
+    wake_observed: Cell<Option<Instant>>,
+}
+
+#[derive(Debug)]
+pub struct ChannelWaker {
+    sender: SyncSender<WakeCall>,
+    // Helper to synchronize sleep_interruptible() and wake()
+    synchronization_point: Arc<NoDelayBarrier>,
+}
+
+#[must_use]
+pub fn new_pair() -> (ChannelSleeper, ChannelWaker) {
+    let (sender, receiver) = sync_channel(1);
+    let synchronization_point = Arc::new(NoDelayBarrier::new());
+    let sleeper = ChannelSleeper {
+        receiver,
+        synchronization_point: synchronization_point.clone(),
+        wake_observed: Cell::new(None),
+    };
+    let waker = ChannelWaker {
+        sender,
+        synchronization_point,
+    };
+
+    (sleeper, waker)
+}
+
+impl Sleeper<Instant> for ChannelSleeper {
+    fn sleep_interruptible(&self, sleep_duration: Duration) -> WakeupReason {
+        let res = self.receiver.recv_timeout(sleep_duration);
+        // If the wake-up raced with the timeout, the instant is already in the
+        // channel. We must still ACK it, otherwise Waker::wake() waits forever.
+        let res = match res {
+            Err(RecvTimeoutError::Timeout) => self.receiver.try_recv().map_err(|e| match e {
+                TryRecvError::Empty => RecvTimeoutError::Timeout,
+                TryRecvError::Disconnected => RecvTimeoutError::Disconnected,
+            }),
+            res => res,
+        };
+        match res {
+            Ok(wake_call) => {
+                self.wake_observed.set(Some(Instant::now()));
+                let reason = WakeupReason::Interrupted {
+                    wake_call_instant: wake_call.instant,
+                };
+
+                if !wake_call.detached {
+                    // Unblock Waker::wake()
+                    self.synchronization_point.wait();
+                    trace::event!(backend = "channel", "ack sent");
+                }
+
+                reason
+            }
+            Err(RecvTimeoutError::Timeout) => WakeupReason::Timeout,
+            Err(RecvTimeoutError::Disconnected) => {
+                panic!("Channel disconnected");
+            }
+        }
+    }
+
+    fn wake_observed(&self) -> Option<Instant> {
+        self.wake_observed.get()
+    }
+}
+
+impl Waker for ChannelWaker {
+    fn wake(&self) {
+        self.sender
+            .send(WakeCall {
+                instant: Instant::now(),
+                detached: false,
+            })
+            .unwrap();
+        // Wait for sleep() to be interrupted.
+        self.synchronization_point.wait();
+        trace::event!(backend = "channel", "ack received");
+    }
+
+    /// Sends a wake-up call like [`Self::wake`]. Every call interrupts one
+    /// sleep. As the channel buffers a single call, this blocks while a
+    /// previous call wasn't received yet.
+    fn wake_detached(&self) -> bool {
+        self.sender
+            .send(WakeCall {
+                instant: Instant::now(),
+                detached: true,
+            })
+            .unwrap();
+        true
+    }
+
+    fn set_handshake(&self, handshake: Handshake) {
+        self.synchronization_point.set_handshake(handshake);
+    }
+}
@@ -0,0 +1,259 @@
+//! Implements [`Sleeper`] and [`Waker`] directly on top of the Linux `futex(2)`
+//! syscall, without any abstraction of the std library in between.
+
+use super::monotonic::to_monotonic_timespec;
+use crate::synchronization::NoDelayBarrier;
+use crate::trace;
+use crate::{Handshake, Sleeper, Waker, WakeupReason};
+use std::cell::Cell;
+use std::io;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const SLEEP_WAS_INTERRUPTED: u32 = 1;
+const SLEEP_NORMAL: u32 = 0;
+
+#[derive(Debug)]
+struct SharedState {
+    // futex word, either SLEEP_NORMAL or SLEEP_WAS_INTERRUPTED. Only set to
+    // SLEEP_WAS_INTERRUPTED while holding the lock of `wake_call_instant`.
+    sleep_state: AtomicU32,
+    wake_call_instant: Mutex<Option<Instant>>,
+    // Whether the wake-up came from wake_detached(), which isn't ACKed
+    detached: AtomicBool,
+}
+
+#[derive(Debug)]
+pub struct FutexSleeper {
+    shared_state: Arc<SharedState>,
+    // Helper to synchronize sleep_interruptible() and wake()
+    synchronization_point: Arc<NoDelayBarrier>,
+    // When the last interrupted sleep observed the wake-up call
+    wake_observed: Cell<Option<Instant>>,
+}
+
+#[derive(Debug)]
+pub struct FutexWaker {
+    shared_state: Arc<SharedState>,
+    // Helper to synchronize sleep_interruptible() and wake()
+    synchronization_point: Arc<NoDelayBarrier>,
+}
+
+#[must_use]
+pub fn new_pair() -> (FutexSleeper, FutexWaker) {
+    let shared_state = Arc::new(SharedState {
+        sleep_state: AtomicU32::new(SLEEP_NORMAL),
+        wake_call_instant: Mutex::new(None),
+        detached: AtomicBool::new(false),
+    });
+    let synchronization_point = Arc::new(NoDelayBarrier::new());
+
+    let sleeper = FutexSleeper {
+        shared_state: shared_state.clone(),
+        synchronization_point: synchronization_point.clone(),
+        wake_observed: Cell::new(None),
+    };
+    let waker = FutexWaker {
+        shared_state,
+        synchronization_point,
+    };
+
+    (sleeper, waker)
+}
+
+/// Creates `sleepers` sleepers that wait on the same futex word. A wake-up
+/// wakes one of them with `FUTEX_WAKE`; the first one to claim the futex word
+/// consumes it.
+#[must_use]
+pub fn new_group(sleepers: usize) -> (Vec<FutexSleeper>, FutexWaker) {
+    let (sleeper, waker) = new_pair();
+    let sleepers = (0..sleepers)
+        .map(|_| FutexSleeper {
+            shared_state: sleeper.shared_state.clone(),
+            synchronization_point: sleeper.synchronization_point.clone(),
+            wake_observed: Cell::new(None),
+        })
+        .collect();
+    (sleepers, waker)
+}
+
+/// Blocks as long as `futex` holds `expected` but at most for `timeout`.
+fn futex_wait(futex: &AtomicU32, expected: u32, timeout: Duration) -> io::Result<()> {
+    let timeout = libc::timespec {
+        tv_sec: timeout.as_secs() as libc::time_t,
+        tv_nsec: timeout.subsec_nanos() as libc::c_long,
+    };
+    // SAFETY: The futex word and the timeout are valid for the duration of
+    // the syscall.
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            futex.as_ptr(),
+            libc::FUTEX_WAIT | libc::FUTEX_PRIVATE_FLAG,
+            expected,
+            &raw const timeout,
+            ptr::null::<u32>(),
+            0_u32,
+        )
+    };
+    if ret == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Blocks as long as `futex` holds `expected` but at most until the absolute
+/// `CLOCK_MONOTONIC` time `deadline`.
+fn futex_wait_bitset(futex: &AtomicU32, expected: u32, deadline: Instant) -> io::Result<()> {
+    let deadline = to_monotonic_timespec(deadline);
+    // SAFETY: The futex word and the deadline are valid for the duration of
+    // the syscall.
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            futex.as_ptr(),
+            libc::FUTEX_WAIT_BITSET | libc::FUTEX_PRIVATE_FLAG,
+            expected,
+            &raw const deadline,
+            ptr::null::<u32>(),
+            libc::FUTEX_BITSET_MATCH_ANY,
+        )
+    };
+    if ret == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Wakes at most one thread blocked on `futex`.
+fn futex_wake_one(futex: &AtomicU32) {
+    // SAFETY: The futex word is valid for the duration of the syscall.
+    unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            futex.as_ptr(),
+            libc::FUTEX_WAKE | libc::FUTEX_PRIVATE_FLAG,
+            1_u32,
+        );
+    }
+}
+
+impl FutexSleeper {
+    /// Resets the futex word and returns the wake-up call and whether it was
+    /// detached, unless another sleeper of the [group](new_group) was faster.
+    fn claim(&self) -> Option<(Instant, bool)> {
+        let mut wake_call_instant = self.shared_state.wake_call_instant.lock().unwrap();
+        let claimed = self
+            .shared_state
+            .sleep_state
+            .swap(SLEEP_NORMAL, Ordering::AcqRel)
+            == SLEEP_WAS_INTERRUPTED;
+        claimed.then(|| {
+            (
+                wake_call_instant
+                    .take()
+                    .expect("should have been set by wake()"),
+                self.shared_state.detached.load(Ordering::Acquire),
+            )
+        })
+    }
+
+    /// Sleeps until `deadline`, passed to the kernel as absolute or relative
+    /// timeout.
+    fn sleep(&self, deadline: Instant, absolute: bool) -> WakeupReason {
+        loop {
+            if self.shared_state.sleep_state.load(Ordering::Acquire) == SLEEP_WAS_INTERRUPTED {
+                let observed = Instant::now();
+                if let Some((wake_call_instant, detached)) = self.claim() {
+                    self.wake_observed.set(Some(observed));
+                    if !detached {
+                        // Unblock Waker::wake()
+                        self.synchronization_point.wait();
+                        trace::event!(backend = "futex", "ack sent");
+                    }
+                    break WakeupReason::Interrupted { wake_call_instant };
+                }
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break WakeupReason::Timeout;
+            }
+
+            // Woken up, timed out, value already changed (EAGAIN), or a
+            // signal arrived (EINTR): in any case, check the state again. This
+            // way, a wake-up that raced with the timeout is still ACKed.
+            let futex = &self.shared_state.sleep_state;
+            let _ = if absolute {
+                futex_wait_bitset(futex, SLEEP_NORMAL, deadline)
+            } else {
+                futex_wait(futex, SLEEP_NORMAL, remaining)
+            };
+        }
+    }
+}
+
+impl Sleeper<Instant> for FutexSleeper {
+    fn sleep_interruptible(&self, sleep_duration: Duration) -> WakeupReason {
+        self.sleep(Instant::now() + sleep_duration, false)
+    }
+
+    /// Uses `FUTEX_WAIT_BITSET`, which takes an absolute `CLOCK_MONOTONIC`
+    /// timeout.
+    fn sleep_until(&self, deadline: Instant) -> WakeupReason {
+        self.sleep(deadline, true)
+    }
+
+    fn wake_observed(&self) -> Option<Instant> {
+        self.wake_observed.get()
+    }
+}
+
+impl Waker for FutexWaker {
+    fn wake(&self) {
+        self.set_interrupted(false);
+        futex_wake_one(&self.shared_state.sleep_state);
+
+        // Wait for Sleeper to ACK
+        self.synchronization_point.wait();
+        trace::event!(backend = "futex", "ack received");
+    }
+
+    /// Wakes the futex without changing the futex word.
+    fn wake_spurious(&self) -> bool {
+        futex_wake_one(&self.shared_state.sleep_state);
+        true
+    }
+
+    /// Sets the futex word like [`Self::wake`]. Wake-ups before the sleeper
+    /// checks the futex word are coalesced into one.
+    fn wake_detached(&self) -> bool {
+        self.set_interrupted(true);
+        futex_wake_one(&self.shared_state.sleep_state);
+        true
+    }
+
+    fn set_handshake(&self, handshake: Handshake) {
+        self.synchronization_point.set_handshake(handshake);
+    }
+}
+
+impl FutexWaker {
+    /// Records the wake-up call and sets the futex word, atomically for the
+    /// sleepers, see [`FutexSleeper::claim`].
+    #[allow(clippy::significant_drop_tightening)]
+    fn set_interrupted(&self, detached: bool) {
+        let mut wake_call_instant = self.shared_state.wake_call_instant.lock().unwrap();
+        *wake_call_instant = Some(Instant::now());
+        self.shared_state
+            .detached
+            .store(detached, Ordering::Release);
+        self.shared_state
+            .sleep_state
+            .store(SLEEP_WAS_INTERRUPTED, Ordering::Release);
+    }
+}
@@ -0,0 +1,190 @@
+//! Implements [`Sleeper`] and [`Waker`] using Linux `io_uring`.
+//!
+//! The sleeper submits a read on an `eventfd` linked with a timeout and waits
+//! for both completions. The waker interrupts the sleep by writing to the
+//! `eventfd`.
+
+use super::monotonic::to_monotonic;
+use crate::synchronization::NoDelayBarrier;
+use crate::trace;
+use crate::{Handshake, Sleeper, Waker, WakeupReason};
+use io_uring::{IoUring, opcode, squeue, types};
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const USER_DATA_READ: u64 = 1;
+const USER_DATA_TIMEOUT: u64 = 2;
+
+#[derive(Debug)]
+struct SharedState {
+    eventfd: File,
+    wake_call_instant: Mutex<Option<Instant>>,
+}
+
+pub struct IoUringSleeper {
+    ring: RefCell<IoUring>,
+    shared_state: Arc<SharedState>,
+    // Helper to synchronize sleep_interruptible() and wake()
+    synchronization_point: Arc<NoDelayBarrier>,
+}
+
+impl std::fmt::Debug for IoUringSleeper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IoUringSleeper")
+            .field("shared_state", &self.shared_state)
+            .field("synchronization_point", &self.synchronization_point)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug)]
+pub struct IoUringWaker {
+    shared_state: Arc<SharedState>,
+    // Helper to synchronize sleep_interruptible() and wake()
+    synchronization_point: Arc<NoDelayBarrier>,
+}
+
+#[must_use]
+pub fn new_pair() -> (IoUringSleeper, IoUringWaker) {
+    let ring = IoUring::new(8).expect("should create io_uring instance");
+    // SAFETY: Plain syscall without pointer arguments.
+    let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC) };
+    assert!(fd >= 0, "should create eventfd: {}", io::Error::last_os_error());
+    // SAFETY: We just created the file descriptor and own it exclusively.
+    let eventfd = unsafe { File::from_raw_fd(fd) };
+
+    let shared_state = Arc::new(SharedState {
+        eventfd,
+        wake_call_instant: Mutex::new(None),
+    });
+    let synchronization_point = Arc::new(NoDelayBarrier::new());
+
+    let sleeper = IoUringSleeper {
+        ring: RefCell::new(ring),
+        shared_state: shared_state.clone(),
+        synchronization_point: synchronization_point.clone(),
+    };
+    let waker = IoUringWaker {
+        shared_state,
+        synchronization_point,
+    };
+
+    (sleeper, waker)
+}
+
+impl IoUringSleeper {
+    /// Sleeps until the timeout, which is absolute (`CLOCK_MONOTONIC`) or
+    /// relative according to `flags`.
+    fn sleep(&self, timeout: types::Timespec, flags: types::TimeoutFlags) -> WakeupReason {
+        let mut ring = self.ring.borrow_mut();
+        let mut buf = [0_u8; 8];
+
+        let read = opcode::Read::new(
+            types::Fd(self.shared_state.eventfd.as_raw_fd()),
+            buf.as_mut_ptr(),
+            buf.len() as u32,
+        )
+        .build()
+        .flags(squeue::Flags::IO_LINK)
+        .user_data(USER_DATA_READ);
+        let link_timeout = opcode::LinkTimeout::new(&raw const timeout)
+            .flags(flags)
+            .build()
+            .user_data(USER_DATA_TIMEOUT);
+
+        // SAFETY: The buffer and the timeout outlive the submission as we wait
+        // for both completions below.
+        unsafe {
+            let mut submission = ring.submission();
+            submission.push(&read).expect("should have free entries");
+            submission
+                .push(&link_timeout)
+                .expect("should have free entries");
+        }
+
+        // Wait for the read and the timeout to complete: one of them
+        // succeeds, the other one gets canceled.
+        let mut read_result = None;
+        let mut completions = 0;
+        while completions < 2 {
+            match ring.submit_and_wait(2 - completions) {
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => panic!("io_uring_enter failed: {e}"),
+            }
+            for cqe in ring.completion() {
+                completions += 1;
+                if cqe.user_data() == USER_DATA_READ {
+                    read_result = Some(cqe.result());
+                }
+            }
+        }
+
+        let mut interrupted = read_result == Some(buf.len() as i32);
+        if !interrupted && self.shared_state.wake_call_instant.lock().unwrap().is_some() {
+            // The wake-up raced with the timeout. Consume the pending event,
+            // we must still ACK it, otherwise Waker::wake() waits forever.
+            (&self.shared_state.eventfd)
+                .read_exact(&mut buf)
+                .expect("should read from eventfd");
+            interrupted = true;
+        }
+
+        if interrupted {
+            let reason = WakeupReason::Interrupted {
+                wake_call_instant: self
+                    .shared_state
+                    .wake_call_instant
+                    .lock()
+                    .unwrap()
+                    .take()
+                    .expect("should have been set by wake()"),
+            };
+
+            // Unblock Waker::wake()
+            self.synchronization_point.wait();
+            trace::event!(backend = "io_uring", "ack sent");
+
+            reason
+        } else {
+            WakeupReason::Timeout
+        }
+    }
+}
+
+impl Sleeper<Instant> for IoUringSleeper {
+    fn sleep_interruptible(&self, sleep_duration: Duration) -> WakeupReason {
+        self.sleep(
+            types::Timespec::from(sleep_duration),
+            types::TimeoutFlags::empty(),
+        )
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> WakeupReason {
+        self.sleep(
+            types::Timespec::from(to_monotonic(deadline)),
+            types::TimeoutFlags::ABS,
+        )
+    }
+}
+
+impl Waker for IoUringWaker {
+    fn wake(&self) {
+        *self.shared_state.wake_call_instant.lock().unwrap() = Some(Instant::now());
+        (&self.shared_state.eventfd)
+            .write_all(&1_u64.to_ne_bytes())
+            .expect("should write to eventfd");
+
+        // Wait for Sleeper to ACK
+        self.synchronization_point.wait();
+        trace::event!(backend = "io_uring", "ack received");
+    }
+
+    fn set_handshake(&self, handshake: Handshake) {
+        self.synchronization_point.set_handshake(handshake);
+    }
+}
@@ -0,0 +1,278 @@
+//! Implements [`Sleeper`] and [`Waker`] using `kqueue` with a user-triggered
+//! `EVFILT_USER` event, as available on macOS and the BSDs.
+//!
+//! Absolute deadlines, see [`Sleeper::sleep_until`], are passed to the kernel
+//! as a one-shot `EVFILT_TIMER` event on macOS and FreeBSD. DragonFly has no
+//! absolute timers and converts them into relative timeouts.
+
+use crate::synchronization::NoDelayBarrier;
+use crate::trace;
+use crate::{Handshake, Sleeper, Waker, WakeupReason};
+use std::io;
+use std::mem;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::ptr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const WAKE_EVENT_IDENT: usize = 1;
+#[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+const TIMER_EVENT_IDENT: usize = 2;
+
+/// Flags of an `EVFILT_TIMER` event that expires at an absolute
+/// `CLOCK_REALTIME` time in nanoseconds.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+const ABSOLUTE_TIMER_FFLAGS: u32 = libc::NOTE_ABSOLUTE | libc::NOTE_NSECONDS;
+#[cfg(target_os = "freebsd")]
+const ABSOLUTE_TIMER_FFLAGS: u32 = libc::NOTE_ABSTIME | libc::NOTE_NSECONDS;
+
+#[derive(Debug)]
+struct SharedState {
+    kqueue: OwnedFd,
+    wake_call_instant: Mutex<Option<Instant>>,
+}
+
+#[derive(Debug)]
+pub struct KqueueSleeper {
+    shared_state: Arc<SharedState>,
+    // Helper to synchronize sleep_interruptible() and wake()
+    synchronization_point: Arc<NoDelayBarrier>,
+}
+
+#[derive(Debug)]
+pub struct KqueueWaker {
+    shared_state: Arc<SharedState>,
+    // Helper to synchronize sleep_interruptible() and wake()
+    synchronization_point: Arc<NoDelayBarrier>,
+}
+
+fn user_event(flags: u16, fflags: u32) -> libc::kevent {
+    // SAFETY: `kevent` is a plain C struct for which all zeroes is valid.
+    let mut event: libc::kevent = unsafe { mem::zeroed() };
+    event.ident = WAKE_EVENT_IDENT as _;
+    event.filter = libc::EVFILT_USER;
+    event.flags = flags as _;
+    event.fflags = fflags;
+    event
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+fn timer_event(flags: u16, realtime_nanos: i64) -> libc::kevent {
+    // SAFETY: `kevent` is a plain C struct for which all zeroes is valid.
+    let mut event: libc::kevent = unsafe { mem::zeroed() };
+    event.ident = TIMER_EVENT_IDENT as _;
+    event.filter = libc::EVFILT_TIMER;
+    event.flags = flags as _;
+    event.fflags = ABSOLUTE_TIMER_FFLAGS;
+    event.data = realtime_nanos as _;
+    event
+}
+
+/// Returns the `CLOCK_REALTIME` time of `deadline` in nanoseconds.
+#[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+fn to_realtime_nanos(deadline: Instant) -> i64 {
+    let mut now = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: The pointer is valid for the duration of the call.
+    let ret = unsafe { libc::clock_gettime(libc::CLOCK_REALTIME, &raw mut now) };
+    assert_eq!(ret, 0, "CLOCK_REALTIME should be available");
+    let realtime = Duration::new(now.tv_sec as u64, now.tv_nsec as u32)
+        + deadline.saturating_duration_since(Instant::now());
+    i64::try_from(realtime.as_nanos()).unwrap_or(i64::MAX)
+}
+
+/// Applies a single change to the kqueue without receiving events.
+fn kevent_change(kqueue: &OwnedFd, change: &libc::kevent) -> io::Result<()> {
+    // SAFETY: The change list is valid for the duration of the syscall and
+    // the event list is empty.
+    let ret = unsafe {
+        libc::kevent(
+            kqueue.as_raw_fd(),
+            change,
+            1,
+            ptr::null_mut(),
+            0,
+            ptr::null(),
+        )
+    };
+    if ret == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+#[must_use]
+pub fn new_pair() -> (KqueueSleeper, KqueueWaker) {
+    // SAFETY: Plain syscall without pointer arguments.
+    let fd = unsafe { libc::kqueue() };
+    assert!(fd >= 0, "should create kqueue: {}", io::Error::last_os_error());
+    // SAFETY: We just created the file descriptor and own it exclusively.
+    let kqueue = unsafe { OwnedFd::from_raw_fd(fd) };
+    // EV_CLEAR: the event is reset after it was delivered once.
+    kevent_change(&kqueue, &user_event(libc::EV_ADD | libc::EV_CLEAR, 0))
+        .expect("should register EVFILT_USER event");
+
+    let shared_state = Arc::new(SharedState {
+        kqueue,
+        wake_call_instant: Mutex::new(None),
+    });
+    let synchronization_point = Arc::new(NoDelayBarrier::new());
+
+    let sleeper = KqueueSleeper {
+        shared_state: shared_state.clone(),
+        synchronization_point: synchronization_point.clone(),
+    };
+    let waker = KqueueWaker {
+        shared_state,
+        synchronization_point,
+    };
+
+    (sleeper, waker)
+}
+
+impl Sleeper<Instant> for KqueueSleeper {
+    fn sleep_interruptible(&self, sleep_duration: Duration) -> WakeupReason {
+        let deadline = Instant::now() + sleep_duration;
+        let mut wait_forever = false;
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let timeout = libc::timespec {
+                tv_sec: remaining.as_secs() as libc::time_t,
+                tv_nsec: remaining.subsec_nanos() as libc::c_long,
+            };
+            // SAFETY: all-zero is a valid `kevent`.
+            let mut event: libc::kevent = unsafe { mem::zeroed() };
+            let timeout_ptr = if wait_forever {
+                ptr::null()
+            } else {
+                &raw const timeout
+            };
+
+            // SAFETY: The event list and the timeout are valid for the
+            // duration of the syscall.
+            let ret = unsafe {
+                libc::kevent(
+                    self.shared_state.kqueue.as_raw_fd(),
+                    ptr::null(),
+                    0,
+                    &raw mut event,
+                    1,
+                    timeout_ptr,
+                )
+            };
+
+            match ret {
+                0 if self.shared_state.wake_call_instant.lock().unwrap().is_some() => {
+                    // The wake-up raced with the timeout. Consume the pending
+                    // event, we must still ACK it, otherwise Waker::wake()
+                    // waits forever.
+                    wait_forever = true;
+                }
+                0 => break WakeupReason::Timeout,
+                1 => break self.interrupted(),
+                _ => assert_interrupted(),
+            }
+        }
+    }
+
+    /// Arms a one-shot `EVFILT_TIMER` at the deadline, converted once into
+    /// `CLOCK_REALTIME`, hence, it moves with changes of the realtime clock.
+    #[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+    fn sleep_until(&self, deadline: Instant) -> WakeupReason {
+        let kqueue = &self.shared_state.kqueue;
+        kevent_change(
+            kqueue,
+            &timer_event(libc::EV_ADD | libc::EV_ONESHOT, to_realtime_nanos(deadline)),
+        )
+        .expect("should register EVFILT_TIMER event");
+
+        loop {
+            // SAFETY: all-zero is a valid `kevent`.
+            let mut event: libc::kevent = unsafe { mem::zeroed() };
+            // SAFETY: The event list is valid for the duration of the
+            // syscall. Without a timeout, it waits for the timer.
+            let ret = unsafe {
+                libc::kevent(
+                    kqueue.as_raw_fd(),
+                    ptr::null(),
+                    0,
+                    &raw mut event,
+                    1,
+                    ptr::null(),
+                )
+            };
+
+            match ret {
+                1 if event.filter == libc::EVFILT_USER => {
+                    // The timer must not expire during a later sleep. Fails
+                    // if it already expired, which is fine.
+                    let _ = kevent_change(kqueue, &timer_event(libc::EV_DELETE, 0));
+                    break self.interrupted();
+                }
+                // The wake-up raced with the timer. Wait for the pending
+                // event, we must still ACK it, otherwise Waker::wake() waits
+                // forever.
+                1 if self
+                    .shared_state
+                    .wake_call_instant
+                    .lock()
+                    .unwrap()
+                    .is_some() => {}
+                1 => break WakeupReason::Timeout,
+                _ => assert_interrupted(),
+            }
+        }
+    }
+}
+
+impl KqueueSleeper {
+    fn interrupted(&self) -> WakeupReason {
+        let reason = WakeupReason::Interrupted {
+            wake_call_instant: self
+                .shared_state
+                .wake_call_instant
+                .lock()
+                .unwrap()
+                .take()
+                .expect("should have been set by wake()"),
+        };
+
+        // Unblock Waker::wake()
+        self.synchronization_point.wait();
+        trace::event!(backend = "kqueue", "ack sent");
+
+        reason
+    }
+}
+
+/// Checks that `kevent()` failed because of a signal, which is retried.
+fn assert_interrupted() {
+    let err = io::Error::last_os_error();
+    assert!(
+        err.kind() == io::ErrorKind::Interrupted,
+        "kevent failed: {err}"
+    );
+}
+
+impl Waker for KqueueWaker {
+    fn wake(&self) {
+        *self.shared_state.wake_call_instant.lock().unwrap() = Some(Instant::now());
+        kevent_change(
+            &self.shared_state.kqueue,
+            &user_event(0, libc::NOTE_TRIGGER),
+        )
+        .expect("should trigger EVFILT_USER event");
+
+        // Wait for Sleeper to ACK
+        self.synchronization_point.wait();
+        trace::event!(backend = "kqueue", "ack received");
+    }
+
+    fn set_handshake(&self, handshake: Handshake) {
+        self.synchronization_point.set_handshake(handshake);
+    }
+}
@@ -0,0 +1,51 @@
+//! Conversion of an [`Instant`] into an absolute `CLOCK_MONOTONIC` time, for
+//! syscalls with absolute timeouts.
+
+use std::time::{Duration, Instant};
+
+/// Returns the `CLOCK_MONOTONIC` time of `instant`.
+///
+/// [`Instant`] is based on `CLOCK_MONOTONIC` on Linux but doesn't expose its
+/// value. Hence, the offset of `instant` to now is applied to the current
+/// time of the clock.
+pub(crate) fn to_monotonic(instant: Instant) -> Duration {
+    let mut now = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: The pointer is valid for the duration of the call.
+    let ret = unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &raw mut now) };
+    assert_eq!(ret, 0, "CLOCK_MONOTONIC should be available");
+    let now_instant = Instant::now();
+    let now = Duration::new(now.tv_sec as u64, now.tv_nsec as u32);
+
+    instant.checked_duration_since(now_instant).map_or_else(
+        || now.saturating_sub(now_instant - instant),
+        |ahead| now + ahead,
+    )
+}
+
+/// Like [`to_monotonic`] but as a `timespec`.
+pub(crate) fn to_monotonic_timespec(instant: Instant) -> libc::timespec {
+    let monotonic = to_monotonic(instant);
+    libc::timespec {
+        tv_sec: monotonic.as_secs() as libc::time_t,
+        tv_nsec: monotonic.subsec_nanos() as libc::c_long,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert2::check;
+
+    #[test]
+    fn test_to_monotonic() {
+        let now = Instant::now();
+        let later = now + Duration::from_secs(1);
+        let diff = to_monotonic(later) - to_monotonic(now);
+        // Both conversions read the clocks at slightly different times.
+        check!(diff > Duration::from_millis(999));
+        check!(diff < Duration::from_millis(1001));
+    }
+}
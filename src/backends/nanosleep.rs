@@ -0,0 +1,56 @@
+//! A non-interruptible [`Sleeper`] using `clock_nanosleep` with an absolute
+//! `CLOCK_MONOTONIC` deadline: the best the OS timer can do.
+//!
+//! Serves as baseline for the timeouts of the other backends, see
+//! [`baseline`](super::baseline). Only the `timeout` scenario can be used with
+//! it, as the [`Waker`] can't interrupt the sleep.
+
+use super::monotonic::to_monotonic_timespec;
+use crate::{Sleeper, Waker, WakeupReason};
+use std::ptr;
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+pub struct NanosleepSleeper;
+
+#[derive(Debug)]
+pub struct NanosleepWaker;
+
+#[must_use]
+pub const fn new_pair() -> (NanosleepSleeper, NanosleepWaker) {
+    (NanosleepSleeper, NanosleepWaker)
+}
+
+impl Sleeper<Instant> for NanosleepSleeper {
+    fn sleep_interruptible(&self, sleep_duration: Duration) -> WakeupReason {
+        self.sleep_until(Instant::now() + sleep_duration)
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> WakeupReason {
+        let deadline = to_monotonic_timespec(deadline);
+        // With an absolute deadline, the sleep can simply be restarted after
+        // a signal (EINTR).
+        loop {
+            // SAFETY: The deadline is valid for the duration of the call.
+            let ret = unsafe {
+                libc::clock_nanosleep(
+                    libc::CLOCK_MONOTONIC,
+                    libc::TIMER_ABSTIME,
+                    &raw const deadline,
+                    ptr::null_mut(),
+                )
+            };
+            match ret {
+                0 => break WakeupReason::Timeout,
+                libc::EINTR => {}
+                err => panic!("clock_nanosleep failed: {err}"),
+            }
+        }
+    }
+}
+
+impl Waker for NanosleepWaker {
+    fn wake(&self) {
+        panic!("the nanosleep baseline can't be interrupted, use the timeout scenario");
+    }
+}
@@ -0,0 +1,117 @@
+//! Implements [`Sleeper`] and [`Waker`] using [`oneshot`] channels from the
+//! `futures` crate and [`tokio::time::timeout`].
+//!
+//! A oneshot channel carries a single wake-up call. Hence, the sleeper
+//! creates a new channel whenever a wake-up was consumed and hands the sender
+//! to the waker. Like the `tokio` backend, the sleeper drives its own
+//! current-thread runtime.
+
+use crate::synchronization::NoDelayBarrier;
+use crate::trace;
+use crate::{Handshake, Sleeper, Waker, WakeupReason};
+use futures_channel::oneshot;
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::runtime::{Builder, Runtime};
+
+/// The sender for the next wake-up call.
+type SenderSlot = Arc<Mutex<Option<oneshot::Sender<Instant>>>>;
+
+#[derive(Debug)]
+pub struct OneshotSleeper {
+    runtime: Runtime,
+    receiver: RefCell<oneshot::Receiver<Instant>>,
+    sender_slot: SenderSlot,
+    // Helper to synchronize sleep_interruptible() and wake()
+    synchronization_point: Arc<NoDelayBarrier>,
+}
+
+#[derive(Debug)]
+pub struct OneshotWaker {
+    sender_slot: SenderSlot,
+    // Helper to synchronize sleep_interruptible() and wake()
+    synchronization_point: Arc<NoDelayBarrier>,
+}
+
+/// Creates a new channel, puts the sender into the slot, and returns the
+/// receiver.
+fn new_channel(sender_slot: &SenderSlot) -> oneshot::Receiver<Instant> {
+    let (sender, receiver) = oneshot::channel();
+    *sender_slot.lock().unwrap() = Some(sender);
+    receiver
+}
+
+#[must_use]
+pub fn new_pair() -> (OneshotSleeper, OneshotWaker) {
+    let runtime = Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .expect("should create tokio runtime");
+    let sender_slot = SenderSlot::default();
+    let receiver = new_channel(&sender_slot);
+    let synchronization_point = Arc::new(NoDelayBarrier::new());
+
+    let sleeper = OneshotSleeper {
+        runtime,
+        receiver: RefCell::new(receiver),
+        sender_slot: sender_slot.clone(),
+        synchronization_point: synchronization_point.clone(),
+    };
+    let waker = OneshotWaker {
+        sender_slot,
+        synchronization_point,
+    };
+
+    (sleeper, waker)
+}
+
+impl Sleeper<Instant> for OneshotSleeper {
+    fn sleep_interruptible(&self, sleep_duration: Duration) -> WakeupReason {
+        let mut receiver = self.receiver.borrow_mut();
+        // The timer must be created inside the runtime context.
+        let res = self
+            .runtime
+            .block_on(async { tokio::time::timeout(sleep_duration, &mut *receiver).await });
+        let wake_call_instant = match res {
+            Ok(res) => Some(res.expect("the sender should not be dropped")),
+            // If the wake-up raced with the timeout, the instant is already in
+            // the channel. We must still ACK it, otherwise Waker::wake() waits
+            // forever.
+            Err(_elapsed) => receiver
+                .try_recv()
+                .expect("the sender should not be dropped"),
+        };
+
+        let Some(wake_call_instant) = wake_call_instant else {
+            return WakeupReason::Timeout;
+        };
+        *receiver = new_channel(&self.sender_slot);
+
+        // Unblock Waker::wake()
+        self.synchronization_point.wait();
+        trace::event!(backend = "oneshot", "ack sent");
+
+        WakeupReason::Interrupted { wake_call_instant }
+    }
+}
+
+impl Waker for OneshotWaker {
+    fn wake(&self) {
+        let sender = self
+            .sender_slot
+            .lock()
+            .unwrap()
+            .take()
+            .expect("should have a sender, as the previous wake-up was ACKed");
+        sender.send(Instant::now()).unwrap();
+
+        // Wait for Sleeper to ACK
+        self.synchronization_point.wait();
+        trace::event!(backend = "oneshot", "ack received");
+    }
+
+    fn set_handshake(&self, handshake: Handshake) {
+        self.synchronization_point.set_handshake(handshake);
+    }
+}
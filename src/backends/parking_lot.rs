@@ -0,0 +1,164 @@
+//! Implements [`Sleeper`] and [`Waker`] using a Mutex and a Condvar from
+//! the `parking_lot` crate.
+
+use crate::synchronization::NoDelayBarrier;
+use crate::trace;
+use crate::{Handshake, Sleeper, Waker, WakeupReason};
+use parking_lot::{Condvar, Mutex};
+use std::cell::Cell;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const SLEEP_WAS_INTERRUPTED: bool = true;
+const SLEEP_NORMAL: bool = false;
+
+#[derive(Debug)]
+struct SleepWakeContext {
+    sleep_state: bool,
+    wake_call_instant: Option<Instant>,
+    // Whether the wake-up came from wake_detached(), which isn't ACKed
+    detached: bool,
+}
+
+impl Default for SleepWakeContext {
+    fn default() -> Self {
+        Self {
+            sleep_state: SLEEP_NORMAL,
+            wake_call_instant: None,
+            detached: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParkingLotSleeper {
+    shared_state: Arc<(Condvar, Mutex<SleepWakeContext>)>,
+    // Helper to synchronize sleep_interruptible() and wake()
+    synchronization_point: Arc<NoDelayBarrier>,
+    // When the last interrupted sleep observed the wake-up call
+    wake_observed: Cell<Option<Instant>>,
+}
+
+#[derive(Debug)]
+pub struct ParkingLotWaker {
+    shared_state: Arc<(Condvar, Mutex<SleepWakeContext>)>,
+    // Helper to synchronize sleep_interruptible() and wake()
+    synchronization_point: Arc<NoDelayBarrier>,
+}
+
+#[must_use]
+pub fn new_pair() -> (ParkingLotSleeper, ParkingLotWaker) {
+    let mutex = Mutex::new(SleepWakeContext::default());
+    let condvar = Condvar::new();
+    let shared_state = Arc::new((condvar, mutex));
+    let synchronization_point = Arc::new(NoDelayBarrier::new());
+
+    let sleeper = ParkingLotSleeper {
+        shared_state: shared_state.clone(),
+        synchronization_point: synchronization_point.clone(),
+        wake_observed: Cell::new(None),
+    };
+    let waker = ParkingLotWaker {
+        shared_state,
+        synchronization_point,
+    };
+
+    (sleeper, waker)
+}
+
+/// Creates `sleepers` sleepers that wait on the same condvar. A wake-up
+/// notifies one of them; the first one to lock the mutex consumes it.
+#[must_use]
+pub fn new_group(sleepers: usize) -> (Vec<ParkingLotSleeper>, ParkingLotWaker) {
+    let (sleeper, waker) = new_pair();
+    let sleepers = (0..sleepers)
+        .map(|_| ParkingLotSleeper {
+            shared_state: sleeper.shared_state.clone(),
+            synchronization_point: sleeper.synchronization_point.clone(),
+            wake_observed: Cell::new(None),
+        })
+        .collect();
+    (sleepers, waker)
+}
+
+impl Sleeper<Instant> for ParkingLotSleeper {
+    #[allow(clippy::significant_drop_tightening)]
+    fn sleep_interruptible(&self, sleep_duration: Duration) -> WakeupReason {
+        let deadline = Instant::now() + sleep_duration;
+        let mut guard = self.shared_state.1.lock();
+
+        // parking_lot's Condvar has no spurious wakeups of its own, but
+        // wake_spurious() injects them.
+        loop {
+            if guard.sleep_state == SLEEP_WAS_INTERRUPTED {
+                self.wake_observed.set(Some(Instant::now()));
+                let wakeup_reason = WakeupReason::Interrupted {
+                    wake_call_instant: guard
+                        .wake_call_instant
+                        .take()
+                        .expect("should have been set by wake()"),
+                };
+                // Reset
+                guard.sleep_state = SLEEP_NORMAL;
+
+                if !guard.detached {
+                    // Unblock Waker::wake()
+                    self.synchronization_point.wait();
+                    trace::event!(backend = "parking_lot", "ack sent");
+                }
+
+                break wakeup_reason;
+            }
+
+            let res = self.shared_state.0.wait_until(&mut guard, deadline);
+            // If the wake-up raced with the timeout, we must still ACK it,
+            // otherwise Waker::wake() waits forever.
+            if res.timed_out() && guard.sleep_state != SLEEP_WAS_INTERRUPTED {
+                break WakeupReason::Timeout;
+            }
+        }
+    }
+
+    fn wake_observed(&self) -> Option<Instant> {
+        self.wake_observed.get()
+    }
+}
+
+impl Waker for ParkingLotWaker {
+    fn wake(&self) {
+        let mut guard = self.shared_state.1.lock();
+        guard.sleep_state = SLEEP_WAS_INTERRUPTED;
+        guard.wake_call_instant = Some(Instant::now());
+        guard.detached = false;
+        self.shared_state.0.notify_one();
+        drop(guard);
+
+        // Wait for Sleeper to ACK
+        self.synchronization_point.wait();
+        trace::event!(backend = "parking_lot", "ack received");
+    }
+
+    /// Notifies the condvar without setting the state.
+    fn wake_spurious(&self) -> bool {
+        let guard = self.shared_state.1.lock();
+        self.shared_state.0.notify_one();
+        drop(guard);
+        true
+    }
+
+    /// Sets the state like [`Self::wake`]. Wake-ups before the sleeper
+    /// checks the state are coalesced into one.
+    fn wake_detached(&self) -> bool {
+        let mut guard = self.shared_state.1.lock();
+        guard.sleep_state = SLEEP_WAS_INTERRUPTED;
+        guard.wake_call_instant = Some(Instant::now());
+        guard.detached = true;
+        self.shared_state.0.notify_one();
+        drop(guard);
+        true
+    }
+
+    fn set_handshake(&self, handshake: Handshake) {
+        self.synchronization_point.set_handshake(handshake);
+    }
+}
@@ -0,0 +1,207 @@
+//! Implements [`Sleeper`] and [`Waker`] on top of a POSIX counting semaphore
+//! (`sem_post(3)` and `sem_timedwait(3)`).
+//!
+//! Unlike the flag-based backends, each wake-up is a token: wake-ups don't
+//! coalesce, the waker never blocks, and each token makes a later sleep
+//! return immediately.
+
+use crate::synchronization::NoDelayBarrier;
+use crate::trace;
+use crate::{Handshake, Sleeper, Waker, WakeupReason};
+use std::cell::{Cell, UnsafeCell};
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Message of a wake-up call, one per token of the semaphore.
+#[derive(Debug)]
+struct WakeCall {
+    instant: Instant,
+    // Whether the wake-up came from wake_detached(), which isn't ACKed
+    detached: bool,
+}
+
+struct SharedState {
+    sem: UnsafeCell<libc::sem_t>,
+    // The wake-up calls of the tokens, in the order of sem_post()
+    wake_calls: Mutex<VecDeque<WakeCall>>,
+}
+
+// SAFETY: The semaphore is only accessed through the sem_* functions, which
+// are thread-safe, and it never moves, as it lives in an `Arc`.
+unsafe impl Send for SharedState {}
+// SAFETY: See above.
+unsafe impl Sync for SharedState {}
+
+impl std::fmt::Debug for SharedState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedState")
+            .field("wake_calls", &self.wake_calls)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SharedState {
+    fn post(&self, detached: bool) {
+        // Queued first: a successful sem_wait() always finds its wake-up call.
+        self.wake_calls.lock().unwrap().push_back(WakeCall {
+            instant: Instant::now(),
+            detached,
+        });
+        // SAFETY: The semaphore was initialized in new_pair().
+        unsafe { libc::sem_post(self.sem.get()) };
+    }
+
+    /// Takes a token, blocking at most until the absolute `CLOCK_REALTIME`
+    /// time `deadline`.
+    fn timed_wait(&self, deadline: &libc::timespec) -> io::Result<()> {
+        // SAFETY: The semaphore was initialized in new_pair() and the
+        // deadline is valid for the duration of the call.
+        let ret = unsafe { libc::sem_timedwait(self.sem.get(), deadline) };
+        if ret == -1 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Takes a token if there is one.
+    fn try_wait(&self) -> bool {
+        // SAFETY: The semaphore was initialized in new_pair().
+        unsafe { libc::sem_trywait(self.sem.get()) == 0 }
+    }
+}
+
+impl Drop for SharedState {
+    fn drop(&mut self) {
+        // SAFETY: Nobody waits on the semaphore anymore.
+        unsafe { libc::sem_destroy(self.sem.get()) };
+    }
+}
+
+#[derive(Debug)]
+pub struct SemaphoreSleeper {
+    shared_state: Arc<SharedState>,
+    // Helper to synchronize sleep_interruptible() and wake()
+    synchronization_point: Arc<NoDelayBarrier>,
+    // When the last interrupted sleep observed the wake-up call
+    wake_observed: Cell<Option<Instant>>,
+}
+
+#[derive(Debug)]
+pub struct SemaphoreWaker {
+    shared_state: Arc<SharedState>,
+    // Helper to synchronize sleep_interruptible() and wake()
+    synchronization_point: Arc<NoDelayBarrier>,
+}
+
+#[must_use]
+pub fn new_pair() -> (SemaphoreSleeper, SemaphoreWaker) {
+    let shared_state = Arc::new(SharedState {
+        // SAFETY: All-zero is a valid bit pattern; sem_init() initializes it.
+        sem: UnsafeCell::new(unsafe { std::mem::zeroed() }),
+        wake_calls: Mutex::new(VecDeque::new()),
+    });
+    // SAFETY: The semaphore is shared between the threads of this process
+    // only and doesn't move anymore.
+    let ret = unsafe { libc::sem_init(shared_state.sem.get(), 0, 0) };
+    assert_eq!(ret, 0, "sem_init should succeed");
+    let synchronization_point = Arc::new(NoDelayBarrier::new());
+
+    let sleeper = SemaphoreSleeper {
+        shared_state: shared_state.clone(),
+        synchronization_point: synchronization_point.clone(),
+        wake_observed: Cell::new(None),
+    };
+    let waker = SemaphoreWaker {
+        shared_state,
+        synchronization_point,
+    };
+
+    (sleeper, waker)
+}
+
+/// Returns the `CLOCK_REALTIME` time of `deadline`, as `sem_timedwait` only
+/// takes absolute realtime timeouts.
+fn to_realtime_timespec(deadline: Instant) -> libc::timespec {
+    let mut now = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: The pointer is valid for the duration of the call.
+    let ret = unsafe { libc::clock_gettime(libc::CLOCK_REALTIME, &raw mut now) };
+    assert_eq!(ret, 0, "CLOCK_REALTIME should be available");
+    let realtime = Duration::new(now.tv_sec as u64, now.tv_nsec as u32)
+        + deadline.saturating_duration_since(Instant::now());
+    libc::timespec {
+        tv_sec: realtime.as_secs() as libc::time_t,
+        tv_nsec: realtime.subsec_nanos() as libc::c_long,
+    }
+}
+
+impl SemaphoreSleeper {
+    fn interrupted(&self) -> WakeupReason {
+        self.wake_observed.set(Some(Instant::now()));
+        let wake_call = self
+            .shared_state
+            .wake_calls
+            .lock()
+            .unwrap()
+            .pop_front()
+            .expect("should have been queued by post()");
+        if !wake_call.detached {
+            // Unblock Waker::wake()
+            self.synchronization_point.wait();
+            trace::event!(backend = "semaphore", "ack sent");
+        }
+        WakeupReason::Interrupted {
+            wake_call_instant: wake_call.instant,
+        }
+    }
+}
+
+impl Sleeper<Instant> for SemaphoreSleeper {
+    fn sleep_interruptible(&self, sleep_duration: Duration) -> WakeupReason {
+        let deadline = Instant::now() + sleep_duration;
+        // Converted once, so a change of the realtime clock during the sleep
+        // doesn't move the deadline back and forth.
+        let realtime_deadline = to_realtime_timespec(deadline);
+        loop {
+            match self.shared_state.timed_wait(&realtime_deadline) {
+                Ok(()) => break self.interrupted(),
+                Err(e) if e.raw_os_error() == Some(libc::EINTR) => {}
+                // If the wake-up raced with the timeout, the token is already
+                // there. We must still ACK it, otherwise Waker::wake() waits
+                // forever.
+                Err(_) if self.shared_state.try_wait() => break self.interrupted(),
+                Err(_) => break WakeupReason::Timeout,
+            }
+        }
+    }
+
+    fn wake_observed(&self) -> Option<Instant> {
+        self.wake_observed.get()
+    }
+}
+
+impl Waker for SemaphoreWaker {
+    fn wake(&self) {
+        self.shared_state.post(false);
+
+        // Wait for Sleeper to ACK
+        self.synchronization_point.wait();
+        trace::event!(backend = "semaphore", "ack received");
+    }
+
+    /// Adds a token. Tokens accumulate: each one interrupts a sleep of its
+    /// own, even if it was posted before the sleep began.
+    fn wake_detached(&self) -> bool {
+        self.shared_state.post(true);
+        true
+    }
+
+    fn set_handshake(&self, handshake: Handshake) {
+        self.synchronization_point.set_handshake(handshake);
+    }
+}
@@ -0,0 +1,130 @@
+//! Implements [`Sleeper`] and [`Waker`] using an [`Event`] from the
+//! `event-listener` crate raced against a [`Timer`] on the smol runtime.
+//!
+//! The sleeper drives the future with [`smol::block_on`], which also drives
+//! the reactor of the timers. This is the smol counterpart of the `tokio`
+//! backend.
+
+use crate::synchronization::NoDelayBarrier;
+use crate::trace;
+use crate::{Handshake, Sleeper, Waker, WakeupReason};
+use event_listener::Event;
+use smol::{Timer, future};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Default)]
+struct SharedState {
+    event: Event,
+    wake_call_instant: Mutex<Option<Instant>>,
+}
+
+#[derive(Debug)]
+pub struct SmolSleeper {
+    shared_state: Arc<SharedState>,
+    // Helper to synchronize sleep_interruptible() and wake()
+    synchronization_point: Arc<NoDelayBarrier>,
+}
+
+#[derive(Debug)]
+pub struct SmolWaker {
+    shared_state: Arc<SharedState>,
+    // Helper to synchronize sleep_interruptible() and wake()
+    synchronization_point: Arc<NoDelayBarrier>,
+}
+
+#[must_use]
+pub fn new_pair() -> (SmolSleeper, SmolWaker) {
+    let shared_state = Arc::new(SharedState::default());
+    let synchronization_point = Arc::new(NoDelayBarrier::new());
+
+    let sleeper = SmolSleeper {
+        shared_state: shared_state.clone(),
+        synchronization_point: synchronization_point.clone(),
+    };
+    let waker = SmolWaker {
+        shared_state,
+        synchronization_point,
+    };
+
+    (sleeper, waker)
+}
+
+impl Sleeper<Instant> for SmolSleeper {
+    fn sleep_interruptible(&self, sleep_duration: Duration) -> WakeupReason {
+        let deadline = Instant::now() + sleep_duration;
+        let is_woken = || {
+            self.shared_state
+                .wake_call_instant
+                .lock()
+                .unwrap()
+                .is_some()
+        };
+        let interrupted = smol::block_on(async {
+            loop {
+                // Listen before checking the state, so that no notification
+                // gets lost in between.
+                let listener = self.shared_state.event.listen();
+                if is_woken() {
+                    break true;
+                }
+                let notified = async {
+                    listener.await;
+                    true
+                };
+                let timed_out = async {
+                    Timer::at(deadline).await;
+                    false
+                };
+                // Notified by a wake-up or spuriously, see
+                // Waker::wake_spurious(): check the state again. If the
+                // wake-up raced with the timeout, we must still ACK it,
+                // otherwise Waker::wake() waits forever.
+                if !future::or(notified, timed_out).await {
+                    break is_woken();
+                }
+            }
+        });
+
+        if interrupted {
+            let reason = WakeupReason::Interrupted {
+                wake_call_instant: self
+                    .shared_state
+                    .wake_call_instant
+                    .lock()
+                    .unwrap()
+                    .take()
+                    .expect("should have been set by wake()"),
+            };
+
+            // Unblock Waker::wake()
+            self.synchronization_point.wait();
+            trace::event!(backend = "smol", "ack sent");
+
+            reason
+        } else {
+            WakeupReason::Timeout
+        }
+    }
+}
+
+impl Waker for SmolWaker {
+    fn wake(&self) {
+        *self.shared_state.wake_call_instant.lock().unwrap() = Some(Instant::now());
+        self.shared_state.event.notify(1);
+
+        // Wait for Sleeper to ACK
+        self.synchronization_point.wait();
+        trace::event!(backend = "smol", "ack received");
+    }
+
+    /// Notifies the sleeper without a wake-up call.
+    fn wake_spurious(&self) -> bool {
+        self.shared_state.event.notify(1);
+        true
+    }
+
+    fn set_handshake(&self, handshake: Handshake) {
+        self.synchronization_point.set_handshake(handshake);
+    }
+}
@@ -0,0 +1,221 @@
+//! Implements [`Sleeper`] and [`Waker`] using an `SRWLock` and a condition
+//! variable from the Windows API (`SleepConditionVariableSRW` and
+//! `WakeConditionVariable`), without the std library in between. The
+//! `Condvar` of the std library wraps the same primitives.
+
+use super::wait_on_address::timeout_ms;
+use crate::synchronization::NoDelayBarrier;
+use crate::trace;
+use crate::{Handshake, Sleeper, Waker, WakeupReason};
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use windows_sys::Win32::System::Threading::{
+    AcquireSRWLockExclusive, CONDITION_VARIABLE, CONDITION_VARIABLE_INIT, ReleaseSRWLockExclusive,
+    SRWLOCK, SRWLOCK_INIT, SleepConditionVariableSRW, WakeConditionVariable,
+};
+
+const SLEEP_WAS_INTERRUPTED: bool = true;
+const SLEEP_NORMAL: bool = false;
+
+#[derive(Debug, Default)]
+struct SleepWakeContext {
+    sleep_state: bool,
+    wake_call_instant: Option<Instant>,
+    // Whether the wake-up came from wake_detached(), which isn't ACKed
+    detached: bool,
+}
+
+struct SharedState {
+    lock: UnsafeCell<SRWLOCK>,
+    condvar: UnsafeCell<CONDITION_VARIABLE>,
+    // only accessed while `lock` is held
+    context: UnsafeCell<SleepWakeContext>,
+}
+
+// SAFETY: SRW locks and condition variables can be used from any thread, and
+// the context is only accessed while the lock is held.
+#[allow(clippy::non_send_fields_in_send_ty)]
+unsafe impl Send for SharedState {}
+// SAFETY: See above.
+unsafe impl Sync for SharedState {}
+
+impl fmt::Debug for SharedState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedState").finish_non_exhaustive()
+    }
+}
+
+impl SharedState {
+    fn lock(&self) -> Guard<'_> {
+        // SAFETY: The lock is valid and never moved, as it lives in an `Arc`.
+        unsafe { AcquireSRWLockExclusive(self.lock.get()) };
+        Guard { state: self }
+    }
+
+    fn notify_one(&self) {
+        // SAFETY: The condition variable is valid and never moved.
+        unsafe { WakeConditionVariable(self.condvar.get()) };
+    }
+}
+
+/// Holds the lock of the [`SharedState`] and grants access to the context.
+struct Guard<'a> {
+    state: &'a SharedState,
+}
+
+impl Guard<'_> {
+    /// Releases the lock, waits for a notification or the timeout, and
+    /// acquires the lock again. May wake up spuriously.
+    fn wait_timeout(&self, timeout: Duration) {
+        // SAFETY: We hold the lock, which the call releases and re-acquires.
+        unsafe {
+            SleepConditionVariableSRW(
+                self.state.condvar.get(),
+                self.state.lock.get(),
+                timeout_ms(timeout),
+                0,
+            );
+        }
+    }
+}
+
+impl Deref for Guard<'_> {
+    type Target = SleepWakeContext;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: We hold the lock.
+        unsafe { &*self.state.context.get() }
+    }
+}
+
+impl DerefMut for Guard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: We hold the lock exclusively.
+        unsafe { &mut *self.state.context.get() }
+    }
+}
+
+impl Drop for Guard<'_> {
+    fn drop(&mut self) {
+        // SAFETY: We hold the lock.
+        unsafe { ReleaseSRWLockExclusive(self.state.lock.get()) };
+    }
+}
+
+#[derive(Debug)]
+pub struct SrwSleeper {
+    shared_state: Arc<SharedState>,
+    // Helper to synchronize sleep_interruptible() and wake()
+    synchronization_point: Arc<NoDelayBarrier>,
+}
+
+#[derive(Debug)]
+pub struct SrwWaker {
+    shared_state: Arc<SharedState>,
+    // Helper to synchronize sleep_interruptible() and wake()
+    synchronization_point: Arc<NoDelayBarrier>,
+}
+
+#[must_use]
+pub fn new_pair() -> (SrwSleeper, SrwWaker) {
+    let shared_state = Arc::new(SharedState {
+        lock: UnsafeCell::new(SRWLOCK_INIT),
+        condvar: UnsafeCell::new(CONDITION_VARIABLE_INIT),
+        context: UnsafeCell::new(SleepWakeContext::default()),
+    });
+    let synchronization_point = Arc::new(NoDelayBarrier::new());
+
+    let sleeper = SrwSleeper {
+        shared_state: shared_state.clone(),
+        synchronization_point: synchronization_point.clone(),
+    };
+    let waker = SrwWaker {
+        shared_state,
+        synchronization_point,
+    };
+
+    (sleeper, waker)
+}
+
+impl Sleeper<Instant> for SrwSleeper {
+    #[allow(clippy::significant_drop_tightening)]
+    fn sleep_interruptible(&self, sleep_duration: Duration) -> WakeupReason {
+        let deadline = Instant::now() + sleep_duration;
+        let mut guard = self.shared_state.lock();
+
+        loop {
+            // Checked before the timeout: if the wake-up raced with the
+            // timeout, we must still ACK it, otherwise Waker::wake() waits
+            // forever.
+            if guard.sleep_state == SLEEP_WAS_INTERRUPTED {
+                let wakeup_reason = WakeupReason::Interrupted {
+                    wake_call_instant: guard
+                        .wake_call_instant
+                        .take()
+                        .expect("should have been set by wake()"),
+                };
+                // Reset
+                guard.sleep_state = SLEEP_NORMAL;
+
+                if !guard.detached {
+                    // Unblock Waker::wake()
+                    self.synchronization_point.wait();
+                    trace::event!(backend = "srw", "ack sent");
+                }
+
+                break wakeup_reason;
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break WakeupReason::Timeout;
+            }
+
+            // Woken up, timed out, or spurious wakeup: in any case, check the
+            // state and the deadline again.
+            guard.wait_timeout(remaining);
+        }
+    }
+}
+
+impl Waker for SrwWaker {
+    fn wake(&self) {
+        let mut guard = self.shared_state.lock();
+        guard.sleep_state = SLEEP_WAS_INTERRUPTED;
+        guard.wake_call_instant = Some(Instant::now());
+        guard.detached = false;
+        self.shared_state.notify_one();
+        drop(guard);
+
+        // Wait for Sleeper to ACK
+        self.synchronization_point.wait();
+        trace::event!(backend = "srw", "ack received");
+    }
+
+    /// Notifies the condition variable without setting the state.
+    fn wake_spurious(&self) -> bool {
+        let guard = self.shared_state.lock();
+        self.shared_state.notify_one();
+        drop(guard);
+        true
+    }
+
+    /// Sets the state like [`Self::wake`]. Wake-ups before the sleeper
+    /// checks the state are coalesced into one.
+    fn wake_detached(&self) -> bool {
+        let mut guard = self.shared_state.lock();
+        guard.sleep_state = SLEEP_WAS_INTERRUPTED;
+        guard.wake_call_instant = Some(Instant::now());
+        guard.detached = true;
+        self.shared_state.notify_one();
+        drop(guard);
+        true
+    }
+
+    fn set_handshake(&self, handshake: Handshake) {
+        self.synchronization_point.set_handshake(handshake);
+    }
+}
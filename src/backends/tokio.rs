@@ -0,0 +1,130 @@
+//! Implements [`Sleeper`] and [`Waker`] using [`Notify`] and
+//! [`tokio::time::timeout`] from the tokio runtime.
+//!
+//! The sleeper drives its own current-thread runtime, so the async sleep is
+//! measured from the perspective of a synchronous caller just like all other
+//! backends.
+
+use crate::synchronization::NoDelayBarrier;
+use crate::trace;
+use crate::{Handshake, Sleeper, Waker, WakeupReason};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::runtime::{Builder, Runtime};
+use tokio::sync::Notify;
+
+#[derive(Debug, Default)]
+struct SharedState {
+    notify: Notify,
+    wake_call_instant: Mutex<Option<Instant>>,
+}
+
+#[derive(Debug)]
+pub struct TokioSleeper {
+    runtime: Runtime,
+    shared_state: Arc<SharedState>,
+    // Helper to synchronize sleep_interruptible() and wake()
+    synchronization_point: Arc<NoDelayBarrier>,
+}
+
+#[derive(Debug)]
+pub struct TokioWaker {
+    shared_state: Arc<SharedState>,
+    // Helper to synchronize sleep_interruptible() and wake()
+    synchronization_point: Arc<NoDelayBarrier>,
+}
+
+#[must_use]
+pub fn new_pair() -> (TokioSleeper, TokioWaker) {
+    let runtime = Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .expect("should create tokio runtime");
+    let shared_state = Arc::new(SharedState::default());
+    let synchronization_point = Arc::new(NoDelayBarrier::new());
+
+    let sleeper = TokioSleeper {
+        runtime,
+        shared_state: shared_state.clone(),
+        synchronization_point: synchronization_point.clone(),
+    };
+    let waker = TokioWaker {
+        shared_state,
+        synchronization_point,
+    };
+
+    (sleeper, waker)
+}
+
+impl Sleeper<Instant> for TokioSleeper {
+    fn sleep_interruptible(&self, sleep_duration: Duration) -> WakeupReason {
+        let deadline = tokio::time::Instant::now() + sleep_duration;
+        let is_woken = || {
+            self.shared_state
+                .wake_call_instant
+                .lock()
+                .unwrap()
+                .is_some()
+        };
+        // The timer must be created inside the runtime context.
+        let interrupted = self.runtime.block_on(async {
+            loop {
+                let notified = self.shared_state.notify.notified();
+                match tokio::time::timeout_at(deadline, notified).await {
+                    Ok(()) if is_woken() => break true,
+                    // Spurious notification, see Waker::wake_spurious().
+                    Ok(()) => {}
+                    // If the wake-up raced with the timeout, consume the
+                    // pending notification: we must still ACK it, otherwise
+                    // Waker::wake() waits forever.
+                    Err(_elapsed) if is_woken() => {
+                        self.shared_state.notify.notified().await;
+                        break true;
+                    }
+                    Err(_elapsed) => break false,
+                }
+            }
+        });
+
+        if interrupted {
+            let reason = WakeupReason::Interrupted {
+                wake_call_instant: self
+                    .shared_state
+                    .wake_call_instant
+                    .lock()
+                    .unwrap()
+                    .take()
+                    .expect("should have been set by wake()"),
+            };
+
+            // Unblock Waker::wake()
+            self.synchronization_point.wait();
+            trace::event!(backend = "tokio", "ack sent");
+
+            reason
+        } else {
+            WakeupReason::Timeout
+        }
+    }
+}
+
+impl Waker for TokioWaker {
+    fn wake(&self) {
+        *self.shared_state.wake_call_instant.lock().unwrap() = Some(Instant::now());
+        self.shared_state.notify.notify_one();
+
+        // Wait for Sleeper to ACK
+        self.synchronization_point.wait();
+        trace::event!(backend = "tokio", "ack received");
+    }
+
+    /// Notifies the sleeper without a wake-up call.
+    fn wake_spurious(&self) -> bool {
+        self.shared_state.notify.notify_one();
+        true
+    }
+
+    fn set_handshake(&self, handshake: Handshake) {
+        self.synchronization_point.set_handshake(handshake);
+    }
+}
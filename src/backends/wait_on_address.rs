@@ -0,0 +1,136 @@
+//! Implements [`Sleeper`] and [`Waker`] using `WaitOnAddress` and
+//! `WakeByAddressSingle` from the Windows API, the Windows equivalent of a
+//! futex.
+
+use crate::synchronization::NoDelayBarrier;
+use crate::trace;
+use crate::{Handshake, Sleeper, Waker, WakeupReason};
+use std::ffi::c_void;
+use std::mem;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use windows_sys::Win32::System::Threading::{WaitOnAddress, WakeByAddressSingle};
+
+const SLEEP_WAS_INTERRUPTED: u32 = 1;
+const SLEEP_NORMAL: u32 = 0;
+
+#[derive(Debug)]
+struct SharedState {
+    // address to wait on, either SLEEP_NORMAL or SLEEP_WAS_INTERRUPTED
+    sleep_state: AtomicU32,
+    wake_call_instant: Mutex<Option<Instant>>,
+}
+
+#[derive(Debug)]
+pub struct WaitOnAddressSleeper {
+    shared_state: Arc<SharedState>,
+    // Helper to synchronize sleep_interruptible() and wake()
+    synchronization_point: Arc<NoDelayBarrier>,
+}
+
+#[derive(Debug)]
+pub struct WaitOnAddressWaker {
+    shared_state: Arc<SharedState>,
+    // Helper to synchronize sleep_interruptible() and wake()
+    synchronization_point: Arc<NoDelayBarrier>,
+}
+
+#[must_use]
+pub fn new_pair() -> (WaitOnAddressSleeper, WaitOnAddressWaker) {
+    let shared_state = Arc::new(SharedState {
+        sleep_state: AtomicU32::new(SLEEP_NORMAL),
+        wake_call_instant: Mutex::new(None),
+    });
+    let synchronization_point = Arc::new(NoDelayBarrier::new());
+
+    let sleeper = WaitOnAddressSleeper {
+        shared_state: shared_state.clone(),
+        synchronization_point: synchronization_point.clone(),
+    };
+    let waker = WaitOnAddressWaker {
+        shared_state,
+        synchronization_point,
+    };
+
+    (sleeper, waker)
+}
+
+/// Converts the timeout to milliseconds, the granularity of the Windows API.
+///
+/// Rounds up so that we never wake up before the deadline.
+pub(super) fn timeout_ms(timeout: Duration) -> u32 {
+    let ms = timeout.as_nanos().div_ceil(1_000_000);
+    // u32::MAX is INFINITE
+    ms.min(u128::from(u32::MAX - 1)) as u32
+}
+
+impl Sleeper<Instant> for WaitOnAddressSleeper {
+    fn sleep_interruptible(&self, sleep_duration: Duration) -> WakeupReason {
+        let deadline = Instant::now() + sleep_duration;
+        let compare = SLEEP_NORMAL;
+
+        loop {
+            if self.shared_state.sleep_state.load(Ordering::Acquire) == SLEEP_WAS_INTERRUPTED {
+                let wakeup_reason = WakeupReason::Interrupted {
+                    wake_call_instant: self
+                        .shared_state
+                        .wake_call_instant
+                        .lock()
+                        .unwrap()
+                        .take()
+                        .expect("should have been set by wake()"),
+                };
+                // Reset
+                self.shared_state
+                    .sleep_state
+                    .store(SLEEP_NORMAL, Ordering::Release);
+
+                // Unblock Waker::wake()
+                self.synchronization_point.wait();
+                trace::event!(backend = "wait_on_address", "ack sent");
+
+                break wakeup_reason;
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break WakeupReason::Timeout;
+            }
+
+            // Woken up, timed out, or spurious wakeup: in any case, check the
+            // state again. This way, a wake-up that raced with the timeout is
+            // still ACKed.
+            // SAFETY: Both addresses are valid for the duration of the call.
+            unsafe {
+                WaitOnAddress(
+                    self.shared_state.sleep_state.as_ptr().cast::<c_void>(),
+                    (&raw const compare).cast::<c_void>(),
+                    mem::size_of::<u32>(),
+                    timeout_ms(remaining),
+                );
+            }
+        }
+    }
+}
+
+impl Waker for WaitOnAddressWaker {
+    fn wake(&self) {
+        *self.shared_state.wake_call_instant.lock().unwrap() = Some(Instant::now());
+        self.shared_state
+            .sleep_state
+            .store(SLEEP_WAS_INTERRUPTED, Ordering::Release);
+        // SAFETY: The address is valid for the duration of the call.
+        unsafe {
+            WakeByAddressSingle(self.shared_state.sleep_state.as_ptr().cast::<c_void>());
+        }
+
+        // Wait for Sleeper to ACK
+        self.synchronization_point.wait();
+        trace::event!(backend = "wait_on_address", "ack received");
+    }
+
+    fn set_handshake(&self, handshake: Handshake) {
+        self.synchronization_point.set_handshake(handshake);
+    }
+}
@@ -0,0 +1,147 @@
+//! Scheduler traces of slow rounds. See [`SlowRounds`].
+//!
+//! Tail outliers can't be explained after the fact: the delay of a round
+//! says nothing about what the CPUs did in the meantime. While
+//! [`SlowRounds`] is active, the kernel records the scheduler events of all
+//! CPUs in the ftrace ring buffer, which keeps only the most recent events.
+//! [`CaptureSink`] saves the buffer whenever the delay of a round exceeds
+//! the threshold, so each trace shows who ran instead of the sleeper. Linux
+//! only; requires root.
+
+use crate::Measurement;
+use crate::runner::MeasurementSink;
+use crate::threads::ThreadIds;
+use crate::tracefs::SchedTrace;
+use std::fs;
+use std::io;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Maximum number of traces saved per run, so an overloaded system doesn't
+/// fill the disk.
+pub const MAX_TRACES: usize = 10;
+
+/// The active capture: the scheduler trace, the threshold of the delay, and
+/// the directory of the saved traces.
+#[derive(Debug)]
+pub struct SlowRounds {
+    trace: SchedTrace,
+    threshold: Duration,
+    dir: PathBuf,
+}
+
+impl SlowRounds {
+    /// Starts to record the scheduler events, see [`SchedTrace::start`], and
+    /// creates `dir`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the tracefs is not available or `dir` can't be created.
+    pub fn start(threshold: Duration, dir: PathBuf) -> io::Result<Self> {
+        let trace = SchedTrace::start()?;
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            trace,
+            threshold,
+            dir,
+        })
+    }
+
+    #[must_use]
+    pub const fn threshold(&self) -> Duration {
+        self.threshold
+    }
+
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns a sink that captures the slow rounds of a run of `backend`
+    /// and passes all measurements on to `inner`. The events before the run
+    /// are discarded.
+    pub fn sink<S: MeasurementSink>(
+        &self,
+        inner: S,
+        backend: &str,
+        timeslice: Duration,
+    ) -> CaptureSink<'_, S> {
+        // A failure surfaces when saving.
+        let _ = self.trace.clear();
+        CaptureSink {
+            inner,
+            slow_rounds: self,
+            prefix: format!("{backend}-{}us", timeslice.as_micros()),
+            round: 0,
+            traces: Vec::new(),
+        }
+    }
+}
+
+/// A [`MeasurementSink`] that saves the scheduler trace after each slow
+/// round and passes the measurements on to `inner`.
+///
+/// At most [`MAX_TRACES`] traces are saved. They are named after the
+/// backend, the timeslice, and the round, e.g.,
+/// `condvar-10000us-round42.txt`. The run stops when saving fails.
+#[derive(Debug)]
+pub struct CaptureSink<'a, S> {
+    inner: S,
+    slow_rounds: &'a SlowRounds,
+    /// File name prefix of the traces.
+    prefix: String,
+    /// The number of the current round, counting skipped ones.
+    round: usize,
+    traces: Vec<PathBuf>,
+}
+
+impl<S> CaptureSink<'_, S> {
+    /// Returns the saved traces.
+    #[must_use]
+    pub fn traces(&self) -> &[PathBuf] {
+        &self.traces
+    }
+
+    #[must_use]
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: MeasurementSink> MeasurementSink for CaptureSink<'_, S> {
+    fn record(&mut self, measurement: Measurement) -> ControlFlow<()> {
+        let round = self.round;
+        self.round += 1;
+        if measurement.wakeup_context.delay > self.slow_rounds.threshold
+            && self.traces.len() < MAX_TRACES
+        {
+            let path = self
+                .slow_rounds
+                .dir
+                .join(format!("{}-round{round}.txt", self.prefix));
+            if let Err(e) = self.slow_rounds.trace.save(&path) {
+                self.inner.fail(format!(
+                    "saving the trace of round {round} to {}: {e}",
+                    path.display()
+                ));
+                return ControlFlow::Break(());
+            }
+            self.traces.push(path);
+        }
+        self.inner.record(measurement)
+    }
+
+    fn skip(&mut self) {
+        self.round += 1;
+        self.inner.skip();
+    }
+
+    fn threads(&mut self, threads: ThreadIds) {
+        self.inner.threads(threads);
+    }
+
+    fn fail(&mut self, reason: String) {
+        self.inner.fail(reason);
+    }
+}
@@ -0,0 +1,83 @@
+//! Time sources for the measurements. See [`Clock`].
+
+use std::sync::OnceLock;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// Source of time for the control and the sleeper thread.
+///
+/// The harness never calls [`Instant::now`] or [`std::thread::sleep`]
+/// directly but always goes through a [`Clock`]. This way, tests can replace
+/// the wall-clock time with a deterministic virtual time.
+///
+/// See [`SystemClock`] and [`VirtualClock`].
+///
+/// [`VirtualClock`]: crate::virtual_time::VirtualClock
+pub trait Clock {
+    /// Returns the current point in time.
+    fn now(&self) -> Instant;
+
+    /// Blocks the current thread until the given point in time, as precisely
+    /// as possible.
+    fn sleep_until(&self, deadline: Instant);
+
+    /// Blocks the current thread for the given duration, as precisely as
+    /// possible.
+    fn sleep(&self, duration: Duration) {
+        self.sleep_until(self.now() + duration);
+    }
+}
+
+/// The real wall-clock time of the system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    /// Optimized sleep that won't have any delay close to the target deadline
+    /// due to busy waiting.
+    ///
+    /// The thread sleeps until [`safe_sleep_margin`] before the deadline and
+    /// busy waits for the rest. Deadlines closer than the margin are busy
+    /// waited entirely.
+    fn sleep_until(&self, deadline: Instant) {
+        let margin = safe_sleep_margin();
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining > margin {
+            sleep(remaining - margin);
+        }
+
+        // busy waiting to not lose a deadline
+        while Instant::now() < deadline {}
+    }
+}
+
+/// Returns how long before a deadline [`SystemClock`] stops sleeping and
+/// starts busy waiting.
+///
+/// The margin is calibrated once per process: it is twice the maximum
+/// overshoot observed for a number of short sleeps, bounded to
+/// `20µs..=2ms`. A fixed margin is either too small for slow systems or wastes
+/// the whole timeslice in busy waiting on fast systems with short timeslices.
+pub fn safe_sleep_margin() -> Duration {
+    const SAMPLES: usize = 32;
+    const SAMPLE_SLEEP: Duration = Duration::from_micros(100);
+    const MIN: Duration = Duration::from_micros(20);
+    const MAX: Duration = Duration::from_millis(2);
+    static MARGIN: OnceLock<Duration> = OnceLock::new();
+
+    *MARGIN.get_or_init(|| {
+        let max_overshoot = (0..SAMPLES)
+            .map(|_| {
+                let begin = Instant::now();
+                sleep(SAMPLE_SLEEP);
+                begin.elapsed().saturating_sub(SAMPLE_SLEEP)
+            })
+            .max()
+            .unwrap_or(MAX);
+        (max_overshoot * 2).clamp(MIN, MAX)
+    })
+}
@@ -0,0 +1,199 @@
+//! Resolution and call overhead of the clocks of the machine. See
+//! [`ClockSource`] and [`measure`].
+//!
+//! Wake-up delays of a few microseconds are only meaningful if the clock that
+//! measures them is much finer and much cheaper to read.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Number of distinct consecutive readings to determine the resolution.
+const RESOLUTION_SAMPLES: usize = 1000;
+
+/// How long the TSC is calibrated against [`Instant`].
+#[cfg(target_arch = "x86_64")]
+const TSC_CALIBRATION: Duration = Duration::from_millis(50);
+
+/// A clock that can be benchmarked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockSource {
+    /// [`Instant::now`], the clock of the harness.
+    Instant,
+    /// `clock_gettime(CLOCK_MONOTONIC)`
+    #[cfg(unix)]
+    Monotonic,
+    /// `clock_gettime(CLOCK_MONOTONIC_RAW)`, not subject to NTP adjustments.
+    #[cfg(target_os = "linux")]
+    MonotonicRaw,
+    /// The time stamp counter of the CPU (`rdtsc`), calibrated against
+    /// [`Instant`].
+    #[cfg(target_arch = "x86_64")]
+    Rdtsc,
+}
+
+impl ClockSource {
+    /// All clocks of this platform.
+    pub const ALL: &[Self] = &[
+        Self::Instant,
+        #[cfg(unix)]
+        Self::Monotonic,
+        #[cfg(target_os = "linux")]
+        Self::MonotonicRaw,
+        #[cfg(target_arch = "x86_64")]
+        Self::Rdtsc,
+    ];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Instant => "Instant::now",
+            #[cfg(unix)]
+            Self::Monotonic => "CLOCK_MONOTONIC",
+            #[cfg(target_os = "linux")]
+            Self::MonotonicRaw => "CLOCK_MONOTONIC_RAW",
+            #[cfg(target_arch = "x86_64")]
+            Self::Rdtsc => "rdtsc",
+        }
+    }
+
+    /// Returns the resolution the OS reports for the clock, if any.
+    #[must_use]
+    pub fn reported_resolution(self) -> Option<Duration> {
+        match self {
+            #[cfg(unix)]
+            Self::Monotonic => clock_getres(libc::CLOCK_MONOTONIC),
+            #[cfg(target_os = "linux")]
+            Self::MonotonicRaw => clock_getres(libc::CLOCK_MONOTONIC_RAW),
+            _ => None,
+        }
+    }
+
+    /// Returns a function that reads the clock in ticks.
+    fn reader(self) -> fn() -> u64 {
+        match self {
+            Self::Instant => read_instant,
+            #[cfg(unix)]
+            Self::Monotonic => || clock_gettime(libc::CLOCK_MONOTONIC),
+            #[cfg(target_os = "linux")]
+            Self::MonotonicRaw => || clock_gettime(libc::CLOCK_MONOTONIC_RAW),
+            #[cfg(target_arch = "x86_64")]
+            // SAFETY: `rdtsc` is available on all x86_64 CPUs.
+            Self::Rdtsc => || unsafe { core::arch::x86_64::_rdtsc() },
+        }
+    }
+
+    /// Returns the length of a tick in ns.
+    fn tick_ns(self) -> f64 {
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            Self::Rdtsc => calibrate(self.reader()),
+            _ => 1.0,
+        }
+    }
+}
+
+/// Nanoseconds since an arbitrary point in time.
+fn read_instant() -> u64 {
+    static BASE: std::sync::OnceLock<Instant> = std::sync::OnceLock::new();
+    BASE.get_or_init(Instant::now).elapsed().as_nanos() as u64
+}
+
+#[cfg(unix)]
+fn clock_gettime(clock: libc::clockid_t) -> u64 {
+    let mut now = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: The pointer is valid for the duration of the call.
+    let ret = unsafe { libc::clock_gettime(clock, &raw mut now) };
+    assert_eq!(ret, 0, "clock should be available");
+    now.tv_sec as u64 * 1_000_000_000 + now.tv_nsec as u64
+}
+
+#[cfg(unix)]
+fn clock_getres(clock: libc::clockid_t) -> Option<Duration> {
+    let mut res = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: The pointer is valid for the duration of the call.
+    let ret = unsafe { libc::clock_getres(clock, &raw mut res) };
+    (ret == 0).then(|| Duration::new(res.tv_sec as u64, res.tv_nsec as u32))
+}
+
+/// Returns the length of a tick of `read` in ns, measured with [`Instant`].
+#[cfg(target_arch = "x86_64")]
+fn calibrate(read: fn() -> u64) -> f64 {
+    let begin = Instant::now();
+    let begin_ticks = read();
+    while begin.elapsed() < TSC_CALIBRATION {}
+    let ticks = read() - begin_ticks;
+    begin.elapsed().as_nanos() as f64 / ticks as f64
+}
+
+/// Result of [`measure`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClockStats {
+    pub source: ClockSource,
+    /// The smallest observed step between two distinct readings, in ns.
+    pub resolution_ns: f64,
+    /// The mean duration of a reading, in ns.
+    pub overhead_ns: f64,
+}
+
+/// Reads the clock `iterations` times to measure the call overhead, and
+/// until it changed [`RESOLUTION_SAMPLES`] times to measure the resolution.
+#[must_use]
+pub fn measure(source: ClockSource, iterations: usize) -> ClockStats {
+    let read = source.reader();
+    let tick_ns = source.tick_ns();
+
+    let begin = Instant::now();
+    for _ in 0..iterations {
+        black_box(read());
+    }
+    let overhead_ns = begin.elapsed().as_nanos() as f64 / iterations.max(1) as f64;
+
+    let mut min_step = u64::MAX;
+    let mut last = read();
+    for _ in 0..RESOLUTION_SAMPLES {
+        let mut now = read();
+        while now == last {
+            now = read();
+        }
+        min_step = min_step.min(now.saturating_sub(last));
+        last = now;
+    }
+
+    ClockStats {
+        source,
+        resolution_ns: min_step as f64 * tick_ns,
+        overhead_ns,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert2::check;
+
+    #[test]
+    fn test_measure() {
+        for &source in ClockSource::ALL {
+            let stats = measure(source, 1000);
+            check!(stats.source == source);
+            check!(stats.resolution_ns > 0.0);
+            check!(stats.overhead_ns > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_readers_are_monotonic() {
+        for &source in ClockSource::ALL {
+            let read = source.reader();
+            let a = read();
+            let b = read();
+            check!(a <= b, "{}", source.name());
+        }
+    }
+}
@@ -0,0 +1,222 @@
+//! Probes how a backend handles several wake-ups for a single sleep, see
+//! [`probe`].
+//!
+//! [`Waker::wake`] waits for the [`Sleeper`] to acknowledge each wake-up, so
+//! the benchmarks never issue a second wake-up before the first one was
+//! consumed. Real code does. Depending on the backend, such wake-ups are
+//! coalesced into one (e.g., a flag), or each one interrupts a sleep of its
+//! own (e.g., a channel or a semaphore), i.e., later sleeps are skipped. A
+//! counting semaphore accumulates the wake-ups as tokens without blocking
+//! the waker, so the skipped sleeps return immediately.
+
+use crate::{Sleeper, Waker, WakeupReason};
+use std::fmt;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// When the wake-ups are issued, relative to the first sleep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// All wake-ups are issued before the sleeper starts to sleep.
+    BeforeSleep,
+    /// All wake-ups are issued a quarter of the timeslice into the sleep.
+    DuringSleep,
+}
+
+impl Phase {
+    pub const ALL: [Self; 2] = [Self::BeforeSleep, Self::DuringSleep];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::BeforeSleep => "before sleep",
+            Self::DuringSleep => "during sleep",
+        }
+    }
+}
+
+/// Result of a [`probe`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Outcome {
+    pub phase: Phase,
+    /// Number of wake-ups issued.
+    pub wakes: usize,
+    /// Number of sleeps that were interrupted.
+    pub interrupted_sleeps: usize,
+    /// The longest of the skipped sleeps, see [`Self::skipped_sleeps`], or
+    /// `None` if no sleep was skipped. Close to zero if the wake-ups
+    /// accumulated before the sleeps.
+    pub skipped_sleep_max: Option<Duration>,
+    /// Time until all wake-up calls returned, or `None` if they didn't return
+    /// after all sleeps, i.e., the waker deadlocked.
+    pub wake_duration: Option<Duration>,
+}
+
+impl Outcome {
+    /// Number of wake-ups that were merged into another one.
+    #[must_use]
+    pub const fn coalesced(&self) -> usize {
+        self.wakes.saturating_sub(self.interrupted_sleeps)
+    }
+
+    /// Number of sleeps after the first one that returned because of the same
+    /// burst of wake-ups.
+    #[must_use]
+    pub const fn skipped_sleeps(&self) -> usize {
+        self.interrupted_sleeps.saturating_sub(1)
+    }
+
+    #[must_use]
+    pub const fn deadlocked(&self) -> bool {
+        self.wake_duration.is_none()
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<12}: {} wakes, {} coalesced, {} sleeps skipped, ",
+            self.phase.name(),
+            self.wakes,
+            self.coalesced(),
+            self.skipped_sleeps()
+        )?;
+        if let Some(max) = self.skipped_sleep_max {
+            write!(f, "skipped sleeps took <= {} µs, ", max.as_micros())?;
+        }
+        match self.wake_duration {
+            Some(duration) => write!(f, "wake calls took {} µs", duration.as_micros()),
+            None => write!(f, "DEADLOCK: wake calls didn't return"),
+        }
+    }
+}
+
+/// Issues `wakes` detached wake-ups ([`Waker::wake_detached`]) and counts
+/// the sleeps of `timeslice` that are interrupted until the first timeout.
+///
+/// Returns `None` if the backend doesn't support detached wake-ups.
+pub fn probe<W: Waker + Send + 'static>(
+    sleeper: &impl Sleeper<Instant>,
+    waker: W,
+    phase: Phase,
+    wakes: usize,
+    timeslice: Duration,
+) -> Option<Outcome> {
+    let (done_sender, done_receiver) = mpsc::channel();
+    // Not joined: in case of a deadlock, it never finishes.
+    thread::spawn(move || {
+        if phase == Phase::DuringSleep {
+            thread::sleep(timeslice / 4);
+        }
+        let begin = Instant::now();
+        let supported = (0..wakes).all(|_| waker.wake_detached());
+        // The waker is sent back as dropping it may disconnect the sleeper.
+        let _ = done_sender.send((supported.then(|| begin.elapsed()), waker));
+    });
+
+    // A blocking waker continues once the sleeper consumes wake-ups.
+    let done = if phase == Phase::BeforeSleep {
+        done_receiver.recv_timeout(timeslice).ok()
+    } else {
+        None
+    };
+
+    // One sleep more than wake-ups, to see the first timeout.
+    let mut interrupted_sleeps = 0;
+    let mut skipped_sleep_max = None::<Duration>;
+    for _ in 0..=wakes {
+        let begin = Instant::now();
+        match sleeper.sleep_interruptible(timeslice) {
+            WakeupReason::Interrupted { .. } => {
+                if interrupted_sleeps > 0 {
+                    let duration = begin.elapsed();
+                    skipped_sleep_max =
+                        Some(skipped_sleep_max.map_or(duration, |max| max.max(duration)));
+                }
+                interrupted_sleeps += 1;
+            }
+            WakeupReason::Timeout => break,
+        }
+    }
+
+    let wake_duration = match done.or_else(|| done_receiver.recv_timeout(timeslice).ok()) {
+        Some((None, _)) => return None,
+        Some((Some(duration), _)) => Some(duration),
+        None => None,
+    };
+    Some(Outcome {
+        phase,
+        wakes,
+        interrupted_sleeps,
+        skipped_sleep_max,
+        wake_duration,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::{channel, condvar};
+    use assert2::check;
+
+    const TIMESLICE: Duration = Duration::from_millis(50);
+
+    #[test]
+    fn test_condvar_coalesces() {
+        for phase in Phase::ALL {
+            let (sleeper, waker) = condvar::new_pair();
+            let outcome = probe(&sleeper, waker, phase, 3, TIMESLICE).unwrap();
+            if phase == Phase::BeforeSleep {
+                check!(outcome.interrupted_sleeps == 1);
+                check!(outcome.coalesced() == 2);
+                check!(outcome.skipped_sleep_max.is_none());
+            } else {
+                // The sleeper may wake up between two wake-ups and sleep
+                // again in time for the next one.
+                check!(outcome.interrupted_sleeps >= 1);
+                check!(outcome.interrupted_sleeps <= outcome.wakes);
+            }
+            check!(!outcome.deadlocked());
+        }
+    }
+
+    #[test]
+    fn test_channel_skips_sleeps() {
+        for phase in Phase::ALL {
+            let (sleeper, waker) = channel::new_pair();
+            let outcome = probe(&sleeper, waker, phase, 3, TIMESLICE).unwrap();
+            check!(outcome.interrupted_sleeps == 3);
+            check!(outcome.skipped_sleeps() == 2);
+            check!(!outcome.deadlocked());
+        }
+    }
+
+    #[cfg(all(feature = "semaphore", target_os = "linux"))]
+    #[test]
+    fn test_semaphore_accumulates() {
+        for phase in Phase::ALL {
+            let (sleeper, waker) = crate::backends::semaphore::new_pair();
+            let outcome = probe(&sleeper, waker, phase, 3, TIMESLICE).unwrap();
+            check!(outcome.interrupted_sleeps == 3);
+            check!(outcome.coalesced() == 0);
+            // The tokens were there before the later sleeps began.
+            check!(outcome.skipped_sleep_max.unwrap() < TIMESLICE / 10);
+            // Posting a token never blocks.
+            check!(outcome.wake_duration.unwrap() < TIMESLICE / 10);
+        }
+    }
+
+    #[test]
+    fn test_unsupported() {
+        #[derive(Debug)]
+        struct WakeOnly;
+        impl Waker for WakeOnly {
+            fn wake(&self) {}
+        }
+
+        let (sleeper, _) = condvar::new_pair();
+        check!(probe(&sleeper, WakeOnly, Phase::BeforeSleep, 3, TIMESLICE).is_none());
+    }
+}
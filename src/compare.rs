@@ -0,0 +1,314 @@
+//! Differences between two sets of [`RunReport`]s, e.g., of the same machine
+//! before and after a kernel update. See [`compare`].
+
+use crate::analysis::Reason;
+use crate::report::RunReport;
+use crate::stats::Summary;
+use crate::units::format_duration;
+use std::fmt::Write;
+use std::time::Duration;
+
+/// The change of a statistic from the old to the new run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Change {
+    pub old: Duration,
+    pub new: Duration,
+}
+
+impl Change {
+    /// Returns the relative change in percent, positive if the new value is
+    /// larger, i.e., slower.
+    #[must_use]
+    pub fn percent(&self) -> f64 {
+        if self.old.is_zero() {
+            if self.new.is_zero() {
+                0.0
+            } else {
+                f64::INFINITY
+            }
+        } else {
+            (self.new.as_secs_f64() / self.old.as_secs_f64() - 1.0) * 100.0
+        }
+    }
+}
+
+/// The differences between the old and the new run of the same
+/// configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Delta {
+    pub backend: String,
+    pub scenario: String,
+    pub interrupt_percent: u8,
+    pub timeout_mode: String,
+    pub timeslice: Duration,
+    pub repeat: usize,
+    /// The remaining conditions of the runs, see [`conditions`].
+    pub conditions: String,
+    pub reason: Reason,
+    pub mean: Change,
+    pub p50: Change,
+    pub p99: Change,
+}
+
+impl Delta {
+    /// Returns whether the mean, the median, or the p99 delay got worse by
+    /// more than `threshold_percent`.
+    #[must_use]
+    pub fn is_regression(&self, threshold_percent: f64) -> bool {
+        [self.mean, self.p50, self.p99]
+            .iter()
+            .any(|change| change.percent() > threshold_percent)
+    }
+}
+
+/// Returns whether both runs were made with the same configuration: the same
+/// backend, scenario, interrupt percentage, timeout mode, timeslice, repeat,
+/// and the same conditions, see [`conditions`].
+fn same_configuration(a: &RunReport, b: &RunReport) -> bool {
+    a.backend == b.backend
+        && a.scenario == b.scenario
+        && a.interrupt_percent == b.interrupt_percent
+        && a.timeout_mode == b.timeout_mode
+        && a.timeslice == b.timeslice
+        && a.repeat == b.repeat
+        && a.cpu_dma_latency == b.cpu_dma_latency
+        && a.placement == b.placement
+        && a.load == b.load
+        && a.priorities == b.priorities
+        && a.detached_wakes == b.detached_wakes
+}
+
+/// Describes the conditions of a run besides its parameters, e.g.,
+/// `load=memory detached`, or `-` if there are none.
+///
+/// The conditions are the idle state restriction, the placement, the
+/// background load, the nice levels, and whether the wake-ups were detached.
+#[must_use]
+pub fn conditions(report: &RunReport) -> String {
+    let mut conditions = Vec::new();
+    if let Some(latency) = report.cpu_dma_latency {
+        conditions.push(format!("cpu_dma_latency={}", format_duration(latency)));
+    }
+    if let Some(placement) = &report.placement {
+        conditions.push(format!("placement={placement}"));
+    }
+    if let Some(load) = &report.load {
+        conditions.push(format!("load={load}"));
+    }
+    if let Some(nice) = report.priorities.waker {
+        conditions.push(format!("waker_nice={nice}"));
+    }
+    if let Some(nice) = report.priorities.sleeper {
+        conditions.push(format!("sleeper_nice={nice}"));
+    }
+    if report.detached_wakes {
+        conditions.push("detached".to_string());
+    }
+    if conditions.is_empty() {
+        "-".to_string()
+    } else {
+        conditions.join(" ")
+    }
+}
+
+/// Pairs the runs of both sets by their parameters and conditions, see
+/// [`conditions`], and returns the differences. Runs without a counterpart
+/// are ignored.
+#[must_use]
+pub fn compare(old: &[RunReport], new: &[RunReport]) -> Vec<Delta> {
+    let mut deltas = Vec::new();
+    for old_run in old {
+        let Some(new_run) = new
+            .iter()
+            .find(|new_run| same_configuration(old_run, new_run))
+        else {
+            continue;
+        };
+
+        let pairs = [
+            (
+                Reason::Interrupted,
+                old_run.interrupted.as_ref(),
+                new_run.interrupted.as_ref(),
+            ),
+            (
+                Reason::Timeouted,
+                old_run.timeouted.as_ref(),
+                new_run.timeouted.as_ref(),
+            ),
+        ];
+        for (reason, old_summary, new_summary) in pairs {
+            if let (Some(old_summary), Some(new_summary)) = (old_summary, new_summary) {
+                let change = |statistic: fn(&Summary) -> Duration| Change {
+                    old: statistic(old_summary),
+                    new: statistic(new_summary),
+                };
+                deltas.push(Delta {
+                    backend: old_run.backend.clone(),
+                    scenario: old_run.scenario.clone(),
+                    interrupt_percent: old_run.interrupt_percent,
+                    timeout_mode: old_run.timeout_mode.clone(),
+                    timeslice: old_run.timeslice,
+                    repeat: old_run.repeat,
+                    conditions: conditions(old_run),
+                    reason,
+                    mean: change(|s| s.mean.value),
+                    p50: change(|s| s.p50.value),
+                    p99: change(|s| s.p99.value),
+                });
+            }
+        }
+    }
+    deltas
+}
+
+fn format_change(change: &Change) -> String {
+    format!(
+        "{} -> {} ({:+.1}%)",
+        format_duration(change.old),
+        format_duration(change.new),
+        change.percent()
+    )
+}
+
+/// Renders the differences as a table, one row per delta. Regressions above
+/// `threshold_percent` are marked.
+#[must_use]
+pub fn render(deltas: &[Delta], threshold_percent: f64) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "| {:<12} | {:<9} | {:>9} | {:<8} | {:>9} | {:>6} | {:<24} | {:<11} | {:<32} | {:<32} | {:<32} | {:<10} |",
+        "backend",
+        "scenario",
+        "interrupt",
+        "timeout",
+        "timeslice",
+        "repeat",
+        "conditions",
+        "reason",
+        "mean",
+        "p50",
+        "p99",
+        ""
+    );
+    for delta in deltas {
+        let _ = writeln!(
+            out,
+            "| {:<12} | {:<9} | {:>8}% | {:<8} | {:>9} | {:>6} | {:<24} | {:<11} | {:<32} | {:<32} | {:<32} | {:<10} |",
+            delta.backend,
+            delta.scenario,
+            delta.interrupt_percent,
+            delta.timeout_mode,
+            format_duration(delta.timeslice),
+            delta.repeat,
+            delta.conditions,
+            delta.reason,
+            format_change(&delta.mean),
+            format_change(&delta.p50),
+            format_change(&delta.p99),
+            if delta.is_regression(threshold_percent) {
+                "REGRESSION"
+            } else {
+                ""
+            }
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::priority::Priorities;
+    use crate::stats::Estimate;
+    use assert2::check;
+
+    fn report(backend: &str, timeslice_ms: u64, mean_us: u64) -> RunReport {
+        let mean = Duration::from_micros(mean_us);
+        let estimate = Estimate {
+            value: mean,
+            ci_low: mean,
+            ci_high: mean,
+        };
+        RunReport {
+            backend: backend.to_string(),
+            scenario: "mixed".to_string(),
+            interrupt_percent: 50,
+            timeout_mode: "relative".to_string(),
+            timeslice: Duration::from_millis(timeslice_ms),
+            seed: 0,
+            repeat: 0,
+            rounds: 10,
+            skipped: 0,
+            negative_delays: 0,
+            interrupted: Some(Summary {
+                count: 10,
+                mean: estimate,
+                p50: estimate,
+                p99: estimate,
+                stddev: Duration::ZERO,
+                jitter: Duration::ZERO,
+            }),
+            timeouted: None,
+            cpu_dma_latency: None,
+            load: None,
+            energy: None,
+            detached_wakes: false,
+            failure: None,
+            placement: None,
+            cpus: None,
+            priorities: Priorities::default(),
+            threads: None,
+            batch_seeds: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_compare() {
+        let old = [report("condvar", 10, 100), report("futex", 10, 50)];
+        let new = [report("condvar", 10, 120), report("condvar", 20, 80)];
+
+        let deltas = compare(&old, &new);
+        check!(deltas.len() == 1);
+        check!(deltas[0].backend == "condvar");
+        check!(deltas[0].reason == Reason::Interrupted);
+        check!(deltas[0].mean.old == Duration::from_micros(100));
+        check!(deltas[0].mean.new == Duration::from_micros(120));
+        check!((deltas[0].p99.percent() - 20.0).abs() < 1e-9);
+        check!(deltas[0].is_regression(10.0));
+        check!(!deltas[0].is_regression(25.0));
+        check!(render(&deltas, 10.0).contains("REGRESSION"));
+    }
+
+    #[test]
+    fn test_compare_conditions() {
+        let restricted = |mean_us| RunReport {
+            cpu_dma_latency: Some(Duration::ZERO),
+            ..report("condvar", 10, mean_us)
+        };
+        let old = [report("condvar", 10, 100), restricted(50)];
+        let new = [restricted(60), report("condvar", 10, 90)];
+
+        let deltas = compare(&old, &new);
+        check!(deltas.len() == 2);
+        check!(deltas[0].conditions == "-");
+        check!(deltas[0].mean.new == Duration::from_micros(90));
+        check!(deltas[1].conditions == "cpu_dma_latency=0ns");
+        check!(deltas[1].mean.new == Duration::from_micros(60));
+        let out = render(&deltas, 10.0);
+        check!(out.contains("cpu_dma_latency=0ns"));
+        check!(out.contains("      50% | relative"));
+    }
+
+    #[test]
+    fn test_change_percent() {
+        let change = |old, new| Change {
+            old: Duration::from_micros(old),
+            new: Duration::from_micros(new),
+        };
+        check!(change(200, 100).percent() == -50.0);
+        check!(change(0, 0).percent() == 0.0);
+        check!(change(0, 1).percent() == f64::INFINITY);
+    }
+}
@@ -0,0 +1,395 @@
+//! The semantics every [`Sleeper`] and [`Waker`] pair must satisfy. See
+//! [`check_pair`].
+//!
+//! The benchmarks rely on them: a wake-up interrupts exactly one sleep, a
+//! sleep without a wake-up times out, the wake-up call instant is taken
+//! during the call of [`Waker::wake`], and [`Waker::wake`] waits for the
+//! acknowledgement of the sleeper. A backend that violates them produces
+//! meaningless numbers or hangs the harness. [`check_pair`] validates a new
+//! backend, e.g., one of a plugin, with one call before it's benchmarked.
+
+use crate::{Sleeper, Waker, WakeupReason};
+use std::fmt::{self, Display, Formatter};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The timeout of the sleeps that are supposed to time out.
+pub const TIMESLICE: Duration = Duration::from_millis(20);
+
+/// The timeout of the sleeps that are supposed to be interrupted.
+const LONG_SLEEP: Duration = Duration::from_millis(500);
+
+/// A check that doesn't finish within this time hangs, e.g., because
+/// [`Waker::wake`] waits for an acknowledgement that never comes.
+const WATCHDOG: Duration = Duration::from_secs(3);
+
+/// Number of rounds of [`Check::NoStaleWakeups`].
+const STALE_ROUNDS: usize = 5;
+
+/// A property of a [`Sleeper`] and [`Waker`] pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Check {
+    /// A sleep without a wake-up times out, not before its timeout.
+    Timeout,
+    /// Like [`Self::Timeout`] but with [`Sleeper::sleep_until`].
+    SleepUntil,
+    /// A wake-up during a sleep interrupts it.
+    Interrupt,
+    /// The reported wake-up call instant lies between the call of
+    /// [`Waker::wake`] and the return of the sleep.
+    WakeCallInstant,
+    /// A wake-up before the sleep is not lost, and [`Waker::wake`] only
+    /// returns after the sleeper took it, i.e., after the sleep began.
+    Handshake,
+    /// A wake-up interrupts exactly one sleep: the next sleep times out.
+    NoStaleWakeups,
+    /// The sleeper goes back to sleep after a spurious wake-up, see
+    /// [`Waker::wake_spurious`]. Skipped if not supported.
+    Spurious,
+}
+
+impl Check {
+    /// All checks, in the order of [`check_pair`].
+    pub const ALL: [Self; 7] = [
+        Self::Timeout,
+        Self::SleepUntil,
+        Self::Interrupt,
+        Self::WakeCallInstant,
+        Self::Handshake,
+        Self::NoStaleWakeups,
+        Self::Spurious,
+    ];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Timeout => "timeout",
+            Self::SleepUntil => "sleep-until",
+            Self::Interrupt => "interrupt",
+            Self::WakeCallInstant => "wake-call-instant",
+            Self::Handshake => "handshake",
+            Self::NoStaleWakeups => "no-stale-wake-ups",
+            Self::Spurious => "spurious",
+        }
+    }
+
+    fn run<S, W>(self, sleeper: S, waker: W) -> Outcome
+    where
+        S: Sleeper<Instant> + Send,
+        W: Waker,
+    {
+        let result = match self {
+            Self::Timeout => check_timeout(&sleeper, false),
+            Self::SleepUntil => check_timeout(&sleeper, true),
+            Self::Interrupt => check_interrupt(sleeper, &waker),
+            Self::WakeCallInstant => check_wake_call_instant(sleeper, &waker),
+            Self::Handshake => check_handshake(sleeper, &waker),
+            Self::NoStaleWakeups => check_no_stale_wakeups(sleeper, &waker),
+            Self::Spurious => return check_spurious(sleeper, &waker),
+        };
+        // Dropped only now, as dropping may disconnect the sleeper.
+        drop(waker);
+        result.map_or_else(Outcome::Failed, |()| Outcome::Passed)
+    }
+}
+
+/// Result of a [`Check`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    /// The pair doesn't support the feature under test.
+    Skipped,
+    /// The property is violated, with the reason.
+    Failed(String),
+}
+
+/// Results of all checks of a pair, see [`check_pair`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conformance {
+    pub results: Vec<(Check, Outcome)>,
+}
+
+impl Conformance {
+    /// Returns whether no check failed.
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    /// Returns the failed checks with their reasons.
+    pub fn failures(&self) -> impl Iterator<Item = (Check, &str)> {
+        self.results
+            .iter()
+            .filter_map(|(check, outcome)| match outcome {
+                Outcome::Failed(reason) => Some((*check, reason.as_str())),
+                _ => None,
+            })
+    }
+}
+
+impl Display for Conformance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (check, outcome) in &self.results {
+            match outcome {
+                Outcome::Passed => writeln!(f, "  {:<17} : passed", check.name())?,
+                Outcome::Skipped => writeln!(f, "  {:<17} : skipped", check.name())?,
+                Outcome::Failed(reason) => {
+                    writeln!(f, "  {:<17} : FAILED: {reason}", check.name())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs all [`Check`]s, each with a fresh pair of `new_pair`, and returns
+/// their results. Takes about half a second.
+///
+/// A check that panics or doesn't finish within a few seconds fails. The
+/// threads of a hanging check are abandoned.
+pub fn check_pair<S, W>(new_pair: impl Fn() -> (S, W)) -> Conformance
+where
+    S: Sleeper<Instant> + Send + 'static,
+    W: Waker + Send + 'static,
+{
+    let results = Check::ALL
+        .into_iter()
+        .map(|check| {
+            let (sleeper, waker) = new_pair();
+            let (sender, receiver) = mpsc::channel();
+            // Not joined: in case of a hang, it never finishes.
+            thread::spawn(move || {
+                let _ = sender.send(check.run(sleeper, waker));
+            });
+            let outcome = match receiver.recv_timeout(WATCHDOG) {
+                Ok(outcome) => outcome,
+                Err(RecvTimeoutError::Timeout) => {
+                    Outcome::Failed(format!("didn't finish within {}s", WATCHDOG.as_secs()))
+                }
+                Err(RecvTimeoutError::Disconnected) => Outcome::Failed("panicked".to_string()),
+            };
+            (check, outcome)
+        })
+        .collect();
+    Conformance { results }
+}
+
+fn check_timeout(sleeper: &impl Sleeper<Instant>, absolute: bool) -> Result<(), String> {
+    let begin = Instant::now();
+    let reason = if absolute {
+        sleeper.sleep_until(begin + TIMESLICE)
+    } else {
+        sleeper.sleep_interruptible(TIMESLICE)
+    };
+    let elapsed = begin.elapsed();
+    match reason {
+        WakeupReason::Interrupted { .. } => Err("interrupted without a wake-up".to_string()),
+        WakeupReason::Timeout if elapsed < TIMESLICE => Err(format!(
+            "timed out after {elapsed:?}, before the timeout of {TIMESLICE:?}"
+        )),
+        WakeupReason::Timeout => Ok(()),
+    }
+}
+
+/// The points in time of a sleep with a single wake-up, see [`wake_once`].
+#[derive(Debug)]
+struct WakeOnce {
+    reason: WakeupReason,
+    sleep_begin: Instant,
+    sleep_end: Instant,
+    wake_begin: Instant,
+    wake_end: Instant,
+}
+
+/// Lets the sleeper sleep after `sleep_delay` and wakes it after
+/// `wake_delay`.
+fn wake_once(
+    sleeper: impl Sleeper<Instant> + Send,
+    waker: &impl Waker,
+    sleep_delay: Duration,
+    wake_delay: Duration,
+) -> WakeOnce {
+    thread::scope(|scope| {
+        let sleeping = scope.spawn(move || {
+            thread::sleep(sleep_delay);
+            let sleep_begin = Instant::now();
+            let reason = sleeper.sleep_interruptible(LONG_SLEEP);
+            (reason, sleep_begin, Instant::now())
+        });
+        thread::sleep(wake_delay);
+        let wake_begin = Instant::now();
+        waker.wake();
+        let wake_end = Instant::now();
+        let (reason, sleep_begin, sleep_end) = sleeping.join().unwrap();
+        WakeOnce {
+            reason,
+            sleep_begin,
+            sleep_end,
+            wake_begin,
+            wake_end,
+        }
+    })
+}
+
+fn check_interrupt(
+    sleeper: impl Sleeper<Instant> + Send,
+    waker: &impl Waker,
+) -> Result<(), String> {
+    let wake = wake_once(sleeper, waker, Duration::ZERO, TIMESLICE);
+    let slept = wake.sleep_end - wake.sleep_begin;
+    match wake.reason {
+        WakeupReason::Timeout => Err("the wake-up didn't interrupt the sleep".to_string()),
+        WakeupReason::Interrupted { .. } if slept >= LONG_SLEEP / 2 => Err(format!(
+            "the sleep returned only {slept:?} after it began, for a wake-up after {TIMESLICE:?}"
+        )),
+        WakeupReason::Interrupted { .. } => Ok(()),
+    }
+}
+
+fn check_wake_call_instant(
+    sleeper: impl Sleeper<Instant> + Send,
+    waker: &impl Waker,
+) -> Result<(), String> {
+    let wake = wake_once(sleeper, waker, Duration::ZERO, TIMESLICE);
+    let WakeupReason::Interrupted { wake_call_instant } = wake.reason else {
+        return Err("the wake-up didn't interrupt the sleep".to_string());
+    };
+    if wake_call_instant < wake.wake_begin || wake_call_instant > wake.sleep_end {
+        return Err(format!(
+            "the wake-up call instant is {:?} after the call of wake(), which returned {:?} after it",
+            wake_call_instant.saturating_duration_since(wake.wake_begin),
+            wake.wake_end - wake.wake_begin
+        ));
+    }
+    Ok(())
+}
+
+fn check_handshake(
+    sleeper: impl Sleeper<Instant> + Send,
+    waker: &impl Waker,
+) -> Result<(), String> {
+    let wake = wake_once(sleeper, waker, TIMESLICE, Duration::ZERO);
+    if wake.reason == WakeupReason::Timeout {
+        return Err("a wake-up before the sleep was lost".to_string());
+    }
+    if wake.wake_end < wake.sleep_begin {
+        return Err(format!(
+            "wake() returned {:?} before the sleep began, without an acknowledgement",
+            wake.sleep_begin - wake.wake_end
+        ));
+    }
+    Ok(())
+}
+
+fn check_no_stale_wakeups(
+    sleeper: impl Sleeper<Instant> + Send,
+    waker: &impl Waker,
+) -> Result<(), String> {
+    let (sender, receiver) = mpsc::channel();
+    // Owns the receiver, so the sleeper stops after a failed round.
+    thread::scope(move |scope| {
+        scope.spawn(move || {
+            for _ in 0..STALE_ROUNDS {
+                let woken = sleeper.sleep_interruptible(LONG_SLEEP);
+                let next = sleeper.sleep_interruptible(TIMESLICE);
+                if sender.send((woken, next)).is_err() {
+                    break;
+                }
+            }
+        });
+        for round in 0..STALE_ROUNDS {
+            thread::sleep(TIMESLICE / 4);
+            waker.wake();
+            let (woken, next) = receiver
+                .recv()
+                .map_err(|_| "the sleeper died".to_string())?;
+            if woken == WakeupReason::Timeout {
+                return Err(format!(
+                    "round {round}: the wake-up didn't interrupt the sleep"
+                ));
+            }
+            if next != WakeupReason::Timeout {
+                return Err(format!(
+                    "round {round}: a single wake-up interrupted two sleeps"
+                ));
+            }
+        }
+        Ok(())
+    })
+}
+
+fn check_spurious(sleeper: impl Sleeper<Instant> + Send, waker: &impl Waker) -> Outcome {
+    thread::scope(|scope| {
+        let sleeping = scope.spawn(move || {
+            let begin = Instant::now();
+            let reason = sleeper.sleep_interruptible(TIMESLICE);
+            (reason, begin.elapsed())
+        });
+        thread::sleep(TIMESLICE / 4);
+        let supported = waker.wake_spurious();
+        let (reason, elapsed) = sleeping.join().unwrap();
+        if !supported {
+            return Outcome::Skipped;
+        }
+        match reason {
+            WakeupReason::Interrupted { .. } => {
+                Outcome::Failed("a spurious wake-up interrupted the sleep".to_string())
+            }
+            WakeupReason::Timeout if elapsed < TIMESLICE => Outcome::Failed(format!(
+                "the sleep returned after {elapsed:?}, before the timeout of {TIMESLICE:?}"
+            )),
+            WakeupReason::Timeout => Outcome::Passed,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::{channel, condvar};
+    use assert2::check;
+
+    #[test]
+    fn test_check_pair() {
+        let conformance = check_pair(condvar::new_pair);
+        check!(conformance.passed(), "{conformance}");
+        check!(conformance.results.len() == Check::ALL.len());
+
+        let conformance = check_pair(channel::new_pair);
+        check!(conformance.passed(), "{conformance}");
+    }
+
+    #[test]
+    fn test_check_pair_violations() {
+        /// Never interrupted, and its waker doesn't wait for an ACK.
+        #[derive(Debug)]
+        struct Deaf;
+        impl Sleeper<Instant> for Deaf {
+            fn sleep_interruptible(&self, sleep_duration: Duration) -> WakeupReason {
+                thread::sleep(sleep_duration);
+                WakeupReason::Timeout
+            }
+        }
+        #[derive(Debug)]
+        struct Ignored;
+        impl Waker for Ignored {
+            fn wake(&self) {}
+        }
+
+        let conformance = check_pair(|| (Deaf, Ignored));
+        let failures = conformance
+            .failures()
+            .map(|(check, _)| check)
+            .collect::<Vec<_>>();
+        check!(
+            failures
+                == [
+                    Check::Interrupt,
+                    Check::WakeCallInstant,
+                    Check::Handshake,
+                    Check::NoStaleWakeups
+                ]
+        );
+    }
+}
@@ -0,0 +1,188 @@
+//! Wake-ups with several blocked sleepers. See [`run`].
+//!
+//! The other benchmarks only ever block a single thread. Effects like
+//! contention on the futex hash buckets of the kernel, the length of the wait
+//! queue of a condvar, longer run queues, and the cost of selecting the thread
+//! to wake only show up with several waiters on the same primitive.
+
+use crate::clock::Clock;
+use crate::runner::{LATEST_WAKE_PERCENT, RunConfig, watchdog_timeout};
+use crate::sleeper_thread::SleeperThread;
+use crate::synchronization::NoDelayBarrier;
+use crate::units::format_duration;
+use crate::{Measurement, Measurements, Sleeper, Waker, WakeupContext, WakeupReason};
+use assert2::check;
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, sync_channel};
+use std::time::Instant;
+
+/// A sleeper thread with its channels.
+struct Slot {
+    thread: SleeperThread,
+    sleep_barrier: Arc<NoDelayBarrier>,
+    receiver: Receiver<Option<WakeupContext>>,
+}
+
+/// Like [`runner::run_with_clock`] but with several sleeper threads, all
+/// blocked on the same primitive.
+///
+/// The sleepers come from [`Backend::new_group`]. All of them sleep at the
+/// same time, and each round, the waker wakes one of them, whichever the
+/// primitive picks.
+///
+/// The interrupted measurements are those of the woken sleeper; the others
+/// time out and are collected as timeouted measurements. The threads are not
+/// pinned, [`RunConfig::cpus`] is ignored.
+///
+/// [`runner::run_with_clock`]: crate::runner::run_with_clock
+/// [`Backend::new_group`]: crate::backends::Backend::new_group
+pub fn run<S, W, C>(config: RunConfig, sleepers: Vec<S>, waker: W, clock: C) -> Measurements
+where
+    S: Sleeper<Instant> + Send + 'static,
+    W: Waker,
+    C: Clock + Clone + Send + 'static,
+{
+    let RunConfig {
+        rounds, timeslice, ..
+    } = config;
+    let mut rng = fastrand::Rng::with_seed(config.seed);
+    let watchdog = watchdog_timeout(timeslice);
+    let mut measurements = Measurements::default();
+
+    let mut slots = Vec::with_capacity(sleepers.len());
+    for sleeper in sleepers {
+        let (sender, receiver) = sync_channel::<Option<WakeupContext>>(1);
+        let sleep_barrier = Arc::new(NoDelayBarrier::new());
+        let thread = SleeperThread::spawn_with_clock(
+            sleep_barrier.clone(),
+            sleeper,
+            timeslice,
+            config.timeout_mode,
+            config.scheduling,
+            sender,
+            clock.clone(),
+        );
+        slots.push(Slot {
+            thread,
+            sleep_barrier,
+            receiver,
+        });
+    }
+    if slots.is_empty() {
+        return measurements;
+    }
+
+    while measurements.rounds < rounds && measurements.skipped < rounds {
+        let wake_offset =
+            config
+                .scenario
+                .next_wake_offset(&mut rng, timeslice, config.interrupt_percent);
+
+        // Start all sleepers of this cycle. As in the runner, the wake-up
+        // offsets are relative to the start of the first sleeper.
+        let mut cycle_begin = None;
+        let mut last_not_before = None;
+        for slot in &slots {
+            // The sleeper starts after both threads arrived at the barrier.
+            // Unlike the runner, this matters: the other sleepers spin on
+            // their barriers and may delay the control thread for a whole
+            // scheduler timeslice between the barrier and `clock.now()`.
+            last_not_before = Some(clock.now());
+            slot.sleep_barrier.wait();
+            cycle_begin.get_or_insert_with(|| clock.now());
+        }
+        let cycle_begin = cycle_begin.expect("should have a sleeper");
+        let last_not_before = last_not_before.expect("should have a sleeper");
+
+        let mut wake_issued = false;
+        if let Some(offset) = wake_offset {
+            clock.sleep_until(cycle_begin + offset);
+            // If we are too late, the wake-up might arrive after all sleepers
+            // timed out and we would wait forever for an ACK. The last
+            // sleeper to start is the last one to time out.
+            let latest_wake = last_not_before + timeslice * LATEST_WAKE_PERCENT / 100;
+            if clock.now() <= latest_wake {
+                waker.wake();
+                wake_issued = true;
+            }
+        }
+
+        let contexts = slots
+            .iter()
+            .map(|slot| slot.receiver.recv_timeout(watchdog).ok())
+            .collect::<Option<Vec<_>>>();
+        let Some(contexts) = contexts else {
+            measurements.failure = Some(format!(
+                "no measurements of all {} sleepers within {} in round {}",
+                slots.len(),
+                format_duration(watchdog),
+                measurements.rounds + measurements.skipped
+            ));
+            for slot in slots {
+                slot.thread.abandon();
+            }
+            return measurements;
+        };
+
+        let interrupted = contexts
+            .iter()
+            .flatten()
+            .filter(|context| matches!(context.reason, WakeupReason::Interrupted { .. }))
+            .count();
+        check!(interrupted <= usize::from(wake_issued));
+        // Without an interrupted measurement, the wake-up was too late or the
+        // woken sleeper didn't record a valid measurement.
+        if wake_offset.is_some() && interrupted == 0 {
+            measurements.skipped += 1;
+            continue;
+        }
+        for wakeup_context in contexts.into_iter().flatten() {
+            let measurement = Measurement { wakeup_context };
+            if matches!(
+                measurement.wakeup_context.reason,
+                WakeupReason::Interrupted { .. }
+            ) {
+                measurements.interrupted.push(measurement);
+            } else {
+                measurements.timeouted.push(measurement);
+            }
+        }
+        measurements.rounds += 1;
+    }
+    measurements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::condvar;
+    use crate::clock::SystemClock;
+    use crate::scenario::Scenario;
+    use std::time::Duration;
+
+    fn check_run<S: Sleeper<Instant> + Send + 'static>(sleepers: Vec<S>, waker: impl Waker) {
+        let config = RunConfig::new(10, Duration::from_millis(20)).with_scenario(Scenario::Always);
+        let measurements = run(config, sleepers, waker, SystemClock);
+
+        check!(measurements.failure.is_none());
+        // On a loaded machine, some rounds miss their schedule.
+        check!(measurements.rounds + measurements.skipped >= 10);
+        // Exactly one sleeper is woken per round.
+        check!(measurements.interrupted.len() == measurements.rounds);
+        // The bystanders of each round time out.
+        check!(measurements.timeouted.len() >= 2 * measurements.interrupted.len());
+    }
+
+    #[test]
+    fn test_run() {
+        let (sleepers, waker) = condvar::new_group(3);
+        check_run(sleepers, waker);
+    }
+
+    #[cfg(all(feature = "futex", target_os = "linux"))]
+    #[test]
+    fn test_run_futex() {
+        let (sleepers, waker) = crate::backends::futex::new_group(3);
+        check_run(sleepers, waker);
+    }
+}
@@ -0,0 +1,169 @@
+//! The CPU a thread is running on. See [`current`] and [`pin_current`].
+//!
+//! If the scheduler migrates the sleeper thread to another CPU while it
+//! sleeps, the wake-up typically takes longer: the target CPU may be busy or
+//! in a deep idle state, and the caches are cold.
+
+use std::io;
+use std::thread::JoinHandle;
+
+/// Returns the id of the CPU the calling thread is currently running on, or
+/// `None` if this is not supported on this platform.
+///
+/// The result may already be outdated when this function returns.
+#[must_use]
+pub fn current() -> Option<u32> {
+    #[cfg(target_os = "linux")]
+    {
+        // SAFETY: sched_getcpu() has no preconditions.
+        let cpu = unsafe { libc::sched_getcpu() };
+        u32::try_from(cpu).ok()
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// Returns the CPUs the calling thread may run on, or `None` if this is not
+/// supported on this platform.
+#[must_use]
+pub fn allowed() -> Option<Vec<u32>> {
+    #[cfg(target_os = "linux")]
+    {
+        // SAFETY: An all-zero cpu_set_t is a valid, empty set.
+        let mut set = unsafe { std::mem::zeroed::<libc::cpu_set_t>() };
+        // SAFETY: The pointer is valid for the duration of the call.
+        let ret = unsafe { libc::sched_getaffinity(0, size_of::<libc::cpu_set_t>(), &raw mut set) };
+        if ret != 0 {
+            return None;
+        }
+        let cpus = (0..libc::CPU_SETSIZE as u32)
+            // SAFETY: The index is within the set.
+            .filter(|&cpu| unsafe { libc::CPU_ISSET(cpu as usize, &set) })
+            .collect();
+        Some(cpus)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// Restores the previous affinity of the calling thread when dropped. See
+/// [`pin_current`].
+#[derive(Debug)]
+pub struct Pinned {
+    previous: Vec<u32>,
+}
+
+impl Drop for Pinned {
+    fn drop(&mut self) {
+        #[cfg(target_os = "linux")]
+        {
+            let set = cpu_set(&self.previous);
+            // SAFETY: The pointer is valid for the duration of the call.
+            unsafe { libc::sched_setaffinity(0, size_of::<libc::cpu_set_t>(), &raw const set) };
+        }
+    }
+}
+
+/// Restricts the calling thread to `cpu` until the returned guard is dropped.
+///
+/// # Errors
+///
+/// Fails if `cpu` is not available to the process or if this is not
+/// supported on this platform, i.e., on other platforms than Linux.
+pub fn pin_current(cpu: u32) -> io::Result<Pinned> {
+    #[cfg(target_os = "linux")]
+    {
+        let previous = allowed().ok_or_else(io::Error::last_os_error)?;
+        let set = cpu_set(&[cpu]);
+        // SAFETY: The pointer is valid for the duration of the call.
+        let ret =
+            unsafe { libc::sched_setaffinity(0, size_of::<libc::cpu_set_t>(), &raw const set) };
+        if ret != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Pinned { previous })
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = cpu;
+        Err(unsupported())
+    }
+}
+
+/// Restricts another thread to `cpu` for the rest of its life.
+///
+/// # Errors
+///
+/// Like [`pin_current`].
+pub fn pin<T>(thread: &JoinHandle<T>, cpu: u32) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::thread::JoinHandleExt;
+
+        let set = cpu_set(&[cpu]);
+        // SAFETY: The thread is alive as long as the handle exists, and the
+        // pointer is valid for the duration of the call.
+        let ret = unsafe {
+            libc::pthread_setaffinity_np(
+                thread.as_pthread_t(),
+                size_of::<libc::cpu_set_t>(),
+                &raw const set,
+            )
+        };
+        if ret != 0 {
+            return Err(io::Error::from_raw_os_error(ret));
+        }
+        Ok(())
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (thread, cpu);
+        Err(unsupported())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn cpu_set(cpus: &[u32]) -> libc::cpu_set_t {
+    // SAFETY: An all-zero cpu_set_t is a valid, empty set.
+    let mut set = unsafe { std::mem::zeroed::<libc::cpu_set_t>() };
+    for &cpu in cpus {
+        // SAFETY: CPU_SET ignores indices outside the set.
+        unsafe { libc::CPU_SET(cpu as usize, &mut set) };
+    }
+    set
+}
+
+#[cfg(not(target_os = "linux"))]
+fn unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "pinning threads is only supported on Linux",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert2::check;
+
+    #[test]
+    fn test_current() {
+        check!(current().is_some() == cfg!(target_os = "linux"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_pin_current() {
+        let before = allowed().unwrap();
+        let cpu = *before.last().unwrap();
+        let pinned = pin_current(cpu).unwrap();
+        check!(allowed().unwrap() == [cpu]);
+        check!(current() == Some(cpu));
+        drop(pinned);
+        check!(allowed().unwrap() == before);
+    }
+}
@@ -0,0 +1,156 @@
+//! Energy consumption of the CPU packages from the RAPL counters of the Linux
+//! powercap interface. See [`Rapl`].
+//!
+//! Spinning strategies may wake up faster but burn more energy. The counters
+//! cover the whole package, i.e., all threads of the harness and everything
+//! else running on the machine. Hence, only the differences between backends
+//! on an otherwise idle machine are meaningful.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The powercap interface of Linux.
+const POWERCAP: &str = "/sys/class/powercap";
+
+/// A package zone, e.g., `intel-rapl:0`. AMD CPUs use the same naming.
+#[derive(Debug)]
+struct Zone {
+    /// The counter in µJ.
+    energy: PathBuf,
+    /// The counter wraps around after this value.
+    max_energy_range: u64,
+}
+
+/// The RAPL package zones of the system.
+#[derive(Debug)]
+pub struct Rapl {
+    zones: Vec<Zone>,
+}
+
+/// The counters of all zones at one point in time, see [`Rapl::read`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnergyReading(Vec<u64>);
+
+impl Rapl {
+    /// Opens the package zones. Returns `None` if there are none or if their
+    /// counters can't be read, e.g., on other platforms than Linux or without
+    /// root, as recent kernels restrict the counters to root.
+    #[must_use]
+    pub fn open() -> Option<Self> {
+        Self::open_at(Path::new(POWERCAP))
+    }
+
+    fn open_at(powercap: &Path) -> Option<Self> {
+        let mut zones = fs::read_dir(powercap)
+            .ok()?
+            .filter_map(Result::ok)
+            .filter(|entry| is_package_zone(&entry.file_name().to_string_lossy()))
+            .map(|entry| {
+                let dir = entry.path();
+                Some(Zone {
+                    energy: dir.join("energy_uj"),
+                    max_energy_range: read_counter(&dir.join("max_energy_range_uj"))?,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        zones.sort_by(|a, b| a.energy.cmp(&b.energy));
+        let rapl = Self { zones };
+        (!rapl.zones.is_empty() && rapl.read().is_some()).then_some(rapl)
+    }
+
+    /// Reads the counters of all zones.
+    #[must_use]
+    pub fn read(&self) -> Option<EnergyReading> {
+        self.zones
+            .iter()
+            .map(|zone| read_counter(&zone.energy))
+            .collect::<Option<_>>()
+            .map(EnergyReading)
+    }
+
+    /// Returns the energy in µJ that all zones consumed between two readings.
+    #[must_use]
+    pub fn consumed(&self, before: &EnergyReading, after: &EnergyReading) -> u64 {
+        self.zones
+            .iter()
+            .zip(before.0.iter().zip(&after.0))
+            .map(|(zone, (&before, &after))| delta(before, after, zone.max_energy_range))
+            .sum()
+    }
+}
+
+/// Top-level zones are packages, e.g., `intel-rapl:0`; subzones, e.g.,
+/// `intel-rapl:0:0`, are part of them.
+fn is_package_zone(name: &str) -> bool {
+    name.strip_prefix("intel-rapl:")
+        .is_some_and(|index| index.parse::<u32>().is_ok())
+}
+
+fn read_counter(path: &Path) -> Option<u64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Difference of two counter values, assuming at most one wrap-around.
+const fn delta(before: u64, after: u64, max_energy_range: u64) -> u64 {
+    if after >= before {
+        after - before
+    } else {
+        max_energy_range - before + after
+    }
+}
+
+/// Returns the energy in µJ per 1000 wake-ups, or `None` if there were no
+/// wake-ups.
+#[must_use]
+pub fn per_1000_wakeups(energy: u64, wakeups: usize) -> Option<u64> {
+    (wakeups > 0).then(|| energy.saturating_mul(1000) / wakeups as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert2::check;
+
+    #[test]
+    fn test_is_package_zone() {
+        check!(is_package_zone("intel-rapl:0"));
+        check!(is_package_zone("intel-rapl:1"));
+        check!(!is_package_zone("intel-rapl:0:0"));
+        check!(!is_package_zone("intel-rapl"));
+        check!(!is_package_zone("dtpm"));
+    }
+
+    #[test]
+    fn test_delta() {
+        check!(delta(100, 250, 1000) == 150);
+        // wrap-around
+        check!(delta(900, 50, 1000) == 150);
+    }
+
+    #[test]
+    fn test_open_at() {
+        let powercap = std::env::temp_dir().join(format!("powercap-{}", std::process::id()));
+        for (zone, energy) in [("intel-rapl:0", "900"), ("intel-rapl:0:0", "5")] {
+            let dir = powercap.join(zone);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("energy_uj"), energy).unwrap();
+            fs::write(dir.join("max_energy_range_uj"), "1000\n").unwrap();
+        }
+
+        let rapl = Rapl::open_at(&powercap).unwrap();
+        let before = rapl.read().unwrap();
+        check!(before == EnergyReading(vec![900]));
+        fs::write(powercap.join("intel-rapl:0/energy_uj"), "50\n").unwrap();
+        let after = rapl.read().unwrap();
+        check!(rapl.consumed(&before, &after) == 150);
+
+        fs::remove_dir_all(&powercap).unwrap();
+        check!(Rapl::open_at(&powercap).is_none());
+    }
+
+    #[test]
+    fn test_per_1000_wakeups() {
+        check!(per_1000_wakeups(500, 100) == Some(5000));
+        check!(per_1000_wakeups(500, 0).is_none());
+    }
+}
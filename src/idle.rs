@@ -0,0 +1,45 @@
+//! Control over CPU idle states. See [`CpuDmaLatency`].
+//!
+//! Waking a CPU from a deep idle state (C-state) takes up to several hundred
+//! microseconds. On an otherwise idle system, this exit latency often
+//! dominates the wake-up delay, no matter which backend is used.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// The PM QoS interface of Linux.
+const CPU_DMA_LATENCY: &str = "/dev/cpu_dma_latency";
+
+/// A request that keeps all CPUs out of idle states with an exit latency
+/// above the requested latency. The request is active until this is dropped.
+#[derive(Debug)]
+pub struct CpuDmaLatency {
+    latency: Duration,
+    // The kernel drops the request when the file is closed.
+    _file: File,
+}
+
+impl CpuDmaLatency {
+    /// Requests the maximum exit latency of idle states, e.g., zero to keep
+    /// the CPUs out of all idle states. Linux only, typically requires root.
+    pub fn request(latency: Duration) -> io::Result<Self> {
+        let micros = i32::try_from(latency.as_micros()).map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidInput, "CPU DMA latency too large")
+        })?;
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(CPU_DMA_LATENCY)
+            .map_err(|e| io::Error::new(e.kind(), format!("{CPU_DMA_LATENCY}: {e}")))?;
+        file.write_all(&micros.to_ne_bytes())?;
+        Ok(Self {
+            latency,
+            _file: file,
+        })
+    }
+
+    #[must_use]
+    pub const fn latency(&self) -> Duration {
+        self.latency
+    }
+}
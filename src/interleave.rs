@@ -0,0 +1,93 @@
+//! Interleaved runs of several backends. See [`run`].
+//!
+//! Running each backend to completion one after the other biases the results
+//! towards slow drift of the environment, e.g., the CPU heating up or a
+//! background daemon waking up: it affects whichever backend runs at that
+//! time. Alternating short batches (A, B, A, B, ...) spreads such drift over
+//! all backends.
+
+use crate::Measurements;
+use crate::backends::Backend;
+use crate::runner::{self, RunConfig};
+use std::num::NonZeroUsize;
+
+/// The result of [`run`].
+#[derive(Debug, Default)]
+pub struct Interleaved {
+    /// The measurements of each backend, in the order of the backends.
+    pub measurements: Vec<Measurements>,
+    /// The seed of each batch, derived from the seed of the config.
+    pub batch_seeds: Vec<u64>,
+}
+
+/// Runs `config.rounds` rounds of each backend, alternating between the
+/// backends after every `batch_rounds` rounds.
+///
+/// Each batch uses a fresh pair of the backend and a seed drawn from an RNG
+/// seeded with the seed of the config, so all backends get the same
+/// schedule within a batch, but the batches differ. The same RNG shuffles
+/// the order of the backends per batch, so no backend always runs first. A
+/// backend that fails is not run in the later batches.
+#[must_use]
+pub fn run(config: RunConfig, backends: &[&Backend], batch_rounds: NonZeroUsize) -> Interleaved {
+    let mut rng = fastrand::Rng::with_seed(config.seed);
+    let mut order = (0..backends.len()).collect::<Vec<_>>();
+    let mut interleaved = Interleaved {
+        measurements: backends.iter().map(|_| Measurements::default()).collect(),
+        batch_seeds: Vec::new(),
+    };
+    let batches = config.rounds.div_ceil(batch_rounds.get());
+    for batch in 0..batches {
+        let rounds = batch_rounds
+            .get()
+            .min(config.rounds - batch * batch_rounds.get());
+        let seed = rng.u64(..);
+        rng.shuffle(&mut order);
+        interleaved.batch_seeds.push(seed);
+        let batch_config = RunConfig {
+            rounds,
+            ..config.with_seed(seed)
+        };
+        for &i in &order {
+            let results = &mut interleaved.measurements[i];
+            if results.failure.is_some() {
+                continue;
+            }
+            let (sleeper, waker) = backends[i].new_pair();
+            results.append(runner::run(
+                batch_config.with_backend(backends[i].name),
+                sleeper,
+                waker,
+            ));
+        }
+    }
+    interleaved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends;
+    use crate::scenario::Scenario;
+    use assert2::check;
+    use std::time::Duration;
+
+    #[test]
+    fn test_run() {
+        let condvar = backends::find("condvar").unwrap();
+        let channel = backends::find("channel").unwrap();
+        let config = RunConfig::new(10, Duration::from_millis(5)).with_scenario(Scenario::Always);
+        let interleaved = run(config, &[condvar, channel], NonZeroUsize::new(4).unwrap());
+
+        check!(interleaved.measurements.len() == 2);
+        check!(interleaved.batch_seeds.len() == 3);
+        let again = run(config, &[condvar, channel], NonZeroUsize::new(4).unwrap());
+        check!(again.batch_seeds == interleaved.batch_seeds);
+        for measurements in interleaved.measurements {
+            check!(measurements.failure.is_none());
+            check!(measurements.rounds <= 10);
+            check!(measurements.rounds + measurements.skipped >= 10);
+            check!(measurements.interrupted.len() == measurements.rounds);
+        }
+    }
+}
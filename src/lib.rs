@@ -14,50 +14,205 @@
     clippy::redundant_pub_crate,
     clippy::fallible_impl_from
 )]
+// serde_derive and clap_derive may depend on different major versions of
+// syn, which can't be unified from here, as Cargo.lock is not committed.
+#![allow(clippy::multiple_crate_versions)]
 #![deny(missing_debug_implementations)]
 
-pub mod channel;
-pub mod condvar;
+pub mod accumulator;
+pub mod analysis;
+pub mod assertions;
+pub mod backends;
+pub mod capture;
+pub mod clock;
+pub mod clockbench;
+pub mod coalescing;
+pub mod compare;
+pub mod conformance;
+pub mod contention;
+pub mod cpu;
+pub mod energy;
+pub mod idle;
+pub mod interleave;
+pub mod load;
+pub mod plain;
+#[cfg(feature = "plugins")]
+pub mod plugin;
+pub mod precision;
+pub mod priority;
+pub mod recording;
+pub mod report;
+pub mod runner;
+pub mod scenario;
+pub mod schedstat;
+pub mod setup;
 pub mod sleeper_thread;
+pub mod soak;
+pub mod stats;
+pub mod suite;
+pub mod sweep;
 pub mod synchronization;
+pub mod threads;
+pub mod throughput;
+pub mod topology;
+mod trace;
+pub mod tracefs;
+pub mod units;
+pub mod virtual_time;
 
+use crate::threads::ThreadIds;
+pub use benchmark_interruptible_sleep_core::{
+    Handshake, SignedDelay, Sleeper, Timestamp, Timing, Waker, measure,
+};
 use std::time::{Duration, Instant};
 
-#[derive(Debug, PartialEq, Eq)]
-pub enum WakeupReason {
-    Timeout,
-    Interrupted { wake_call_instant: Instant },
-}
+/// The [`benchmark_interruptible_sleep_core::WakeupReason`] of the std clock.
+pub type WakeupReason = benchmark_interruptible_sleep_core::WakeupReason<Instant>;
 
 #[derive(Debug)]
 pub struct WakeupContext {
     pub reason: WakeupReason,
     pub expected_duration: Duration,
     pub actual_duration: Duration,
+    /// Zero if the sleep returned early, see [`Self::signed_delay`].
     pub delay: Duration,
+    /// The CPU of the sleeper thread right before the sleep, see
+    /// [`cpu::current`]. `None` unless enabled with
+    /// [`RunConfig::scheduling`](runner::RunConfig::scheduling).
+    pub cpu_before: Option<u32>,
+    /// The CPU of the sleeper thread right after the wake-up.
+    pub cpu_after: Option<u32>,
+    /// The time the sleeper thread was runnable but waited for a CPU during
+    /// the sleep, see [`schedstat`]. Like the CPUs, only recorded if
+    /// enabled.
+    pub run_delay: Option<Duration>,
+    /// When the backend observed the wake-up call, see
+    /// [`Sleeper::wake_observed`]. `None` for timeouts and for backends that
+    /// don't record it. The wake-up call itself is in [`Self::reason`].
+    pub wake_observed: Option<Instant>,
+    /// When the sleeper returned from the backend.
+    pub returned: Instant,
+    /// When the sleeper thread recorded the measurement, after reading the
+    /// CPU and the run delay.
+    pub recorded: Instant,
 }
 
-/// A sleeper that puts the executing thread context into an interruptible
-/// sleep.
-pub trait Sleeper {
-    /// Puts the thread into sleep that is interruptible.
-    fn sleep_interruptible(&self, sleep_duration: Duration) -> WakeupReason;
+impl WakeupContext {
+    /// Returns the delay including its sign, see [`SignedDelay`].
+    #[must_use]
+    pub fn signed_delay(&self) -> SignedDelay {
+        SignedDelay::between(self.expected_duration, self.actual_duration)
+    }
+
+    /// Returns whether the sleep returned before its expected duration,
+    /// e.g., because of a clock anomaly. The delay is zero then.
+    #[must_use]
+    pub fn negative_delay(&self) -> bool {
+        self.signed_delay().is_negative()
+    }
+
+    /// Returns whether the sleeper thread woke up on another CPU, or `None` if
+    /// the CPU is unknown.
+    #[must_use]
+    pub fn migrated(&self) -> Option<bool> {
+        Some(self.cpu_before? != self.cpu_after?)
+    }
+
+    /// Splits the delay into the time until the sleeper thread was made
+    /// runnable and the time it waited for a CPU afterward, or `None` if the
+    /// run delay is unknown.
+    #[must_use]
+    pub fn delay_breakdown(&self) -> Option<(Duration, Duration)> {
+        self.run_delay
+            .map(|run_delay| split_delay(self.delay, run_delay))
+    }
+
+    /// Returns the time from the backend observing the wake-up call until
+    /// the sleeper returned from the backend, or `None` if unknown.
+    #[must_use]
+    pub fn return_path(&self) -> Option<Duration> {
+        Some(self.returned.saturating_duration_since(self.wake_observed?))
+    }
+
+    /// Attributes the delay of an interrupted wake-up to its phases, or
+    /// `None` if the backend doesn't record when it observed the wake-up.
+    #[must_use]
+    pub fn attribution(&self) -> Option<Attribution> {
+        self.return_path()
+            .map(|return_path| Attribution::split(self.delay, self.run_delay, return_path))
+    }
 }
 
-/// A waker for a [`Sleeper`].
-pub trait Waker {
-    /// Wakes the corresponding [`Sleeper`].
-    ///
-    /// The implementation is supposed to send the current [`Instant`] to the
-    /// [`Sleeper`] so that it can properly construct the [`WakeupContext`].
-    ///
-    /// This function waits for the [`Sleeper`] to acknowledge the wake-up
-    /// call. The main motivation of this property is to facilitate
-    /// unit-testing and prevent race conditions. This synchronization should
-    /// add as little delay as possible by using a [`NoDelayBarrier`].
-    ///
-    /// [`NoDelayBarrier`]: crate::synchronization::NoDelayBarrier
-    fn wake(&self);
+/// Where the delay of an interrupted wake-up went, see
+/// [`WakeupContext::attribution`]. The phases add up to the delay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Attribution {
+    /// From the wake-up call until the backend observed it, without the
+    /// scheduling latency.
+    pub notification: Duration,
+    /// The time the sleeper thread was runnable but waited for a CPU, see
+    /// [`WakeupContext::run_delay`]. `None` if unknown, then the
+    /// notification latency includes it.
+    pub scheduling: Option<Duration>,
+    /// From the backend observing the wake-up call until the sleeper
+    /// returned from the backend, e.g., acknowledging the wake-up and
+    /// releasing a lock.
+    pub return_path: Duration,
+}
+
+impl Attribution {
+    /// Splits `delay`. As with [`split_delay`], the run delay is capped, at
+    /// the time until the wake-up was observed.
+    pub(crate) fn split(
+        delay: Duration,
+        run_delay: Option<Duration>,
+        return_path: Duration,
+    ) -> Self {
+        let return_path = return_path.min(delay);
+        let until_observed = delay - return_path;
+        let scheduling = run_delay.map(|run_delay| run_delay.min(until_observed));
+        Self {
+            notification: until_observed - scheduling.unwrap_or_default(),
+            scheduling,
+            return_path,
+        }
+    }
+}
+
+/// Splits `delay` into (until runnable, queueing). The run delay also covers
+/// the preemptions of the sleeper thread right before it started to sleep,
+/// hence, it is capped at `delay`.
+pub(crate) fn split_delay(delay: Duration, run_delay: Duration) -> (Duration, Duration) {
+    let queueing = run_delay.min(delay);
+    (delay - queueing, queueing)
+}
+
+/// How the sleeper thread passes the timeout to the [`Sleeper`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeoutMode {
+    /// [`Sleeper::sleep_interruptible`]
+    #[default]
+    Relative,
+    /// [`Sleeper::sleep_until`]
+    Absolute,
+}
+
+impl TimeoutMode {
+    pub const ALL: [Self; 2] = [Self::Relative, Self::Absolute];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Relative => "relative",
+            Self::Absolute => "absolute",
+        }
+    }
+
+    /// Returns the mode with the given name, if any.
+    #[must_use]
+    pub fn find(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name() == name)
+    }
 }
 
 #[derive(Debug)]
@@ -65,17 +220,40 @@ pub struct Measurement {
     pub wakeup_context: WakeupContext,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Measurements {
     pub interrupted: Vec<Measurement>,
     pub timeouted: Vec<Measurement>,
     pub rounds: usize,
+    /// Rounds that were dropped because the control thread couldn't issue the
+    /// wake-up in time. They are not part of `rounds`.
+    pub skipped: usize,
+    /// Why the run was aborted, if it was, see [`runner::MeasurementSink::fail`].
+    pub failure: Option<String>,
+    /// The ids of the threads, see [`runner::MeasurementSink::threads`]. Of
+    /// the first run if several were appended.
+    pub threads: Option<ThreadIds>,
+}
+
+impl Measurements {
+    /// Adds the measurements of a later run, e.g., of the next batch.
+    pub fn append(&mut self, other: Self) {
+        self.interrupted.extend(other.interrupted);
+        self.timeouted.extend(other.timeouted);
+        self.rounds += other.rounds;
+        self.skipped += other.skipped;
+        self.failure = self.failure.take().or(other.failure);
+        self.threads = self.threads.or(other.threads);
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::backends::{channel, condvar};
+    use crate::clock::{Clock, SystemClock};
     use crate::synchronization::NoDelayBarrier;
+    use crate::virtual_time::VirtualClock;
     use assert2::check;
     use std::sync::Arc;
     use std::sync::mpsc::sync_channel;
@@ -84,36 +262,71 @@ mod tests {
     const TIMESLICE: Duration = Duration::from_millis(50);
 
     // basic functionality test for sleeper and waker
-    fn test_sleeper(sleeper: impl Sleeper + Send + 'static, waker: impl Waker + 'static) {
-        let (sender, receiver) = sync_channel::<WakeupContext>(1);
+    fn test_sleeper(sleeper: impl Sleeper<Instant> + Send + 'static, waker: impl Waker + 'static) {
+        test_sleeper_with_timeout_mode(sleeper, waker, TimeoutMode::Relative);
+    }
+
+    fn test_sleeper_with_timeout_mode(
+        sleeper: impl Sleeper<Instant> + Send + 'static,
+        waker: impl Waker + 'static,
+        timeout_mode: TimeoutMode,
+    ) {
+        let (sender, receiver) = sync_channel::<Option<WakeupContext>>(1);
         let sleep_barrier = Arc::new(NoDelayBarrier::new());
 
-        let thread =
-            sleeper_thread::SleeperThread::spawn(sleep_barrier.clone(), sleeper, TIMESLICE, sender);
+        let thread = sleeper_thread::SleeperThread::spawn_with_clock(
+            sleep_barrier.clone(),
+            sleeper,
+            TIMESLICE,
+            timeout_mode,
+            true,
+            sender,
+            SystemClock,
+        );
 
-        eprintln!("test case 1/3");
+        eprintln!("test case 1/4");
         {
             sleep_barrier.wait();
-            let wakeup_context = receiver.recv().unwrap();
+            let wakeup_context = receiver.recv().unwrap().unwrap();
             check!(wakeup_context.reason == WakeupReason::Timeout);
             check!(wakeup_context.actual_duration >= TIMESLICE);
+            check!(wakeup_context.attribution().is_none());
         }
-        eprintln!("test case 2/3");
+        eprintln!("test case 2/4");
         {
             sleep_barrier.wait();
             sleep(Duration::from_millis(1));
             waker.wake();
-            let wakeup_context = receiver.recv().unwrap();
+            let wakeup_context = receiver.recv().unwrap().unwrap();
             assert2::assert!(matches!(
                 wakeup_context.reason,
                 WakeupReason::Interrupted { .. }
             ));
             check!(wakeup_context.actual_duration <= TIMESLICE / 2);
+            check!(wakeup_context.recorded >= wakeup_context.returned);
+            if let Some(attribution) = wakeup_context.attribution() {
+                check!(
+                    attribution.notification
+                        + attribution.scheduling.unwrap_or_default()
+                        + attribution.return_path
+                        == wakeup_context.delay
+                );
+            }
+        }
+        eprintln!("test case 3/4");
+        {
+            sleep_barrier.wait();
+            let wakeup_context = receiver.recv().unwrap().unwrap();
+            check!(wakeup_context.reason == WakeupReason::Timeout);
+            check!(wakeup_context.actual_duration >= TIMESLICE);
         }
-        eprintln!("test case 3/3");
+        eprintln!("test case 4/4");
         {
+            // The sleeper must go back to sleep after a spurious wake-up.
             sleep_barrier.wait();
-            let wakeup_context = receiver.recv().unwrap();
+            sleep(Duration::from_millis(1));
+            waker.wake_spurious();
+            let wakeup_context = receiver.recv().unwrap().unwrap();
             check!(wakeup_context.reason == WakeupReason::Timeout);
             check!(wakeup_context.actual_duration >= TIMESLICE);
         }
@@ -133,4 +346,182 @@ mod tests {
         let (sleeper, waker) = condvar::new_pair();
         test_sleeper(sleeper, waker);
     }
+
+    #[test]
+    #[cfg(all(feature = "futex", target_os = "linux"))]
+    fn test_futex_sleeper() {
+        let (sleeper, waker) = backends::futex::new_pair();
+        test_sleeper(sleeper, waker);
+    }
+
+    #[test]
+    #[cfg(all(feature = "io_uring", target_os = "linux"))]
+    fn test_io_uring_sleeper() {
+        let (sleeper, waker) = backends::io_uring::new_pair();
+        test_sleeper(sleeper, waker);
+    }
+
+    #[test]
+    #[cfg(all(feature = "semaphore", target_os = "linux"))]
+    fn test_semaphore_sleeper() {
+        let (sleeper, waker) = backends::semaphore::new_pair();
+        test_sleeper(sleeper, waker);
+    }
+
+    #[test]
+    #[cfg(all(
+        feature = "kqueue",
+        any(
+            target_os = "macos",
+            target_os = "ios",
+            target_os = "freebsd",
+            target_os = "dragonfly"
+        )
+    ))]
+    fn test_kqueue_sleeper() {
+        let (sleeper, waker) = backends::kqueue::new_pair();
+        test_sleeper(sleeper, waker);
+    }
+
+    #[test]
+    #[cfg(feature = "parking_lot")]
+    fn test_parking_lot_sleeper() {
+        let (sleeper, waker) = backends::parking_lot::new_pair();
+        test_sleeper(sleeper, waker);
+    }
+
+    #[test]
+    #[cfg(feature = "tokio")]
+    fn test_tokio_sleeper() {
+        let (sleeper, waker) = backends::tokio::new_pair();
+        test_sleeper(sleeper, waker);
+    }
+
+    #[test]
+    #[cfg(feature = "oneshot")]
+    fn test_oneshot_sleeper() {
+        let (sleeper, waker) = backends::oneshot::new_pair();
+        test_sleeper(sleeper, waker);
+    }
+
+    #[test]
+    #[cfg(feature = "async_channel")]
+    fn test_async_channel_sleeper() {
+        let (sleeper, waker) = backends::async_channel::new_pair();
+        test_sleeper(sleeper, waker);
+    }
+
+    #[test]
+    #[cfg(feature = "smol")]
+    fn test_smol_sleeper() {
+        let (sleeper, waker) = backends::smol::new_pair();
+        test_sleeper(sleeper, waker);
+    }
+
+    #[test]
+    #[cfg(all(feature = "windows", windows))]
+    fn test_wait_on_address_sleeper() {
+        let (sleeper, waker) = backends::wait_on_address::new_pair();
+        test_sleeper(sleeper, waker);
+    }
+
+    #[test]
+    #[cfg(all(feature = "windows", windows))]
+    fn test_srw_sleeper() {
+        let (sleeper, waker) = backends::srw::new_pair();
+        test_sleeper(sleeper, waker);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_nanosleep_baseline() {
+        let baseline = backends::baseline().unwrap();
+        let (sleeper, waker) = baseline.new_pair();
+        let config = runner::RunConfig::new(5, Duration::from_millis(1))
+            .with_scenario(scenario::Scenario::Timeout);
+        let measurements = runner::run(config, sleeper, waker);
+        check!(measurements.timeouted.len() == 5);
+        check!(measurements.interrupted.is_empty());
+    }
+
+    #[test]
+    fn test_boxed_backends() {
+        for backend in backends::available() {
+            for timeout_mode in TimeoutMode::ALL {
+                eprintln!(
+                    "backend: {}, timeout: {}",
+                    backend.name,
+                    timeout_mode.name()
+                );
+                let (sleeper, waker) = backend.new_pair();
+                test_sleeper_with_timeout_mode(sleeper, waker, timeout_mode);
+            }
+        }
+    }
+
+    #[test]
+    fn test_attribution_split() {
+        let us = Duration::from_micros;
+        let attribution = Attribution::split(us(100), Some(us(30)), us(20));
+        check!(attribution.notification == us(50));
+        check!(attribution.scheduling == Some(us(30)));
+        check!(attribution.return_path == us(20));
+
+        // The run delay also covers the return path and before the sleep.
+        let attribution = Attribution::split(us(100), Some(us(500)), us(20));
+        check!(attribution.notification == Duration::ZERO);
+        check!(attribution.scheduling == Some(us(80)));
+
+        let attribution = Attribution::split(us(100), None, us(20));
+        check!(attribution.notification == us(80));
+        check!(attribution.scheduling.is_none());
+    }
+
+    #[test]
+    fn test_virtual_sleeper() {
+        for timeout_mode in TimeoutMode::ALL {
+            let clock = VirtualClock::new();
+            let (sleeper, waker) = virtual_time::new_pair(&clock);
+            let (sender, receiver) = sync_channel::<Option<WakeupContext>>(1);
+            let sleep_barrier = Arc::new(NoDelayBarrier::new());
+            let thread = sleeper_thread::SleeperThread::spawn_with_clock(
+                sleep_barrier.clone(),
+                sleeper,
+                TIMESLICE,
+                timeout_mode,
+                false,
+                sender,
+                clock.clone(),
+            );
+
+            // The time only advances while the control thread sleeps on the
+            // clock, so the durations are exact.
+            sleep_barrier.wait();
+            clock.sleep(TIMESLICE);
+            let wakeup_context = receiver.recv().unwrap().unwrap();
+            check!(wakeup_context.reason == WakeupReason::Timeout);
+            check!(wakeup_context.actual_duration == TIMESLICE);
+
+            sleep_barrier.wait();
+            clock.sleep(TIMESLICE / 4);
+            waker.wake();
+            let wakeup_context = receiver.recv().unwrap().unwrap();
+            assert2::assert!(matches!(
+                wakeup_context.reason,
+                WakeupReason::Interrupted { .. }
+            ));
+            check!(wakeup_context.actual_duration == TIMESLICE / 4);
+            check!(wakeup_context.delay == Duration::ZERO);
+
+            // The sleeper must go back to sleep after a spurious wake-up.
+            sleep_barrier.wait();
+            waker.wake_spurious();
+            clock.sleep(TIMESLICE);
+            let wakeup_context = receiver.recv().unwrap().unwrap();
+            check!(wakeup_context.reason == WakeupReason::Timeout);
+            check!(wakeup_context.actual_duration == TIMESLICE);
+
+            drop(thread);
+        }
+    }
 }
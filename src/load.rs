@@ -0,0 +1,253 @@
+//! Background load during the measurements. See [`Load`] and
+//! [`LoadGenerator`].
+//!
+//! An idle machine hides sources of tail latency that a real application
+//! faces. A [`LoadGenerator`] runs a [`Load`] on background threads while the
+//! benchmarks measure the wake-ups.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+
+/// Size of the pages touched by the memory load. Smaller than or equal to the
+/// page size on all supported platforms.
+const PAGE_SIZE: usize = 4096;
+
+/// Largest transient allocation of the memory load.
+const MAX_ALLOCATION: usize = 64 << 20;
+
+/// Number of small allocations per cycle of the memory load.
+const SMALL_ALLOCATIONS: usize = 1024;
+
+/// Largest temporary file of the memory load. Smaller than the allocations,
+/// as it is written back to disk.
+const MAX_FILE_SIZE: usize = 8 << 20;
+
+/// Kind of background load.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Load {
+    /// Churns the allocator, the virtual memory, and the page cache.
+    ///
+    /// Runs large transient allocations whose pages are touched, many small
+    /// allocations, on Unix anonymous `mmap`/`munmap` cycles, and temporary
+    /// files that are written, dropped from the page cache, and read back.
+    /// Causes page faults, TLB shootdowns, and contention on allocator and
+    /// kernel locks.
+    Memory,
+}
+
+impl Load {
+    /// All loads.
+    pub const ALL: &[Self] = &[Self::Memory];
+
+    /// Returns the name, as used on the command line.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Memory => "memory",
+        }
+    }
+
+    /// Returns a short human-readable description.
+    #[must_use]
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::Memory => {
+                "allocator churn, page faults, mmap/munmap, and page cache misses on background threads"
+            }
+        }
+    }
+
+    /// Looks up a load by its [name](Self::name).
+    #[must_use]
+    pub fn find(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|load| load.name() == name)
+    }
+
+    /// Runs one cycle of the load.
+    fn cycle(self, rng: &mut fastrand::Rng) {
+        match self {
+            Self::Memory => memory_cycle(rng),
+        }
+    }
+}
+
+/// Background threads running a [`Load`]. The load runs until this is
+/// dropped.
+#[derive(Debug)]
+pub struct LoadGenerator {
+    load: Load,
+    should_stop: Arc<AtomicBool>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl LoadGenerator {
+    /// Starts the load on `threads` background threads.
+    #[must_use]
+    pub fn start(load: Load, threads: usize) -> Self {
+        let should_stop = Arc::new(AtomicBool::new(false));
+        let handles = (0..threads)
+            .map(|i| {
+                let should_stop = should_stop.clone();
+                thread::Builder::new()
+                    .name(format!("load-{i}"))
+                    .spawn(move || {
+                        let mut rng = fastrand::Rng::new();
+                        while !should_stop.load(Ordering::Relaxed) {
+                            load.cycle(&mut rng);
+                        }
+                    })
+                    .expect("should spawn load thread")
+            })
+            .collect();
+        Self {
+            load,
+            should_stop,
+            handles,
+        }
+    }
+
+    #[must_use]
+    pub const fn load(&self) -> Load {
+        self.load
+    }
+
+    #[must_use]
+    pub const fn threads(&self) -> usize {
+        self.handles.len()
+    }
+}
+
+impl Drop for LoadGenerator {
+    fn drop(&mut self) {
+        self.should_stop.store(true, Ordering::Relaxed);
+        for handle in self.handles.drain(..) {
+            handle.join().expect("load thread should not panic");
+        }
+    }
+}
+
+fn memory_cycle(rng: &mut fastrand::Rng) {
+    // A large allocation is served by fresh pages from the OS, so touching
+    // them faults each one in.
+    let mut large = vec![0_u8; rng.usize(PAGE_SIZE..=MAX_ALLOCATION)];
+    for page in large.chunks_mut(PAGE_SIZE) {
+        page[0] = 1;
+    }
+    drop(std::hint::black_box(large));
+
+    let small = (0..SMALL_ALLOCATIONS)
+        .map(|_| vec![0_u8; rng.usize(16..=1024)])
+        .collect::<Vec<_>>();
+    drop(std::hint::black_box(small));
+
+    #[cfg(unix)]
+    mmap_cycle(rng.usize(PAGE_SIZE..=MAX_ALLOCATION));
+
+    let path =
+        std::env::temp_dir().join(format!("load-{}-{:016x}", std::process::id(), rng.u64(..)));
+    page_cache_cycle(&path, rng.usize(PAGE_SIZE..=MAX_FILE_SIZE))
+        .expect("should write a temporary file");
+}
+
+/// Writes a temporary file, drops its pages from the page cache, and reads it
+/// back, which fills the page cache again. The file is removed afterward.
+fn page_cache_cycle(path: &Path, len: usize) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(path)?;
+    let result = (|| {
+        file.write_all(&vec![1_u8; len])?;
+        // Only clean pages can be dropped.
+        file.sync_data()?;
+        drop_cached_pages(&file)?;
+        file.seek(SeekFrom::Start(0))?;
+        let mut contents = Vec::with_capacity(len);
+        file.read_to_end(&mut contents)?;
+        drop(std::hint::black_box(contents));
+        Ok(())
+    })();
+    drop(file);
+    fs::remove_file(path)?;
+    result
+}
+
+/// Drops the pages of `file` from the page cache.
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+fn drop_cached_pages(file: &File) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: The file descriptor is valid for the duration of the call.
+    let ret = unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED) };
+    match ret {
+        0 => Ok(()),
+        errno => Err(io::Error::from_raw_os_error(errno)),
+    }
+}
+
+/// Without `posix_fadvise()`, the file is read back from the page cache.
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+#[allow(clippy::unnecessary_wraps)]
+const fn drop_cached_pages(_file: &File) -> io::Result<()> {
+    Ok(())
+}
+
+/// Maps anonymous memory, touches each page, and unmaps it again.
+#[cfg(unix)]
+fn mmap_cycle(len: usize) {
+    // SAFETY: An anonymous private mapping doesn't alias any memory.
+    let addr = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        )
+    };
+    assert_ne!(addr, libc::MAP_FAILED, "mmap should succeed");
+    for offset in (0..len).step_by(PAGE_SIZE) {
+        // SAFETY: The offset is within the mapping, which is writable.
+        unsafe { addr.cast::<u8>().add(offset).write_volatile(1) };
+    }
+    // SAFETY: The mapping is not used afterward.
+    let ret = unsafe { libc::munmap(addr, len) };
+    assert_eq!(ret, 0, "munmap should succeed");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert2::check;
+    use std::time::Duration;
+
+    #[test]
+    fn test_find() {
+        for load in Load::ALL {
+            check!(Load::find(load.name()) == Some(*load));
+        }
+        check!(Load::find("unknown").is_none());
+    }
+
+    #[test]
+    fn test_page_cache_cycle() {
+        let path = std::env::temp_dir().join(format!("load-test-{}", std::process::id()));
+        check!(page_cache_cycle(&path, 3 * PAGE_SIZE).is_ok());
+        check!(!path.exists());
+    }
+
+    #[test]
+    fn test_generator_stops() {
+        let generator = LoadGenerator::start(Load::Memory, 2);
+        check!(generator.threads() == 2);
+        thread::sleep(Duration::from_millis(50));
+        // Joins the threads.
+        drop(generator);
+    }
+}
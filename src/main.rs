@@ -14,154 +14,1934 @@
     clippy::redundant_pub_crate,
     clippy::fallible_impl_from
 )]
+// serde_derive and clap_derive may depend on different major versions of
+// syn, which can't be unified from here, as Cargo.lock is not committed.
+#![allow(clippy::multiple_crate_versions)]
 #![deny(missing_debug_implementations)]
 
-use assert2::check;
-use benchmark_interruptible_sleep::synchronization::NoDelayBarrier;
-use benchmark_interruptible_sleep::{Measurement, Measurements, Sleeper, Waker, WakeupContext, WakeupReason, channel, sleeper_thread, condvar};
-use std::sync::Arc;
-use std::sync::mpsc::sync_channel;
-use std::thread::sleep;
-use std::time::{Duration, Instant};
-
-/// Optimized sleep that won't have any delay close to the target timeout due to
-/// busy waiting.
-fn sleep_optimized(duration: Duration) {
-    const SAFE_SLEEP: Duration = Duration::from_millis(2);
-    let begin = Instant::now();
-    if duration > SAFE_SLEEP {
-        sleep(duration - SAFE_SLEEP);
-    }
-
-    // busy waiting to not lose a timeout
-    while begin.elapsed() <= duration {}
-}
-
-/// Runs many cycles of [`Sleeper::sleep_interruptible`] in a thread: some
-/// timeout normally and some get interrupted.
-///
-/// Collects the effective delay between the [`Waker::wake`] call and the actual
-/// awakening. While doing so, this thread is synchronized with a corresponding
-/// [`SleeperThread`].
-///
-/// # Arguments
-/// - `timeslice`: The normal time slice for that we put threads into sleep. Reducing the selected
-///   time frame increases the impact of OS scheduling and additional runtime overhead.
-fn test_runs(
+use benchmark_interruptible_sleep::analysis::{
+    self, Baselines, ByWakeOffset, Filter, Reason, RunAnalysis,
+};
+use benchmark_interruptible_sleep::assertions::{Bounds, Verdict};
+use benchmark_interruptible_sleep::backends::Backend;
+use benchmark_interruptible_sleep::capture::SlowRounds;
+use benchmark_interruptible_sleep::clock::SystemClock;
+use benchmark_interruptible_sleep::clockbench::{self, ClockSource};
+use benchmark_interruptible_sleep::coalescing::{self, Phase};
+use benchmark_interruptible_sleep::energy::Rapl;
+use benchmark_interruptible_sleep::idle::CpuDmaLatency;
+use benchmark_interruptible_sleep::load::{Load, LoadGenerator};
+use benchmark_interruptible_sleep::plain::{self, Environment};
+#[cfg(feature = "plugins")]
+use benchmark_interruptible_sleep::plugin;
+use benchmark_interruptible_sleep::precision;
+use benchmark_interruptible_sleep::priority::Priorities;
+use benchmark_interruptible_sleep::recording::{self, RecordingSink, RecordingWriter, RunHeader};
+use benchmark_interruptible_sleep::report::{self, RawRun, RunReport};
+use benchmark_interruptible_sleep::runner::{MeasurementSink, RunConfig};
+use benchmark_interruptible_sleep::scenario::Scenario;
+use benchmark_interruptible_sleep::setup;
+use benchmark_interruptible_sleep::soak::SoakSink;
+use benchmark_interruptible_sleep::stats::{
+    CONFIDENCE_LEVEL, Estimate, Summary, mann_whitney_u, mean_delay, percentile_delay, run_to_run,
+};
+use benchmark_interruptible_sleep::suite::Suite;
+use benchmark_interruptible_sleep::sweep::{self, SweepPoint, SweepRange};
+use benchmark_interruptible_sleep::throughput;
+use benchmark_interruptible_sleep::topology::{CpuPair, Placement, Topology};
+use benchmark_interruptible_sleep::tracefs::TraceMarker;
+use benchmark_interruptible_sleep::units::{format_duration, parse_duration};
+use benchmark_interruptible_sleep::{
+    Measurement, Measurements, TimeoutMode, backends, compare, conformance, contention, interleave,
+    runner,
+};
+use clap::{Parser, Subcommand};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::thread;
+use std::time::Duration;
+
+/// Benchmarks interruptible sleepers: how fast is a sleeping thread running
+/// again after it was woken up?
+#[derive(Debug, Parser)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Arguments of the default command `bench`.
+    #[command(flatten)]
+    bench: BenchArgs,
+    /// Load additional backends from this shared library, see the `plugin`
+    /// module. Can be given multiple times.
+    #[cfg(feature = "plugins")]
+    #[arg(long, global = true, value_name = "LIBRARY")]
+    plugin: Vec<PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Run the benchmarks (default).
+    Bench(Box<BenchArgs>),
+    /// List the compiled-in backends, the scenarios, the loads, and the
+    /// placements.
+    List,
+    /// Recompute the statistics from raw measurements, see `bench --raw`.
+    Analyze {
+        /// Raw measurements written by `bench --raw`, as JSON, CSV, or
+        /// recording.
+        file: PathBuf,
+        /// Only analyze runs of this backend.
+        #[arg(long)]
+        backend: Option<String>,
+        /// Only analyze runs with this timeslice, e.g. `10ms`.
+        #[arg(long, value_parser = parse_duration)]
+        timeslice: Option<Duration>,
+        /// Only analyze measurements with this wakeup reason: `interrupted` or
+        /// `timeouted`.
+        #[arg(long)]
+        reason: Option<Reason>,
+    },
+    /// Measure the resolution and call overhead of the clocks of this
+    /// machine.
+    Clockbench {
+        /// Number of readings of each clock to measure the overhead.
+        #[arg(long, default_value_t = 1_000_000)]
+        iterations: usize,
+    },
+    /// Show the CPU topology and the CPUs each placement of `bench
+    /// --placement` would use. Linux only.
+    Topology,
+    /// Check the semantics of the backends that the benchmarks rely on, see
+    /// the `conformance` module. Exits with code 2 if a backend violates
+    /// them.
+    Conformance {
+        /// Only check this backend.
+        #[arg(long)]
+        backend: Option<String>,
+    },
+    /// Compare two result files, see `bench --json`.
+    Compare {
+        /// Results of the baseline.
+        old: PathBuf,
+        /// Results to compare with the baseline.
+        new: PathBuf,
+        /// Mark changes of the mean, p50, or p99 delay above this percentage
+        /// as regressions.
+        #[arg(long, default_value_t = 10.0)]
+        threshold: f64,
+    },
+}
+
+#[derive(Debug, clap::Args)]
+#[command(group(clap::ArgGroup::new("assertions").multiple(true)))]
+struct BenchArgs {
+    /// Number of sleep cycles per backend and timeslice.
+    #[arg(long, default_value_t = 100)]
     rounds: usize,
-    sleeper: impl Sleeper + Send + 'static,
-    waker: impl Waker + 'static,
+    /// Run each backend and timeslice this many times, each time with fresh
+    /// threads and the same schedule, and report the variation of the delay
+    /// between the runs alongside the variation within them.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..), conflicts_with_all = ["sweep", "versus", "interleave", "soak", "compare_idle_states", "compare_timeout_modes", "coalescing", "handshake_cost", "cold_warm", "precision", "contention", "setup_cost", "throughput", "suite"])]
+    repeats: u32,
+    /// Benchmark a logarithmic range of timeslices instead of the default
+    /// list, e.g. `100us..100ms`.
+    #[arg(long, value_name = "FROM..TO")]
+    sweep: Option<SweepRange>,
+    /// Number of timeslices per power of ten in sweep mode.
+    #[arg(long, default_value_t = 4, requires = "sweep")]
+    sweep_steps: usize,
+    /// Seed for the random schedule of the interruptions. Random by default.
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Schedule of the interruptions, see the `list` command.
+    #[arg(long, default_value = "mixed", value_parser = parse_scenario)]
+    scenario: Scenario,
+    /// Percentage of the rounds that are interrupted in the `mixed` and
+    /// `spurious` scenarios.
+    #[arg(long, default_value_t = 50, value_parser = clap::value_parser!(u8).range(0..=100))]
+    interrupt_percent: u8,
+    /// Compare two backends with the same schedule and test whether their
+    /// delays differ significantly, e.g. `condvar,futex`.
+    #[arg(long, value_name = "A,B", value_parser = parse_backend_pair, conflicts_with = "sweep")]
+    versus: Option<(String, String)>,
+    /// Alternate between the backends after this many rounds (A, B, A, B,
+    /// ...) instead of running each backend to completion, so slow drift of
+    /// the environment affects all backends equally. Applies to the default
+    /// mode and `--versus`.
+    #[arg(long, value_name = "BATCH_ROUNDS", conflicts_with_all = ["sweep", "soak", "compare_idle_states", "compare_timeout_modes", "coalescing", "handshake_cost", "cold_warm", "precision", "contention", "setup_cost", "throughput", "suite"])]
+    interleave: Option<NonZeroUsize>,
+    /// Significance level of the comparisons of `--versus`,
+    /// `--compare-idle-states`, `--compare-timeout-modes`, `--cold-warm`, and
+    /// `--handshake-cost`.
+    #[arg(long, default_value_t = 0.05)]
+    alpha: f64,
+    /// Keep the CPUs out of idle states with an exit latency above this, e.g.
+    /// `0us`, by holding a request on `/dev/cpu_dma_latency` during the run.
+    /// Linux only, typically requires root.
+    #[arg(long, value_name = "LATENCY", value_parser = parse_duration)]
+    cpu_dma_latency: Option<Duration>,
+    /// Run this background load during all measurements, see the `list`
+    /// command.
+    #[arg(long, value_parser = parse_load)]
+    load: Option<Load>,
+    /// Number of threads of `--load`. Half of the CPUs by default.
+    #[arg(long, requires = "load")]
+    load_threads: Option<usize>,
+    /// Pin the waker and the sleeper to two CPUs with this relation, picked
+    /// from the CPU topology, see the `topology` command. Linux only.
+    #[arg(long, value_parser = parse_placement)]
+    placement: Option<Placement>,
+    /// Nice level of the waker, i.e., the control thread, during the runs,
+    /// e.g., `10` to wake the sleeper from a thread with a lower priority.
+    /// Levels below the current one typically require root. Linux only.
+    #[arg(long, allow_hyphen_values = true, value_parser = clap::value_parser!(i32).range(-20..=19), conflicts_with_all = ["coalescing", "precision", "contention", "setup_cost", "throughput"])]
+    waker_nice: Option<i32>,
+    /// Like `--waker-nice` but for the sleeper thread.
+    #[arg(long, allow_hyphen_values = true, value_parser = clap::value_parser!(i32).range(-20..=19), conflicts_with_all = ["coalescing", "precision", "contention", "setup_cost", "throughput"])]
+    sleeper_nice: Option<i32>,
+    /// Run each backend and timeslice with deep idle states allowed and
+    /// prevented by `--cpu-dma-latency` (default `0us`), and compare the
+    /// delays.
+    #[arg(long, conflicts_with_all = ["sweep", "versus", "soak"])]
+    compare_idle_states: bool,
+    /// Pass the timeout to the sleeper as `relative` duration or as
+    /// `absolute` deadline.
+    #[arg(long, default_value = "relative", value_parser = parse_timeout_mode)]
+    timeout_mode: TimeoutMode,
+    /// Run each backend and timeslice with relative and with absolute
+    /// timeouts and compare the delays.
+    #[arg(long, conflicts_with_all = ["sweep", "versus", "soak", "compare_idle_states", "timeout_mode"])]
+    compare_timeout_modes: bool,
+    /// Run the backend indefinitely and print rolling statistics of the last
+    /// minute, the last ten minutes, and the total. Stop with Ctrl+C. With
+    /// `--raw FILE.bin`, the measurements are streamed to the file.
+    #[arg(long, value_name = "BACKEND", conflicts_with_all = ["sweep", "versus", "json"])]
+    soak: Option<String>,
+    /// Issue this many wake-ups at once, before and during a sleep, and
+    /// report for each backend whether they are coalesced, skip later sleeps,
+    /// or deadlock.
+    #[arg(long, value_name = "WAKES", conflicts_with_all = ["sweep", "versus", "soak", "compare_idle_states", "compare_timeout_modes", "json", "raw"])]
+    coalescing: Option<usize>,
+    /// Run each backend that supports detached wake-ups with and without the
+    /// ACK handshake of the harness, and report the difference of the delays
+    /// as the intrusion cost of the handshake. Uses the `always` scenario.
+    #[arg(long, conflicts_with_all = ["sweep", "versus", "soak", "compare_idle_states", "compare_timeout_modes", "coalescing", "precision"])]
+    handshake_cost: bool,
+    /// Compare the delays of cold wake-ups, after the sleeper slept for
+    /// `--idle`, with warm wake-ups in a tight loop with `--timeslice`. Both
+    /// runs use the `always` scenario.
+    #[arg(long, conflicts_with_all = ["sweep", "versus", "soak", "compare_idle_states", "compare_timeout_modes", "coalescing", "handshake_cost"])]
+    cold_warm: bool,
+    /// Time the sleeper sleeps before each cold wake-up.
+    #[arg(long, default_value = "2s", value_parser = parse_duration, requires = "cold_warm")]
+    idle: Duration,
+    /// Number of cold wake-ups per backend.
+    #[arg(long, default_value_t = 10, requires = "cold_warm")]
+    cold_rounds: usize,
+    /// Sleep this many times in a row without interruptions and report the
+    /// overshoot of the timeouts and their cumulative drift per backend.
+    #[arg(long, value_name = "SLEEPS", conflicts_with_all = ["sweep", "versus", "soak", "compare_idle_states", "compare_timeout_modes", "coalescing", "cold_warm", "json", "raw"])]
+    precision: Option<usize>,
+    /// Block 1, 2, 4, ... up to this many sleepers at once, wake a random one
+    /// of them each round, and report how the delay scales with the number
+    /// of sleepers. Should not exceed the number of CPUs.
+    #[arg(long, value_name = "MAX_SLEEPERS", conflicts_with_all = ["sweep", "versus", "soak", "compare_idle_states", "compare_timeout_modes", "coalescing", "cold_warm", "precision", "handshake_cost", "placement", "json", "raw"])]
+    contention: Option<NonZeroUsize>,
+    /// Construct this many pairs per backend and report the cost of the
+    /// construction, of the teardown, and of the first wake-up compared
+    /// with later ones.
+    #[arg(long, value_name = "SAMPLES", conflicts_with_all = ["sweep", "versus", "soak", "compare_idle_states", "compare_timeout_modes", "coalescing", "cold_warm", "precision", "handshake_cost", "contention", "json", "raw"])]
+    setup_cost: Option<usize>,
+    /// Wake each backend in a closed loop for this long, e.g. `1s`, with the
+    /// sleeper going back to sleep immediately, and report the sustained
+    /// wake-ups per second alongside their latency.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, conflicts_with_all = ["sweep", "versus", "soak", "compare_idle_states", "compare_timeout_modes", "coalescing", "cold_warm", "precision", "handshake_cost", "contention", "setup_cost", "placement", "json", "raw"])]
+    throughput: Option<Duration>,
+    /// Execute the runs of this JSON suite file in order, each with its own
+    /// backend, timeslice, rounds, scenario, interrupt percentage, timeout
+    /// mode, seed, placement, and load, and aggregate them into one report.
+    /// See the README for the format.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["sweep", "versus", "soak", "compare_idle_states", "compare_timeout_modes", "coalescing", "cold_warm", "precision", "handshake_cost", "contention", "setup_cost", "throughput", "baseline", "rounds", "scenario", "interrupt_percent", "timeout_mode", "placement", "load"])]
+    suite: Option<PathBuf>,
+    /// Timeslice in soak, coalescing, cold-warm, precision, contention, and
+    /// setup-cost mode.
+    #[arg(long, default_value = "10ms", value_parser = parse_duration)]
     timeslice: Duration,
-) -> Measurements {
-    let waker = Arc::new(waker);
-    let mut timeouted_results = Vec::<Measurement>::with_capacity(1_000_000);
-    let mut interrupted_results = Vec::<Measurement>::with_capacity(1_000_000);
-    // We only transport one item at a time. Threads are synchronized.
-    let (sender, receiver) = sync_channel::<WakeupContext>(1);
-    let sleep_barrier = Arc::new(NoDelayBarrier::new());
-    let _thread =
-        sleeper_thread::SleeperThread::spawn(sleep_barrier.clone(), sleeper, timeslice, sender);
-
-    loop {
-        if interrupted_results.len() + timeouted_results.len() >= rounds {
-            break;
-        }
-
-        let do_interrupt = fastrand::bool();
-        let sleep_duration = if do_interrupt {
-            let max_us = timeslice.as_micros() as usize;
-            let max_us = max_us * 95 / 100;
-            let rand_us = fastrand::usize(0..=max_us);
-            Duration::from_micros(rand_us as u64)
+    /// Interval of the reports in soak mode.
+    #[arg(long, default_value = "10s", value_parser = parse_duration, requires = "soak")]
+    report_interval: Duration,
+    /// Additionally run the non-interruptible `nanosleep` baseline with every
+    /// timeslice, and report the timeout overshoot of each backend relative
+    /// to it. Linux only.
+    #[arg(long, conflicts_with_all = ["sweep", "versus", "soak", "compare_idle_states", "compare_timeout_modes", "coalescing", "cold_warm", "precision", "handshake_cost"])]
+    baseline: bool,
+    /// Fail with exit code 2 unless the mean delay of the interrupted
+    /// wake-ups is below this bound in every run, e.g., in CI.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, group = "assertions", conflicts_with_all = ["soak", "coalescing", "precision", "contention", "setup_cost", "throughput"])]
+    assert_mean_delay_below: Option<Duration>,
+    /// Like `--assert-mean-delay-below` but for the p99 delay.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, group = "assertions", conflicts_with_all = ["soak", "coalescing", "precision", "contention", "setup_cost", "throughput"])]
+    assert_p99_below: Option<Duration>,
+    /// Only check the runs of this backend against the assertions.
+    #[arg(long, value_name = "BACKEND", requires = "assertions")]
+    assert_backend: Option<String>,
+    /// Write the verdict of the assertions as JSON to this file.
+    #[arg(long, value_name = "FILE", requires = "assertions")]
+    assert_json: Option<PathBuf>,
+    /// Record the CPU of the sleeper thread before and after each sleep and
+    /// its run delay from `/proc/thread-self/schedstat`, to report
+    /// migrations and how much of the delay was spent waiting for a CPU.
+    /// Costs a few syscalls per round. Linux only.
+    #[arg(long, conflicts_with_all = ["coalescing", "precision", "setup_cost", "throughput"])]
+    sched_stats: bool,
+    /// Write the begin, the wake-up, and the end of each round to the ftrace
+    /// `trace_marker`, to correlate `perf` or `trace-cmd` recordings with the
+    /// rounds. Linux only, typically requires root.
+    #[arg(long, conflicts_with_all = ["coalescing", "precision", "contention", "setup_cost", "throughput"])]
+    trace_markers: bool,
+    /// Record the scheduler events of all CPUs with ftrace and save the
+    /// trace of each round whose delay exceeds this threshold, e.g.,
+    /// `500us`, at most 10 per run. Implies `--trace-markers`. Linux only,
+    /// requires root.
+    #[arg(long, value_name = "DELAY", value_parser = parse_duration, conflicts_with_all = ["coalescing", "precision", "contention", "setup_cost", "throughput", "interleave"])]
+    capture_slow_rounds: Option<Duration>,
+    /// Directory of the traces of `--capture-slow-rounds`.
+    #[arg(
+        long,
+        value_name = "DIR",
+        default_value = "traces",
+        requires = "capture_slow_rounds"
+    )]
+    capture_dir: PathBuf,
+    /// Output format: `human` is meant to be read; `plain` prints a
+    /// column-aligned, deterministic report of the environment and all runs
+    /// at the end, meant to be committed and diffed, and moves the regular
+    /// output to stderr.
+    #[arg(long, default_value = "human", value_parser = ["human", "plain"], conflicts_with_all = ["soak", "coalescing", "precision", "contention", "setup_cost", "throughput"])]
+    format: String,
+    /// Write the statistics of all runs as JSON to this file.
+    #[arg(long, value_name = "FILE")]
+    json: Option<PathBuf>,
+    /// Write the raw measurements of all runs to this file: as CSV if the
+    /// file name ends with `.csv`, as compact binary recording if it ends
+    /// with `.bin`, otherwise as JSON.
+    #[arg(long, value_name = "FILE")]
+    raw: Option<PathBuf>,
+    /// Write a timeline of all rounds in the Chrome trace format to this
+    /// file, e.g., for Perfetto.
+    #[cfg(feature = "trace")]
+    #[arg(long, value_name = "FILE")]
+    trace: Option<PathBuf>,
+}
+
+/// Default timeslices in milliseconds.
+const TIMESLICES_MS: [u64; 6] = [2, 5, 10, 25, 50, 100];
+
+/// Returns the backend of a name from the command line. The names are only
+/// checked after parsing, once the plugins are loaded.
+fn find_backend(name: &str) -> io::Result<&'static Backend> {
+    backends::find(name).ok_or_else(|| {
+        let names = backends::available()
+            .iter()
+            .map(|backend| backend.name)
+            .collect::<Vec<_>>();
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "unknown or not compiled-in backend `{name}`, available: {}",
+                names.join(", ")
+            ),
+        )
+    })
+}
+
+fn parse_backend_pair(s: &str) -> Result<(String, String), String> {
+    let (a, b) = s
+        .split_once(',')
+        .ok_or_else(|| "expected two backends, e.g. `condvar,futex`".to_string())?;
+    Ok((a.to_string(), b.to_string()))
+}
+
+fn parse_scenario(name: &str) -> Result<Scenario, String> {
+    Scenario::find(name).ok_or_else(|| {
+        let names = Scenario::ALL
+            .iter()
+            .map(|scenario| scenario.name())
+            .collect::<Vec<_>>();
+        format!("unknown scenario `{name}`, available: {}", names.join(", "))
+    })
+}
+
+fn parse_placement(name: &str) -> Result<Placement, String> {
+    Placement::find(name).ok_or_else(|| {
+        let names = Placement::ALL.iter().map(|p| p.name()).collect::<Vec<_>>();
+        format!(
+            "unknown placement `{name}`, available: {}",
+            names.join(", ")
+        )
+    })
+}
+
+fn parse_load(name: &str) -> Result<Load, String> {
+    Load::find(name).ok_or_else(|| {
+        let names = Load::ALL.iter().map(|load| load.name()).collect::<Vec<_>>();
+        format!("unknown load `{name}`, available: {}", names.join(", "))
+    })
+}
+
+fn parse_timeout_mode(name: &str) -> Result<TimeoutMode, String> {
+    TimeoutMode::find(name)
+        .ok_or_else(|| format!("unknown timeout mode `{name}`, expected relative or absolute"))
+}
+
+fn format_estimate(estimate: &Estimate) -> String {
+    format!(
+        "{:>5} µs ({:.0}% CI: {}..{} µs)",
+        estimate.value.as_micros(),
+        CONFIDENCE_LEVEL * 100.0,
+        estimate.ci_low.as_micros(),
+        estimate.ci_high.as_micros()
+    )
+}
+
+fn print_summary(out: &mut dyn Write, label: &str, summary: Option<&Summary>) -> io::Result<()> {
+    writeln!(out, "  {label:<11} (#): {}", summary.map_or(0, |s| s.count))?;
+    if let Some(summary) = summary {
+        writeln!(out, "  |- mean delay  : {}", format_estimate(&summary.mean))?;
+        writeln!(out, "  |- p50 delay   : {}", format_estimate(&summary.p50))?;
+        writeln!(out, "  |- p99 delay   : {}", format_estimate(&summary.p99))?;
+        writeln!(
+            out,
+            "  |- stddev      : {:>5} µs",
+            summary.stddev.as_micros()
+        )?;
+        writeln!(
+            out,
+            "  |- jitter      : {:>5} µs",
+            summary.jitter.as_micros()
+        )?;
+    }
+    Ok(())
+}
+
+fn print_analysis(out: &mut dyn Write, report: &RunReport) -> io::Result<()> {
+    if let Some(failure) = &report.failure {
+        writeln!(out, "FAILED: {failure}")?;
+    }
+    writeln!(out, "Rounds        (#): {}", report.rounds)?;
+    print_summary(out, "interrupted", report.interrupted.as_ref())?;
+    print_summary(out, "timeouted", report.timeouted.as_ref())?;
+    if report.skipped > 0 {
+        writeln!(out, "  skipped     (#): {}", report.skipped)?;
+    }
+    if report.negative_delays > 0 {
+        writeln!(
+            out,
+            "  negative    (#): {}, returned before the expected duration",
+            report.negative_delays
+        )?;
+    }
+    if let Some(energy) = report.energy_per_1000_wakeups() {
+        writeln!(
+            out,
+            "  energy         : {:.1} mJ per 1000 wake-ups",
+            energy as f64 / 1000.0
+        )?;
+    }
+    if let Some(threads) = &report.threads {
+        writeln!(out, "  threads        : {threads}")?;
+    }
+    Ok(())
+}
+
+fn print_scheduling(out: &mut dyn Write, run: &RunAnalysis) -> io::Result<()> {
+    if let Some(breakdown) = run.breakdown() {
+        writeln!(out, "  delay          : {breakdown}")?;
+    }
+    if let Some(attribution) = run.attribution() {
+        writeln!(out, "  attribution    : {attribution}")?;
+    }
+    if let Some(migrations) = run.migrations() {
+        writeln!(out, "  migrated    (%): {migrations}")?;
+    }
+    let buckets = run.interrupted.offsets();
+    if !buckets.is_empty() {
+        writeln!(out, "  wake offset    : {}", ByWakeOffset(&buckets))?;
+    }
+    Ok(())
+}
+
+fn print_comparison(
+    out: &mut dyn Write,
+    label: &str,
+    a: &[Measurement],
+    b: &[Measurement],
+    alpha: f64,
+) -> io::Result<()> {
+    writeln!(
+        out,
+        "  {label:<11}: mean delay {:>5} µs vs {:>5} µs",
+        mean_delay(a).as_micros(),
+        mean_delay(b).as_micros()
+    )?;
+    match mann_whitney_u(a, b) {
+        Some(res) => writeln!(
+            out,
+            "  |- Mann-Whitney U: p={:.4} -> {}",
+            res.p_value,
+            if res.is_significant(alpha) {
+                "significant"
+            } else {
+                "not significant"
+            }
+        ),
+        None => writeln!(out, "  |- Mann-Whitney U: not enough measurements"),
+    }
+}
+
+fn print_run_to_run(out: &mut dyn Write, reports: &[RunReport]) -> io::Result<()> {
+    for (label, summaries) in [
+        (
+            "interrupted",
+            reports
+                .iter()
+                .filter_map(|report| report.interrupted)
+                .collect::<Vec<_>>(),
+        ),
+        (
+            "timeouted",
+            reports
+                .iter()
+                .filter_map(|report| report.timeouted)
+                .collect::<Vec<_>>(),
+        ),
+    ] {
+        let Some(res) = run_to_run(&summaries) else {
+            continue;
+        };
+        writeln!(out, "  {label:<11} (#): {} runs", res.runs)?;
+        writeln!(
+            out,
+            "  |- mean delay  : {:>5} µs (runs: {}..{} µs)",
+            res.mean.as_micros(),
+            res.min.as_micros(),
+            res.max.as_micros()
+        )?;
+        writeln!(
+            out,
+            "  |- between runs: {:>5} µs stddev of the means ({:.1}%)",
+            res.between.as_micros(),
+            res.between_percent()
+        )?;
+        writeln!(
+            out,
+            "  |- within runs : {:>5} µs stddev, pooled",
+            res.within.as_micros()
+        )?;
+    }
+    Ok(())
+}
+
+/// Where the raw measurements of the runs go, see `--raw`.
+#[derive(Debug)]
+enum Raw {
+    None,
+    /// Collected and written at the end, as CSV or JSON.
+    Collected(Vec<RawRun>),
+    /// Written to the recording while measuring.
+    Recording(RecordingWriter<BufWriter<File>>),
+}
+
+impl Raw {
+    /// Creates the recording right away if `path` is one.
+    fn new(path: Option<&Path>) -> io::Result<Self> {
+        match path {
+            Some(path) if report::is_recording(path) => {
+                let file = File::create(path).map_err(with_path(path))?;
+                Ok(Self::Recording(RecordingWriter::new(BufWriter::new(file))?))
+            }
+            Some(_) => Ok(Self::Collected(Vec::new())),
+            None => Ok(Self::None),
+        }
+    }
+
+    /// Runs the backend with `sink` and writes the measurements to the
+    /// recording, if any, while measuring. `header` describes the run in the
+    /// recording.
+    fn run(
+        &mut self,
+        backend: &Backend,
+        config: RunConfig,
+        header: &RunHeader,
+        sink: &mut impl MeasurementSink,
+    ) -> io::Result<()> {
+        let (sleeper, waker) = backend.new_pair();
+        if let Self::Recording(writer) = self {
+            let mut sink = RecordingSink::new(sink, writer, header)?;
+            runner::run_with_sink(config, sleeper, waker, SystemClock, &mut sink);
         } else {
-            timeslice
+            runner::run_with_sink(config, sleeper, waker, SystemClock, sink);
+        }
+        Ok(())
+    }
+
+    /// Writes the collected runs to `path` or flushes the recording.
+    fn finish(self, path: &Path) -> io::Result<()> {
+        match self {
+            Self::None => Ok(()),
+            Self::Collected(runs) => report::write_raw(path, &runs),
+            Self::Recording(writer) => writer.finish().map(drop),
+        }
+    }
+}
+
+/// Destination of the regular output of the `bench` command: stdout, or
+/// stderr with `--format plain`, so that stdout only contains the plain-text
+/// report.
+#[derive(Debug, Clone, Copy)]
+enum Output {
+    Stdout,
+    Stderr,
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Stdout => io::stdout().write(buf),
+            Self::Stderr => io::stderr().write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Stdout => io::stdout().flush(),
+            Self::Stderr => io::stderr().flush(),
+        }
+    }
+}
+
+/// State of the `bench` command: the common parameters of all runs and the
+/// collected results.
+#[derive(Debug)]
+struct Bench {
+    rounds: usize,
+    seed: u64,
+    scenario: Scenario,
+    interrupt_percent: u8,
+    timeout_mode: TimeoutMode,
+    /// Whether the wake-ups skip the ACK handshake.
+    detached_wakes: bool,
+    /// The active request, if any.
+    cpu_dma_latency: Option<CpuDmaLatency>,
+    /// The background load, if any.
+    load: Option<LoadGenerator>,
+    /// The RAPL counters, if available.
+    rapl: Option<Rapl>,
+    /// The placement of the threads and the CPUs they are pinned to, if any.
+    placement: Option<(Placement, CpuPair)>,
+    /// The nice levels of the threads.
+    priorities: Priorities,
+    /// Whether the rounds are marked in the kernel trace.
+    trace_markers: bool,
+    /// Whether the CPUs and the run delay of the sleeper are recorded.
+    sched_stats: bool,
+    /// The capture of the scheduler trace of slow rounds, if requested.
+    slow_rounds: Option<SlowRounds>,
+    /// The timeout baseline, if requested.
+    baseline: Option<&'static Backend>,
+    /// The batch size if the backends are interleaved.
+    interleave: Option<NonZeroUsize>,
+    /// Number of runs of each backend and timeslice.
+    repeats: usize,
+    /// Index of the current run among the repeats, see [`RunReport::repeat`].
+    repeat: usize,
+    reports: Vec<RunReport>,
+    raw: Raw,
+    /// Where the regular output goes.
+    out: Output,
+}
+
+impl Bench {
+    fn config(&self, timeslice: Duration) -> RunConfig {
+        RunConfig::new(self.rounds, timeslice)
+            .with_seed(self.seed)
+            .with_scenario(self.scenario)
+            .with_interrupt_percent(self.interrupt_percent)
+            .with_timeout_mode(self.timeout_mode)
+            .with_detached_wakes(self.detached_wakes)
+            .with_cpus(self.placement.map(|(_, cpus)| cpus))
+            .with_priorities(self.priorities)
+            .with_trace_markers(self.trace_markers || self.slow_rounds.is_some())
+            .with_scheduling(self.sched_stats)
+    }
+
+    /// Returns the parameters of a run for its raw measurements, including
+    /// the conditions of the bench that are not part of `config`.
+    fn run_header(&self, backend: &Backend, config: &RunConfig) -> RunHeader {
+        RunHeader {
+            repeat: self.repeat,
+            cpu_dma_latency: self.cpu_dma_latency.as_ref().map(CpuDmaLatency::latency),
+            load: self
+                .load
+                .as_ref()
+                .map(|load| load.load().name().to_string()),
+            placement: self
+                .placement
+                .map(|(placement, _)| placement.name().to_string()),
+            ..RunHeader::new(backend.name, config)
+        }
+    }
+
+    /// Runs the backend and collects the results of the run.
+    ///
+    /// With a recording, the measurements are only accumulated, so the memory
+    /// stays bounded, no matter how many rounds the run has.
+    fn run_backend(
+        &mut self,
+        backend: &'static Backend,
+        timeslice: Duration,
+    ) -> io::Result<RunAnalysis> {
+        let config = self.config(timeslice).with_backend(backend.name);
+        let header = self.run_header(backend, &config);
+        if !matches!(self.raw, Raw::Recording(_)) {
+            let measurements = self.run_measured(backend, timeslice)?;
+            return Ok(RunAnalysis::of_measurements(header, &measurements));
+        }
+        let mut analysis = RunAnalysis::new(header.clone());
+        let energy = self.run_with(backend, config, &header, &mut analysis)?;
+        self.reports.push(RunReport {
+            energy,
+            ..RunReport::from_analysis(&analysis)
+        });
+        Ok(analysis)
+    }
+
+    /// Runs the backend, collects the results of the run, and returns all its
+    /// measurements, e.g., for a comparison. Not for recordings, see
+    /// [`Self::run_backend`].
+    fn run_measured(
+        &mut self,
+        backend: &'static Backend,
+        timeslice: Duration,
+    ) -> io::Result<Measurements> {
+        let config = self.config(timeslice).with_backend(backend.name);
+        let header = self.run_header(backend, &config);
+        let mut measurements = Measurements::default();
+        let energy = self.run_with(backend, config, &header, &mut measurements)?;
+        self.collect(backend, &config, &measurements, energy);
+        Ok(measurements)
+    }
+
+    /// Runs the backend into `sink`, saves the traces of the slow rounds, and
+    /// returns the consumed energy.
+    fn run_with(
+        &mut self,
+        backend: &'static Backend,
+        config: RunConfig,
+        header: &RunHeader,
+        sink: &mut impl MeasurementSink,
+    ) -> io::Result<Option<u64>> {
+        let energy_before = self.rapl.as_ref().and_then(Rapl::read);
+        match &self.slow_rounds {
+            Some(slow_rounds) => {
+                let mut sink = slow_rounds.sink(sink, backend.name, config.timeslice);
+                self.raw.run(backend, config, header, &mut sink)?;
+                if !sink.traces().is_empty() {
+                    writeln!(
+                        self.out,
+                        "  slow rounds    : {} traces saved to {}",
+                        sink.traces().len(),
+                        slow_rounds.dir().display()
+                    )?;
+                }
+            }
+            None => self.raw.run(backend, config, header, sink)?,
+        }
+        Ok(self
+            .rapl
+            .as_ref()
+            .zip(energy_before)
+            .and_then(|(rapl, before)| rapl.read().map(|after| rapl.consumed(&before, &after))))
+    }
+
+    /// Runs the backends interleaved in batches and collects the results of
+    /// the runs. The energy is not measured.
+    ///
+    /// The batches of the backends alternate, so all measurements are kept
+    /// until the runs are complete. Not for recordings, see
+    /// [`Self::run_backend`].
+    fn run_interleaved(
+        &mut self,
+        backends: &[&Backend],
+        timeslice: Duration,
+        batch_rounds: NonZeroUsize,
+    ) -> io::Result<Vec<Measurements>> {
+        let config = self.config(timeslice);
+        let interleaved = interleave::run(config, backends, batch_rounds);
+        for (backend, measurements) in backends.iter().zip(&interleaved.measurements) {
+            self.collect(backend, &config, measurements, None);
+            let report = self
+                .reports
+                .last_mut()
+                .expect("should have the report of the run");
+            report.batch_seeds.clone_from(&interleaved.batch_seeds);
+        }
+        Ok(interleaved.measurements)
+    }
+
+    /// Collects the report and the raw measurements of a run.
+    fn collect(
+        &mut self,
+        backend: &Backend,
+        config: &RunConfig,
+        measurements: &Measurements,
+        energy: Option<u64>,
+    ) {
+        let header = self.run_header(backend, config);
+        self.reports.push(RunReport {
+            cpu_dma_latency: header.cpu_dma_latency,
+            load: header.load.clone(),
+            energy,
+            placement: header.placement.clone(),
+            repeat: header.repeat,
+            ..RunReport::new(backend.name, config, measurements)
+        });
+        if let Raw::Collected(runs) = &mut self.raw {
+            runs.push(RawRun::with_header(header, measurements));
+        }
+    }
+
+    /// Runs the baseline with the timeout scenario and returns the mean delay
+    /// of the timeouts.
+    fn run_baseline(
+        &mut self,
+        baseline: &'static Backend,
+        timeslice: Duration,
+    ) -> io::Result<Duration> {
+        writeln!(
+            self.out,
+            "BASELINE: {} Sleeper, timeslice={:>3}ms, rounds={}",
+            baseline.name,
+            timeslice.as_millis(),
+            self.rounds
+        )?;
+        let scenario = self.scenario;
+        self.scenario = Scenario::Timeout;
+        let analysis = self.run_backend(baseline, timeslice);
+        self.scenario = scenario;
+        let analysis = analysis?;
+        print_analysis(
+            &mut self.out,
+            self.reports
+                .last()
+                .expect("should have the report of the run"),
+        )?;
+        writeln!(self.out)?;
+        Ok(analysis.timeouted.delays.mean())
+    }
+
+    fn run_default(&mut self) -> io::Result<()> {
+        let rounds = self.rounds;
+        let available = backends::available().iter().collect::<Vec<_>>();
+        for timeslice in TIMESLICES_MS {
+            let baseline = self
+                .baseline
+                .map(|baseline| self.run_baseline(baseline, Duration::from_millis(timeslice)))
+                .transpose()?;
+            let first = self.reports.len();
+            let interleaved = match self.interleave {
+                Some(batch_rounds) => self.run_interleaved(
+                    &available,
+                    Duration::from_millis(timeslice),
+                    batch_rounds,
+                )?,
+                None => Vec::new(),
+            };
+            for (i, backend) in available.iter().enumerate() {
+                // The repeats conflict with interleaving, so the runs of a
+                // backend are consecutive. They all use the seed of the
+                // bench: with the same schedule of interruptions, the spread
+                // of their means is the run-to-run variation of the system
+                // and the backend, not of the schedule.
+                let first_repeat = first + i * self.repeats;
+                for repeat in 0..self.repeats {
+                    let of_repeats = if self.repeats > 1 {
+                        format!(", repeat {}/{}", repeat + 1, self.repeats)
+                    } else {
+                        String::new()
+                    };
+                    writeln!(
+                        self.out,
+                        "TEST RUN: {} Sleeper, timeslice={:>3}ms, rounds={rounds}{of_repeats}",
+                        backend.name, timeslice
+                    )?;
+                    let analysis = match interleaved.get(i) {
+                        Some(measurements) => {
+                            let config = self.config(Duration::from_millis(timeslice));
+                            let header = self.run_header(backend, &config);
+                            RunAnalysis::of_measurements(header, measurements)
+                        }
+                        None => {
+                            self.repeat = repeat;
+                            let analysis =
+                                self.run_backend(backend, Duration::from_millis(timeslice));
+                            self.repeat = 0;
+                            analysis?
+                        }
+                    };
+                    let report = &self.reports[first_repeat + repeat];
+                    print_analysis(&mut self.out, report)?;
+                    print_scheduling(&mut self.out, &analysis)?;
+                    if let (Some(baseline), Some(timeouted)) = (baseline, report.timeouted.as_ref())
+                    {
+                        writeln!(
+                            self.out,
+                            "  overshoot      : {:+} µs vs baseline",
+                            timeouted.mean.value.as_micros() as i128 - baseline.as_micros() as i128
+                        )?;
+                    }
+                    writeln!(self.out)?;
+                }
+                if self.repeats > 1 {
+                    writeln!(
+                        self.out,
+                        "REPEATS: {} Sleeper, timeslice={:>3}ms, {} runs",
+                        backend.name, timeslice, self.repeats
+                    )?;
+                    print_run_to_run(
+                        &mut self.out,
+                        &self.reports[first_repeat..first_repeat + self.repeats],
+                    )?;
+                    writeln!(self.out)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn run_versus(
+        &mut self,
+        a: &'static Backend,
+        b: &'static Backend,
+        alpha: f64,
+    ) -> io::Result<()> {
+        let rounds = self.rounds;
+        for timeslice in TIMESLICES_MS {
+            writeln!(
+                self.out,
+                "VERSUS: {} vs {} Sleeper, timeslice={:>3}ms, rounds={rounds}, alpha={alpha}",
+                a.name, b.name, timeslice
+            )?;
+            let timeslice = Duration::from_millis(timeslice);
+            let [measurements_a, measurements_b] = match self.interleave {
+                Some(batch_rounds) => self
+                    .run_interleaved(&[a, b], timeslice, batch_rounds)?
+                    .try_into()
+                    .expect("should have the measurements of both backends"),
+                None => [
+                    self.run_measured(a, timeslice)?,
+                    self.run_measured(b, timeslice)?,
+                ],
+            };
+            print_comparison(
+                &mut self.out,
+                "interrupted",
+                &measurements_a.interrupted,
+                &measurements_b.interrupted,
+                alpha,
+            )?;
+            print_comparison(
+                &mut self.out,
+                "timeouted",
+                &measurements_a.timeouted,
+                &measurements_b.timeouted,
+                alpha,
+            )?;
+            writeln!(self.out)?;
+        }
+        Ok(())
+    }
+
+    /// Runs each backend without and with the CPU DMA latency request.
+    fn run_idle_states(&mut self, latency: Duration, alpha: f64) -> io::Result<()> {
+        let rounds = self.rounds;
+        for timeslice in TIMESLICES_MS {
+            for backend in backends::available() {
+                writeln!(
+                    self.out,
+                    "IDLE STATES: {} Sleeper, allowed vs cpu_dma_latency={}, timeslice={:>3}ms, rounds={rounds}, alpha={alpha}",
+                    backend.name,
+                    format_duration(latency),
+                    timeslice
+                )?;
+                self.cpu_dma_latency = None;
+                let allowed = self.run_measured(backend, Duration::from_millis(timeslice))?;
+                self.cpu_dma_latency = Some(CpuDmaLatency::request(latency)?);
+                let prevented = self.run_measured(backend, Duration::from_millis(timeslice));
+                self.cpu_dma_latency = None;
+                let prevented = prevented?;
+                print_comparison(
+                    &mut self.out,
+                    "interrupted",
+                    &allowed.interrupted,
+                    &prevented.interrupted,
+                    alpha,
+                )?;
+                print_comparison(
+                    &mut self.out,
+                    "timeouted",
+                    &allowed.timeouted,
+                    &prevented.timeouted,
+                    alpha,
+                )?;
+                writeln!(self.out)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs each backend with relative and with absolute timeouts.
+    fn run_timeout_modes(&mut self, alpha: f64) -> io::Result<()> {
+        let rounds = self.rounds;
+        for timeslice in TIMESLICES_MS {
+            for backend in backends::available() {
+                writeln!(
+                    self.out,
+                    "TIMEOUT MODES: {} Sleeper, relative vs absolute, timeslice={:>3}ms, rounds={rounds}, alpha={alpha}",
+                    backend.name, timeslice
+                )?;
+                self.timeout_mode = TimeoutMode::Relative;
+                let relative = self.run_measured(backend, Duration::from_millis(timeslice))?;
+                self.timeout_mode = TimeoutMode::Absolute;
+                let absolute = self.run_measured(backend, Duration::from_millis(timeslice))?;
+                print_comparison(
+                    &mut self.out,
+                    "interrupted",
+                    &relative.interrupted,
+                    &absolute.interrupted,
+                    alpha,
+                )?;
+                print_comparison(
+                    &mut self.out,
+                    "timeouted",
+                    &relative.timeouted,
+                    &absolute.timeouted,
+                    alpha,
+                )?;
+                writeln!(self.out)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs each backend that supports detached wake-ups with and without the
+    /// ACK handshake.
+    fn run_handshake_cost(&mut self, alpha: f64) -> io::Result<()> {
+        let rounds = self.rounds;
+        self.scenario = Scenario::Always;
+        for timeslice in TIMESLICES_MS {
+            for backend in backends::available() {
+                if !backend.supports_detached_wakes() {
+                    continue;
+                }
+                writeln!(
+                    self.out,
+                    "HANDSHAKE COST: {} Sleeper, ACK vs detached, timeslice={:>3}ms, rounds={rounds}, alpha={alpha}",
+                    backend.name, timeslice
+                )?;
+                self.detached_wakes = false;
+                let acked = self.run_measured(backend, Duration::from_millis(timeslice))?;
+                self.detached_wakes = true;
+                let detached = self.run_measured(backend, Duration::from_millis(timeslice));
+                self.detached_wakes = false;
+                let detached = detached?;
+                print_comparison(
+                    &mut self.out,
+                    "ACK/detached",
+                    &acked.interrupted,
+                    &detached.interrupted,
+                    alpha,
+                )?;
+                writeln!(
+                    self.out,
+                    "  handshake cost : {:+} µs per wake-up",
+                    mean_delay(&acked.interrupted).as_micros() as i128
+                        - mean_delay(&detached.interrupted).as_micros() as i128
+                )?;
+                writeln!(self.out)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs each backend with cold wake-ups after `idle` and with warm
+    /// wake-ups in a tight loop.
+    fn run_cold_warm(
+        &mut self,
+        idle: Duration,
+        cold_rounds: usize,
+        warm_timeslice: Duration,
+        alpha: f64,
+    ) -> io::Result<()> {
+        let rounds = self.rounds;
+        self.scenario = Scenario::Always;
+        for backend in backends::available() {
+            writeln!(
+                self.out,
+                "COLD VS WARM: {} Sleeper, idle={} vs timeslice={}, rounds={cold_rounds} vs {rounds}, alpha={alpha}",
+                backend.name,
+                format_duration(idle),
+                format_duration(warm_timeslice)
+            )?;
+            self.rounds = cold_rounds;
+            let cold = self.run_measured(backend, idle);
+            self.rounds = rounds;
+            let cold = cold?;
+            print_summary(
+                &mut self.out,
+                "cold",
+                self.reports.last().and_then(|r| r.interrupted.as_ref()),
+            )?;
+            let warm = self.run_measured(backend, warm_timeslice)?;
+            print_summary(
+                &mut self.out,
+                "warm",
+                self.reports.last().and_then(|r| r.interrupted.as_ref()),
+            )?;
+            print_comparison(
+                &mut self.out,
+                "cold/warm",
+                &cold.interrupted,
+                &warm.interrupted,
+                alpha,
+            )?;
+            writeln!(self.out)?;
+        }
+        Ok(())
+    }
+
+    fn run_sweep(&mut self, range: SweepRange, steps_per_decade: usize) -> io::Result<()> {
+        let rounds = self.rounds;
+        let timeslices = range.timeslices(steps_per_decade);
+        let mut points = Vec::new();
+
+        for timeslice in timeslices {
+            for backend in backends::available() {
+                writeln!(
+                    self.out,
+                    "SWEEP: {} Sleeper, timeslice={:>8}, rounds={rounds}",
+                    backend.name,
+                    format_duration(timeslice)
+                )?;
+                let analysis = self.run_backend(backend, timeslice)?;
+                points.push(SweepPoint {
+                    backend: backend.name,
+                    timeslice,
+                    interrupted_mean_delay: analysis.interrupted.delays.mean(),
+                    timeouted_mean_delay: analysis.timeouted.delays.mean(),
+                });
+            }
+        }
+
+        writeln!(self.out, "{}", sweep::render_table(&points))?;
+        write!(self.out, "{}", sweep::render_chart(&points))?;
+        Ok(())
+    }
+
+    /// Executes the runs of the suite in order and prints a summary of all
+    /// runs at the end.
+    fn run_suite(&mut self, suite: &Suite) -> io::Result<()> {
+        let seed = self.seed;
+        let total = suite.runs.len();
+        for (i, run) in suite.runs.iter().enumerate() {
+            writeln!(
+                self.out,
+                "SUITE RUN {}/{total}: {} Sleeper, timeslice={}, rounds={}, scenario={}, interrupt={}%",
+                i + 1,
+                run.backend.name,
+                format_duration(run.timeslice),
+                run.rounds,
+                run.scenario.name(),
+                run.interrupt_percent
+            )?;
+            let config = run.config(seed);
+            self.rounds = config.rounds;
+            self.seed = config.seed;
+            self.scenario = config.scenario;
+            self.interrupt_percent = config.interrupt_percent;
+            self.timeout_mode = config.timeout_mode;
+            self.placement = run
+                .placement
+                .map(|placement| pick_cpus(&mut self.out, placement))
+                .transpose()?;
+            // Stop the load of the previous run first.
+            self.load = None;
+            self.load = run
+                .load
+                .map(|load| start_load(&mut self.out, load, run.load_threads))
+                .transpose()?;
+            let analysis = self.run_backend(run.backend, run.timeslice)?;
+            print_analysis(
+                &mut self.out,
+                self.reports
+                    .last()
+                    .expect("should have the report of the run"),
+            )?;
+            print_scheduling(&mut self.out, &analysis)?;
+            writeln!(self.out)?;
+        }
+        self.load = None;
+
+        writeln!(self.out, "SUITE SUMMARY:")?;
+        writeln!(
+            self.out,
+            "  {:>3}  {:<14} {:>9}  {:<9} {:>5}  {:<11} {:<7} {:>10}  {:>10}",
+            "#", "backend", "timeslice", "scenario", "intr%", "placement", "load", "mean", "p99"
+        )?;
+        for (i, report) in self.reports.iter().enumerate() {
+            let (mean, p99) = report.interrupted.as_ref().map_or_else(
+                || ("-".to_string(), "-".to_string()),
+                |s| (format_duration(s.mean.value), format_duration(s.p99.value)),
+            );
+            writeln!(
+                self.out,
+                "  {:>3}  {:<14} {:>9}  {:<9} {:>5}  {:<11} {:<7} {:>10}  {:>10}{}",
+                i + 1,
+                report.backend,
+                format_duration(report.timeslice),
+                report.scenario,
+                report.interrupt_percent,
+                report.placement.as_deref().unwrap_or("-"),
+                report.load.as_deref().unwrap_or("-"),
+                mean,
+                p99,
+                if report.failure.is_some() {
+                    "  FAILED"
+                } else {
+                    ""
+                }
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Runs each backend with 1, 2, 4, ... up to `max_sleepers` sleepers.
+    fn run_contention(
+        &mut self,
+        max_sleepers: NonZeroUsize,
+        timeslice: Duration,
+    ) -> io::Result<()> {
+        let rounds = self.rounds;
+        let max_sleepers = max_sleepers.get();
+        for backend in backends::available() {
+            writeln!(
+                self.out,
+                "CONTENTION: {} Sleeper, timeslice={}, rounds={rounds}",
+                backend.name,
+                format_duration(timeslice)
+            )?;
+            if !backend.supports_groups() {
+                writeln!(
+                    self.out,
+                    "  not supported: no primitive shared by several sleepers"
+                )?;
+                writeln!(self.out)?;
+                continue;
+            }
+            writeln!(
+                self.out,
+                "  {:>8}  {:>10}  {:>10}  {:>8}",
+                "sleepers", "mean", "p99", "skipped"
+            )?;
+            let counts = std::iter::successors(Some(1_usize), |n| n.checked_mul(2))
+                .take_while(|&n| n < max_sleepers)
+                .chain([max_sleepers]);
+            for sleepers in counts {
+                let (group, waker) = backend
+                    .new_group(sleepers)
+                    .expect("should support several sleepers");
+                let measurements =
+                    contention::run(self.config(timeslice), group, waker, SystemClock);
+                if let Some(failure) = &measurements.failure {
+                    writeln!(self.out, "  {sleepers:>8}  FAILED: {failure}")?;
+                    break;
+                }
+                writeln!(
+                    self.out,
+                    "  {sleepers:>8}  {:>10}  {:>10}  {:>8}",
+                    format_duration(mean_delay(&measurements.interrupted)),
+                    format_duration(percentile_delay(&measurements.interrupted, 99.0)),
+                    measurements.skipped
+                )?;
+            }
+            writeln!(self.out)?;
+        }
+        Ok(())
+    }
+
+    /// Measures the setup and teardown cost of each backend.
+    fn run_setup_cost(&mut self, samples: usize, timeslice: Duration) -> io::Result<()> {
+        for backend in backends::available() {
+            writeln!(
+                self.out,
+                "SETUP COST: {} Sleeper, timeslice={}, samples={samples}, {} cycles each",
+                backend.name,
+                format_duration(timeslice),
+                setup::CYCLES
+            )?;
+            let cost = setup::measure(
+                || backend.new_pair(),
+                samples,
+                self.config(timeslice),
+                SystemClock,
+            );
+            if cost.samples < samples {
+                writeln!(
+                    self.out,
+                    "  skipped        : {} pairs missed their schedule",
+                    samples - cost.samples
+                )?;
+            }
+            writeln!(
+                self.out,
+                "  construction   : {}",
+                format_duration(cost.construction)
+            )?;
+            writeln!(
+                self.out,
+                "  teardown       : {}",
+                format_duration(cost.teardown)
+            )?;
+            writeln!(
+                self.out,
+                "  first wake-up  : {}",
+                format_duration(cost.first_wake)
+            )?;
+            writeln!(
+                self.out,
+                "  later wake-ups : {}",
+                format_duration(cost.steady_wake)
+            )?;
+            writeln!(
+                self.out,
+                "  first penalty  : {:+} µs compared with later ones",
+                cost.first_wake.as_micros() as i128 - cost.steady_wake.as_micros() as i128
+            )?;
+            writeln!(self.out)?;
+        }
+        Ok(())
+    }
+
+    /// Runs the backend until stdout is closed. Measurements are not
+    /// collected but only recorded, if requested; the memory usage is
+    /// bounded.
+    fn run_soak(
+        &mut self,
+        backend: &Backend,
+        timeslice: Duration,
+        report_interval: Duration,
+    ) -> io::Result<()> {
+        let config = RunConfig {
+            rounds: usize::MAX,
+            ..self.config(timeslice)
         };
+        writeln!(
+            self.out,
+            "SOAK: {} Sleeper, timeslice={}, report every {}",
+            backend.name,
+            format_duration(timeslice),
+            format_duration(report_interval)
+        )?;
+        let header = self.run_header(backend, &config);
+        let mut sink = SoakSink::new(report_interval, io::stdout());
+        match &self.slow_rounds {
+            Some(slow_rounds) => self.raw.run(
+                backend,
+                config,
+                &header,
+                &mut slow_rounds.sink(sink, backend.name, timeslice),
+            ),
+            None => self.raw.run(backend, config, &header, &mut sink),
+        }
+    }
+}
 
-        // Wait for the other thread to start a new cycle.
-        sleep_barrier.wait();
+/// Probes the wake coalescing semantics of all backends.
+fn run_coalescing(wakes: usize, timeslice: Duration) {
+    for backend in backends::available() {
+        println!(
+            "COALESCING: {} Sleeper, timeslice={}",
+            backend.name,
+            format_duration(timeslice)
+        );
+        for phase in Phase::ALL {
+            let (sleeper, waker) = backend.new_pair();
+            match coalescing::probe(&sleeper, waker, phase, wakes, timeslice) {
+                Some(outcome) => println!("  {outcome}"),
+                None => println!("  {:<12}: detached wake-ups not supported", phase.name()),
+            }
+        }
+        println!();
+    }
+}
 
-        sleep_optimized(sleep_duration);
-        if do_interrupt {
-            waker.wake();
+/// Measures the timeout precision of all backends and, if available, of the
+/// baseline.
+fn run_precision(sleeps: usize, timeslice: Duration, timeout_mode: TimeoutMode) {
+    for backend in backends::baseline()
+        .into_iter()
+        .chain(backends::available())
+    {
+        println!(
+            "PRECISION: {} Sleeper, timeslice={}, sleeps={sleeps}, timeout mode={}",
+            backend.name,
+            format_duration(timeslice),
+            timeout_mode.name()
+        );
+        let (sleeper, _waker) = backend.new_pair();
+        let precision = precision::measure(&sleeper, &SystemClock, timeslice, sleeps, timeout_mode);
+        println!(
+            "  overshoot      : mean={} p50={} p90={} p99={} max={}",
+            format_duration(precision.mean_overshoot()),
+            format_duration(precision.overshoot(50.0)),
+            format_duration(precision.overshoot(90.0)),
+            format_duration(precision.overshoot(99.0)),
+            format_duration(precision.overshoot(100.0))
+        );
+        println!(
+            "  drift          : {} total, {} per sleep",
+            format_duration(precision.drift),
+            format_duration(precision.drift_per_sleep())
+        );
+        if precision.early > 0 {
+            println!(
+                "  early          : {} sleeps returned before the deadline",
+                precision.early
+            );
         }
+        println!();
+    }
+}
 
-        let wakeup_context = receiver.recv().unwrap();
+/// Measures the sustained wake-up throughput of all backends.
+fn run_throughput(duration: Duration) {
+    for backend in backends::available() {
+        println!(
+            "THROUGHPUT: {} Sleeper, duration={}",
+            backend.name,
+            format_duration(duration)
+        );
+        let (sleeper, waker) = backend.new_pair();
+        let throughput = throughput::measure(sleeper, &waker, duration);
+        println!(
+            "  wake-ups       : {} ({:.0} per second)",
+            throughput.wakes,
+            throughput.per_second()
+        );
+        println!(
+            "  delay          : mean={} p50={} p99={} of {} wake-ups that found the sleeper asleep",
+            format_duration(throughput.mean_delay()),
+            format_duration(throughput.delay(50.0)),
+            format_duration(throughput.delay(99.0)),
+            throughput.delays.len()
+        );
+        if throughput.timeouts > 0 {
+            println!(
+                "  timeouts       : {} sleeps timed out despite the closed loop",
+                throughput.timeouts
+            );
+        }
+        println!();
+    }
+}
+
+fn print_backends(out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "Compiled-in backends:")?;
+    for backend in backends::available() {
+        writeln!(out, "  {:<16} {}", backend.name, backend.description)?;
+    }
+    Ok(())
+}
+
+/// Installs a subscriber that writes all spans and events to `path` in the
+/// Chrome trace format. The trace is complete when the guard is dropped.
+#[cfg(feature = "trace")]
+fn init_trace(path: &Path) -> io::Result<tracing_chrome::FlushGuard> {
+    use tracing_subscriber::prelude::*;
+
+    let file = std::fs::File::create(path).map_err(with_path(path))?;
+    let (layer, guard) = tracing_chrome::ChromeLayerBuilder::new()
+        .writer(file)
+        .include_args(true)
+        .build();
+    tracing_subscriber::registry().with(layer).init();
+    Ok(guard)
+}
+
+/// Starts the load on `threads` threads, half of the CPUs by default.
+fn start_load(
+    out: &mut dyn Write,
+    load: Load,
+    threads: Option<usize>,
+) -> io::Result<LoadGenerator> {
+    let threads = threads.unwrap_or_else(|| {
+        thread::available_parallelism().map_or(1, |cpus| (cpus.get() / 2).max(1))
+    });
+    writeln!(out, "Load: {} on {threads} threads", load.name())?;
+    Ok(LoadGenerator::start(load, threads))
+}
+
+/// Picks the CPUs of the placement from the topology.
+fn pick_cpus(out: &mut dyn Write, placement: Placement) -> io::Result<(Placement, CpuPair)> {
+    let topology = Topology::read().ok_or_else(topology_unavailable)?;
+    let cpus = topology.pick(placement).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "this machine has no CPUs for the placement {}",
+                placement.name()
+            ),
+        )
+    })?;
+    writeln!(out, "Placement: {} ({cpus})", placement.name())?;
+    Ok((placement, cpus))
+}
+
+fn bench(args: BenchArgs) -> io::Result<ExitCode> {
+    #[cfg(feature = "trace")]
+    let _trace_guard = args.trace.as_deref().map(init_trace).transpose()?;
 
-        if do_interrupt {
-            check!(matches!(
-                wakeup_context.reason,
-                WakeupReason::Interrupted { .. }
+    let versus = match &args.versus {
+        Some((a, b)) => Some((find_backend(a)?, find_backend(b)?)),
+        None => None,
+    };
+    let soak = args.soak.as_deref().map(find_backend).transpose()?;
+    if let Some(name) = &args.assert_backend {
+        find_backend(name)?;
+    }
+
+    let compares = versus.is_some()
+        || args.interleave.is_some()
+        || args.compare_idle_states
+        || args.compare_timeout_modes
+        || args.handshake_cost
+        || args.cold_warm;
+    if compares && args.raw.as_deref().is_some_and(report::is_recording) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "comparisons and interleaving keep all measurements in memory, write the raw measurements as CSV or JSON instead of a recording (.bin)",
+        ));
+    }
+
+    let plain = args.format == "plain";
+    let mut out = if plain {
+        Output::Stderr
+    } else {
+        Output::Stdout
+    };
+    let seed = args.seed.unwrap_or_else(|| fastrand::u64(..));
+    print_backends(&mut out)?;
+    writeln!(out, "Seed: {seed}")?;
+    writeln!(out, "PID: {}", std::process::id())?;
+    // In comparison mode, the request is only active for every second run.
+    let cpu_dma_latency = match args.cpu_dma_latency {
+        Some(latency) if !args.compare_idle_states => {
+            writeln!(out, "CPU DMA latency: {}", format_duration(latency))?;
+            Some(CpuDmaLatency::request(latency)?)
+        }
+        _ => None,
+    };
+    let load = args
+        .load
+        .map(|load| start_load(&mut out, load, args.load_threads))
+        .transpose()?;
+    let placement = args
+        .placement
+        .map(|placement| pick_cpus(&mut out, placement))
+        .transpose()?;
+    if args.trace_markers {
+        // Fails early instead of in every run.
+        TraceMarker::open().map_err(|e| io::Error::new(e.kind(), format!("trace markers: {e}")))?;
+        writeln!(out, "Trace markers: enabled")?;
+    }
+    let slow_rounds = args
+        .capture_slow_rounds
+        .map(|threshold| {
+            let slow_rounds = SlowRounds::start(threshold, args.capture_dir.clone())
+                .map_err(|e| io::Error::new(e.kind(), format!("capturing slow rounds: {e}")))?;
+            writeln!(
+                out,
+                "Capturing slow rounds: delay above {}, traces in {}",
+                format_duration(threshold),
+                args.capture_dir.display()
+            )?;
+            Ok::<_, io::Error>(slow_rounds)
+        })
+        .transpose()?;
+    let priorities = Priorities {
+        waker: args.waker_nice,
+        sleeper: args.sleeper_nice,
+    };
+    if priorities.is_set() {
+        writeln!(out, "Nice levels: {priorities}")?;
+    }
+    if let Some(batch_rounds) = args.interleave {
+        writeln!(out, "Interleaving: batches of {batch_rounds} rounds")?;
+    }
+    if args.repeats > 1 {
+        writeln!(
+            out,
+            "Repeats: {} runs per backend and timeslice",
+            args.repeats
+        )?;
+    }
+    writeln!(out)?;
+
+    let baseline = if args.baseline {
+        let baseline = backends::baseline().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the baseline is not supported on this platform",
+            )
+        })?;
+        Some(baseline)
+    } else {
+        None
+    };
+
+    let mut bench = Bench {
+        rounds: args.rounds,
+        seed,
+        scenario: args.scenario,
+        interrupt_percent: args.interrupt_percent,
+        timeout_mode: args.timeout_mode,
+        detached_wakes: false,
+        cpu_dma_latency,
+        load,
+        rapl: Rapl::open(),
+        placement,
+        priorities,
+        trace_markers: args.trace_markers,
+        sched_stats: args.sched_stats,
+        slow_rounds,
+        baseline,
+        interleave: args.interleave,
+        repeats: args.repeats as usize,
+        repeat: 0,
+        reports: Vec::new(),
+        raw: Raw::new(args.raw.as_deref())?,
+        out,
+    };
+    if let Some(path) = &args.suite {
+        let suite = Suite::read(path).map_err(with_path(path))?;
+        bench.run_suite(&suite)?;
+    } else if let Some(wakes) = args.coalescing {
+        run_coalescing(wakes, args.timeslice);
+    } else if let Some(sleeps) = args.precision {
+        run_precision(sleeps, args.timeslice, args.timeout_mode);
+    } else if let Some(duration) = args.throughput {
+        run_throughput(duration);
+    } else if let Some(samples) = args.setup_cost {
+        bench.run_setup_cost(samples, args.timeslice)?;
+    } else if let Some(max_sleepers) = args.contention {
+        bench.run_contention(max_sleepers, args.timeslice)?;
+    } else if args.handshake_cost {
+        bench.run_handshake_cost(args.alpha)?;
+    } else if args.cold_warm {
+        bench.run_cold_warm(args.idle, args.cold_rounds, args.timeslice, args.alpha)?;
+    } else if args.compare_timeout_modes {
+        bench.run_timeout_modes(args.alpha)?;
+    } else if args.compare_idle_states {
+        bench.run_idle_states(args.cpu_dma_latency.unwrap_or(Duration::ZERO), args.alpha)?;
+    } else if let Some(backend) = soak {
+        if matches!(bench.raw, Raw::Collected(_)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "soak mode only writes raw measurements as recording (.bin)",
             ));
-            interrupted_results.push(Measurement { wakeup_context });
-        } else {
-            check!(wakeup_context.reason == WakeupReason::Timeout);
-            timeouted_results.push(Measurement { wakeup_context });
         }
+        bench.run_soak(backend, args.timeslice, args.report_interval)?;
+        if let Some(path) = &args.raw {
+            bench.raw.finish(path).map_err(with_path(path))?;
+        }
+        return Ok(ExitCode::SUCCESS);
+    } else if let Some(range) = args.sweep {
+        bench.run_sweep(range, args.sweep_steps)?;
+    } else if let Some((a, b)) = versus {
+        bench.run_versus(a, b, args.alpha)?;
+    } else {
+        bench.run_default()?;
     }
 
-    let rounds = interrupted_results.len() + timeouted_results.len();
-    Measurements {
-        interrupted: interrupted_results,
-        timeouted: timeouted_results,
-        rounds,
+    if let Some(path) = args.json {
+        report::write_json(&path, &bench.reports).map_err(with_path(&path))?;
+    }
+    if let Some(path) = &args.raw {
+        bench.raw.finish(path).map_err(with_path(path))?;
+    }
+    if plain {
+        let report = plain::render(&Environment::capture(seed), &bench.reports);
+        io::stdout().write_all(report.as_bytes())?;
+    }
+
+    let bounds = Bounds {
+        backend: args.assert_backend,
+        mean: args.assert_mean_delay_below,
+        p99: args.assert_p99_below,
+    };
+    if bounds.is_empty() {
+        return Ok(ExitCode::SUCCESS);
+    }
+    let verdict = bounds.check(&bench.reports);
+    print_verdict(&mut out, &verdict)?;
+    if let Some(path) = args.assert_json {
+        report::write_json(&path, &verdict).map_err(with_path(&path))?;
     }
+    Ok(if verdict.passed {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(EXIT_CHECK_FAILED)
+    })
 }
 
-fn calc_mean(data: &[Measurement]) -> Duration {
-    let len = data.len();
-    if len == 0 {
-        Duration::ZERO
+/// Exit code if the runs violate the assertions or a backend violates the
+/// conformance checks, to tell it apart from errors.
+const EXIT_CHECK_FAILED: u8 = 2;
+
+fn print_verdict(out: &mut dyn Write, verdict: &Verdict) -> io::Result<()> {
+    writeln!(out)?;
+    if verdict.passed {
+        writeln!(out, "ASSERTIONS: passed ({} runs checked)", verdict.checked)?;
+    } else if verdict.checked == 0 {
+        writeln!(out, "ASSERTIONS: failed, no run to check")?;
     } else {
-        let sum = data
-            .iter()
-            .map(|m| m.wakeup_context.delay)
-            .sum::<Duration>();
-        sum / (len as u32)
+        writeln!(
+            out,
+            "ASSERTIONS: failed ({} violations in {} runs checked)",
+            verdict.violations.len(),
+            verdict.checked
+        )?;
+        for violation in &verdict.violations {
+            writeln!(out, "  {violation}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Loads and registers the backends of all plugins given with `--plugin`.
+#[cfg(feature = "plugins")]
+fn load_plugins(paths: &[PathBuf]) -> io::Result<()> {
+    if paths.is_empty() {
+        return Ok(());
     }
+    let mut loaded = Vec::new();
+    for path in paths {
+        loaded.extend(plugin::load(path).map_err(with_path(path))?);
+    }
+    backends::register(loaded).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+/// Adds the path to the error message.
+fn with_path(path: &Path) -> impl FnOnce(io::Error) -> io::Error + '_ {
+    move |e| io::Error::new(e.kind(), format!("{}: {e}", path.display()))
+}
+
+fn list() -> io::Result<()> {
+    print_backends(&mut io::stdout())?;
+    println!();
+    println!("Scenarios:");
+    for scenario in Scenario::ALL {
+        println!("  {:<16} {}", scenario.name(), scenario.description());
+    }
+    println!();
+    println!("Loads:");
+    for load in Load::ALL {
+        println!("  {:<16} {}", load.name(), load.description());
+    }
+    println!();
+    println!("Placements:");
+    for placement in Placement::ALL {
+        println!("  {:<16} {}", placement.name(), placement.description());
+    }
+    Ok(())
 }
 
-fn print_analysis(measurements: Measurements) {
-    let interrupted_delay_mean = calc_mean(&measurements.interrupted);
-    let timeouted_delay_mean = calc_mean(&measurements.timeouted);
+/// Checks the semantics of the backends, see [`conformance::check_pair`].
+fn conformance(backend: Option<&'static Backend>) -> ExitCode {
+    let mut passed = true;
+    for backend in backend.map_or_else(backends::available, std::slice::from_ref) {
+        println!("CONFORMANCE: {} Sleeper", backend.name);
+        let conformance = conformance::check_pair(|| backend.new_pair());
+        print!("{conformance}");
+        println!();
+        passed &= conformance.passed();
+    }
+    if passed {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(EXIT_CHECK_FAILED)
+    }
+}
 
-    println!("Rounds        (#): {}", measurements.rounds);
-    println!("  interrupted (#): {}", measurements.interrupted.len());
+fn topology() -> io::Result<()> {
+    let topology = Topology::read().ok_or_else(topology_unavailable)?;
+    println!(
+        "CPUs: {}, cores: {}, L3 caches: {}, NUMA nodes: {}",
+        topology.cpus().len(),
+        topology.cores(),
+        topology.llcs(),
+        topology.nodes()
+    );
+    println!();
     println!(
-        "  |- mean delay  : {:>5} µs",
-        interrupted_delay_mean.as_micros()
+        "{:>5} {:>8} {:>5} {:>5} {:>5}",
+        "cpu", "package", "core", "l3", "node"
     );
-    println!("  timeouted   (#): {}", measurements.timeouted.len());
+    for cpu in topology.cpus() {
+        println!(
+            "{:>5} {:>8} {:>5} {:>5} {:>5}",
+            cpu.id,
+            cpu.package,
+            cpu.core,
+            cpu.llc
+                .map_or_else(|| "-".to_string(), |llc| llc.to_string()),
+            cpu.node
+        );
+    }
+    println!();
+    println!("Placements:");
+    for &placement in Placement::ALL {
+        match topology.pick(placement) {
+            Some(cpus) => println!("  {:<16} {cpus}", placement.name()),
+            None => println!("  {:<16} not available", placement.name()),
+        }
+    }
+    Ok(())
+}
+
+fn topology_unavailable() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "the CPU topology is not available on this platform",
+    )
+}
+
+fn clockbench(iterations: usize) {
     println!(
-        "  |- mean delay  : {:>5} µs",
-        timeouted_delay_mean.as_micros()
+        "{:<20} | {:>12} | {:>12} | {:>12} |",
+        "clock", "resolution", "reported", "overhead"
     );
+    for &source in ClockSource::ALL {
+        let stats = clockbench::measure(source, iterations);
+        println!(
+            "{:<20} | {:>9.1} ns | {:>12} | {:>9.1} ns |",
+            source.name(),
+            stats.resolution_ns,
+            source
+                .reported_resolution()
+                .map_or_else(|| "-".to_string(), format_duration),
+            stats.overhead_ns
+        );
+    }
 }
 
-fn main() {
-    let rounds = 100;
-    let timeslices_ms = [2, 5, 10, 25, 50, 100];
+fn analyze(file: &Path, filter: &Filter) -> io::Result<()> {
+    if !report::is_recording(file) {
+        let raw_runs = report::read_raw(file).map_err(with_path(file))?;
+        print!("{}", analysis::render(&raw_runs, filter));
+        return Ok(());
+    }
 
-    for timeslice in timeslices_ms {
-        // Sleeper #1: mutex + condvar
-        {
-            println!("TEST RUN: Condvar Sleeper, timeslice={:>3}ms, rounds={rounds}", timeslice);
-            let (sleeper, waker) = condvar::new_pair();
-            let measurements = test_runs(rounds, sleeper, waker, Duration::from_millis(timeslice));
-            print_analysis(measurements);
-        }
+    // A recording may not fit into memory: its runs are accumulated record by
+    // record, in one pass for the baselines, then in one for the analysis.
+    let mut baselines = Baselines::default();
+    for run in recording::open(file)
+        .map_err(with_path(file))?
+        .runs_as::<RunAnalysis>()
+    {
+        baselines.add(&run.map_err(with_path(file))?);
+    }
+    for run in recording::open(file)
+        .map_err(with_path(file))?
+        .runs_as::<RunAnalysis>()
+    {
+        let run = run.map_err(with_path(file))?;
+        print!("{}", analysis::render_run(&run, filter, &baselines));
+    }
+    Ok(())
+}
 
-        println!();
-        // Sleeper #2: channel
-        {
-            println!(
-                "TEST RUN: Channel Sleeper, timeslice={:>3}ms, rounds={rounds}",
-                timeslice
-            );
-            let (sleeper, waker) = channel::new_pair();
-            let measurements = test_runs(rounds, sleeper, waker, Duration::from_millis(timeslice));
-            print_analysis(measurements);
-        }
+fn compare_results(old: &Path, new: &Path, threshold: f64) -> io::Result<()> {
+    let old = report::read_json::<Vec<RunReport>>(old).map_err(with_path(old))?;
+    let new = report::read_json::<Vec<RunReport>>(new).map_err(with_path(new))?;
+    let deltas = compare::compare(&old, &new);
+    print!("{}", compare::render(&deltas, threshold));
 
-        println!();
+    let regressions = deltas
+        .iter()
+        .filter(|delta| delta.is_regression(threshold))
+        .count();
+    println!();
+    println!(
+        "{regressions} of {} comparisons regressed by more than {threshold}%",
+        deltas.len()
+    );
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    // Before the backend names are checked, so that they can select the
+    // backends of the plugins.
+    #[cfg(feature = "plugins")]
+    if let Err(e) = load_plugins(&args.plugin) {
+        eprintln!("error: {e}");
+        return ExitCode::FAILURE;
     }
+    let res = match args.command {
+        None => return exit_code(bench(args.bench)),
+        Some(Command::Bench(args)) => return exit_code(bench(*args)),
+        Some(Command::List) => list(),
+        Some(Command::Analyze {
+            file,
+            backend,
+            timeslice,
+            reason,
+        }) => analyze(
+            &file,
+            &Filter {
+                backend,
+                timeslice,
+                reason,
+            },
+        ),
+        Some(Command::Topology) => topology(),
+        Some(Command::Clockbench { iterations }) => {
+            clockbench(iterations);
+            Ok(())
+        }
+        Some(Command::Conformance { backend }) => {
+            match backend.as_deref().map(find_backend).transpose() {
+                Ok(backend) => return conformance(backend),
+                Err(e) => Err(e),
+            }
+        }
+        Some(Command::Compare {
+            old,
+            new,
+            threshold,
+        }) => compare_results(&old, &new, threshold),
+    };
+
+    exit_code(res.map(|()| ExitCode::SUCCESS))
+}
+
+fn exit_code(res: io::Result<ExitCode>) -> ExitCode {
+    res.unwrap_or_else(|e| {
+        eprintln!("error: {e}");
+        ExitCode::FAILURE
+    })
 }
@@ -0,0 +1,321 @@
+//! Plain-text reports for diffing. See [`render`].
+//!
+//! The regular output of `bench` is meant to be read and changes its shape
+//! with every new feature. The plain-text report is meant to be committed and
+//! diffed between machines and kernels: one `key value` line per fact, values
+//! in integer nanoseconds, no locale-dependent formatting, and the runs in a
+//! deterministic order. A changed result changes exactly one line.
+
+use crate::report::RunReport;
+use crate::stats::Summary;
+use std::fmt::{Display, Write};
+use std::fs;
+use std::thread;
+
+/// Version of the layout of the report. Increased when lines are removed or
+/// change their meaning.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Width of the key column.
+const KEY_WIDTH: usize = 28;
+
+/// The machine and the build the benchmark ran on. Unknown values are
+/// `None`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Environment {
+    /// Version of this crate.
+    pub version: &'static str,
+    pub os: &'static str,
+    pub arch: &'static str,
+    /// Release of the kernel, Linux only.
+    pub kernel: Option<String>,
+    /// Model name of the first CPU, Linux only.
+    pub cpu_model: Option<String>,
+    /// Number of CPUs available to the process.
+    pub cpus: Option<usize>,
+    /// The current clock source, Linux only.
+    pub clocksource: Option<String>,
+    /// The seed of the `bench` command.
+    pub seed: u64,
+}
+
+impl Environment {
+    /// Captures the environment of the running process.
+    #[must_use]
+    pub fn capture(seed: u64) -> Self {
+        let read = |path| {
+            fs::read_to_string(path)
+                .ok()
+                .map(|value| value.trim().to_string())
+        };
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            kernel: read("/proc/sys/kernel/osrelease"),
+            cpu_model: read("/proc/cpuinfo").and_then(|cpuinfo| {
+                cpuinfo
+                    .lines()
+                    .find_map(|line| line.strip_prefix("model name"))
+                    .and_then(|line| line.split_once(':'))
+                    .map(|(_, model)| model.trim().to_string())
+            }),
+            cpus: thread::available_parallelism().ok().map(|cpus| cpus.get()),
+            clocksource: read("/sys/devices/system/clocksource/clocksource0/current_clocksource"),
+            seed,
+        }
+    }
+}
+
+/// Renders the environment and all runs.
+///
+/// The runs are ordered by backend, scenario, interrupt percentage, timeout
+/// mode, timeslice, and repeat, so the order of the runs doesn't matter,
+/// e.g., with `--interleave`.
+#[must_use]
+pub fn render(environment: &Environment, reports: &[RunReport]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "# benchmark-interruptible-sleep plain report, format {FORMAT_VERSION}"
+    );
+    let _ = writeln!(out, "[environment]");
+    line(&mut out, "version", environment.version);
+    line(&mut out, "os", environment.os);
+    line(&mut out, "arch", environment.arch);
+    line(&mut out, "kernel", optional(environment.kernel.as_ref()));
+    line(
+        &mut out,
+        "cpu_model",
+        optional(environment.cpu_model.as_ref()),
+    );
+    line(&mut out, "cpus", optional(environment.cpus));
+    line(
+        &mut out,
+        "clocksource",
+        optional(environment.clocksource.as_ref()),
+    );
+    line(&mut out, "seed", environment.seed);
+
+    let mut reports = reports.iter().collect::<Vec<_>>();
+    reports.sort_by(|a, b| {
+        (
+            &a.backend,
+            &a.scenario,
+            a.interrupt_percent,
+            &a.timeout_mode,
+            a.timeslice,
+            a.repeat,
+        )
+            .cmp(&(
+                &b.backend,
+                &b.scenario,
+                b.interrupt_percent,
+                &b.timeout_mode,
+                b.timeslice,
+                b.repeat,
+            ))
+    });
+    for report in reports {
+        render_run(&mut out, report);
+    }
+    out
+}
+
+fn render_run(out: &mut String, report: &RunReport) {
+    let _ = writeln!(out);
+    // The first run of a configuration has no suffix, so the headers of
+    // runs that weren't repeated stay the same.
+    let repeat = if report.repeat > 0 {
+        format!(" repeat={}", report.repeat)
+    } else {
+        String::new()
+    };
+    let _ = writeln!(
+        out,
+        "[run backend={} scenario={} interrupt_percent={} timeout_mode={} timeslice_ns={}{repeat}]",
+        report.backend,
+        report.scenario,
+        report.interrupt_percent,
+        report.timeout_mode,
+        report.timeslice.as_nanos()
+    );
+    line(out, "seed", report.seed);
+    line(out, "rounds", report.rounds);
+    line(out, "skipped", report.skipped);
+    line(out, "negative_delays", report.negative_delays);
+    line(
+        out,
+        "failure",
+        // Keeps the report line-based.
+        optional(report.failure.as_ref().map(|f| f.replace('\n', " "))),
+    );
+    line(out, "detached_wakes", report.detached_wakes);
+    line(
+        out,
+        "cpu_dma_latency_ns",
+        optional(report.cpu_dma_latency.map(|latency| latency.as_nanos())),
+    );
+    line(out, "load", optional(report.load.as_ref()));
+    line(out, "placement", optional(report.placement.as_ref()));
+    line(
+        out,
+        "waker_cpu",
+        optional(report.cpus.map(|cpus| cpus.waker)),
+    );
+    line(
+        out,
+        "sleeper_cpu",
+        optional(report.cpus.map(|cpus| cpus.sleeper)),
+    );
+    line(out, "waker_nice", optional(report.priorities.waker));
+    line(out, "sleeper_nice", optional(report.priorities.sleeper));
+    line(out, "energy_uj", optional(report.energy));
+    render_summary(out, "interrupted", report.interrupted.as_ref());
+    render_summary(out, "timeouted", report.timeouted.as_ref());
+}
+
+fn render_summary(out: &mut String, reason: &str, summary: Option<&Summary>) {
+    line(
+        out,
+        &format!("{reason}.count"),
+        summary.map_or(0, |summary| summary.count),
+    );
+    let Some(summary) = summary else {
+        return;
+    };
+    for (name, estimate) in [
+        ("mean", &summary.mean),
+        ("p50", &summary.p50),
+        ("p99", &summary.p99),
+    ] {
+        line(
+            out,
+            &format!("{reason}.{name}_ns"),
+            estimate.value.as_nanos(),
+        );
+        line(
+            out,
+            &format!("{reason}.{name}_ci_low_ns"),
+            estimate.ci_low.as_nanos(),
+        );
+        line(
+            out,
+            &format!("{reason}.{name}_ci_high_ns"),
+            estimate.ci_high.as_nanos(),
+        );
+    }
+    line(
+        out,
+        &format!("{reason}.stddev_ns"),
+        summary.stddev.as_nanos(),
+    );
+    line(
+        out,
+        &format!("{reason}.jitter_ns"),
+        summary.jitter.as_nanos(),
+    );
+}
+
+fn line(out: &mut String, key: &str, value: impl Display) {
+    let _ = writeln!(out, "{key:<KEY_WIDTH$} {value}");
+}
+
+/// Renders missing values as `-`.
+fn optional(value: Option<impl Display>) -> String {
+    value.map_or_else(|| "-".to_string(), |value| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::priority::Priorities;
+    use crate::stats::Estimate;
+    use assert2::check;
+    use std::time::Duration;
+
+    fn report(backend: &str, timeslice_ms: u64) -> RunReport {
+        let estimate = |us| Estimate {
+            value: Duration::from_micros(us),
+            ci_low: Duration::from_micros(us - 1),
+            ci_high: Duration::from_micros(us + 1),
+        };
+        RunReport {
+            backend: backend.to_string(),
+            scenario: "mixed".to_string(),
+            interrupt_percent: 50,
+            timeout_mode: "relative".to_string(),
+            timeslice: Duration::from_millis(timeslice_ms),
+            seed: 7,
+            repeat: 0,
+            rounds: 10,
+            skipped: 0,
+            negative_delays: 0,
+            interrupted: Some(Summary {
+                count: 5,
+                mean: estimate(12),
+                p50: estimate(10),
+                p99: estimate(30),
+                stddev: Duration::from_micros(4),
+                jitter: Duration::from_micros(3),
+            }),
+            timeouted: None,
+            cpu_dma_latency: None,
+            load: None,
+            energy: None,
+            detached_wakes: false,
+            failure: None,
+            placement: None,
+            cpus: None,
+            priorities: Priorities::default(),
+            threads: None,
+            batch_seeds: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let environment = Environment {
+            version: "0.1.0",
+            os: "linux",
+            arch: "x86_64",
+            kernel: Some("6.1.0".to_string()),
+            cpu_model: None,
+            cpus: Some(4),
+            clocksource: Some("tsc".to_string()),
+            seed: 7,
+        };
+        let reports = [
+            report("futex", 10),
+            RunReport {
+                repeat: 1,
+                ..report("condvar", 2)
+            },
+            report("condvar", 10),
+            report("condvar", 2),
+        ];
+        let out = render(&environment, &reports);
+
+        check!(out.contains("\nkernel                       6.1.0\n"));
+        check!(out.contains("\ncpu_model                    -\n"));
+        check!(out.contains("\ninterrupted.mean_ns          12000\n"));
+        check!(out.contains("\ninterrupted.p99_ci_high_ns   31000\n"));
+        check!(out.contains("\ntimeouted.count              0\n"));
+        check!(!out.contains("timeouted.mean_ns"));
+
+        let runs = out
+            .lines()
+            .filter(|line| line.starts_with("[run"))
+            .collect::<Vec<_>>();
+        check!(
+            runs == [
+                "[run backend=condvar scenario=mixed interrupt_percent=50 timeout_mode=relative timeslice_ns=2000000]",
+                "[run backend=condvar scenario=mixed interrupt_percent=50 timeout_mode=relative timeslice_ns=2000000 repeat=1]",
+                "[run backend=condvar scenario=mixed interrupt_percent=50 timeout_mode=relative timeslice_ns=10000000]",
+                "[run backend=futex scenario=mixed interrupt_percent=50 timeout_mode=relative timeslice_ns=10000000]",
+            ]
+        );
+        // Deterministic.
+        check!(render(&environment, &reports) == out);
+    }
+}
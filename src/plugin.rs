@@ -0,0 +1,71 @@
+//! Backends loaded at runtime from shared libraries. See [`load`].
+//!
+//! In-house wake-up primitives can be benchmarked with this harness without
+//! forking the crate: a plugin is a `cdylib` that depends on this crate and
+//! exports a [`Register`] function under the name [`REGISTER_SYMBOL`]:
+//!
+//! ```ignore
+//! use benchmark_interruptible_sleep::backends::Backend;
+//!
+//! #[unsafe(no_mangle)]
+//! pub fn benchmark_interruptible_sleep_backends() -> Vec<Backend> {
+//!     vec![Backend::new("in-house", "our in-house event", || {
+//!         let (sleeper, waker) = in_house::new_pair();
+//!         (Box::new(sleeper), Box::new(waker))
+//!     })]
+//! }
+//! ```
+//!
+//! The Rust ABI is not stable: the plugin must be built with the same
+//! compiler and the same version of this crate as the binary.
+
+use crate::backends::Backend;
+use libloading::Library;
+use std::io;
+use std::path::Path;
+
+/// Name of the [`Register`] function a plugin exports.
+pub const REGISTER_SYMBOL: &str = "benchmark_interruptible_sleep_backends";
+
+/// Signature of the function a plugin exports under [`REGISTER_SYMBOL`]. It
+/// returns the backends of the plugin.
+pub type Register = fn() -> Vec<Backend>;
+
+/// Loads the plugin at `path` and returns its backends, e.g., for
+/// [`backends::register`]. The library is never unloaded, as the backends
+/// refer to its code and data.
+///
+/// # Errors
+///
+/// Fails if the library can't be loaded or doesn't export
+/// [`REGISTER_SYMBOL`].
+///
+/// [`backends::register`]: crate::backends::register
+pub fn load(path: &Path) -> io::Result<Vec<Backend>> {
+    // SAFETY: Loading a library runs its initialization code; the user
+    // vouches for the plugin by passing it.
+    let library = unsafe { Library::new(path) }.map_err(invalid)?;
+    // SAFETY: The type of the symbol is part of the documented contract of
+    // a plugin.
+    let register =
+        *unsafe { library.get::<Register>(REGISTER_SYMBOL.as_bytes()) }.map_err(invalid)?;
+    let backends = register();
+    // The backends refer to the library.
+    std::mem::forget(library);
+    Ok(backends)
+}
+
+fn invalid(e: libloading::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert2::check;
+
+    #[test]
+    fn test_load_missing() {
+        check!(load(Path::new("/nonexistent/libplugin.so")).is_err());
+    }
+}
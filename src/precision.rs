@@ -0,0 +1,128 @@
+//! Measures the precision of the timeouts alone, without interruptions. See
+//! [`measure`].
+//!
+//! The delay of a wake-up consists of the wake-up latency and, for timeouts,
+//! of the precision of the timer. This module isolates the latter: the sleeper
+//! sleeps many times in a row without ever being interrupted, and each sleep
+//! starts right after the previous one. With relative timeouts, the overshoots
+//! accumulate into a drift from the ideal schedule.
+
+use crate::clock::Clock;
+use crate::stats::{mean, percentile};
+use crate::{Sleeper, TimeoutMode, WakeupReason};
+use std::time::{Duration, Instant};
+
+/// Result of [`measure`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Precision {
+    pub timeslice: Duration,
+    pub timeout_mode: TimeoutMode,
+    /// How late each sleep returned after its deadline, sorted.
+    pub overshoots: Vec<Duration>,
+    /// Number of sleeps that returned before their deadline.
+    pub early: usize,
+    /// How late the last sleep returned compared with the ideal schedule of
+    /// back-to-back timeslices.
+    pub drift: Duration,
+}
+
+impl Precision {
+    /// Returns the `p`-th percentile (`0.0..=100.0`) of the overshoots.
+    #[must_use]
+    pub fn overshoot(&self, p: f64) -> Duration {
+        percentile(&self.overshoots, p)
+    }
+
+    #[must_use]
+    pub fn mean_overshoot(&self) -> Duration {
+        mean(&self.overshoots)
+    }
+
+    /// Returns the drift averaged over all sleeps.
+    #[must_use]
+    pub fn drift_per_sleep(&self) -> Duration {
+        match u32::try_from(self.overshoots.len()) {
+            Ok(0) | Err(_) => Duration::ZERO,
+            Ok(sleeps) => self.drift / sleeps,
+        }
+    }
+}
+
+/// Sleeps `sleeps` times for `timeslice` back to back and measures the
+/// overshoot of each timeout and the cumulative drift.
+///
+/// With [`TimeoutMode::Relative`], each sleep starts when the previous one
+/// returned, so the drift is the sum of all overshoots plus the overhead in
+/// between. With [`TimeoutMode::Absolute`], the deadlines follow the ideal
+/// schedule, so the drift is just the overshoot of the last sleep.
+///
+/// # Panics
+///
+/// Panics if a sleep is interrupted.
+pub fn measure(
+    sleeper: &impl Sleeper<Instant>,
+    clock: &impl Clock,
+    timeslice: Duration,
+    sleeps: usize,
+    timeout_mode: TimeoutMode,
+) -> Precision {
+    let mut overshoots = Vec::with_capacity(sleeps);
+    let mut early = 0;
+    let start = clock.now();
+    let mut ideal_end = start;
+    let mut end = start;
+
+    for _ in 0..sleeps {
+        ideal_end += timeslice;
+        let begin = clock.now();
+        let (deadline, reason) = match timeout_mode {
+            TimeoutMode::Relative => (begin + timeslice, sleeper.sleep_interruptible(timeslice)),
+            TimeoutMode::Absolute => (ideal_end, sleeper.sleep_until(ideal_end)),
+        };
+        end = clock.now();
+        assert_eq!(reason, WakeupReason::Timeout, "nobody wakes the sleeper");
+
+        if end < deadline {
+            early += 1;
+        }
+        overshoots.push(end.saturating_duration_since(deadline));
+    }
+
+    overshoots.sort_unstable();
+    Precision {
+        timeslice,
+        timeout_mode,
+        overshoots,
+        early,
+        drift: end.saturating_duration_since(ideal_end),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::condvar;
+    use crate::clock::SystemClock;
+    use assert2::check;
+
+    const TIMESLICE: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn test_relative_drift_accumulates() {
+        let (sleeper, _waker) = condvar::new_pair();
+        let precision = measure(&sleeper, &SystemClock, TIMESLICE, 20, TimeoutMode::Relative);
+        check!(precision.overshoots.len() == 20);
+        check!(precision.early == 0);
+        check!(precision.drift >= precision.overshoots.iter().sum::<Duration>());
+        check!(precision.overshoot(100.0) >= precision.overshoot(50.0));
+    }
+
+    #[test]
+    fn test_absolute_drift_does_not_accumulate() {
+        let (sleeper, _waker) = condvar::new_pair();
+        let precision = measure(&sleeper, &SystemClock, TIMESLICE, 20, TimeoutMode::Absolute);
+        check!(precision.overshoots.len() == 20);
+        check!(precision.early == 0);
+        check!(precision.drift <= precision.overshoot(100.0));
+    }
+}
@@ -0,0 +1,196 @@
+//! Scheduling priorities (nice levels) of the threads. See [`Priorities`].
+//!
+//! In real producer/consumer setups, the notifying thread often runs at a
+//! different priority than the waiting one. If the waker runs at a lower
+//! priority, it may be preempted while it issues the wake-up; if the sleeper
+//! runs at a lower priority, it may have to wait for a CPU after the wake-up.
+//! On Linux, the nice level is a property of each thread.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::thread;
+
+/// Nice levels of the waker and the sleeper during a run. `None` keeps the
+/// nice level of the thread.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Priorities {
+    pub waker: Option<i32>,
+    pub sleeper: Option<i32>,
+}
+
+impl Priorities {
+    /// Returns whether any nice level is set.
+    #[must_use]
+    pub const fn is_set(&self) -> bool {
+        self.waker.is_some() || self.sleeper.is_some()
+    }
+}
+
+impl Display for Priorities {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let nice =
+            |nice: Option<i32>| nice.map_or_else(|| "unchanged".to_string(), |n| n.to_string());
+        write!(
+            f,
+            "waker nice {}, sleeper nice {}",
+            nice(self.waker),
+            nice(self.sleeper)
+        )
+    }
+}
+
+/// Returns the kernel's id of the calling thread, or `None` if this is not
+/// supported on this platform.
+#[must_use]
+pub fn current_tid() -> Option<i32> {
+    #[cfg(target_os = "linux")]
+    {
+        // SAFETY: gettid() has no preconditions.
+        Some(unsafe { libc::gettid() })
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// Returns the nice level of the thread with the kernel's id `tid`.
+///
+/// # Errors
+///
+/// Fails if the thread doesn't exist or if this is not supported on this
+/// platform, i.e., on other platforms than Linux.
+pub fn nice(tid: i32) -> io::Result<i32> {
+    #[cfg(target_os = "linux")]
+    {
+        // getpriority() may legitimately return -1, so errno is the only
+        // indication of an error.
+        // SAFETY: The pointer to errno is valid for the calling thread.
+        unsafe { *libc::__errno_location() = 0 };
+        // SAFETY: getpriority() has no memory-safety preconditions.
+        let nice = unsafe { libc::getpriority(libc::PRIO_PROCESS, tid as libc::id_t) };
+        let error = io::Error::last_os_error();
+        if nice == -1 && error.raw_os_error() != Some(0) {
+            return Err(error);
+        }
+        Ok(nice)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = tid;
+        Err(unsupported())
+    }
+}
+
+/// Sets the nice level of the thread with the kernel's id `tid`. Raising it
+/// is always allowed, lowering it typically requires root.
+///
+/// # Errors
+///
+/// Fails if the thread doesn't exist, if the caller lacks the permission, or
+/// if this is not supported on this platform.
+pub fn set_nice(tid: i32, nice: i32) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        // SAFETY: setpriority() has no memory-safety preconditions.
+        let ret = unsafe { libc::setpriority(libc::PRIO_PROCESS, tid as libc::id_t, nice) };
+        if ret != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (tid, nice);
+        Err(unsupported())
+    }
+}
+
+/// Restores the previous nice level of the calling thread when dropped. See
+/// [`renice_current`].
+#[derive(Debug)]
+pub struct Reniced {
+    tid: i32,
+    previous: i32,
+}
+
+impl Drop for Reniced {
+    fn drop(&mut self) {
+        // Checked by `renice_current()` in advance.
+        let _ = set_nice(self.tid, self.previous);
+    }
+}
+
+/// Sets the nice level of the calling thread until the returned guard is
+/// dropped.
+///
+/// # Errors
+///
+/// Fails like [`set_nice`], and also if the previous nice level couldn't be
+/// restored afterward, e.g., because raising it without root can't be
+/// undone. This is checked on a scratch thread first, so the calling thread
+/// is never left at the wrong nice level.
+pub fn renice_current(nice: i32) -> io::Result<Reniced> {
+    let tid = current_tid().ok_or_else(unsupported)?;
+    let previous = self::nice(tid)?;
+    // New threads inherit the nice level of the calling thread.
+    thread::spawn(move || {
+        let tid = current_tid().ok_or_else(unsupported)?;
+        set_nice(tid, nice)?;
+        set_nice(tid, previous).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("nice level {nice} can't be undone afterward: {e}"),
+            )
+        })
+    })
+    .join()
+    .expect("should not panic")?;
+
+    set_nice(tid, nice)?;
+    Ok(Reniced { tid, previous })
+}
+
+fn unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "nice levels of threads are only supported on Linux",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert2::check;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_renice_current() {
+        let tid = current_tid().unwrap();
+        let before = nice(tid).unwrap();
+        // Keeping the nice level is always allowed and can always be undone.
+        let reniced = renice_current(before).unwrap();
+        check!(nice(tid).unwrap() == before);
+        drop(reniced);
+        check!(nice(tid).unwrap() == before);
+
+        // Only the scratch thread is affected by a raised nice level.
+        let other = thread::spawn(move || {
+            let tid = current_tid().unwrap();
+            set_nice(tid, before + 1).unwrap();
+            nice(tid).unwrap()
+        });
+        check!(other.join().unwrap() == before + 1);
+        check!(nice(tid).unwrap() == before);
+    }
+
+    #[test]
+    fn test_display() {
+        let priorities = Priorities {
+            waker: Some(10),
+            sleeper: None,
+        };
+        check!(priorities.to_string() == "waker nice 10, sleeper nice unchanged");
+    }
+}
@@ -0,0 +1,577 @@
+//! A compact binary format of raw measurements that is written while the
+//! measurements happen. See [`RecordingWriter`] and [`RecordingReader`].
+//!
+//! Runs with millions of rounds don't fit into memory as [`RawRun`]s and
+//! produce huge CSV files. A recording streams each measurement as a compact
+//! record instead, and is read back one run at a time, see
+//! [`RecordingReader::runs`], or accumulated one record at a time, see
+//! [`RecordingReader::runs_as`].
+//!
+//! # Format
+//!
+//! The file starts with [`MAGIC`], followed by records, each starting with a
+//! tag byte. All integers are little-endian.
+//!
+//! - `0`: begin of a run. A `u32` length and the [`RunHeader`] as JSON of
+//!   that length. All following records belong to this run.
+//! - `1`: a measurement of [`MEASUREMENT_SIZE`] bytes: flags (`u32`, bit 0:
+//!   interrupted, bit 1: `cpu_before`, bit 2: `cpu_after`, bit 3:
+//!   `run_delay`, bit 4: `return_path` known), expected duration, actual
+//!   duration, delay, and run delay in ns (`u64` each), `cpu_before` and
+//!   `cpu_after` (`u32` each), and the return path in ns (`u64`), see
+//!   [`RawMeasurement::return_path`].
+//! - `2`: a skipped round.
+//!
+//! A truncated last record, e.g., of an interrupted soak run, is ignored.
+
+use crate::priority::Priorities;
+use crate::report::{RawMeasurement, RawRun, default_interrupt_percent};
+use crate::runner::{MeasurementSink, RunConfig};
+use crate::threads::ThreadIds;
+use crate::topology::CpuPair;
+use crate::units::{
+    deserialize_nanos, deserialize_optional_nanos, serialize_nanos, serialize_optional_nanos,
+};
+use crate::{Measurement, Measurements};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::marker::PhantomData;
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::time::Duration;
+
+/// The first bytes of a recording, the last one is the version.
+pub const MAGIC: &[u8; 8] = b"BISLEEP\x02";
+
+/// Size of a measurement record without the tag.
+pub const MEASUREMENT_SIZE: usize = 52;
+
+const TAG_RUN: u8 = 0;
+const TAG_MEASUREMENT: u8 = 1;
+const TAG_SKIPPED: u8 = 2;
+
+const FLAG_INTERRUPTED: u32 = 1 << 0;
+const FLAG_CPU_BEFORE: u32 = 1 << 1;
+const FLAG_CPU_AFTER: u32 = 1 << 2;
+const FLAG_RUN_DELAY: u32 = 1 << 3;
+const FLAG_RETURN_PATH: u32 = 1 << 4;
+
+/// The parameters of a run, see [`RawRun`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunHeader {
+    pub backend: String,
+    pub scenario: String,
+    pub timeout_mode: String,
+    #[serde(
+        rename = "timeslice_ns",
+        serialize_with = "serialize_nanos",
+        deserialize_with = "deserialize_nanos"
+    )]
+    pub timeslice: Duration,
+    pub seed: u64,
+    /// See [`RawRun::repeat`]. Missing in recordings of older versions.
+    #[serde(default)]
+    pub repeat: usize,
+    /// See [`RawRun::interrupt_percent`]. Missing in recordings of older
+    /// versions.
+    #[serde(default = "default_interrupt_percent")]
+    pub interrupt_percent: u8,
+    /// See [`RawRun::detached_wakes`]. Missing in recordings of older
+    /// versions.
+    #[serde(default)]
+    pub detached_wakes: bool,
+    /// See [`RawRun::cpu_dma_latency`]. Missing in recordings of older
+    /// versions.
+    #[serde(
+        default,
+        rename = "cpu_dma_latency_ns",
+        serialize_with = "serialize_optional_nanos",
+        deserialize_with = "deserialize_optional_nanos"
+    )]
+    pub cpu_dma_latency: Option<Duration>,
+    /// See [`RawRun::load`]. Missing in recordings of older versions.
+    #[serde(default)]
+    pub load: Option<String>,
+    /// See [`RawRun::placement`]. Missing in recordings of older versions.
+    #[serde(default)]
+    pub placement: Option<String>,
+    /// See [`RawRun::cpus`]. Missing in recordings of older versions.
+    #[serde(default)]
+    pub cpus: Option<CpuPair>,
+    /// See [`RawRun::priorities`]. Missing in recordings of older versions.
+    #[serde(default)]
+    pub priorities: Priorities,
+}
+
+impl RunHeader {
+    #[must_use]
+    pub fn new(backend: &str, config: &RunConfig) -> Self {
+        Self {
+            backend: backend.to_string(),
+            scenario: config.scenario.name().to_string(),
+            timeout_mode: config.timeout_mode.name().to_string(),
+            timeslice: config.timeslice,
+            seed: config.seed,
+            repeat: 0,
+            interrupt_percent: config.interrupt_percent,
+            detached_wakes: config.detached_wakes,
+            cpu_dma_latency: None,
+            load: None,
+            placement: None,
+            cpus: config.cpus,
+            priorities: config.priorities,
+        }
+    }
+
+    /// Returns the parameters of a run.
+    #[must_use]
+    pub fn of(run: &RawRun) -> Self {
+        Self {
+            backend: run.backend.clone(),
+            scenario: run.scenario.clone(),
+            timeout_mode: run.timeout_mode.clone(),
+            timeslice: run.timeslice,
+            seed: run.seed,
+            repeat: run.repeat,
+            interrupt_percent: run.interrupt_percent,
+            detached_wakes: run.detached_wakes,
+            cpu_dma_latency: run.cpu_dma_latency,
+            load: run.load.clone(),
+            placement: run.placement.clone(),
+            cpus: run.cpus,
+            priorities: run.priorities,
+        }
+    }
+
+    /// Returns a run without measurements.
+    fn into_run(self) -> RawRun {
+        RawRun::with_header(self, &Measurements::default())
+    }
+}
+
+/// A run built from the records of a recording, see
+/// [`RecordingReader::runs_as`].
+pub trait FromRecords {
+    /// Begins a run, before any of its measurements.
+    fn begin(header: RunHeader) -> Self;
+
+    fn record(&mut self, measurement: RawMeasurement);
+
+    fn skip(&mut self);
+}
+
+impl FromRecords for RawRun {
+    fn begin(header: RunHeader) -> Self {
+        header.into_run()
+    }
+
+    fn record(&mut self, measurement: RawMeasurement) {
+        self.measurements.push(measurement);
+    }
+
+    fn skip(&mut self) {
+        self.skipped += 1;
+    }
+}
+
+/// A record of a recording.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Record {
+    Run(RunHeader),
+    Measurement(RawMeasurement),
+    Skipped,
+}
+
+/// Writes a recording record by record.
+#[derive(Debug)]
+pub struct RecordingWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> RecordingWriter<W> {
+    /// Writes the [`MAGIC`] to `out`, which should be buffered.
+    pub fn new(mut out: W) -> io::Result<Self> {
+        out.write_all(MAGIC)?;
+        Ok(Self { out })
+    }
+
+    /// Begins a new run. All following records belong to it.
+    pub fn begin_run(&mut self, header: &RunHeader) -> io::Result<()> {
+        let json = serde_json::to_vec(header)?;
+        let len = u32::try_from(json.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "run header too large"))?;
+        self.out.write_all(&[TAG_RUN])?;
+        self.out.write_all(&len.to_le_bytes())?;
+        self.out.write_all(&json)
+    }
+
+    pub fn record(&mut self, measurement: &RawMeasurement) -> io::Result<()> {
+        let mut record = [0; 1 + MEASUREMENT_SIZE];
+        record[0] = TAG_MEASUREMENT;
+        encode(measurement, &mut record[1..]);
+        self.out.write_all(&record)
+    }
+
+    pub fn skip(&mut self) -> io::Result<()> {
+        self.out.write_all(&[TAG_SKIPPED])
+    }
+
+    /// Writes a complete run.
+    pub fn write_run(&mut self, run: &RawRun) -> io::Result<()> {
+        self.begin_run(&RunHeader::of(run))?;
+        for measurement in &run.measurements {
+            self.record(measurement)?;
+        }
+        for _ in 0..run.skipped {
+            self.skip()?;
+        }
+        Ok(())
+    }
+
+    /// Flushes and returns the output.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+fn encode(m: &RawMeasurement, buf: &mut [u8]) {
+    let flags = [
+        (m.interrupted, FLAG_INTERRUPTED),
+        (m.cpu_before.is_some(), FLAG_CPU_BEFORE),
+        (m.cpu_after.is_some(), FLAG_CPU_AFTER),
+        (m.run_delay.is_some(), FLAG_RUN_DELAY),
+        (m.return_path.is_some(), FLAG_RETURN_PATH),
+    ]
+    .into_iter()
+    .filter(|(set, _)| *set)
+    .fold(0, |flags, (_, flag)| flags | flag);
+    let nanos = |duration: Duration| (duration.as_nanos() as u64).to_le_bytes();
+
+    buf[0..4].copy_from_slice(&flags.to_le_bytes());
+    buf[4..12].copy_from_slice(&nanos(m.expected_duration));
+    buf[12..20].copy_from_slice(&nanos(m.actual_duration));
+    buf[20..28].copy_from_slice(&nanos(m.delay));
+    buf[28..36].copy_from_slice(&nanos(m.run_delay.unwrap_or_default()));
+    buf[36..40].copy_from_slice(&m.cpu_before.unwrap_or_default().to_le_bytes());
+    buf[40..44].copy_from_slice(&m.cpu_after.unwrap_or_default().to_le_bytes());
+    buf[44..52].copy_from_slice(&nanos(m.return_path.unwrap_or_default()));
+}
+
+fn decode(buf: &[u8; MEASUREMENT_SIZE]) -> RawMeasurement {
+    let u32_at = |i: usize| u32::from_le_bytes(buf[i..i + 4].try_into().unwrap());
+    let nanos_at =
+        |i: usize| Duration::from_nanos(u64::from_le_bytes(buf[i..i + 8].try_into().unwrap()));
+    let flags = u32_at(0);
+    let has = |flag: u32| flags & flag != 0;
+
+    RawMeasurement {
+        interrupted: has(FLAG_INTERRUPTED),
+        expected_duration: nanos_at(4),
+        actual_duration: nanos_at(12),
+        delay: nanos_at(20),
+        run_delay: has(FLAG_RUN_DELAY).then(|| nanos_at(28)),
+        cpu_before: has(FLAG_CPU_BEFORE).then(|| u32_at(36)),
+        cpu_after: has(FLAG_CPU_AFTER).then(|| u32_at(40)),
+        return_path: has(FLAG_RETURN_PATH).then(|| nanos_at(44)),
+    }
+}
+
+/// A [`MeasurementSink`] that writes each measurement to a recording and
+/// passes it on to `inner`.
+///
+/// The run fails when writing fails.
+#[derive(Debug)]
+pub struct RecordingSink<'a, S, W: Write> {
+    inner: S,
+    writer: &'a mut RecordingWriter<W>,
+}
+
+impl<'a, S: MeasurementSink, W: Write> RecordingSink<'a, S, W> {
+    /// Begins a new run in the recording.
+    pub fn new(
+        inner: S,
+        writer: &'a mut RecordingWriter<W>,
+        header: &RunHeader,
+    ) -> io::Result<Self> {
+        writer.begin_run(header)?;
+        Ok(Self { inner, writer })
+    }
+}
+
+impl<S: MeasurementSink, W: Write> MeasurementSink for RecordingSink<'_, S, W> {
+    fn record(&mut self, measurement: Measurement) -> ControlFlow<()> {
+        if let Err(e) = self.writer.record(&RawMeasurement::from(&measurement)) {
+            self.inner.fail(format!("writing the recording: {e}"));
+            return ControlFlow::Break(());
+        }
+        self.inner.record(measurement)
+    }
+
+    fn skip(&mut self) {
+        // A write error surfaces with the next measurement.
+        let _ = self.writer.skip();
+        self.inner.skip();
+    }
+
+    fn threads(&mut self, threads: ThreadIds) {
+        self.inner.threads(threads);
+    }
+
+    fn fail(&mut self, reason: String) {
+        self.inner.fail(reason);
+    }
+}
+
+/// Reads a recording record by record.
+#[derive(Debug)]
+pub struct RecordingReader<R: Read> {
+    input: R,
+}
+
+impl<R: Read> RecordingReader<R> {
+    /// Checks the [`MAGIC`] at the begin of `input`, which should be
+    /// buffered.
+    pub fn new(mut input: R) -> io::Result<Self> {
+        let mut magic = [0; MAGIC.len()];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a recording or unsupported version"));
+        }
+        Ok(Self { input })
+    }
+
+    /// Returns an iterator over the runs with their measurements. Only one
+    /// run is in memory at a time.
+    #[must_use]
+    pub const fn runs(self) -> Runs<R> {
+        self.runs_as()
+    }
+
+    /// Like [`Self::runs`] but builds the runs as `T`, e.g., to accumulate
+    /// them in fixed-size memory.
+    #[must_use]
+    pub const fn runs_as<T: FromRecords>(self) -> Runs<R, T> {
+        Runs {
+            reader: self,
+            next_header: None,
+            run: PhantomData,
+        }
+    }
+
+    /// Returns the next record or `None` at the end of the recording.
+    pub fn next_record(&mut self) -> io::Result<Option<Record>> {
+        let mut tag = [0];
+        if self.input.read(&mut tag)? == 0 {
+            return Ok(None);
+        }
+        let record = match tag[0] {
+            TAG_RUN => {
+                let mut len = [0; 4];
+                if !self.read_record(&mut len)? {
+                    return Ok(None);
+                }
+                let mut json = vec![0; u32::from_le_bytes(len) as usize];
+                if !self.read_record(&mut json)? {
+                    return Ok(None);
+                }
+                Record::Run(serde_json::from_slice(&json)?)
+            }
+            TAG_MEASUREMENT => {
+                let mut buf = [0; MEASUREMENT_SIZE];
+                if !self.read_record(&mut buf)? {
+                    return Ok(None);
+                }
+                Record::Measurement(decode(&buf))
+            }
+            TAG_SKIPPED => Record::Skipped,
+            tag => return Err(invalid(&format!("unknown record tag {tag}"))),
+        };
+        Ok(Some(record))
+    }
+
+    /// Reads the rest of a record. Returns `false` if it is truncated.
+    fn read_record(&mut self, buf: &mut [u8]) -> io::Result<bool> {
+        match self.input.read_exact(buf) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl<R: Read> Iterator for RecordingReader<R> {
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+/// The runs of a recording, see [`RecordingReader::runs`].
+#[derive(Debug)]
+pub struct Runs<R: Read, T = RawRun> {
+    reader: RecordingReader<R>,
+    /// The header of the next run, if read already.
+    next_header: Option<RunHeader>,
+    run: PhantomData<T>,
+}
+
+impl<R: Read, T: FromRecords> Runs<R, T> {
+    /// Returns the next run or `None` at the end of the recording.
+    pub fn next_run(&mut self) -> io::Result<Option<T>> {
+        let header = match self.next_header.take() {
+            Some(header) => header,
+            None => match self.reader.next_record()? {
+                Some(Record::Run(header)) => header,
+                Some(_) => return Err(invalid("record before the first run")),
+                None => return Ok(None),
+            },
+        };
+        let mut run = T::begin(header);
+        loop {
+            match self.reader.next_record()? {
+                Some(Record::Measurement(measurement)) => run.record(measurement),
+                Some(Record::Skipped) => run.skip(),
+                Some(Record::Run(header)) => {
+                    self.next_header = Some(header);
+                    break;
+                }
+                None => break,
+            }
+        }
+        Ok(Some(run))
+    }
+}
+
+impl<R: Read, T: FromRecords> Iterator for Runs<R, T> {
+    type Item = io::Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_run().transpose()
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Writes the runs as a recording to `path`.
+pub fn write(path: &Path, runs: &[RawRun]) -> io::Result<()> {
+    let mut writer = RecordingWriter::new(BufWriter::new(File::create(path)?))?;
+    for run in runs {
+        writer.write_run(run)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+/// Opens the recording at `path`.
+pub fn open(path: &Path) -> io::Result<RecordingReader<BufReader<File>>> {
+    RecordingReader::new(BufReader::new(File::open(path)?))
+}
+
+/// Reads all runs of the recording at `path`. See [`RecordingReader::runs`]
+/// to read one run at a time.
+pub fn read(path: &Path) -> io::Result<Vec<RawRun>> {
+    open(path)?.runs().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner;
+    use crate::virtual_time::{self, VirtualClock};
+    use assert2::check;
+
+    fn measurement() -> RawMeasurement {
+        RawMeasurement {
+            interrupted: true,
+            expected_duration: Duration::from_micros(700),
+            actual_duration: Duration::from_micros(760),
+            delay: Duration::from_micros(60),
+            cpu_before: Some(3),
+            cpu_after: None,
+            run_delay: Some(Duration::from_micros(5)),
+            return_path: Some(Duration::from_micros(2)),
+        }
+    }
+
+    #[test]
+    fn test_measurement_roundtrip() {
+        let mut buf = [0; MEASUREMENT_SIZE];
+        encode(&measurement(), &mut buf);
+        check!(decode(&buf) == measurement());
+        let unknown = RawMeasurement {
+            return_path: None,
+            ..measurement()
+        };
+        encode(&unknown, &mut buf);
+        check!(decode(&buf) == unknown);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let clock = VirtualClock::new();
+        let (sleeper, waker) = virtual_time::new_pair(&clock);
+        let config = RunConfig::new(20, Duration::from_secs(1)).with_seed(7);
+        let measurements = runner::run_with_clock(config, sleeper, waker, clock);
+        let mut runs = [
+            RawRun::new("a", &config, &measurements),
+            RawRun::new("b", &config, &measurements),
+        ];
+        runs[1].skipped = 2;
+        runs[1].repeat = 1;
+
+        let path = std::env::temp_dir().join(format!("raw-{}.bin", std::process::id()));
+        write(&path, &runs).unwrap();
+        let read = read(&path);
+        std::fs::remove_file(&path).unwrap();
+        check!(read.unwrap() == runs);
+    }
+
+    #[test]
+    fn test_runs() {
+        let config = RunConfig::new(1, Duration::from_millis(1));
+        let mut writer = RecordingWriter::new(Vec::new()).unwrap();
+        writer.begin_run(&RunHeader::new("a", &config)).unwrap();
+        writer.record(&measurement()).unwrap();
+        writer.skip().unwrap();
+        writer.begin_run(&RunHeader::new("b", &config)).unwrap();
+        let bytes = writer.finish().unwrap();
+
+        let mut runs = RecordingReader::new(bytes.as_slice()).unwrap().runs();
+        let a = runs.next_run().unwrap().unwrap();
+        check!(a.backend == "a");
+        check!(a.measurements == [measurement()]);
+        check!(a.skipped == 1);
+        let b = runs.next_run().unwrap().unwrap();
+        check!(b.backend == "b");
+        check!(b.measurements.is_empty());
+        check!(runs.next_run().unwrap().is_none());
+
+        let mut writer = RecordingWriter::new(Vec::new()).unwrap();
+        writer.skip().unwrap();
+        let bytes = writer.finish().unwrap();
+        let mut runs = RecordingReader::new(bytes.as_slice()).unwrap().runs();
+        check!(runs.next_run().is_err());
+    }
+
+    #[test]
+    fn test_truncated() {
+        let mut writer = RecordingWriter::new(Vec::new()).unwrap();
+        let header = RunHeader::new("a", &RunConfig::new(1, Duration::from_millis(1)));
+        writer.begin_run(&header).unwrap();
+        writer.record(&measurement()).unwrap();
+        writer.record(&measurement()).unwrap();
+        let mut bytes = writer.finish().unwrap();
+        bytes.truncate(bytes.len() - 10);
+
+        let records = RecordingReader::new(bytes.as_slice())
+            .unwrap()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        check!(records == [Record::Run(header), Record::Measurement(measurement())]);
+    }
+}
@@ -0,0 +1,918 @@
+//! Machine-readable reports of runs: the statistics of a run, see
+//! [`RunReport`], and its raw measurements, see [`RawRun`].
+//!
+//! Reports are stored as JSON. Raw measurements are stored as JSON or CSV,
+//! see [`write_raw`].
+
+use crate::analysis::RunAnalysis;
+use crate::energy;
+use crate::priority::Priorities;
+use crate::recording::{self, RunHeader};
+use crate::runner::RunConfig;
+use crate::scenario::DEFAULT_INTERRUPT_PERCENT;
+use crate::stats::{Summary, summarize, summarize_delays};
+use crate::threads::ThreadIds;
+use crate::topology::CpuPair;
+use crate::units::{
+    deserialize_nanos, deserialize_optional_nanos, serialize_nanos, serialize_optional_nanos,
+};
+use crate::{
+    Attribution, Measurement, Measurements, SignedDelay, TimeoutMode, WakeupReason, split_delay,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// The statistics of a single run of one backend with one timeslice.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunReport {
+    pub backend: String,
+    pub scenario: String,
+    /// See [`RunConfig::interrupt_percent`]. Missing in files of older
+    /// versions.
+    #[serde(default = "default_interrupt_percent")]
+    pub interrupt_percent: u8,
+    /// See [`TimeoutMode`]. Missing in files of older versions.
+    #[serde(default = "default_timeout_mode")]
+    pub timeout_mode: String,
+    #[serde(
+        rename = "timeslice_ns",
+        serialize_with = "serialize_nanos",
+        deserialize_with = "deserialize_nanos"
+    )]
+    pub timeslice: Duration,
+    pub seed: u64,
+    /// Index of the run among the repeats of its configuration, see
+    /// `bench --repeats`. Zero if not repeated. Missing in files of older
+    /// versions.
+    #[serde(default)]
+    pub repeat: usize,
+    pub rounds: usize,
+    pub skipped: usize,
+    /// Number of rounds whose sleep returned before its expected duration,
+    /// see [`WakeupContext::negative_delay`]. Their delay is counted as zero.
+    /// Missing in files of older versions.
+    ///
+    /// [`WakeupContext::negative_delay`]: crate::WakeupContext::negative_delay
+    #[serde(default)]
+    pub negative_delays: usize,
+    /// `None` if no round was interrupted.
+    pub interrupted: Option<Summary>,
+    /// `None` if no round timed out.
+    pub timeouted: Option<Summary>,
+    /// The maximum exit latency of idle states during the run, see
+    /// [`CpuDmaLatency`]. `None` if idle states were not restricted.
+    ///
+    /// [`CpuDmaLatency`]: crate::idle::CpuDmaLatency
+    #[serde(
+        default,
+        rename = "cpu_dma_latency_ns",
+        serialize_with = "serialize_optional_nanos",
+        deserialize_with = "deserialize_optional_nanos"
+    )]
+    pub cpu_dma_latency: Option<Duration>,
+    /// The name of the background [`Load`] during the run, if any.
+    ///
+    /// [`Load`]: crate::load::Load
+    #[serde(default)]
+    pub load: Option<String>,
+    /// The energy the CPU packages consumed during the run, see [`Rapl`].
+    /// `None` if the RAPL counters are not available.
+    ///
+    /// [`Rapl`]: crate::energy::Rapl
+    #[serde(default, rename = "energy_uj")]
+    pub energy: Option<u64>,
+    /// Whether the wake-ups were issued without the ACK handshake, see
+    /// [`RunConfig::detached_wakes`].
+    #[serde(default)]
+    pub detached_wakes: bool,
+    /// Why the run was aborted, e.g., because the sleeper hung. The
+    /// statistics only cover the rounds before.
+    #[serde(default)]
+    pub failure: Option<String>,
+    /// The name of the [`Placement`] of the threads, if they were pinned.
+    ///
+    /// [`Placement`]: crate::topology::Placement
+    #[serde(default)]
+    pub placement: Option<String>,
+    /// The CPUs the threads were pinned to, see [`RunConfig::cpus`].
+    #[serde(default)]
+    pub cpus: Option<CpuPair>,
+    /// The nice levels of the threads, see [`RunConfig::priorities`].
+    #[serde(default)]
+    pub priorities: Priorities,
+    /// The ids of the threads, e.g., to find them in a `perf` recording.
+    #[serde(default)]
+    pub threads: Option<ThreadIds>,
+    /// The seeds of the batches if the run was interleaved with other
+    /// backends, see [`interleave::run`]. Empty otherwise.
+    ///
+    /// [`interleave::run`]: crate::interleave::run
+    #[serde(default)]
+    pub batch_seeds: Vec<u64>,
+}
+
+impl RunReport {
+    /// Summarizes the measurements of a run. The bootstrap resampling uses the
+    /// seed of the run.
+    #[must_use]
+    pub fn new(backend: &str, config: &RunConfig, measurements: &Measurements) -> Self {
+        Self {
+            backend: backend.to_string(),
+            scenario: config.scenario.name().to_string(),
+            interrupt_percent: config.interrupt_percent,
+            timeout_mode: config.timeout_mode.name().to_string(),
+            timeslice: config.timeslice,
+            seed: config.seed,
+            repeat: 0,
+            rounds: measurements.rounds,
+            skipped: measurements.skipped,
+            negative_delays: measurements
+                .interrupted
+                .iter()
+                .chain(&measurements.timeouted)
+                .filter(|m| m.wakeup_context.negative_delay())
+                .count(),
+            interrupted: summarize(&measurements.interrupted, config.seed),
+            timeouted: summarize(&measurements.timeouted, config.seed),
+            cpu_dma_latency: None,
+            load: None,
+            energy: None,
+            detached_wakes: config.detached_wakes,
+            failure: measurements.failure.clone(),
+            placement: None,
+            cpus: config.cpus,
+            priorities: config.priorities,
+            threads: measurements.threads,
+            batch_seeds: Vec::new(),
+        }
+    }
+
+    /// Returns the energy in µJ per 1000 wake-ups, if known.
+    #[must_use]
+    pub fn energy_per_1000_wakeups(&self) -> Option<u64> {
+        energy::per_1000_wakeups(self.energy?, self.rounds)
+    }
+
+    /// Summarizes a run that was accumulated in fixed-size memory. The
+    /// confidence intervals are analytic, see [`DelayAccumulator::summary`].
+    ///
+    /// [`DelayAccumulator::summary`]: crate::accumulator::DelayAccumulator::summary
+    #[must_use]
+    pub fn from_analysis(run: &RunAnalysis) -> Self {
+        let header = &run.header;
+        Self {
+            backend: header.backend.clone(),
+            scenario: header.scenario.clone(),
+            interrupt_percent: header.interrupt_percent,
+            timeout_mode: header.timeout_mode.clone(),
+            timeslice: header.timeslice,
+            seed: header.seed,
+            repeat: header.repeat,
+            rounds: run.rounds,
+            skipped: run.skipped,
+            negative_delays: run.negative_delays,
+            interrupted: run.interrupted.delays.summary(),
+            timeouted: run.timeouted.delays.summary(),
+            cpu_dma_latency: header.cpu_dma_latency,
+            load: header.load.clone(),
+            energy: None,
+            detached_wakes: header.detached_wakes,
+            failure: run.failure.clone(),
+            placement: header.placement.clone(),
+            cpus: header.cpus,
+            priorities: header.priorities,
+            threads: run.threads,
+            batch_seeds: Vec::new(),
+        }
+    }
+
+    /// Recomputes the statistics of a run from its raw measurements.
+    #[must_use]
+    pub fn from_raw(raw: &RawRun) -> Self {
+        Self {
+            backend: raw.backend.clone(),
+            scenario: raw.scenario.clone(),
+            interrupt_percent: raw.interrupt_percent,
+            timeout_mode: raw.timeout_mode.clone(),
+            timeslice: raw.timeslice,
+            seed: raw.seed,
+            repeat: raw.repeat,
+            rounds: raw.measurements.len(),
+            skipped: raw.skipped,
+            negative_delays: raw
+                .measurements
+                .iter()
+                .filter(|m| m.signed_delay().is_negative())
+                .count(),
+            interrupted: summarize_delays(&raw.delays(true), raw.seed),
+            timeouted: summarize_delays(&raw.delays(false), raw.seed),
+            cpu_dma_latency: raw.cpu_dma_latency,
+            load: raw.load.clone(),
+            energy: None,
+            detached_wakes: raw.detached_wakes,
+            failure: None,
+            placement: raw.placement.clone(),
+            cpus: raw.cpus,
+            priorities: raw.priorities,
+            threads: None,
+            batch_seeds: Vec::new(),
+        }
+    }
+}
+
+/// The raw measurements of a single run, for later analysis.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RawRun {
+    pub backend: String,
+    pub scenario: String,
+    /// See [`TimeoutMode`]. Missing in files of older versions.
+    #[serde(default = "default_timeout_mode")]
+    pub timeout_mode: String,
+    #[serde(
+        rename = "timeslice_ns",
+        serialize_with = "serialize_nanos",
+        deserialize_with = "deserialize_nanos"
+    )]
+    pub timeslice: Duration,
+    pub seed: u64,
+    /// See [`RunReport::repeat`]. Missing in files of older versions.
+    #[serde(default)]
+    pub repeat: usize,
+    /// See [`RunReport::interrupt_percent`]. Missing in files of older
+    /// versions.
+    #[serde(default = "default_interrupt_percent")]
+    pub interrupt_percent: u8,
+    /// See [`RunReport::detached_wakes`]. Missing in files of older versions.
+    #[serde(default)]
+    pub detached_wakes: bool,
+    /// See [`RunReport::cpu_dma_latency`]. Missing in files of older
+    /// versions.
+    #[serde(
+        default,
+        rename = "cpu_dma_latency_ns",
+        serialize_with = "serialize_optional_nanos",
+        deserialize_with = "deserialize_optional_nanos"
+    )]
+    pub cpu_dma_latency: Option<Duration>,
+    /// See [`RunReport::load`]. Missing in files of older versions.
+    #[serde(default)]
+    pub load: Option<String>,
+    /// See [`RunReport::placement`]. Missing in files of older versions.
+    #[serde(default)]
+    pub placement: Option<String>,
+    /// See [`RunReport::cpus`]. Missing in files of older versions.
+    #[serde(default)]
+    pub cpus: Option<CpuPair>,
+    /// See [`RunReport::priorities`]. Missing in files of older versions.
+    #[serde(default)]
+    pub priorities: Priorities,
+    pub skipped: usize,
+    pub measurements: Vec<RawMeasurement>,
+}
+
+/// A single [`Measurement`] without the absolute points in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RawMeasurement {
+    pub interrupted: bool,
+    #[serde(
+        rename = "expected_duration_ns",
+        serialize_with = "serialize_nanos",
+        deserialize_with = "deserialize_nanos"
+    )]
+    pub expected_duration: Duration,
+    #[serde(
+        rename = "actual_duration_ns",
+        serialize_with = "serialize_nanos",
+        deserialize_with = "deserialize_nanos"
+    )]
+    pub actual_duration: Duration,
+    #[serde(
+        rename = "delay_ns",
+        serialize_with = "serialize_nanos",
+        deserialize_with = "deserialize_nanos"
+    )]
+    pub delay: Duration,
+    /// Missing in files of older versions.
+    #[serde(default)]
+    pub cpu_before: Option<u32>,
+    #[serde(default)]
+    pub cpu_after: Option<u32>,
+    #[serde(
+        default,
+        rename = "run_delay_ns",
+        serialize_with = "serialize_optional_nanos",
+        deserialize_with = "deserialize_optional_nanos"
+    )]
+    pub run_delay: Option<Duration>,
+    /// See [`WakeupContext::return_path`](crate::WakeupContext::return_path).
+    #[serde(
+        default,
+        rename = "return_path_ns",
+        serialize_with = "serialize_optional_nanos",
+        deserialize_with = "deserialize_optional_nanos"
+    )]
+    pub return_path: Option<Duration>,
+}
+
+impl RawMeasurement {
+    /// See [`WakeupContext::signed_delay`](crate::WakeupContext::signed_delay).
+    #[must_use]
+    pub fn signed_delay(&self) -> SignedDelay {
+        SignedDelay::between(self.expected_duration, self.actual_duration)
+    }
+
+    /// See [`WakeupContext::migrated`](crate::WakeupContext::migrated).
+    #[must_use]
+    pub fn migrated(&self) -> Option<bool> {
+        Some(self.cpu_before? != self.cpu_after?)
+    }
+
+    /// See [`WakeupContext::delay_breakdown`](crate::WakeupContext::delay_breakdown).
+    #[must_use]
+    pub fn delay_breakdown(&self) -> Option<(Duration, Duration)> {
+        self.run_delay
+            .map(|run_delay| split_delay(self.delay, run_delay))
+    }
+
+    /// See [`WakeupContext::attribution`](crate::WakeupContext::attribution).
+    #[must_use]
+    pub fn attribution(&self) -> Option<Attribution> {
+        self.return_path
+            .map(|return_path| Attribution::split(self.delay, self.run_delay, return_path))
+    }
+}
+
+impl From<&Measurement> for RawMeasurement {
+    fn from(measurement: &Measurement) -> Self {
+        let context = &measurement.wakeup_context;
+        Self {
+            interrupted: matches!(context.reason, WakeupReason::Interrupted { .. }),
+            expected_duration: context.expected_duration,
+            actual_duration: context.actual_duration,
+            delay: context.delay,
+            cpu_before: context.cpu_before,
+            cpu_after: context.cpu_after,
+            run_delay: context.run_delay,
+            return_path: context.return_path(),
+        }
+    }
+}
+
+impl RawRun {
+    /// Captures the measurements of a run.
+    #[must_use]
+    pub fn new(backend: &str, config: &RunConfig, measurements: &Measurements) -> Self {
+        Self::with_header(RunHeader::new(backend, config), measurements)
+    }
+
+    /// Captures the measurements of a run with the parameters of `header`.
+    #[must_use]
+    pub fn with_header(header: RunHeader, measurements: &Measurements) -> Self {
+        Self {
+            backend: header.backend,
+            scenario: header.scenario,
+            timeout_mode: header.timeout_mode,
+            timeslice: header.timeslice,
+            seed: header.seed,
+            repeat: header.repeat,
+            interrupt_percent: header.interrupt_percent,
+            detached_wakes: header.detached_wakes,
+            cpu_dma_latency: header.cpu_dma_latency,
+            load: header.load,
+            placement: header.placement,
+            cpus: header.cpus,
+            priorities: header.priorities,
+            skipped: measurements.skipped,
+            measurements: measurements
+                .interrupted
+                .iter()
+                .chain(&measurements.timeouted)
+                .map(RawMeasurement::from)
+                .collect(),
+        }
+    }
+
+    /// Returns the delays of either the interrupted or the timeouted
+    /// measurements.
+    #[must_use]
+    pub fn delays(&self, interrupted: bool) -> Vec<Duration> {
+        self.measurements
+            .iter()
+            .filter(|m| m.interrupted == interrupted)
+            .map(|m| m.delay)
+            .collect()
+    }
+}
+
+pub(crate) const fn default_interrupt_percent() -> u8 {
+    DEFAULT_INTERRUPT_PERCENT
+}
+
+fn default_timeout_mode() -> String {
+    TimeoutMode::default().name().to_string()
+}
+
+/// Writes `value` as pretty-printed JSON to `path`.
+pub fn write_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut writer, value)?;
+    writeln!(writer)?;
+    writer.flush()
+}
+
+/// Reads a value written by [`write_json`] from `path`.
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> io::Result<T> {
+    let reader = BufReader::new(File::open(path)?);
+    Ok(serde_json::from_reader(reader)?)
+}
+
+/// Header of the CSV format of the raw measurements, one measurement per row.
+/// The CPU, run delay, and return path columns are empty if unknown. `run` is
+/// the index of the run in the file, which groups the rows, and `repeat` the
+/// index of the run among its repeats, see [`RawRun::repeat`]. The columns
+/// after it are the conditions of the run, empty if not set.
+const CSV_HEADER: &str = "backend,scenario,timeslice_ns,seed,skipped,interrupted,expected_duration_ns,actual_duration_ns,delay_ns,cpu_before,cpu_after,run_delay_ns,timeout_mode,return_path_ns,run,repeat,interrupt_percent,detached_wakes,cpu_dma_latency_ns,load,placement,waker_cpu,sleeper_cpu,waker_nice,sleeper_nice";
+
+/// Number of columns of [`CSV_HEADER`].
+const CSV_COLUMNS: usize = 25;
+
+/// Writes the raw measurements to `path`: as CSV if the file extension is
+/// `csv`, as [recording] if it is `bin`, otherwise as JSON.
+///
+/// # Errors
+///
+/// Besides I/O errors, fails if a name contains a line break, which CSV
+/// files can't represent line by line.
+pub fn write_raw(path: &Path, runs: &[RawRun]) -> io::Result<()> {
+    if is_recording(path) {
+        return recording::write(path, runs);
+    }
+    if !is_csv(path) {
+        return write_json(path, runs);
+    }
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "{CSV_HEADER}")?;
+    for (i, run) in runs.iter().enumerate() {
+        let backend = csv_field(&run.backend)?;
+        let scenario = csv_field(&run.scenario)?;
+        let timeout_mode = csv_field(&run.timeout_mode)?;
+        let conditions = format!(
+            "{},{},{},{},{},{},{},{},{}",
+            run.interrupt_percent,
+            run.detached_wakes,
+            format_optional(run.cpu_dma_latency.map(|d| d.as_nanos())),
+            csv_field(run.load.as_deref().unwrap_or_default())?,
+            csv_field(run.placement.as_deref().unwrap_or_default())?,
+            format_optional(run.cpus.map(|cpus| cpus.waker)),
+            format_optional(run.cpus.map(|cpus| cpus.sleeper)),
+            format_optional(run.priorities.waker),
+            format_optional(run.priorities.sleeper),
+        );
+        for m in &run.measurements {
+            writeln!(
+                writer,
+                "{backend},{scenario},{},{},{},{},{},{},{},{},{},{},{timeout_mode},{},{i},{},{conditions}",
+                run.timeslice.as_nanos(),
+                run.seed,
+                run.skipped,
+                m.interrupted,
+                m.expected_duration.as_nanos(),
+                m.actual_duration.as_nanos(),
+                m.delay.as_nanos(),
+                format_optional(m.cpu_before),
+                format_optional(m.cpu_after),
+                format_optional(m.run_delay.map(|d| d.as_nanos())),
+                format_optional(m.return_path.map(|d| d.as_nanos())),
+                run.repeat
+            )?;
+        }
+    }
+    writer.flush()
+}
+
+/// Reads raw measurements written by [`write_raw`].
+pub fn read_raw(path: &Path) -> io::Result<Vec<RawRun>> {
+    if is_recording(path) {
+        return recording::read(path);
+    }
+    if !is_csv(path) {
+        return read_json(path);
+    }
+
+    let reader = BufReader::new(File::open(path)?);
+    let mut runs = Vec::<RawRun>::new();
+    let mut last_run_id = None;
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if i == 0 || line.trim().is_empty() {
+            continue;
+        }
+        let invalid = |what: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: invalid {what}", i + 1),
+            )
+        };
+        let fields = split_csv_line(&line).ok_or_else(|| invalid("quoting"))?;
+        let mut fields = fields.iter().map(String::as_str).collect::<Vec<_>>();
+        // Files of older versions lack the trailing optional columns.
+        if (9..CSV_COLUMNS).contains(&fields.len()) {
+            fields.resize(CSV_COLUMNS, "");
+        }
+        let [
+            backend,
+            scenario,
+            timeslice,
+            seed,
+            skipped,
+            interrupted,
+            expected,
+            actual,
+            delay,
+            cpu_before,
+            cpu_after,
+            run_delay,
+            timeout_mode,
+            return_path,
+            run_id,
+            repeat,
+            interrupt_percent,
+            detached_wakes,
+            cpu_dma_latency,
+            load,
+            placement,
+            waker_cpu,
+            sleeper_cpu,
+            waker_nice,
+            sleeper_nice,
+        ] = fields[..]
+        else {
+            return Err(invalid("number of columns"));
+        };
+        let nanos = |field: &str, what: &str| {
+            field
+                .parse::<u64>()
+                .map(Duration::from_nanos)
+                .map_err(|_| invalid(what))
+        };
+        let optional = |field: &str, what: &str| {
+            if field.is_empty() {
+                Ok(None)
+            } else {
+                field.parse::<u64>().map(Some).map_err(|_| invalid(what))
+            }
+        };
+        let cpu = |field: &str, what: &str| {
+            optional(field, what)?
+                .map(|cpu| u32::try_from(cpu).map_err(|_| invalid(what)))
+                .transpose()
+        };
+        let timeout_mode = if timeout_mode.is_empty() {
+            default_timeout_mode()
+        } else {
+            timeout_mode.to_string()
+        };
+        let timeslice = nanos(timeslice, "timeslice_ns")?;
+        let seed = seed.parse::<u64>().map_err(|_| invalid("seed"))?;
+        let skipped = skipped.parse::<usize>().map_err(|_| invalid("skipped"))?;
+        let run_id = optional(run_id, "run")?;
+        let repeat = optional(repeat, "repeat")?
+            .map(|repeat| usize::try_from(repeat).map_err(|_| invalid("repeat")))
+            .transpose()?
+            .unwrap_or_default();
+        let interrupt_percent = if interrupt_percent.is_empty() {
+            DEFAULT_INTERRUPT_PERCENT
+        } else {
+            interrupt_percent
+                .parse()
+                .map_err(|_| invalid("interrupt_percent"))?
+        };
+        let detached_wakes = !detached_wakes.is_empty()
+            && detached_wakes
+                .parse()
+                .map_err(|_| invalid("detached_wakes"))?;
+        let cpu_dma_latency =
+            optional(cpu_dma_latency, "cpu_dma_latency_ns")?.map(Duration::from_nanos);
+        let name = |field: &str| (!field.is_empty()).then(|| field.to_string());
+        let cpus = match (
+            cpu(waker_cpu, "waker_cpu")?,
+            cpu(sleeper_cpu, "sleeper_cpu")?,
+        ) {
+            (Some(waker), Some(sleeper)) => Some(CpuPair { waker, sleeper }),
+            (None, None) => None,
+            _ => return Err(invalid("cpus")),
+        };
+        let nice = |field: &str, what: &str| {
+            if field.is_empty() {
+                Ok(None)
+            } else {
+                field.parse::<i32>().map(Some).map_err(|_| invalid(what))
+            }
+        };
+        let priorities = Priorities {
+            waker: nice(waker_nice, "waker_nice")?,
+            sleeper: nice(sleeper_nice, "sleeper_nice")?,
+        };
+        let measurement = RawMeasurement {
+            interrupted: interrupted.parse().map_err(|_| invalid("interrupted"))?,
+            expected_duration: nanos(expected, "expected_duration_ns")?,
+            actual_duration: nanos(actual, "actual_duration_ns")?,
+            delay: nanos(delay, "delay_ns")?,
+            cpu_before: cpu(cpu_before, "cpu_before")?,
+            cpu_after: cpu(cpu_after, "cpu_after")?,
+            run_delay: optional(run_delay, "run_delay_ns")?.map(Duration::from_nanos),
+            return_path: optional(return_path, "return_path_ns")?.map(Duration::from_nanos),
+        };
+
+        // Consecutive rows of the same run belong together. Files of older
+        // versions have no run column: there, consecutive rows with the same
+        // run parameters belong to one run. The repeats of a run share all
+        // other parameters, including the seed.
+        let same_run = runs.last().is_some_and(|run| run.repeat == repeat)
+            && match run_id {
+                Some(_) => run_id == last_run_id,
+                None => runs.last().is_some_and(|run| {
+                    run.backend == backend
+                        && run.scenario == scenario
+                        && run.timeout_mode == timeout_mode
+                        && run.timeslice == timeslice
+                        && run.seed == seed
+                }),
+            };
+        last_run_id = run_id;
+        match runs.last_mut() {
+            Some(run) if same_run => run.measurements.push(measurement),
+            _ => runs.push(RawRun {
+                backend: backend.to_string(),
+                scenario: scenario.to_string(),
+                timeout_mode,
+                timeslice,
+                seed,
+                repeat,
+                interrupt_percent,
+                detached_wakes,
+                cpu_dma_latency,
+                load: name(load),
+                placement: name(placement),
+                cpus,
+                priorities,
+                skipped,
+                measurements: vec![measurement],
+            }),
+        }
+    }
+    Ok(runs)
+}
+
+/// Quotes `value` if it contains a comma or a quote, as in RFC 4180.
+fn csv_field(value: &str) -> io::Result<Cow<'_, str>> {
+    if value.contains(['\n', '\r']) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{value:?} contains a line break"),
+        ));
+    }
+    Ok(if value.contains([',', '"']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    })
+}
+
+/// Splits a line of CSV into its fields, unquoting them, see [`csv_field`].
+/// Returns `None` if a quote is not closed.
+fn split_csv_line(line: &str) -> Option<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return None;
+    }
+    fields.push(field);
+    Some(fields)
+}
+
+fn format_optional(value: Option<impl ToString>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+fn is_csv(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "csv")
+}
+
+/// Returns whether `path` is written as [recording].
+#[must_use]
+pub fn is_recording(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "bin")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Sleeper;
+    use crate::backends::condvar;
+    use crate::runner;
+    use crate::scenario::Scenario;
+    use crate::virtual_time::{self, VirtualClock};
+    use assert2::check;
+    use std::time::Instant;
+
+    #[test]
+    fn test_json() {
+        let clock = VirtualClock::new();
+        let (sleeper, waker) = virtual_time::new_pair(&clock);
+        let config = RunConfig::new(50, Duration::from_secs(1)).with_seed(7);
+        let measurements = runner::run_with_clock(config, sleeper, waker, clock);
+
+        let report = RunReport::new("virtual", &config, &measurements);
+        let json = serde_json::to_value(&report).unwrap();
+
+        check!(json["backend"] == "virtual");
+        check!(json["timeslice_ns"] == 1_000_000_000_u64);
+        check!(json["seed"] == 7);
+        check!(json["rounds"] == 50);
+        check!(json["interrupted"]["mean"]["value_ns"] == 0);
+        check!(json["interrupted"]["p99"]["ci_high_ns"] == 0);
+
+        let deserialized = serde_json::from_value::<RunReport>(json).unwrap();
+        check!(deserialized == report);
+    }
+
+    #[test]
+    fn test_raw_roundtrip() {
+        let clock = VirtualClock::new();
+        let (sleeper, waker) = virtual_time::new_pair(&clock);
+        let config = RunConfig::new(50, Duration::from_secs(1)).with_seed(7);
+        let measurements = runner::run_with_clock(config, sleeper, waker, clock);
+
+        let raw = RawRun::new("virtual", &config, &measurements);
+        let json = serde_json::to_string(&raw).unwrap();
+        let deserialized = serde_json::from_str::<RawRun>(&json).unwrap();
+        check!(deserialized == raw);
+        // The threads of a run are not part of its raw measurements.
+        let report = RunReport {
+            threads: None,
+            ..RunReport::new("virtual", &config, &measurements)
+        };
+        check!(RunReport::from_raw(&raw) == report);
+    }
+
+    #[test]
+    fn test_negative_delays() {
+        /// Times out right away, as if the clock jumped.
+        #[derive(Debug)]
+        struct Early;
+        impl Sleeper<Instant> for Early {
+            fn sleep_interruptible(&self, _sleep_duration: Duration) -> WakeupReason {
+                WakeupReason::Timeout
+            }
+        }
+        let (_, waker) = condvar::new_pair();
+        let config = RunConfig::new(5, Duration::from_millis(1)).with_scenario(Scenario::Timeout);
+        let measurements = runner::run(config, Early, waker);
+
+        let report = RunReport::new("early", &config, &measurements);
+        check!(report.rounds == 5);
+        check!(report.negative_delays == 5);
+        check!(report.timeouted.unwrap().mean.value == Duration::ZERO);
+        let raw = RawRun::new("early", &config, &measurements);
+        check!(RunReport::from_raw(&raw).negative_delays == 5);
+    }
+
+    #[test]
+    fn test_from_analysis() {
+        let clock = VirtualClock::new();
+        let (sleeper, waker) = virtual_time::new_pair(&clock);
+        let config = RunConfig::new(50, Duration::from_secs(1)).with_seed(7);
+        let header = RunHeader::new("virtual", &config);
+        let mut analysis = RunAnalysis::new(header);
+        runner::run_with_sink(config, sleeper, waker, clock, &mut analysis);
+
+        let report = RunReport::from_analysis(&analysis);
+        check!(report.rounds == 50);
+        check!(report.threads.is_some());
+        let interrupted = report.interrupted.unwrap();
+        check!(interrupted.count + report.timeouted.map_or(0, |s| s.count) == 50);
+        check!(interrupted.mean.value == Duration::ZERO);
+        check!(interrupted.p99.ci_high == Duration::ZERO);
+    }
+
+    #[test]
+    fn test_raw_csv_roundtrip() {
+        let clock = VirtualClock::new();
+        let (sleeper, waker) = virtual_time::new_pair(&clock);
+        let config = RunConfig::new(20, Duration::from_secs(1)).with_seed(7);
+        let measurements = runner::run_with_clock(config, sleeper, waker, clock);
+        // The same parameters twice, a repeat, and names that must be quoted.
+        let runs = [
+            RawRun::new("a", &config, &measurements),
+            RawRun::new("a", &config, &measurements),
+            RawRun {
+                repeat: 1,
+                ..RawRun::new("a", &config, &measurements)
+            },
+            RawRun::new("plugin \"b\", v2", &config, &measurements),
+        ];
+
+        let path = std::env::temp_dir().join(format!("raw-{}.csv", std::process::id()));
+        write_raw(&path, &runs).unwrap();
+        let read = read_raw(&path);
+        std::fs::remove_file(&path).unwrap();
+        check!(read.unwrap() == runs);
+    }
+
+    #[test]
+    fn test_raw_conditions_roundtrip() {
+        let clock = VirtualClock::new();
+        let (sleeper, waker) = virtual_time::new_pair(&clock);
+        let config = RunConfig::new(20, Duration::from_secs(1))
+            .with_seed(7)
+            .with_interrupt_percent(20);
+        let measurements = runner::run_with_clock(config, sleeper, waker, clock);
+        // Conditions the virtual clock can't apply are only reported.
+        let config = config
+            .with_detached_wakes(true)
+            .with_cpus(Some(CpuPair {
+                waker: 0,
+                sleeper: 1,
+            }))
+            .with_priorities(Priorities {
+                waker: Some(-5),
+                sleeper: None,
+            });
+        let header = RunHeader {
+            cpu_dma_latency: Some(Duration::ZERO),
+            load: Some("cpu".to_string()),
+            placement: Some("same-core".to_string()),
+            ..RunHeader::new("a", &config)
+        };
+        let runs = [RawRun::with_header(header, &measurements)];
+        let report = RunReport {
+            cpu_dma_latency: Some(Duration::ZERO),
+            load: Some("cpu".to_string()),
+            placement: Some("same-core".to_string()),
+            threads: None,
+            ..RunReport::new("a", &config, &measurements)
+        };
+        check!(report.interrupt_percent == 20);
+
+        for extension in ["json", "csv", "bin"] {
+            let path = std::env::temp_dir()
+                .join(format!("raw-conditions-{}.{extension}", std::process::id()));
+            write_raw(&path, &runs).unwrap();
+            let read = read_raw(&path);
+            std::fs::remove_file(&path).unwrap();
+            let read = read.unwrap();
+            check!(read == runs, "{extension}");
+            check!(RunReport::from_raw(&read[0]) == report, "{extension}");
+        }
+    }
+
+    #[test]
+    fn test_raw_csv_repeats_without_run_column() {
+        let row = |repeat| format!("a,mixed,1000000,7,0,true,0,1000,1000,,,,relative,,,{repeat}\n");
+        let path = std::env::temp_dir().join(format!("raw-repeats-{}.csv", std::process::id()));
+        std::fs::write(
+            &path,
+            format!("{CSV_HEADER}\n{}{}{}", row(0), row(0), row(1)),
+        )
+        .unwrap();
+        let read = read_raw(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let runs = read.unwrap();
+        check!(runs.len() == 2);
+        check!(runs[0].measurements.len() == 2);
+        check!(runs[1].repeat == 1);
+    }
+
+    #[test]
+    fn test_csv_fields() {
+        check!(csv_field("condvar").unwrap() == "condvar");
+        check!(csv_field("a,b").unwrap() == "\"a,b\"");
+        check!(csv_field("a\"b").unwrap() == "\"a\"\"b\"");
+        check!(csv_field("a\nb").is_err());
+
+        check!(split_csv_line("a,,c").unwrap() == ["a", "", "c"]);
+        check!(split_csv_line("\"a,b\",\"c\"\"d\"").unwrap() == ["a,b", "c\"d"]);
+        check!(split_csv_line("\"a,b").is_none());
+    }
+}
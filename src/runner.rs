@@ -0,0 +1,737 @@
+//! The measurement loop of the control thread. See [`run`].
+
+use crate::clock::{Clock, SystemClock};
+use crate::cpu::{self, Pinned};
+use crate::priority::{self, Priorities, Reniced};
+use crate::scenario::{DEFAULT_INTERRUPT_PERCENT, Scenario};
+use crate::sleeper_thread::SleeperThread;
+use crate::synchronization::NoDelayBarrier;
+use crate::threads::{self, ThreadIds};
+use crate::topology::CpuPair;
+use crate::trace;
+use crate::tracefs::TraceMarker;
+use crate::units::format_duration;
+use crate::{
+    Handshake, Measurement, Measurements, Sleeper, TimeoutMode, Waker, WakeupContext, WakeupReason,
+};
+use assert2::check;
+use std::io;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::sync::mpsc::{RecvTimeoutError, sync_channel};
+use std::time::{Duration, Instant};
+
+/// A scheduled wake-up is only issued if the control thread is not later than
+/// this percentage of the timeslice. Otherwise, the wake-up might race with the
+/// timeout of the sleeper.
+pub(crate) const LATEST_WAKE_PERCENT: u32 = 98;
+
+/// The watchdog gives up on a round after this many timeslices without a
+/// measurement, but not before [`WATCHDOG_MIN`].
+const WATCHDOG_TIMESLICES: u32 = 10;
+
+/// Lower bound of the watchdog timeout, so that scheduling hiccups of a
+/// loaded system don't fail a backend.
+const WATCHDOG_MIN: Duration = Duration::from_secs(1);
+
+/// How long the control thread waits for the measurement of a round.
+pub(crate) fn watchdog_timeout(timeslice: Duration) -> Duration {
+    (timeslice * WATCHDOG_TIMESLICES).max(WATCHDOG_MIN)
+}
+
+/// Parameters of a measurement run. See [`run`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunConfig {
+    /// Number of valid sleep cycles to measure.
+    pub rounds: usize,
+    /// The normal time slice for that we put threads into sleep. Reducing the
+    /// selected time frame increases the impact of OS scheduling and
+    /// additional runtime overhead.
+    pub timeslice: Duration,
+    /// Seed for the random schedule of the interruptions. Runs with the same
+    /// seed and timeslice interrupt the sleeper at the same offsets, which
+    /// makes the measurements of different backends comparable.
+    pub seed: u64,
+    /// Which rounds are interrupted and when.
+    pub scenario: Scenario,
+    /// Percentage of the rounds that are interrupted in the
+    /// [`Scenario::Mixed`] and [`Scenario::Spurious`] scenarios.
+    pub interrupt_percent: u8,
+    /// Whether the sleeper gets a relative or an absolute timeout.
+    pub timeout_mode: TimeoutMode,
+    /// Whether the wake-ups are issued with [`Waker::wake_detached`] instead
+    /// of [`Waker::wake`], i.e., without the ACK handshake. The backend must
+    /// support it, see [`Backend::supports_detached_wakes`].
+    ///
+    /// [`Backend::supports_detached_wakes`]: crate::backends::Backend::supports_detached_wakes
+    pub detached_wakes: bool,
+    /// The CPUs the control thread and the sleeper thread are pinned to
+    /// during the run, if any. Linux only.
+    pub cpus: Option<CpuPair>,
+    /// The nice levels of the control thread, i.e., the waker, and the
+    /// sleeper thread during the run. Linux only.
+    pub priorities: Priorities,
+    /// The name of the backend, for the names of the threads, see
+    /// [`threads`].
+    pub backend: Option<&'static str>,
+    /// Whether the begin, the wake-up, and the end of each round are written
+    /// to the kernel trace, see [`TraceMarker`]. Linux only.
+    pub trace_markers: bool,
+    /// Whether the sleeper thread records its CPU before and after each sleep
+    /// and its run delay, see [`WakeupContext::cpu_before`] and
+    /// [`WakeupContext::run_delay`]. Off by default, as it costs a few
+    /// syscalls per round. Linux only.
+    pub scheduling: bool,
+}
+
+impl RunConfig {
+    /// Creates a new config with a random seed.
+    #[must_use]
+    pub fn new(rounds: usize, timeslice: Duration) -> Self {
+        Self {
+            rounds,
+            timeslice,
+            seed: fastrand::u64(..),
+            scenario: Scenario::default(),
+            interrupt_percent: DEFAULT_INTERRUPT_PERCENT,
+            timeout_mode: TimeoutMode::default(),
+            detached_wakes: false,
+            cpus: None,
+            priorities: Priorities::default(),
+            backend: None,
+            trace_markers: false,
+            scheduling: false,
+        }
+    }
+
+    /// Replaces the seed.
+    #[must_use]
+    pub const fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Replaces the scenario.
+    #[must_use]
+    pub const fn with_scenario(mut self, scenario: Scenario) -> Self {
+        self.scenario = scenario;
+        self
+    }
+
+    /// Replaces the percentage of interrupted rounds.
+    #[must_use]
+    pub const fn with_interrupt_percent(mut self, interrupt_percent: u8) -> Self {
+        self.interrupt_percent = interrupt_percent;
+        self
+    }
+
+    /// Replaces the timeout mode.
+    #[must_use]
+    pub const fn with_timeout_mode(mut self, timeout_mode: TimeoutMode) -> Self {
+        self.timeout_mode = timeout_mode;
+        self
+    }
+
+    /// Replaces whether the wake-ups are detached.
+    #[must_use]
+    pub const fn with_detached_wakes(mut self, detached_wakes: bool) -> Self {
+        self.detached_wakes = detached_wakes;
+        self
+    }
+
+    /// Replaces the CPUs the threads are pinned to.
+    #[must_use]
+    pub const fn with_cpus(mut self, cpus: Option<CpuPair>) -> Self {
+        self.cpus = cpus;
+        self
+    }
+
+    /// Replaces the nice levels of the threads.
+    #[must_use]
+    pub const fn with_priorities(mut self, priorities: Priorities) -> Self {
+        self.priorities = priorities;
+        self
+    }
+
+    /// Replaces the name of the backend.
+    #[must_use]
+    pub const fn with_backend(mut self, backend: &'static str) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// Replaces whether the rounds are marked in the kernel trace.
+    #[must_use]
+    pub const fn with_trace_markers(mut self, trace_markers: bool) -> Self {
+        self.trace_markers = trace_markers;
+        self
+    }
+
+    /// Replaces whether the CPUs and the run delay are recorded.
+    #[must_use]
+    pub const fn with_scheduling(mut self, scheduling: bool) -> Self {
+        self.scheduling = scheduling;
+        self
+    }
+}
+
+/// Runs many cycles of [`Sleeper::sleep_interruptible`] in a thread: some
+/// timeout normally and some get interrupted.
+///
+/// Collects the effective delay between the [`Waker::wake`] call and the actual
+/// awakening. While doing so, this thread is synchronized with a corresponding
+/// [`SleeperThread`].
+pub fn run(
+    config: RunConfig,
+    sleeper: impl Sleeper<Instant> + Send + 'static,
+    waker: impl Waker,
+) -> Measurements {
+    run_with_clock(config, sleeper, waker, SystemClock)
+}
+
+/// Like [`run`] but takes all time measurements and control-side sleeps with
+/// the given [`Clock`].
+pub fn run_with_clock<C: Clock + Clone + Send + 'static>(
+    config: RunConfig,
+    sleeper: impl Sleeper<Instant> + Send + 'static,
+    waker: impl Waker,
+    clock: C,
+) -> Measurements {
+    let mut measurements = Measurements {
+        interrupted: Vec::with_capacity(config.rounds),
+        timeouted: Vec::with_capacity(config.rounds),
+        ..Measurements::default()
+    };
+    run_with_sink(config, sleeper, waker, clock, &mut measurements);
+    measurements
+}
+
+/// Receives the measurements of a run as they happen. See [`run_with_sink`].
+pub trait MeasurementSink {
+    /// Called for each valid measurement. Returning [`ControlFlow::Break`]
+    /// stops the run.
+    fn record(&mut self, measurement: Measurement) -> ControlFlow<()>;
+
+    /// Called for each round without a valid measurement.
+    fn skip(&mut self) {}
+
+    /// Called at the begin of the run with the ids of the threads.
+    fn threads(&mut self, _threads: ThreadIds) {}
+
+    /// Called when the run is aborted, e.g., because the watchdog gave up on
+    /// a hanging sleeper or the threads can't be pinned. The run stops
+    /// afterward.
+    fn fail(&mut self, _reason: String) {}
+}
+
+impl MeasurementSink for Measurements {
+    fn record(&mut self, measurement: Measurement) -> ControlFlow<()> {
+        if matches!(
+            measurement.wakeup_context.reason,
+            WakeupReason::Interrupted { .. }
+        ) {
+            self.interrupted.push(measurement);
+        } else {
+            self.timeouted.push(measurement);
+        }
+        self.rounds += 1;
+        ControlFlow::Continue(())
+    }
+
+    fn skip(&mut self) {
+        self.skipped += 1;
+    }
+
+    fn fail(&mut self, reason: String) {
+        self.failure = Some(reason);
+    }
+
+    fn threads(&mut self, threads: ThreadIds) {
+        self.threads = Some(threads);
+    }
+}
+
+impl<S: MeasurementSink> MeasurementSink for &mut S {
+    fn record(&mut self, measurement: Measurement) -> ControlFlow<()> {
+        (**self).record(measurement)
+    }
+
+    fn skip(&mut self) {
+        (**self).skip();
+    }
+
+    fn threads(&mut self, threads: ThreadIds) {
+        (**self).threads(threads);
+    }
+
+    fn fail(&mut self, reason: String) {
+        (**self).fail(reason);
+    }
+}
+
+/// Like [`run_with_clock`] but passes each measurement to `sink` instead of
+/// collecting them. This keeps the memory bounded, e.g., for runs with
+/// [`usize::MAX`] rounds.
+pub fn run_with_sink<C: Clock + Clone + Send + 'static>(
+    config: RunConfig,
+    sleeper: impl Sleeper<Instant> + Send + 'static,
+    waker: impl Waker,
+    clock: C,
+    sink: &mut impl MeasurementSink,
+) {
+    let RunConfig {
+        rounds, timeslice, ..
+    } = config;
+    let mut rng = fastrand::Rng::with_seed(config.seed);
+    // We only transport one item at a time. Threads are synchronized.
+    let (sender, receiver) = sync_channel::<Option<WakeupContext>>(1);
+    let sleep_barrier = Arc::new(NoDelayBarrier::new());
+    // Threads on the same CPU can't meet while spinning.
+    let handshake = Handshake {
+        yielding: config.cpus.is_some_and(|cpus| cpus.waker == cpus.sleeper),
+        timeout: None,
+    };
+    sleep_barrier.set_handshake(handshake);
+    let watchdog = watchdog_timeout(timeslice);
+    // A sleeper that never ACKs must not hang `wake()`: the watchdog then
+    // reports the missing measurement.
+    waker.set_handshake(Handshake {
+        timeout: Some(watchdog),
+        ..handshake
+    });
+    let thread = SleeperThread::spawn_with_clock(
+        sleep_barrier.clone(),
+        sleeper,
+        timeslice,
+        config.timeout_mode,
+        config.scheduling,
+        sender,
+        clock.clone(),
+    );
+    sink.threads(ThreadIds::with_sleeper(thread.tid()));
+    // Restores the name of the control thread at the end of the run.
+    let _renamed = config.backend.map(|backend| {
+        thread.set_name(&threads::name("sleeper", backend));
+        threads::rename_current(&threads::name("waker", backend))
+    });
+    let marker = match config.trace_markers.then(TraceMarker::open).transpose() {
+        Ok(marker) => marker,
+        Err(e) => {
+            sink.fail(format!("can't open the trace marker: {e}"));
+            return;
+        }
+    };
+    // Restores the affinity of the control thread at the end of the run.
+    let _pinned = match config.cpus.map(|cpus| pin(&thread, cpus)).transpose() {
+        Ok(pinned) => pinned,
+        Err(e) => {
+            sink.fail(format!("can't pin the threads: {e}"));
+            return;
+        }
+    };
+    // Restores the nice level of the control thread at the end of the run.
+    let _reniced = match renice(&thread, config.priorities) {
+        Ok(reniced) => reniced,
+        Err(e) => {
+            sink.fail(format!("can't set the nice levels: {e}"));
+            return;
+        }
+    };
+
+    let mut measured = 0;
+    let mut skipped = 0;
+    loop {
+        if measured >= rounds {
+            break;
+        }
+        // Give up if the schedule can't be met at all, e.g., for tiny
+        // timeslices on an overloaded system.
+        if skipped >= rounds {
+            break;
+        }
+
+        let wake_offset =
+            config
+                .scenario
+                .next_wake_offset(&mut rng, timeslice, config.interrupt_percent);
+        let do_interrupt = wake_offset.is_some();
+        let sleep_duration = wake_offset.unwrap_or(timeslice);
+        let spurious_wake_offset = config.scenario.spurious_wake_offset(wake_offset, timeslice);
+
+        trace::span!(
+            "round",
+            round = measured + skipped,
+            ?sleep_duration,
+            do_interrupt
+        );
+        let round = measured + skipped;
+        if let Some(marker) = &marker {
+            marker.mark(format_args!(
+                "round {round} begin sleep_ns={} interrupt={do_interrupt}",
+                sleep_duration.as_nanos()
+            ));
+        }
+        // Wait for the other thread to start a new cycle.
+        sleep_barrier.wait();
+        // The sleeper thread starts its sleep right after the barrier, so all
+        // deadlines of this cycle are relative to this point in time. Absolute
+        // deadlines prevent that the overhead of the control thread adds up.
+        let cycle_begin = clock.now();
+
+        if let Some(offset) = spurious_wake_offset {
+            clock.sleep_until(cycle_begin + offset);
+            trace::event!("spurious wake issued");
+            waker.wake_spurious();
+        }
+        clock.sleep_until(cycle_begin + sleep_duration);
+        let mut wake_issued = false;
+        if do_interrupt {
+            // If we are too late, the wake-up might arrive after the sleeper
+            // timed out and we would wait forever for the ACK of the sleeper.
+            let latest_wake = cycle_begin + timeslice * LATEST_WAKE_PERCENT / 100;
+            if clock.now() <= latest_wake {
+                trace::event!("wake issued");
+                if config.detached_wakes {
+                    // The sleeper doesn't ACK, the measurement is the only
+                    // synchronization.
+                    check!(waker.wake_detached(), "detached wake-ups must be supported");
+                } else {
+                    waker.wake();
+                }
+                wake_issued = true;
+                // After the wake-up, so the marker doesn't delay it.
+                if let Some(marker) = &marker {
+                    marker.mark(format_args!("round {round} wake issued"));
+                }
+            }
+        }
+
+        let wakeup_context = match receiver.recv_timeout(watchdog) {
+            Ok(wakeup_context) => wakeup_context,
+            Err(e) => {
+                trace::event!("watchdog expired");
+                let reason = match e {
+                    RecvTimeoutError::Timeout => {
+                        format!("no measurement within {}", format_duration(watchdog))
+                    }
+                    RecvTimeoutError::Disconnected => "the sleeper thread died".to_string(),
+                };
+                sink.fail(format!("{reason} in round {}", measured + skipped));
+                thread.abandon();
+                return;
+            }
+        };
+
+        // Either the control thread missed its schedule or the sleeper thread
+        // started to sleep after the wake-up: the measurement is meaningless.
+        let Some(wakeup_context) = wakeup_context.filter(|_| wake_issued || !do_interrupt) else {
+            trace::event!(wake_issued, "round skipped");
+            if let Some(marker) = &marker {
+                marker.mark(format_args!("round {round} skipped"));
+            }
+            skipped += 1;
+            sink.skip();
+            continue;
+        };
+
+        if do_interrupt {
+            // The control thread missed its schedule by so much that the
+            // sleep timed out first. The sleeper never ACKed the wake-up and
+            // may still see it in its next sleep, so the rest of the run
+            // would be meaningless.
+            if wakeup_context.reason == WakeupReason::Timeout {
+                trace::event!("wake-up after the timeout");
+                sink.fail(format!(
+                    "the wake-up came after the timeout in round {}",
+                    measured + skipped
+                ));
+                return;
+            }
+        } else {
+            check!(wakeup_context.reason == WakeupReason::Timeout);
+        }
+        trace::event!(delay = ?wakeup_context.delay, "round measured");
+        if let Some(marker) = &marker {
+            marker.mark(format_args!(
+                "round {round} end delay_ns={}",
+                wakeup_context.delay.as_nanos()
+            ));
+        }
+        measured += 1;
+        if sink.record(Measurement { wakeup_context }).is_break() {
+            break;
+        }
+    }
+}
+
+/// Pins the sleeper thread and the calling control thread.
+fn pin(thread: &SleeperThread, cpus: CpuPair) -> io::Result<Pinned> {
+    thread.pin(cpus.sleeper)?;
+    cpu::pin_current(cpus.waker)
+}
+
+/// Sets the nice levels of the sleeper thread and the calling control thread.
+fn renice(thread: &SleeperThread, priorities: Priorities) -> io::Result<Option<Reniced>> {
+    if let Some(nice) = priorities.sleeper {
+        thread.set_nice(nice)?;
+    }
+    priorities.waker.map(priority::renice_current).transpose()
+}
+
+/// Interrupts the sleeper `rounds` times, always `wake_offset` after the
+/// sleeper started to sleep, and returns the sum of all delays.
+///
+/// Rounds without a valid measurement are repeated. This is an adapter for
+/// benchmark harnesses that measure the time themselves, such as criterion's
+/// `iter_custom`.
+pub fn total_wake_delay(
+    rounds: u64,
+    sleeper: impl Sleeper<Instant> + Send + 'static,
+    waker: impl Waker,
+    wake_offset: Duration,
+) -> Duration {
+    let clock = SystemClock;
+    // Long enough to never time out before the wake-up.
+    let timeslice = wake_offset * 2 + Duration::from_millis(10);
+    let (sender, receiver) = sync_channel::<Option<WakeupContext>>(1);
+    let sleep_barrier = Arc::new(NoDelayBarrier::new());
+    let _thread = SleeperThread::spawn(sleep_barrier.clone(), sleeper, timeslice, sender);
+
+    let mut total = Duration::ZERO;
+    let mut measured = 0;
+    while measured < rounds {
+        sleep_barrier.wait();
+        let cycle_begin = clock.now();
+        clock.sleep_until(cycle_begin + wake_offset);
+        waker.wake();
+
+        if let Some(wakeup_context) = receiver.recv().unwrap() {
+            check!(matches!(
+                wakeup_context.reason,
+                WakeupReason::Interrupted { .. }
+            ));
+            total += wakeup_context.delay;
+            measured += 1;
+        }
+    }
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::virtual_time::{self, VirtualClock};
+
+    #[test]
+    fn test_run_with_virtual_clock() {
+        const TIMESLICE: Duration = Duration::from_secs(60);
+        let clock = VirtualClock::new();
+        let (sleeper, waker) = virtual_time::new_pair(&clock);
+
+        let config = RunConfig::new(200, TIMESLICE);
+        let measurements = run_with_clock(config, sleeper, waker, clock);
+
+        check!(measurements.rounds == 200);
+        check!(measurements.skipped == 0);
+        check!(measurements.interrupted.len() + measurements.timeouted.len() == 200);
+        for measurement in &measurements.interrupted {
+            let context = &measurement.wakeup_context;
+            check!(context.expected_duration <= TIMESLICE * 95 / 100);
+            check!(context.actual_duration == context.expected_duration);
+            check!(context.delay == Duration::ZERO);
+        }
+        for measurement in &measurements.timeouted {
+            let context = &measurement.wakeup_context;
+            check!(context.actual_duration == TIMESLICE);
+            check!(context.delay == Duration::ZERO);
+        }
+    }
+
+    #[test]
+    fn test_run_absolute_timeouts() {
+        const TIMESLICE: Duration = Duration::from_secs(60);
+        let clock = VirtualClock::new();
+        let (sleeper, waker) = virtual_time::new_pair(&clock);
+
+        let config = RunConfig::new(50, TIMESLICE).with_timeout_mode(TimeoutMode::Absolute);
+        let measurements = run_with_clock(config, sleeper, waker, clock);
+
+        check!(measurements.rounds == 50);
+        check!(!measurements.timeouted.is_empty());
+        for measurement in measurements
+            .interrupted
+            .iter()
+            .chain(&measurements.timeouted)
+        {
+            check!(measurement.wakeup_context.delay == Duration::ZERO);
+        }
+        for measurement in &measurements.timeouted {
+            check!(measurement.wakeup_context.actual_duration == TIMESLICE);
+        }
+    }
+
+    #[test]
+    fn test_run_spurious_wakeups() {
+        const TIMESLICE: Duration = Duration::from_secs(60);
+        let run = |scenario| {
+            let clock = VirtualClock::new();
+            let (sleeper, waker) = virtual_time::new_pair(&clock);
+            let config = RunConfig::new(50, TIMESLICE)
+                .with_seed(42)
+                .with_scenario(scenario);
+            run_with_clock(config, sleeper, waker, clock)
+        };
+
+        let mixed = run(Scenario::Mixed);
+        let spurious = run(Scenario::Spurious);
+        // The sleeper went back to sleep after each spurious wake-up.
+        check!(spurious.rounds == 50);
+        check!(spurious.skipped == 0);
+        check!(spurious.interrupted.len() == mixed.interrupted.len());
+        for (a, b) in mixed.interrupted.iter().zip(&spurious.interrupted) {
+            check!(a.wakeup_context.expected_duration == b.wakeup_context.expected_duration);
+            check!(b.wakeup_context.delay == Duration::ZERO);
+        }
+    }
+
+    #[test]
+    fn test_same_seed_same_schedule() {
+        fn schedule(seed: u64) -> Vec<Duration> {
+            let clock = VirtualClock::new();
+            let (sleeper, waker) = virtual_time::new_pair(&clock);
+            let config = RunConfig::new(50, Duration::from_secs(60)).with_seed(seed);
+            run_with_clock(config, sleeper, waker, clock)
+                .interrupted
+                .iter()
+                .map(|m| m.wakeup_context.expected_duration)
+                .collect()
+        }
+
+        check!(schedule(42) == schedule(42));
+        check!(schedule(42) != schedule(43));
+    }
+
+    #[test]
+    fn test_run_detached_wakes() {
+        let (sleeper, waker) = crate::backends::condvar::new_pair();
+        let config = RunConfig::new(10, Duration::from_millis(5))
+            .with_scenario(Scenario::Always)
+            .with_detached_wakes(true);
+        // Test succeeds if this does not get stuck without the ACKs.
+        let measurements = run(config, sleeper, waker);
+        check!(measurements.timeouted.is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_run_pinned() {
+        let (sleeper, waker) = crate::backends::condvar::new_pair();
+        let cpu = *cpu::allowed().unwrap().last().unwrap();
+        let before = cpu::allowed();
+        let config = RunConfig::new(5, Duration::from_millis(5))
+            .with_cpus(Some(CpuPair {
+                waker: cpu,
+                sleeper: cpu,
+            }))
+            .with_scheduling(true);
+        let measurements = run(config, sleeper, waker);
+        check!(measurements.failure.is_none());
+        for measurement in measurements
+            .interrupted
+            .iter()
+            .chain(&measurements.timeouted)
+        {
+            check!(measurement.wakeup_context.cpu_after == Some(cpu));
+        }
+        // The control thread is unpinned afterward.
+        check!(cpu::allowed() == before);
+    }
+
+    #[test]
+    fn test_watchdog() {
+        struct Hang;
+        impl Sleeper<Instant> for Hang {
+            fn sleep_interruptible(&self, _sleep_duration: Duration) -> WakeupReason {
+                loop {
+                    std::thread::park();
+                }
+            }
+        }
+        struct NoWake;
+        impl Waker for NoWake {
+            fn wake(&self) {}
+        }
+
+        let config = RunConfig::new(10, Duration::from_millis(1)).with_scenario(Scenario::Timeout);
+        let measurements = run(config, Hang, NoWake);
+        check!(measurements.rounds == 0);
+        let failure = measurements.failure.unwrap();
+        check!(failure.contains("no measurement within 1s in round 0"));
+    }
+
+    #[test]
+    fn test_watchdog_ack() {
+        /// Ignores the wake-up, so it never ACKs.
+        struct Deaf;
+        impl Sleeper<Instant> for Deaf {
+            fn sleep_interruptible(&self, _sleep_duration: Duration) -> WakeupReason {
+                loop {
+                    std::thread::park();
+                }
+            }
+        }
+        /// Waits for the ACK like the backends.
+        struct AckWaker(NoDelayBarrier);
+        impl Waker for AckWaker {
+            fn wake(&self) {
+                self.0.wait();
+            }
+
+            fn set_handshake(&self, handshake: Handshake) {
+                self.0.set_handshake(handshake);
+            }
+        }
+
+        let config = RunConfig::new(10, Duration::from_millis(1)).with_scenario(Scenario::Always);
+        // Test succeeds if this does not get stuck in `wake()`.
+        let measurements = run(config, Deaf, AckWaker(NoDelayBarrier::new()));
+        check!(measurements.rounds == 0);
+        let failure = measurements.failure.unwrap();
+        check!(failure.contains("no measurement within 1s in round 0"));
+    }
+
+    #[test]
+    fn test_wake_after_timeout() {
+        /// Always times out, like a sleeper whose wake-up came too late.
+        struct Late;
+        impl Sleeper<Instant> for Late {
+            fn sleep_interruptible(&self, sleep_duration: Duration) -> WakeupReason {
+                std::thread::sleep(sleep_duration);
+                WakeupReason::Timeout
+            }
+        }
+        struct AckWaker(NoDelayBarrier);
+        impl Waker for AckWaker {
+            fn wake(&self) {
+                self.0.wait();
+            }
+
+            fn set_handshake(&self, handshake: Handshake) {
+                self.0.set_handshake(handshake);
+            }
+        }
+
+        let config = RunConfig::new(10, Duration::from_millis(1)).with_scenario(Scenario::Always);
+        let measurements = run(config, Late, AckWaker(NoDelayBarrier::new()));
+        check!(measurements.rounds == 0);
+        let failure = measurements.failure.unwrap();
+        check!(failure.contains("the wake-up came after the timeout in round 0"));
+    }
+
+    #[test]
+    fn test_total_wake_delay() {
+        let (sleeper, waker) = crate::backends::channel::new_pair();
+        // Test succeeds if this does not get stuck.
+        let _ = total_wake_delay(5, sleeper, waker, Duration::from_millis(1));
+    }
+}
@@ -0,0 +1,183 @@
+//! Schedules of the interruptions during a run. See [`Scenario`].
+
+use std::time::Duration;
+
+/// Interrupts are scheduled within this percentage of the timeslice.
+const INTERRUPT_WINDOW_PERCENT: u32 = 95;
+
+/// Default percentage of the interrupted rounds of [`Scenario::Mixed`] and
+/// [`Scenario::Spurious`].
+pub const DEFAULT_INTERRUPT_PERCENT: u8 = 50;
+
+/// Decides which rounds of a run get interrupted and when.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Scenario {
+    /// Half of the rounds, chosen randomly, are interrupted at a random point
+    /// in time within the timeslice. The others time out normally. The
+    /// percentage of interrupted rounds is configurable, see
+    /// [`RunConfig::interrupt_percent`].
+    ///
+    /// [`RunConfig::interrupt_percent`]: crate::runner::RunConfig::interrupt_percent
+    #[default]
+    Mixed,
+    /// Like [`Self::Mixed`] but each round additionally gets a spurious
+    /// wake-up halfway to the wake-up or the timeout, see
+    /// [`Waker::wake_spurious`]. With the same seed, the delays compared with
+    /// [`Self::Mixed`] show the cost of handling spurious wake-ups.
+    ///
+    /// [`Waker::wake_spurious`]: crate::Waker::wake_spurious
+    Spurious,
+    /// Every round is interrupted at a random point in time in the second half
+    /// of the timeslice, i.e., the sleeper always slept at least half of it.
+    Always,
+    /// No round is interrupted, every round times out. Required by the
+    /// [baseline](crate::backends::baseline).
+    Timeout,
+}
+
+impl Scenario {
+    /// All scenarios.
+    pub const ALL: &[Self] = &[Self::Mixed, Self::Spurious, Self::Always, Self::Timeout];
+
+    /// Returns the name, as used on the command line.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Mixed => "mixed",
+            Self::Spurious => "spurious",
+            Self::Always => "always",
+            Self::Timeout => "timeout",
+        }
+    }
+
+    /// Returns a short human-readable description.
+    #[must_use]
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::Mixed => "random interrupts within the timeslice, half of the rounds time out",
+            Self::Spurious => "like mixed, plus a spurious wake-up in each round (if supported)",
+            Self::Always => "every round is interrupted in the second half of the timeslice",
+            Self::Timeout => "no round is interrupted, every round times out",
+        }
+    }
+
+    /// Looks up a scenario by its [name](Self::name).
+    #[must_use]
+    pub fn find(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|scenario| scenario.name() == name)
+    }
+
+    /// Returns the offset of the wake-up relative to the begin of the sleep
+    /// for the next round, or `None` if the round should time out.
+    ///
+    /// `interrupt_percent` is the percentage of interrupted rounds of
+    /// [`Self::Mixed`] and [`Self::Spurious`].
+    pub(crate) fn next_wake_offset(
+        self,
+        rng: &mut fastrand::Rng,
+        timeslice: Duration,
+        interrupt_percent: u8,
+    ) -> Option<Duration> {
+        let max_ns = (timeslice * INTERRUPT_WINDOW_PERCENT / 100).as_nanos() as u64;
+        match self {
+            Self::Mixed | Self::Spurious => {
+                // Draws the same numbers for every percentage, so with the
+                // same seed, the interrupted rounds of a lower percentage
+                // are also interrupted at a higher one, at the same offset.
+                let roll = rng.u8(..100);
+                let offset = Duration::from_nanos(rng.u64(0..=max_ns));
+                (roll < interrupt_percent).then_some(offset)
+            }
+            Self::Always => {
+                let min_ns = (timeslice / 2).as_nanos() as u64;
+                Some(Duration::from_nanos(rng.u64(min_ns..=max_ns)))
+            }
+            Self::Timeout => None,
+        }
+    }
+
+    /// Returns the offset of the spurious wake-up relative to the begin of the
+    /// sleep for a round with the given wake-up offset, or `None` if the
+    /// scenario has no spurious wake-ups.
+    ///
+    /// Doesn't use the random number generator, so the schedule of the real
+    /// wake-ups is the same as in [`Self::Mixed`].
+    pub(crate) fn spurious_wake_offset(
+        self,
+        wake_offset: Option<Duration>,
+        timeslice: Duration,
+    ) -> Option<Duration> {
+        match self {
+            Self::Mixed | Self::Always | Self::Timeout => None,
+            Self::Spurious => Some(wake_offset.unwrap_or(timeslice) / 2),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert2::check;
+
+    #[test]
+    fn test_find() {
+        for scenario in Scenario::ALL {
+            check!(Scenario::find(scenario.name()) == Some(*scenario));
+        }
+        check!(Scenario::find("foo").is_none());
+    }
+
+    #[test]
+    fn test_always_interrupted() {
+        let timeslice = Duration::from_millis(10);
+        let mut rng = fastrand::Rng::with_seed(42);
+        for _ in 0..100 {
+            let offset = Scenario::Always
+                .next_wake_offset(&mut rng, timeslice, DEFAULT_INTERRUPT_PERCENT)
+                .unwrap();
+            check!(offset >= timeslice / 2);
+            check!(offset < timeslice);
+        }
+    }
+
+    #[test]
+    fn test_interrupt_percent() {
+        let timeslice = Duration::from_millis(10);
+        let mut rng = fastrand::Rng::with_seed(42);
+        let mut interrupted = |percent| {
+            (0..1000)
+                .filter(|_| {
+                    Scenario::Mixed
+                        .next_wake_offset(&mut rng, timeslice, percent)
+                        .is_some()
+                })
+                .count()
+        };
+        check!(interrupted(0) == 0);
+        check!(interrupted(100) == 1000);
+        check!((700..900).contains(&interrupted(80)));
+        check!((400..600).contains(&interrupted(DEFAULT_INTERRUPT_PERCENT)));
+    }
+
+    #[test]
+    fn test_interrupt_percent_nested() {
+        let timeslice = Duration::from_millis(10);
+        let schedule = |percent| {
+            let mut rng = fastrand::Rng::with_seed(42);
+            (0..1000)
+                .map(|_| Scenario::Mixed.next_wake_offset(&mut rng, timeslice, percent))
+                .collect::<Vec<_>>()
+        };
+        let lower = schedule(DEFAULT_INTERRUPT_PERCENT);
+        let higher = schedule(DEFAULT_INTERRUPT_PERCENT + 1);
+        for (lower, higher) in lower.iter().zip(&higher) {
+            if lower.is_some() {
+                check!(lower == higher);
+            }
+        }
+        check!(lower.iter().flatten().count() < higher.iter().flatten().count());
+    }
+}
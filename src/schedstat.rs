@@ -0,0 +1,60 @@
+//! Scheduler statistics of a thread from `/proc/thread-self/schedstat`. See
+//! [`SchedStat`].
+//!
+//! The delay of a wake-up consists of the time until the sleeper thread is
+//! made runnable, which depends on the backend, and the time the runnable
+//! thread waits for a CPU, which depends on the scheduler. The latter is the
+//! `run_delay` of the thread.
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::time::Duration;
+
+/// Handle to the scheduler statistics of the thread that opened it.
+#[derive(Debug)]
+pub struct SchedStat {
+    file: File,
+}
+
+impl SchedStat {
+    /// Opens the statistics of the calling thread. Returns `None` if they are
+    /// not available, e.g., on other platforms than Linux or on kernels
+    /// without `CONFIG_SCHED_INFO`.
+    #[must_use]
+    pub fn open() -> Option<Self> {
+        let stat = Self {
+            file: File::open("/proc/thread-self/schedstat").ok()?,
+        };
+        stat.run_delay().map(|_| stat)
+    }
+
+    /// Returns the total time the thread was runnable but waited for a CPU.
+    #[must_use]
+    pub fn run_delay(&self) -> Option<Duration> {
+        // The file is regenerated on each read from the beginning.
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(0)).ok()?;
+        let mut content = String::new();
+        file.read_to_string(&mut content).ok()?;
+        parse_run_delay(&content)
+    }
+}
+
+/// Parses `<time on cpu ns> <run delay ns> <number of timeslices>`.
+fn parse_run_delay(content: &str) -> Option<Duration> {
+    let run_delay = content.split_whitespace().nth(1)?.parse().ok()?;
+    Some(Duration::from_nanos(run_delay))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert2::check;
+
+    #[test]
+    fn test_parse_run_delay() {
+        check!(parse_run_delay("1234 5678 9\n") == Some(Duration::from_nanos(5678)));
+        check!(parse_run_delay("1234\n").is_none());
+        check!(parse_run_delay("").is_none());
+    }
+}
@@ -0,0 +1,118 @@
+//! Setup and teardown cost of the backends. See [`measure`].
+//!
+//! The other benchmarks only measure steady-state cycles. Short-lived tasks
+//! also pay for constructing the [`Sleeper`]/[`Waker`] pair, e.g., creating
+//! file descriptors or allocating a channel, and for the first sleep/wake
+//! cycle, which may initialize state lazily. For them, this can outweigh the
+//! per-wake-up latency.
+
+use crate::clock::Clock;
+use crate::runner::{self, RunConfig};
+use crate::scenario::Scenario;
+use crate::stats::percentile;
+use crate::{Sleeper, Waker};
+use std::time::{Duration, Instant};
+
+/// Number of sleep/wake cycles per pair. The first one is the cold cycle,
+/// the others are steady-state cycles.
+pub const CYCLES: usize = 10;
+
+/// Result of [`measure`]. All durations are medians over the samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetupCost {
+    /// Number of pairs whose wake-ups were measured. Pairs with a skipped
+    /// cycle are excluded.
+    pub samples: usize,
+    /// Duration of constructing a pair.
+    pub construction: Duration,
+    /// Duration of dropping a pair.
+    pub teardown: Duration,
+    /// Delay of the first wake-up of a pair.
+    pub first_wake: Duration,
+    /// Delay of the later wake-ups of a pair.
+    pub steady_wake: Duration,
+}
+
+/// Constructs `samples` pairs with `new_pair` and runs [`CYCLES`] interrupted
+/// sleep/wake cycles with each.
+///
+/// The teardown is measured with separate pairs that are dropped right after
+/// their construction, as the runner consumes the pairs it measures. Only
+/// the scenario of `config` is replaced.
+pub fn measure<S, W, C>(
+    new_pair: impl Fn() -> (S, W),
+    samples: usize,
+    config: RunConfig,
+    clock: C,
+) -> SetupCost
+where
+    S: Sleeper<Instant> + Send + 'static,
+    W: Waker,
+    C: Clock + Clone + Send + 'static,
+{
+    let config = RunConfig {
+        rounds: CYCLES,
+        ..config.with_scenario(Scenario::Always)
+    };
+    let mut construction = Vec::with_capacity(samples);
+    let mut teardown = Vec::with_capacity(samples);
+    let mut first_wake = Vec::with_capacity(samples);
+    let mut steady_wake = Vec::with_capacity(samples * CYCLES);
+
+    for _ in 0..samples {
+        let begin = clock.now();
+        let pair = new_pair();
+        construction.push(clock.now() - begin);
+        let begin = clock.now();
+        drop(pair);
+        teardown.push(clock.now() - begin);
+
+        let (sleeper, waker) = new_pair();
+        let measurements = runner::run_with_clock(config, sleeper, waker, clock.clone());
+        // A skipped first cycle still initialized the pair, so the first
+        // measurement is only the cold one if nothing was skipped.
+        if measurements.skipped > 0 {
+            continue;
+        }
+        let mut delays = measurements
+            .interrupted
+            .iter()
+            .map(|m| m.wakeup_context.delay);
+        first_wake.extend(delays.next());
+        steady_wake.extend(delays);
+    }
+
+    SetupCost {
+        samples: first_wake.len(),
+        construction: median(construction),
+        teardown: median(teardown),
+        first_wake: median(first_wake),
+        steady_wake: median(steady_wake),
+    }
+}
+
+fn median(mut durations: Vec<Duration>) -> Duration {
+    durations.sort_unstable();
+    percentile(&durations, 50.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::virtual_time::{self, VirtualClock};
+    use assert2::check;
+
+    #[test]
+    fn test_measure() {
+        let clock = VirtualClock::new();
+        let config = RunConfig::new(1000, Duration::from_secs(60));
+        let cost = measure(|| virtual_time::new_pair(&clock), 3, config, clock.clone());
+
+        check!(cost.samples == 3);
+        // Virtual time only advances when the control thread sleeps.
+        check!(cost.construction == Duration::ZERO);
+        check!(cost.teardown == Duration::ZERO);
+        check!(cost.first_wake == Duration::ZERO);
+        check!(cost.steady_wake == Duration::ZERO);
+    }
+}
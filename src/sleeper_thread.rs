@@ -1,85 +1,150 @@
 //! Module for sleeper control. See [`SleeperThread`].
 
+use crate::clock::{Clock, SystemClock};
+use crate::priority;
+use crate::schedstat::SchedStat;
 use crate::synchronization::NoDelayBarrier;
-use crate::{Sleeper, WakeupContext, WakeupReason};
-use assert2::check;
-use std::sync::atomic::{AtomicBool, Ordering};
+use crate::{Sleeper, TimeoutMode, WakeupContext, WakeupReason, cpu, measure};
+use crate::{threads, trace};
+use std::io;
+use std::sync::atomic::{AtomicI32, AtomicU8, Ordering};
 use std::sync::mpsc::SyncSender;
 use std::sync::{Arc, Barrier};
 use std::thread;
 use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
 
-const SHOULD_EXIT: bool = true;
-const SHOULD_CONTINUE: bool = false;
+// States of the thread, see `SleeperThread::thread_task`.
+const SHOULD_CONTINUE: u8 = 0;
+const SHOULD_PAUSE: u8 = 1;
+const PAUSED: u8 = 2;
+const SHOULD_EXIT: u8 = 3;
 
 /// Handle to a thread that continuously sleeps on a [`Sleeper`] and measures
 /// the effective wakeup times.
 ///
-/// The results are send through a channel for further analysis.
+/// The results are send through a channel for further analysis. `None` is
+/// sent for a cycle without a valid measurement: the wake-up was issued before
+/// the thread even started to sleep, which happens for tiny timeslices when the
+/// thread is scheduled late.
 ///
 /// The thread is supposed to be used by the controlling thread, doing the
-/// actual interruptions and collecting measurements.
+/// actual interruptions and collecting measurements. Between measurement
+/// phases, the controlling thread can [`pause`] the thread instead of
+/// dropping it, which keeps the state of the backend.
+///
+/// [`pause`]: Self::pause
 #[derive(Debug)]
 pub struct SleeperThread {
-    thread_task: Arc<AtomicBool>,
+    thread_task: Arc<AtomicU8>,
     handle: Option<JoinHandle<()>>,
     sleep_barrier: Arc<NoDelayBarrier>,
+    /// The kernel's id of the thread, if supported on this platform.
+    tid: Option<i32>,
 }
 
 impl SleeperThread {
-    fn thread_fn<S: Sleeper>(
+    #[allow(clippy::too_many_arguments)]
+    fn thread_fn<S: Sleeper<Instant>, C: Clock>(
         sleeper: S,
+        clock: C,
         sleep_barrier: Arc<NoDelayBarrier>,
-        thread_task: Arc<AtomicBool>,
+        thread_task: Arc<AtomicU8>,
         default_sleep_duration: Duration,
-        sender: SyncSender<WakeupContext>,
+        timeout_mode: TimeoutMode,
+        scheduling: bool,
+        sender: SyncSender<Option<WakeupContext>>,
         thread_startup_barrier: Arc<Barrier>,
+        tid: Arc<AtomicI32>,
     ) -> impl FnOnce() {
         move || {
+            if let Some(current) = priority::current_tid() {
+                tid.store(current, Ordering::SeqCst);
+            }
+            // Must be opened by this thread.
+            let schedstat = scheduling.then(SchedStat::open).flatten();
+            let run_delay = || schedstat.as_ref().and_then(SchedStat::run_delay);
+            let current_cpu = || scheduling.then(cpu::current).flatten();
+
             // Notify caller that thread has started.
             thread_startup_barrier.wait();
             loop {
+                // Sampled before the barrier, so that reading the file
+                // doesn't delay the begin of the sleep. Preemptions while
+                // waiting in the barrier count as queueing, which is capped
+                // at the delay, see `split_delay`.
+                let run_delay_before = run_delay();
+
                 // Wait for the control thread to be ready for the next
                 // measurement cycle.
                 sleep_barrier.wait();
 
                 // Exit thread gracefully if necessary.
-                if thread_task.load(Ordering::SeqCst) == SHOULD_EXIT {
-                    break;
+                match thread_task.load(Ordering::SeqCst) {
+                    SHOULD_EXIT => break,
+                    // Met `pause()` in the barrier instead of the control
+                    // thread. Park instead of spinning in the barrier while
+                    // paused.
+                    SHOULD_PAUSE => {
+                        thread_task.store(PAUSED, Ordering::SeqCst);
+                        while thread_task.load(Ordering::SeqCst) == PAUSED {
+                            thread::park();
+                        }
+                        continue;
+                    }
+                    _ => {}
                 }
 
-                let begin = Instant::now();
-                let wakeup_reason = sleeper.sleep_interruptible(default_sleep_duration);
-                let actual_sleep_duration_with_overhead = begin.elapsed();
+                trace::span!("sleep", timeslice = ?default_sleep_duration);
+                trace::event!("sleep start");
+                let cpu_before = current_cpu();
+                let begin = clock.now();
+                let wakeup_reason = match timeout_mode {
+                    TimeoutMode::Relative => sleeper.sleep_interruptible(default_sleep_duration),
+                    TimeoutMode::Absolute => sleeper.sleep_until(begin + default_sleep_duration),
+                };
+                let end = clock.now();
+                let wake_observed = match wakeup_reason {
+                    WakeupReason::Interrupted { .. } => sleeper.wake_observed(),
+                    WakeupReason::Timeout => None,
+                };
+                let cpu_after = current_cpu();
+                let run_delay_after = run_delay();
+                trace::event!(reason = ?wakeup_reason, actual = ?(end - begin), "wake observed");
 
                 // Exit directly, ignoring the sender.
                 if thread_task.load(Ordering::SeqCst) == SHOULD_EXIT {
                     break;
                 }
 
-                // Determine the ideal/perfect sleep duration.
-                let actual_expected_sleep_duration =
-                    if let WakeupReason::Interrupted { wake_call_instant } = wakeup_reason {
-                        check!(wake_call_instant >= begin);
-                        wake_call_instant - begin
-                    } else {
-                        default_sleep_duration
-                    };
-
-                // The delay between `sleep()` and `wake()`.
-                check!(actual_expected_sleep_duration <= actual_sleep_duration_with_overhead);
-                let delay = actual_sleep_duration_with_overhead - actual_expected_sleep_duration;
+                // Determine the ideal/perfect sleep duration and the delay
+                // between `sleep()` and `wake()`.
+                // A sleep that returned early, e.g., because of a clock
+                // anomaly, is recorded with a negative signed delay instead
+                // of aborting the run, see `WakeupContext::signed_delay`.
+                let Some(timing) = measure(&wakeup_reason, begin, end, default_sleep_duration)
+                else {
+                    sender.send(None).unwrap();
+                    continue;
+                };
 
                 let wakeup_context = WakeupContext {
                     reason: wakeup_reason,
-                    expected_duration: actual_expected_sleep_duration,
-                    actual_duration: actual_sleep_duration_with_overhead,
-                    delay,
+                    expected_duration: timing.expected_duration,
+                    actual_duration: timing.actual_duration,
+                    delay: timing.delay,
+                    cpu_before,
+                    cpu_after,
+                    run_delay: run_delay_after
+                        .zip(run_delay_before)
+                        .map(|(after, before)| after.saturating_sub(before)),
+                    wake_observed,
+                    returned: end,
+                    recorded: clock.now(),
                 };
 
                 // Send the result to the control thread, allowing analysis.
-                sender.send(wakeup_context).unwrap();
+                sender.send(Some(wakeup_context)).unwrap();
             }
         }
     }
@@ -89,36 +154,177 @@ impl SleeperThread {
     /// Waits for the thread to start. Afterward, the thread will wait for
     /// sleep() events, synchronized via  the shared `sleep_barrier` of type
     /// [`NoDelayBarrier`].
-    pub fn spawn<S: Sleeper + Send + 'static>(
+    pub fn spawn<S: Sleeper<Instant> + Send + 'static>(
         sleep_barrier: Arc<NoDelayBarrier>,
         sleeper: S,
         default_sleep_duration: Duration,
-        sender: SyncSender<WakeupContext>,
+        sender: SyncSender<Option<WakeupContext>>,
     ) -> Self {
-        let thread_task = Arc::new(AtomicBool::new(SHOULD_CONTINUE));
+        Self::spawn_with_clock(
+            sleep_barrier,
+            sleeper,
+            default_sleep_duration,
+            TimeoutMode::Relative,
+            false,
+            sender,
+            SystemClock,
+        )
+    }
+
+    /// Like [`Self::spawn`] but the thread passes the timeout as specified by
+    /// `timeout_mode` and takes all time measurements with the given
+    /// [`Clock`]. With `scheduling`, the thread records its CPUs and its run
+    /// delay in each [`WakeupContext`], which costs a few syscalls per cycle.
+    pub fn spawn_with_clock<S: Sleeper<Instant> + Send + 'static, C: Clock + Send + 'static>(
+        sleep_barrier: Arc<NoDelayBarrier>,
+        sleeper: S,
+        default_sleep_duration: Duration,
+        timeout_mode: TimeoutMode,
+        scheduling: bool,
+        sender: SyncSender<Option<WakeupContext>>,
+        clock: C,
+    ) -> Self {
+        let thread_task = Arc::new(AtomicU8::new(SHOULD_CONTINUE));
         let thread_startup_barrier = Arc::new(Barrier::new(2));
+        let tid = Arc::new(AtomicI32::new(0));
         let handle = {
             let thread_task = thread_task.clone();
             let sleep_barrier = sleep_barrier.clone();
             thread::spawn(Self::thread_fn(
                 sleeper,
+                clock,
                 sleep_barrier,
                 thread_task,
                 default_sleep_duration,
+                timeout_mode,
+                scheduling,
                 sender,
                 thread_startup_barrier.clone(),
+                tid.clone(),
             ))
         };
 
         // Wait for thread to start up.
         thread_startup_barrier.wait();
 
+        let tid = tid.load(Ordering::SeqCst);
         Self {
             handle: Some(handle),
             thread_task,
             sleep_barrier,
+            tid: (tid != 0).then_some(tid),
+        }
+    }
+}
+
+impl SleeperThread {
+    /// Restricts the thread to `cpu`, see [`cpu::pin`].
+    ///
+    /// # Errors
+    ///
+    /// Like [`cpu::pin`].
+    pub fn pin(&self, cpu: u32) -> io::Result<()> {
+        let handle = self
+            .handle
+            .as_ref()
+            .expect("thread should not be abandoned");
+        cpu::pin(handle, cpu)
+    }
+
+    /// Returns the kernel's id of the thread, if supported on this platform.
+    #[must_use]
+    pub const fn tid(&self) -> Option<i32> {
+        self.tid
+    }
+
+    /// Names the thread, see [`threads::rename`].
+    pub fn set_name(&self, name: &str) {
+        if let Some(handle) = &self.handle {
+            threads::rename(handle, name);
+        }
+    }
+
+    /// Sets the nice level of the thread for the rest of its life, see
+    /// [`priority::set_nice`].
+    ///
+    /// # Errors
+    ///
+    /// Like [`priority::set_nice`].
+    pub fn set_nice(&self, nice: i32) -> io::Result<()> {
+        let tid = self.tid.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                "nice levels of threads are only supported on Linux",
+            )
+        })?;
+        priority::set_nice(tid, nice)
+    }
+
+    /// Pauses the thread between two measurement cycles: instead of spinning
+    /// in the barrier, it parks until [`Self::resume`], e.g., to leave the
+    /// CPU idle or to reconfigure the load between measurement phases. The
+    /// sleeper and its backend state are kept.
+    ///
+    /// Must not be called during a cycle, i.e., between entering the barrier
+    /// and receiving the measurement. Returns once the thread is parked; does
+    /// nothing if it is already paused.
+    pub fn pause(&self) {
+        if self
+            .thread_task
+            .compare_exchange(
+                SHOULD_CONTINUE,
+                SHOULD_PAUSE,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            )
+            .is_err()
+        {
+            return;
+        }
+        // Meet the thread in the barrier like the control thread does, so
+        // the barrier stays in step. Unblocking it instead races with the
+        // thread entering it and can leave a stale arrival behind.
+        self.sleep_barrier.wait();
+        while self.thread_task.load(Ordering::SeqCst) == SHOULD_PAUSE {
+            thread::yield_now();
+        }
+    }
+
+    /// Resumes a thread paused by [`Self::pause`]. Afterward, it waits in the
+    /// barrier for the next measurement cycle again. Does nothing if the
+    /// thread is not paused.
+    pub fn resume(&self) {
+        if self
+            .thread_task
+            .compare_exchange(PAUSED, SHOULD_CONTINUE, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+        {
+            self.unpark();
+        }
+    }
+
+    /// Returns whether the thread is paused, see [`Self::pause`].
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        self.thread_task.load(Ordering::SeqCst) == PAUSED
+    }
+
+    fn unpark(&self) {
+        if let Some(handle) = &self.handle {
+            handle.thread().unpark();
         }
     }
+
+    /// Tells the thread to exit but doesn't wait for it, e.g., because the
+    /// sleeper hangs. A thread can't be killed; if the sleeper never returns,
+    /// the thread and its resources leak until the process exits.
+    pub fn abandon(mut self) {
+        self.thread_task.store(SHOULD_EXIT, Ordering::SeqCst);
+        self.sleep_barrier.unblock();
+        self.unpark();
+        // Detaches the thread.
+        drop(self.handle.take());
+    }
 }
 
 impl Drop for SleeperThread {
@@ -126,11 +332,23 @@ impl Drop for SleeperThread {
         // Tell thread to exit on it's next iteration.
         self.thread_task.store(SHOULD_EXIT, Ordering::SeqCst);
 
+        let Some(handle) = self.handle.take() else {
+            // Abandoned.
+            return;
+        };
+
         // unblock thread from "waiting for work"
-        self.sleep_barrier.unblock();
+        //
+        // The thread might not have entered the barrier yet, in which case a
+        // single unblock() is lost. Hence, we repeat it until the thread is
+        // gone.
+        while !handle.is_finished() {
+            self.sleep_barrier.unblock();
+            handle.thread().unpark();
+            thread::yield_now();
+        }
 
         // terminate thread handle
-        let handle = self.handle.take().expect("should still have thread handle");
         handle.join().expect("should gracefully exit thread");
     }
 }
@@ -138,16 +356,17 @@ impl Drop for SleeperThread {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{Handshake, Waker};
+    use assert2::check;
     use std::sync::mpsc;
     use std::thread::sleep;
-    use crate::Waker;
 
     struct Dummy;
     impl Waker for Dummy {
         fn wake(&self) {}
     }
 
-    impl Sleeper for Dummy {
+    impl Sleeper<Instant> for Dummy {
         fn sleep_interruptible(&self, _sleep_duration: Duration) -> WakeupReason {
             sleep(Duration::from_millis(50));
             WakeupReason::Timeout
@@ -163,4 +382,72 @@ mod tests {
         // Test succeeds if this does not get stuck.
         drop(thread);
     }
+
+    #[test]
+    fn test_pause_resume() {
+        struct Timeouts;
+        impl Sleeper<Instant> for Timeouts {
+            fn sleep_interruptible(&self, sleep_duration: Duration) -> WakeupReason {
+                sleep(sleep_duration);
+                WakeupReason::Timeout
+            }
+        }
+
+        let sleep_barrier = Arc::new(NoDelayBarrier::new());
+        let (sender, receiver) = mpsc::sync_channel(1);
+        let thread = SleeperThread::spawn(
+            sleep_barrier.clone(),
+            Timeouts,
+            Duration::from_millis(1),
+            sender,
+        );
+
+        sleep_barrier.wait();
+        check!(receiver.recv().unwrap().is_some());
+
+        thread.pause();
+        check!(thread.is_paused());
+        // Pausing twice is fine, and the thread doesn't start a cycle.
+        thread.pause();
+        sleep(Duration::from_millis(10));
+        check!(receiver.try_recv().is_err());
+
+        thread.resume();
+        check!(!thread.is_paused());
+        sleep_barrier.wait();
+        check!(receiver.recv().unwrap().is_some());
+
+        // Dropping a paused thread must not get stuck.
+        thread.pause();
+        drop(thread);
+    }
+
+    #[test]
+    fn test_pause_resume_stress() {
+        struct Immediate;
+        impl Sleeper<Instant> for Immediate {
+            fn sleep_interruptible(&self, _sleep_duration: Duration) -> WakeupReason {
+                WakeupReason::Timeout
+            }
+        }
+
+        let sleep_barrier = Arc::new(NoDelayBarrier::new());
+        sleep_barrier.set_handshake(Handshake {
+            yielding: true,
+            ..Handshake::default()
+        });
+        let (sender, receiver) = mpsc::sync_channel(1);
+        let thread = SleeperThread::spawn(sleep_barrier.clone(), Immediate, Duration::ZERO, sender);
+
+        // A stale arrival left in the barrier would let the thread start a
+        // cycle on its own, or make the control thread wait forever.
+        for _ in 0..1000 {
+            thread.pause();
+            check!(thread.is_paused());
+            thread.resume();
+            sleep_barrier.wait();
+            check!(receiver.recv().is_ok());
+        }
+        check!(receiver.try_recv().is_err());
+    }
 }
@@ -0,0 +1,327 @@
+//! Endless runs with periodic reports and bounded memory. See [`SoakSink`].
+//!
+//! The wake-up delay drifts with the thermal state of the system and with
+//! background activity. This is only visible when observing a system for a
+//! long time.
+
+use crate::runner::MeasurementSink;
+use crate::stats::{bucket_high, bucket_index};
+use crate::units::format_duration;
+use crate::{Measurement, WakeupReason};
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::ops::ControlFlow;
+use std::time::{Duration, Instant};
+
+/// Number of histogram buckets of an [`Aggregate`], enough for any delay.
+const BUCKETS: usize = 64;
+
+/// The rolling windows of the reports, in seconds.
+const WINDOWS: [(u64, &str); 2] = [(60, "last 1m"), (600, "last 10m")];
+
+/// The largest rolling window, in seconds. Older data is only part of the
+/// total.
+const MAX_WINDOW: u64 = 600;
+
+/// Aggregated delays of a fixed size, no matter how many delays were added.
+///
+/// Percentiles are only available as an upper bound, with the resolution of
+/// the power-of-two buckets of [`histogram`].
+///
+/// [`histogram`]: crate::stats::histogram
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Aggregate {
+    pub count: u64,
+    pub sum: Duration,
+    pub max: Duration,
+    histogram: [u64; BUCKETS],
+}
+
+impl Default for Aggregate {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            sum: Duration::ZERO,
+            max: Duration::ZERO,
+            histogram: [0; BUCKETS],
+        }
+    }
+}
+
+impl Aggregate {
+    pub fn add(&mut self, delay: Duration) {
+        self.count += 1;
+        self.sum += delay;
+        self.max = self.max.max(delay);
+        self.histogram[bucket_index(delay).min(BUCKETS - 1)] += 1;
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        self.count += other.count;
+        self.sum += other.sum;
+        self.max = self.max.max(other.max);
+        for (count, other_count) in self.histogram.iter_mut().zip(other.histogram) {
+            *count += other_count;
+        }
+    }
+
+    /// Returns the mean delay or zero if there is none.
+    #[must_use]
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_nanos((self.sum.as_nanos() / u128::from(self.count)) as u64)
+        }
+    }
+
+    /// Returns an upper bound of the `p`-th percentile (`0.0..=100.0`).
+    #[must_use]
+    pub fn percentile_upper_bound(&self, p: f64) -> Duration {
+        let rank = (p / 100.0 * self.count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (index, count) in self.histogram.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return bucket_high(index).min(self.max);
+            }
+        }
+        self.max
+    }
+}
+
+/// The delays of the last [`MAX_WINDOW`] seconds, in slots of one second,
+/// plus the total.
+#[derive(Debug, Clone, Default)]
+pub struct Rolling {
+    /// (second since begin, delays of that second)
+    slots: VecDeque<(u64, Aggregate)>,
+    total: Aggregate,
+}
+
+impl Rolling {
+    /// Adds a delay that was measured in the given second since the begin.
+    pub fn add(&mut self, second: u64, delay: Duration) {
+        match self.slots.back_mut() {
+            Some((slot_second, aggregate)) if *slot_second == second => aggregate.add(delay),
+            _ => {
+                let mut aggregate = Aggregate::default();
+                aggregate.add(delay);
+                self.slots.push_back((second, aggregate));
+            }
+        }
+        while self
+            .slots
+            .front()
+            .is_some_and(|(slot_second, _)| slot_second + MAX_WINDOW <= second)
+        {
+            self.slots.pop_front();
+        }
+        self.total.add(delay);
+    }
+
+    /// Returns the delays of the last `seconds` seconds before `now_second`.
+    #[must_use]
+    pub fn window(&self, now_second: u64, seconds: u64) -> Aggregate {
+        let mut aggregate = Aggregate::default();
+        for (_, slot) in self
+            .slots
+            .iter()
+            .filter(|(slot_second, _)| slot_second + seconds > now_second)
+        {
+            aggregate.merge(slot);
+        }
+        aggregate
+    }
+
+    #[must_use]
+    pub const fn total(&self) -> &Aggregate {
+        &self.total
+    }
+}
+
+/// A [`MeasurementSink`] for endless runs: keeps rolling statistics of the
+/// last minute, the last ten minutes, and the total, and writes them to `out`
+/// every `interval`.
+///
+/// The run stops when writing to `out` fails.
+#[derive(Debug)]
+pub struct SoakSink<W: Write> {
+    begin: Instant,
+    interval: Duration,
+    next_report: Instant,
+    interrupted: Rolling,
+    timeouted: Rolling,
+    skipped: u64,
+    out: W,
+}
+
+impl<W: Write> SoakSink<W> {
+    #[must_use]
+    pub fn new(interval: Duration, out: W) -> Self {
+        Self::new_at(Instant::now(), interval, out)
+    }
+
+    fn new_at(begin: Instant, interval: Duration, out: W) -> Self {
+        Self {
+            begin,
+            interval,
+            next_report: begin + interval,
+            interrupted: Rolling::default(),
+            timeouted: Rolling::default(),
+            skipped: 0,
+            out,
+        }
+    }
+
+    /// Adds a measurement that was taken at `now` and writes a report if one
+    /// is due.
+    pub fn record_at(&mut self, measurement: &Measurement, now: Instant) -> io::Result<()> {
+        let second = (now - self.begin).as_secs();
+        let context = &measurement.wakeup_context;
+        let rolling = match context.reason {
+            WakeupReason::Interrupted { .. } => &mut self.interrupted,
+            WakeupReason::Timeout => &mut self.timeouted,
+        };
+        rolling.add(second, context.delay);
+
+        if now >= self.next_report {
+            let report = self.render(now);
+            self.out.write_all(report.as_bytes())?;
+            self.out.flush()?;
+            // Don't catch up on missed reports.
+            while self.next_report <= now {
+                self.next_report += self.interval;
+            }
+        }
+        Ok(())
+    }
+
+    /// Renders the report at `now`.
+    #[must_use]
+    pub fn render(&self, now: Instant) -> String {
+        let elapsed = now - self.begin;
+        let second = elapsed.as_secs();
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "SOAK: {} elapsed, skipped (#): {}",
+            format_duration(Duration::from_secs(second)),
+            self.skipped
+        );
+        let _ = writeln!(
+            out,
+            "  {:<11} | {:<8} | {:>9} | {:>10} | {:>10} | {:>10} |",
+            "reason", "window", "#", "mean", "p99 (<=)", "max"
+        );
+        for (label, rolling) in [
+            ("interrupted", &self.interrupted),
+            ("timeouted", &self.timeouted),
+        ] {
+            let windows = WINDOWS
+                .iter()
+                .map(|&(seconds, name)| (name, rolling.window(second, seconds)))
+                .chain([("total", *rolling.total())]);
+            for (name, aggregate) in windows {
+                let _ = writeln!(
+                    out,
+                    "  {label:<11} | {name:<8} | {:>9} | {:>10} | {:>10} | {:>10} |",
+                    aggregate.count,
+                    format_duration(aggregate.mean()),
+                    format_duration(aggregate.percentile_upper_bound(99.0)),
+                    format_duration(aggregate.max)
+                );
+            }
+        }
+        out
+    }
+}
+
+impl<W: Write> MeasurementSink for SoakSink<W> {
+    fn record(&mut self, measurement: Measurement) -> ControlFlow<()> {
+        match self.record_at(&measurement, Instant::now()) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(_) => ControlFlow::Break(()),
+        }
+    }
+
+    fn skip(&mut self) {
+        self.skipped += 1;
+    }
+
+    fn fail(&mut self, reason: String) {
+        let _ = writeln!(self.out, "SOAK: FAILED: {reason}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WakeupContext;
+    use assert2::check;
+
+    fn measurement(delay_us: u64) -> Measurement {
+        Measurement {
+            wakeup_context: WakeupContext {
+                reason: WakeupReason::Timeout,
+                expected_duration: Duration::ZERO,
+                actual_duration: Duration::from_micros(delay_us),
+                delay: Duration::from_micros(delay_us),
+                cpu_before: None,
+                cpu_after: None,
+                run_delay: None,
+                wake_observed: None,
+                returned: Instant::now(),
+                recorded: Instant::now(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let mut aggregate = Aggregate::default();
+        for us in 1..=100 {
+            aggregate.add(Duration::from_micros(us));
+        }
+        check!(aggregate.count == 100);
+        check!(aggregate.mean() == Duration::from_nanos(50_500));
+        check!(aggregate.max == Duration::from_micros(100));
+        // p50 = 50µs is in the bucket 32µs..64µs
+        check!(aggregate.percentile_upper_bound(50.0) == Duration::from_micros(64));
+        check!(aggregate.percentile_upper_bound(99.0) == Duration::from_micros(100));
+    }
+
+    #[test]
+    fn test_rolling() {
+        let mut rolling = Rolling::default();
+        rolling.add(0, Duration::from_micros(10));
+        rolling.add(30, Duration::from_micros(20));
+        rolling.add(90, Duration::from_micros(30));
+        check!(rolling.window(90, 60).count == 1);
+        check!(rolling.window(90, 600).count == 3);
+
+        rolling.add(1000, Duration::from_micros(40));
+        // Old slots are dropped, the total keeps everything.
+        check!(rolling.slots.len() == 1);
+        check!(rolling.window(1000, 600).count == 1);
+        check!(rolling.total().count == 4);
+    }
+
+    #[test]
+    fn test_soak_sink_reports() {
+        let begin = Instant::now();
+        let mut sink = SoakSink::new_at(begin, Duration::from_secs(10), Vec::new());
+
+        sink.record_at(&measurement(10), begin + Duration::from_secs(1))
+            .unwrap();
+        check!(sink.out.is_empty());
+
+        sink.record_at(&measurement(20), begin + Duration::from_secs(25))
+            .unwrap();
+        let report = String::from_utf8(sink.out.clone()).unwrap();
+        check!(report.matches("SOAK:").count() == 1);
+        check!(report.contains("25s elapsed"));
+        check!(sink.next_report == begin + Duration::from_secs(30));
+    }
+}
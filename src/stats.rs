@@ -0,0 +1,635 @@
+//! Statistics over [`Measurement`]s.
+
+use crate::Measurement;
+use crate::units::{deserialize_nanos, serialize_nanos};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Number of resamples for the bootstrap confidence intervals.
+pub const BOOTSTRAP_RESAMPLES: usize = 1000;
+
+/// Confidence level of the bootstrap confidence intervals.
+pub const CONFIDENCE_LEVEL: f64 = 0.95;
+
+/// Returns the mean delay of the measurements or zero if there are none.
+#[must_use]
+pub fn mean_delay(data: &[Measurement]) -> Duration {
+    let len = data.len();
+    if len == 0 {
+        Duration::ZERO
+    } else {
+        let sum = data
+            .iter()
+            .map(|m| m.wakeup_context.delay)
+            .sum::<Duration>();
+        sum / (len as u32)
+    }
+}
+
+/// Returns the `p`-th percentile (`0.0..=100.0`) of the delays, using the
+/// nearest-rank method, or zero if there are no measurements.
+#[must_use]
+pub fn percentile_delay(data: &[Measurement], p: f64) -> Duration {
+    percentile(&sorted_delays(data), p)
+}
+
+fn sorted_delays(data: &[Measurement]) -> Vec<Duration> {
+    let mut delays = data
+        .iter()
+        .map(|m| m.wakeup_context.delay)
+        .collect::<Vec<_>>();
+    delays.sort_unstable();
+    delays
+}
+
+/// Returns the mean of the delays or zero if there are none.
+pub(crate) fn mean(delays: &[Duration]) -> Duration {
+    if delays.is_empty() {
+        Duration::ZERO
+    } else {
+        delays.iter().sum::<Duration>() / (delays.len() as u32)
+    }
+}
+
+/// Like [`percentile_delay`] but for already sorted delays.
+#[must_use]
+pub fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn p50(sorted: &[Duration]) -> Duration {
+    percentile(sorted, 50.0)
+}
+
+fn p99(sorted: &[Duration]) -> Duration {
+    percentile(sorted, 99.0)
+}
+
+/// A point estimate with its bootstrap confidence interval at
+/// [`CONFIDENCE_LEVEL`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Estimate {
+    #[serde(
+        rename = "value_ns",
+        serialize_with = "serialize_nanos",
+        deserialize_with = "deserialize_nanos"
+    )]
+    pub value: Duration,
+    #[serde(
+        rename = "ci_low_ns",
+        serialize_with = "serialize_nanos",
+        deserialize_with = "deserialize_nanos"
+    )]
+    pub ci_low: Duration,
+    #[serde(
+        rename = "ci_high_ns",
+        serialize_with = "serialize_nanos",
+        deserialize_with = "deserialize_nanos"
+    )]
+    pub ci_high: Duration,
+}
+
+/// Estimates `statistic` of the sorted delays and its confidence interval with
+/// the percentile bootstrap: the statistic is computed for many resamples
+/// (with replacement) of the delays.
+fn bootstrap(
+    sorted: &[Duration],
+    statistic: fn(&[Duration]) -> Duration,
+    rng: &mut fastrand::Rng,
+) -> Estimate {
+    let mut resample = vec![Duration::ZERO; sorted.len()];
+    let mut estimates = (0..BOOTSTRAP_RESAMPLES)
+        .map(|_| {
+            for delay in &mut resample {
+                *delay = sorted[rng.usize(..sorted.len())];
+            }
+            resample.sort_unstable();
+            statistic(&resample)
+        })
+        .collect::<Vec<_>>();
+    estimates.sort_unstable();
+
+    let alpha = 1.0 - CONFIDENCE_LEVEL;
+    let low = (alpha / 2.0 * BOOTSTRAP_RESAMPLES as f64).floor() as usize;
+    let high = ((1.0 - alpha / 2.0) * BOOTSTRAP_RESAMPLES as f64).ceil() as usize - 1;
+    Estimate {
+        value: statistic(sorted),
+        ci_low: estimates[low],
+        ci_high: estimates[high],
+    }
+}
+
+/// Summary of the delays of a set of measurements. See [`summarize`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Summary {
+    /// Number of measurements.
+    pub count: usize,
+    /// Mean delay.
+    pub mean: Estimate,
+    /// Median of the delay.
+    pub p50: Estimate,
+    /// 99th percentile of the delay.
+    pub p99: Estimate,
+    /// Sample standard deviation of the delay, see [`stddev`].
+    #[serde(
+        default,
+        rename = "stddev_ns",
+        serialize_with = "serialize_nanos",
+        deserialize_with = "deserialize_nanos"
+    )]
+    pub stddev: Duration,
+    /// Jitter of the delay, see [`jitter`].
+    #[serde(
+        default,
+        rename = "jitter_ns",
+        serialize_with = "serialize_nanos",
+        deserialize_with = "deserialize_nanos"
+    )]
+    pub jitter: Duration,
+}
+
+/// Summarizes the delays of the measurements, including bootstrap confidence
+/// intervals. The resampling is seeded, so the result is reproducible.
+///
+/// Returns `None` if there are no measurements.
+#[must_use]
+pub fn summarize(data: &[Measurement], seed: u64) -> Option<Summary> {
+    let delays = data
+        .iter()
+        .map(|m| m.wakeup_context.delay)
+        .collect::<Vec<_>>();
+    summarize_delays(&delays, seed)
+}
+
+/// Like [`summarize`] but for plain delays, in the order of the
+/// measurements.
+#[must_use]
+pub fn summarize_delays(delays: &[Duration], seed: u64) -> Option<Summary> {
+    if delays.is_empty() {
+        return None;
+    }
+    let mut sorted = delays.to_vec();
+    sorted.sort_unstable();
+    let mut rng = fastrand::Rng::with_seed(seed);
+    Some(Summary {
+        count: sorted.len(),
+        mean: bootstrap(&sorted, mean, &mut rng),
+        p50: bootstrap(&sorted, p50, &mut rng),
+        p99: bootstrap(&sorted, p99, &mut rng),
+        stddev: stddev(delays),
+        jitter: jitter(delays),
+    })
+}
+
+/// Returns the sample standard deviation of the delays, or zero if there are
+/// fewer than two.
+#[must_use]
+pub fn stddev(delays: &[Duration]) -> Duration {
+    if delays.len() < 2 {
+        return Duration::ZERO;
+    }
+    let mean = mean(delays).as_secs_f64();
+    let sum_of_squares = delays
+        .iter()
+        .map(|delay| (delay.as_secs_f64() - mean).powi(2))
+        .sum::<f64>();
+    Duration::from_secs_f64((sum_of_squares / (delays.len() - 1) as f64).sqrt())
+}
+
+/// Returns the jitter of the delays: the mean absolute difference of
+/// successive delays, in the order of the measurements.
+///
+/// Unlike [`stddev`], a slow drift of the delay doesn't count as jitter. Zero
+/// if there are fewer than two delays.
+#[must_use]
+pub fn jitter(delays: &[Duration]) -> Duration {
+    if delays.len() < 2 {
+        return Duration::ZERO;
+    }
+    let sum = delays
+        .windows(2)
+        .map(|pair| pair[0].abs_diff(pair[1]))
+        .sum::<Duration>();
+    sum / (delays.len() - 1) as u32
+}
+
+/// Variation of the delay across repeated runs of the same configuration.
+/// See [`run_to_run`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunToRun {
+    /// Number of runs.
+    pub runs: usize,
+    /// Mean of the mean delays of the runs.
+    pub mean: Duration,
+    /// Smallest mean delay of a run.
+    pub min: Duration,
+    /// Largest mean delay of a run.
+    pub max: Duration,
+    /// Sample standard deviation of the mean delays of the runs.
+    pub between: Duration,
+    /// Pooled standard deviation of the delays within the runs.
+    pub within: Duration,
+}
+
+impl RunToRun {
+    /// Returns the between-run standard deviation relative to the mean, in
+    /// percent, or zero if the mean is zero.
+    #[must_use]
+    pub fn between_percent(&self) -> f64 {
+        if self.mean.is_zero() {
+            0.0
+        } else {
+            self.between.as_secs_f64() / self.mean.as_secs_f64() * 100.0
+        }
+    }
+}
+
+/// Returns the between-run and the within-run variation of the summaries of
+/// repeated runs, or `None` if there are fewer than two.
+///
+/// A between-run standard deviation well above `within / sqrt(count)`
+/// means that the runs differ by more than their sampling error, e.g.,
+/// because the system changed between them.
+#[must_use]
+pub fn run_to_run(summaries: &[Summary]) -> Option<RunToRun> {
+    if summaries.len() < 2 {
+        return None;
+    }
+    let means = summaries
+        .iter()
+        .map(|summary| summary.mean.value)
+        .collect::<Vec<_>>();
+    let (sum_of_squares, degrees_of_freedom) =
+        summaries
+            .iter()
+            .fold((0.0, 0), |(sum_of_squares, degrees_of_freedom), summary| {
+                let n = summary.count.saturating_sub(1);
+                (
+                    sum_of_squares + summary.stddev.as_secs_f64().powi(2) * n as f64,
+                    degrees_of_freedom + n,
+                )
+            });
+    let within = if degrees_of_freedom == 0 {
+        Duration::ZERO
+    } else {
+        Duration::from_secs_f64((sum_of_squares / degrees_of_freedom as f64).sqrt())
+    };
+    Some(RunToRun {
+        runs: summaries.len(),
+        mean: mean(&means),
+        min: means.iter().copied().min().unwrap_or_default(),
+        max: means.iter().copied().max().unwrap_or_default(),
+        between: stddev(&means),
+        within,
+    })
+}
+
+/// Result of [`outliers`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Outliers {
+    /// Delays above this bound are outliers.
+    pub upper_fence: Duration,
+    /// Number of outliers.
+    pub count: usize,
+}
+
+/// Counts the delays above Tukey's upper fence `Q3 + 1.5 × IQR`, i.e., the
+/// unusually slow wake-ups. Unusually fast wake-ups are not of interest.
+///
+/// The delays must be sorted.
+#[must_use]
+pub fn outliers(sorted: &[Duration]) -> Outliers {
+    let q1 = percentile(sorted, 25.0);
+    let q3 = percentile(sorted, 75.0);
+    let upper_fence = q3 + (q3 - q1) * 3 / 2;
+    Outliers {
+        upper_fence,
+        count: sorted.iter().filter(|&&delay| delay > upper_fence).count(),
+    }
+}
+
+/// A bucket of a [`histogram`]: the number of delays in `low..high`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistogramBucket {
+    pub low: Duration,
+    pub high: Duration,
+    pub count: usize,
+}
+
+/// Sorts the delays into buckets with bounds growing by powers of two:
+/// `0..1µs`, `1µs..2µs`, `2µs..4µs`, and so on. This keeps the long tail of
+/// the delays readable.
+///
+/// Empty buckets below the smallest delay are omitted.
+#[must_use]
+pub fn histogram(delays: &[Duration]) -> Vec<HistogramBucket> {
+    let Some(max_index) = delays.iter().copied().map(bucket_index).max() else {
+        return Vec::new();
+    };
+    let mut counts = vec![0; max_index + 1];
+    for &delay in delays {
+        counts[bucket_index(delay)] += 1;
+    }
+
+    let first = counts.iter().position(|&count| count > 0).unwrap_or(0);
+    (first..=max_index)
+        .map(|index| HistogramBucket {
+            low: bucket_low(index),
+            high: bucket_high(index),
+            count: counts[index],
+        })
+        .collect()
+}
+
+/// Returns the index of the [`histogram`] bucket of the delay.
+pub(crate) const fn bucket_index(delay: Duration) -> usize {
+    let micros = delay.as_micros() as u64;
+    if micros == 0 {
+        0
+    } else {
+        1 + micros.ilog2() as usize
+    }
+}
+
+/// Returns the inclusive lower bound of the [`histogram`] bucket.
+pub(crate) const fn bucket_low(index: usize) -> Duration {
+    if index == 0 {
+        Duration::ZERO
+    } else {
+        Duration::from_micros(1 << (index - 1))
+    }
+}
+
+/// Returns the exclusive upper bound of the [`histogram`] bucket.
+pub(crate) const fn bucket_high(index: usize) -> Duration {
+    Duration::from_micros(1 << index)
+}
+
+/// Result of [`mann_whitney_u`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MannWhitneyU {
+    /// The U statistic of the first sample.
+    pub u: f64,
+    /// The standardized U statistic of the normal approximation.
+    pub z: f64,
+    /// The two-sided p-value: the probability to observe a difference at least
+    /// this large if both samples come from the same distribution.
+    pub p_value: f64,
+}
+
+impl MannWhitneyU {
+    /// Returns whether the difference is significant at level `alpha`, e.g.,
+    /// `0.05`.
+    #[must_use]
+    pub fn is_significant(&self, alpha: f64) -> bool {
+        self.p_value < alpha
+    }
+}
+
+/// Performs a two-sided Mann-Whitney U test on the delays of two samples: is
+/// one of them systematically slower than the other?
+///
+/// The test makes no assumption about the distribution of the delays, which
+/// typically has a long tail. It uses the normal approximation with tie and
+/// continuity correction, which is reasonable for 20 or more measurements per
+/// sample. Returns `None` if one of the samples is empty.
+#[must_use]
+pub fn mann_whitney_u(a: &[Measurement], b: &[Measurement]) -> Option<MannWhitneyU> {
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+
+    // (delay, belongs to a)
+    let mut combined = a
+        .iter()
+        .map(|m| (m.wakeup_context.delay, true))
+        .chain(b.iter().map(|m| (m.wakeup_context.delay, false)))
+        .collect::<Vec<_>>();
+    combined.sort_unstable_by_key(|(delay, _)| *delay);
+
+    // Ties get the average of their ranks.
+    let mut rank_sum_a = 0.0;
+    let mut tie_sum = 0.0;
+    let mut i = 0;
+    while i < combined.len() {
+        let j = i + combined[i..]
+            .iter()
+            .take_while(|(delay, _)| *delay == combined[i].0)
+            .count();
+        let ties = (j - i) as f64;
+        let rank = (i + j + 1) as f64 / 2.0;
+        let ties_in_a = combined[i..j].iter().filter(|(_, in_a)| *in_a).count();
+        rank_sum_a += rank * ties_in_a as f64;
+        tie_sum += ties * ties * ties - ties;
+        i = j;
+    }
+
+    let n1 = a.len() as f64;
+    let n2 = b.len() as f64;
+    let n = n1 + n2;
+    let u = rank_sum_a - n1 * (n1 + 1.0) / 2.0;
+    let mean = n1 * n2 / 2.0;
+    let variance = n1 * n2 / 12.0 * ((n + 1.0) - tie_sum / (n * (n - 1.0)));
+
+    let z = if variance > 0.0 {
+        let diff = u - mean;
+        (diff.abs() - 0.5).max(0.0).copysign(diff) / variance.sqrt()
+    } else {
+        // All delays are equal.
+        0.0
+    };
+    let p_value = erfc(z.abs() / std::f64::consts::SQRT_2).min(1.0);
+
+    Some(MannWhitneyU { u, z, p_value })
+}
+
+/// Complementary error function with a fractional error below `1.2e-7`.
+///
+/// See "Numerical Recipes in C", 2nd edition, section 6.2.
+fn erfc(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.5 * x.abs());
+    let poly = -1.265_512_23
+        + t * (1.000_023_68
+            + t * (0.374_091_96
+                + t * (0.096_784_18
+                    + t * (-0.186_288_06
+                        + t * (0.278_868_07
+                            + t * (-1.135_203_98
+                                + t * (1.488_515_87 + t * (-0.822_152_23 + t * 0.170_872_77))))))));
+    let res = t * (-x * x + poly).exp();
+    if x >= 0.0 { res } else { 2.0 - res }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{WakeupContext, WakeupReason};
+    use assert2::check;
+    use std::time::Instant;
+
+    fn measurements(delays_us: &[u64]) -> Vec<Measurement> {
+        delays_us
+            .iter()
+            .map(|&us| Measurement {
+                wakeup_context: WakeupContext {
+                    reason: WakeupReason::Timeout,
+                    expected_duration: Duration::ZERO,
+                    actual_duration: Duration::from_micros(us),
+                    delay: Duration::from_micros(us),
+                    cpu_before: None,
+                    cpu_after: None,
+                    run_delay: None,
+                    wake_observed: None,
+                    returned: Instant::now(),
+                    recorded: Instant::now(),
+                },
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_percentile_delay() {
+        let data = measurements(&(1..=100).collect::<Vec<_>>());
+        check!(percentile_delay(&data, 50.0) == Duration::from_micros(50));
+        check!(percentile_delay(&data, 99.0) == Duration::from_micros(99));
+        check!(percentile_delay(&data, 100.0) == Duration::from_micros(100));
+        check!(percentile_delay(&data, 0.0) == Duration::from_micros(1));
+        check!(percentile_delay(&[], 99.0) == Duration::ZERO);
+    }
+
+    #[test]
+    fn test_summarize() {
+        check!(summarize(&[], 0).is_none());
+
+        let constant = measurements(&[10; 20]);
+        let summary = summarize(&constant, 0).unwrap();
+        let expected = Duration::from_micros(10);
+        check!(summary.count == 20);
+        check!(summary.mean.ci_low == expected);
+        check!(summary.mean.ci_high == expected);
+
+        let data = measurements(&(1..=200).collect::<Vec<_>>());
+        let summary = summarize(&data, 42).unwrap();
+        check!(summary == summarize(&data, 42).unwrap());
+        check!(summary.mean.ci_low < summary.mean.value);
+        check!(summary.mean.value < summary.mean.ci_high);
+        check!(summary.p99.ci_low <= summary.p99.value);
+        check!(summary.p99.value <= summary.p99.ci_high);
+    }
+
+    #[test]
+    fn test_stddev_and_jitter() {
+        let us = |values: &[u64]| {
+            values
+                .iter()
+                .copied()
+                .map(Duration::from_micros)
+                .collect::<Vec<_>>()
+        };
+        check!(stddev(&us(&[10])) == Duration::ZERO);
+        check!(jitter(&us(&[10])) == Duration::ZERO);
+        check!(stddev(&us(&[10; 5])) == Duration::ZERO);
+
+        // mean 5 µs, squared deviations 16 + 0 + 16, divided by 2
+        let stddev = stddev(&us(&[1, 5, 9])).as_secs_f64() * 1e6;
+        check!((stddev - 4.0).abs() < 1e-3);
+
+        // Same values, different order: alternating is jittery, sorted isn't.
+        check!(jitter(&us(&[1, 9, 1, 9])) == Duration::from_micros(8));
+        check!(jitter(&us(&[1, 1, 9, 9])) == Duration::from_micros(8) / 3);
+    }
+
+    #[test]
+    fn test_run_to_run() {
+        let summary = |mean_us, stddev_us, count| {
+            let estimate = Estimate {
+                value: Duration::from_micros(mean_us),
+                ci_low: Duration::from_micros(mean_us),
+                ci_high: Duration::from_micros(mean_us),
+            };
+            Summary {
+                count,
+                mean: estimate,
+                p50: estimate,
+                p99: estimate,
+                stddev: Duration::from_micros(stddev_us),
+                jitter: Duration::ZERO,
+            }
+        };
+        check!(run_to_run(&[]).is_none());
+        check!(run_to_run(&[summary(10, 2, 5)]).is_none());
+
+        // Means 10, 20, 30 µs; variances 9 and 36 µs² with 2 and 8 degrees
+        // of freedom.
+        let res = run_to_run(&[summary(10, 3, 3), summary(20, 6, 9), summary(30, 0, 1)]).unwrap();
+        check!(res.runs == 3);
+        check!(res.mean == Duration::from_micros(20));
+        check!(res.min == Duration::from_micros(10));
+        check!(res.max == Duration::from_micros(30));
+        let between = res.between.as_secs_f64() * 1e6;
+        check!((between - 10.0).abs() < 1e-3);
+        check!((res.between_percent() - 50.0).abs() < 1e-3);
+        let within = res.within.as_secs_f64() * 1e6;
+        check!((within - (306.0_f64 / 10.0).sqrt()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_outliers() {
+        let mut delays = (1..=100).map(Duration::from_micros).collect::<Vec<_>>();
+        delays.push(Duration::from_millis(10));
+        let res = outliers(&delays);
+        check!(res.count == 1);
+        check!(res.upper_fence < Duration::from_millis(10));
+    }
+
+    #[test]
+    fn test_histogram() {
+        check!(histogram(&[]).is_empty());
+
+        let delays = [3, 3, 5, 40].map(Duration::from_micros);
+        let buckets = histogram(&delays);
+        check!(buckets.first().unwrap().low == Duration::from_micros(2));
+        check!(buckets.last().unwrap().high == Duration::from_micros(64));
+        check!(buckets.iter().map(|b| b.count).collect::<Vec<_>>() == [2, 1, 0, 0, 1]);
+
+        let buckets = histogram(&[Duration::from_nanos(500)]);
+        check!(buckets.len() == 1);
+        check!(buckets[0].low == Duration::ZERO);
+        check!(buckets[0].high == Duration::from_micros(1));
+    }
+
+    #[test]
+    fn test_erfc() {
+        check!((erfc(0.0) - 1.0).abs() < 1e-6);
+        check!((erfc(1.0) - 0.157_299_2).abs() < 1e-6);
+        check!((erfc(-1.0) - 1.842_700_8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_mann_whitney_u_separated() {
+        let a = measurements(&[1, 2, 3, 4, 5]);
+        let b = measurements(&[6, 7, 8, 9, 10]);
+        let res = mann_whitney_u(&a, &b).unwrap();
+        check!(res.u == 0.0);
+        check!((res.p_value - 0.0122).abs() < 1e-3);
+        check!(res.is_significant(0.05));
+        check!(!res.is_significant(0.01));
+    }
+
+    #[test]
+    fn test_mann_whitney_u_same() {
+        let a = measurements(&[1, 5, 2, 8, 3, 9]);
+        let res = mann_whitney_u(&a, &a).unwrap();
+        check!(res.z == 0.0);
+        check!(!res.is_significant(0.05));
+
+        let constant = measurements(&[7, 7, 7]);
+        let res = mann_whitney_u(&constant, &constant).unwrap();
+        check!(res.p_value == 1.0);
+        check!(mann_whitney_u(&constant, &[]).is_none());
+    }
+}
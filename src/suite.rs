@@ -0,0 +1,247 @@
+//! Suites of runs described in a file. See [`Suite`].
+//!
+//! Comparison matrices, e.g., of backends, timeslices, interrupt
+//! percentages, placements, and loads, are hard to express and to reproduce
+//! with ad-hoc flags. A suite lists all runs with their parameters in one
+//! JSON file:
+//!
+//! ```json
+//! {
+//!   "seed": 42,
+//!   "runs": [
+//!     { "backend": "condvar", "timeslice": "10ms" },
+//!     { "backend": "futex", "timeslice": "10ms", "rounds": 500,
+//!       "interrupt_percent": 80, "placement": "smt-sibling",
+//!       "load": "memory", "load_threads": 2 }
+//!   ]
+//! }
+//! ```
+//!
+//! Only `backend` and `timeslice` are required. The other parameters default
+//! to the defaults of the `bench` command.
+
+use crate::TimeoutMode;
+use crate::backends::{self, Backend};
+use crate::load::Load;
+use crate::runner::RunConfig;
+use crate::scenario::{DEFAULT_INTERRUPT_PERCENT, Scenario};
+use crate::topology::Placement;
+use crate::units::parse_duration;
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+/// Default number of rounds of a run.
+const DEFAULT_ROUNDS: usize = 100;
+
+/// A suite file as written by the user, before validation.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SuiteFile {
+    seed: Option<u64>,
+    runs: Vec<RunEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RunEntry {
+    backend: String,
+    timeslice: String,
+    rounds: Option<usize>,
+    scenario: Option<String>,
+    interrupt_percent: Option<u8>,
+    timeout_mode: Option<String>,
+    seed: Option<u64>,
+    placement: Option<String>,
+    load: Option<String>,
+    load_threads: Option<usize>,
+}
+
+/// The runs of a suite, in the order of the file.
+#[derive(Debug, Clone)]
+pub struct Suite {
+    pub runs: Vec<SuiteRun>,
+}
+
+/// One run of a [`Suite`].
+#[derive(Debug, Clone)]
+pub struct SuiteRun {
+    pub backend: &'static Backend,
+    pub timeslice: Duration,
+    pub rounds: usize,
+    pub scenario: Scenario,
+    /// See [`RunConfig::interrupt_percent`].
+    pub interrupt_percent: u8,
+    pub timeout_mode: TimeoutMode,
+    /// The seed of the run, else the seed of the suite. `None` if the file
+    /// has neither.
+    pub seed: Option<u64>,
+    /// Where to pin the threads, if at all.
+    pub placement: Option<Placement>,
+    /// The background load during the run, if any.
+    pub load: Option<Load>,
+    /// Number of threads of the load. `None` for the default.
+    pub load_threads: Option<usize>,
+}
+
+impl SuiteRun {
+    /// Returns the config of the run, with `seed` if the run has none. The
+    /// CPUs of the placement are not set.
+    #[must_use]
+    pub fn config(&self, seed: u64) -> RunConfig {
+        RunConfig::new(self.rounds, self.timeslice)
+            .with_seed(self.seed.unwrap_or(seed))
+            .with_scenario(self.scenario)
+            .with_interrupt_percent(self.interrupt_percent)
+            .with_timeout_mode(self.timeout_mode)
+    }
+
+    fn from_entry(entry: RunEntry, seed: Option<u64>) -> Result<Self, String> {
+        let backend = backends::find(&entry.backend)
+            .ok_or_else(|| format!("unknown or not compiled-in backend `{}`", entry.backend))?;
+        let timeslice = parse_duration(&entry.timeslice).map_err(|e| e.to_string())?;
+        let rounds = entry.rounds.unwrap_or(DEFAULT_ROUNDS);
+        if rounds == 0 {
+            return Err("rounds must be positive".to_string());
+        }
+        if entry.load_threads.is_some() && entry.load.is_none() {
+            return Err("load_threads requires a load".to_string());
+        }
+        let interrupt_percent = entry.interrupt_percent.unwrap_or(DEFAULT_INTERRUPT_PERCENT);
+        if interrupt_percent > 100 {
+            return Err(format!(
+                "interrupt_percent {interrupt_percent} is above 100"
+            ));
+        }
+        Ok(Self {
+            backend,
+            timeslice,
+            rounds,
+            scenario: lookup("scenario", entry.scenario, Scenario::find)?.unwrap_or_default(),
+            interrupt_percent,
+            timeout_mode: lookup("timeout mode", entry.timeout_mode, TimeoutMode::find)?
+                .unwrap_or_default(),
+            seed: entry.seed.or(seed),
+            placement: lookup("placement", entry.placement, Placement::find)?,
+            load: lookup("load", entry.load, Load::find)?,
+            load_threads: entry.load_threads,
+        })
+    }
+}
+
+impl Suite {
+    /// Reads a suite from a JSON file.
+    pub fn read(path: &Path) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses and validates a suite in JSON.
+    pub fn parse(json: &str) -> io::Result<Self> {
+        let file = serde_json::from_str::<SuiteFile>(json)?;
+        if file.runs.is_empty() {
+            return Err(invalid("the suite has no runs".to_string()));
+        }
+        let runs = file
+            .runs
+            .into_iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                SuiteRun::from_entry(entry, file.seed)
+                    .map_err(|message| invalid(format!("run {}: {message}", i + 1)))
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Self { runs })
+    }
+}
+
+/// Looks up an optional name with `find`.
+fn lookup<T>(
+    kind: &str,
+    name: Option<String>,
+    find: impl Fn(&str) -> Option<T>,
+) -> Result<Option<T>, String> {
+    name.map(|name| find(&name).ok_or_else(|| format!("unknown {kind} `{name}`")))
+        .transpose()
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert2::check;
+
+    #[test]
+    fn test_parse() {
+        let suite = Suite::parse(
+            r#"{
+                "seed": 42,
+                "runs": [
+                    { "backend": "condvar", "timeslice": "10ms" },
+                    { "backend": "channel", "timeslice": "500us", "rounds": 20,
+                      "scenario": "spurious", "interrupt_percent": 80,
+                      "timeout_mode": "absolute", "seed": 7,
+                      "placement": "smt-sibling", "load": "memory",
+                      "load_threads": 2 }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        check!(suite.runs.len() == 2);
+        let run = &suite.runs[0];
+        check!(run.backend.name == "condvar");
+        check!(run.timeslice == Duration::from_millis(10));
+        check!(run.rounds == DEFAULT_ROUNDS);
+        check!(run.scenario == Scenario::Mixed);
+        check!(run.interrupt_percent == DEFAULT_INTERRUPT_PERCENT);
+        check!(run.seed == Some(42));
+        check!(run.placement.is_none());
+        check!(run.load.is_none());
+
+        let run = &suite.runs[1];
+        check!(run.backend.name == "channel");
+        check!(run.timeslice == Duration::from_micros(500));
+        check!(run.scenario == Scenario::Spurious);
+        check!(run.timeout_mode == TimeoutMode::Absolute);
+        check!(run.placement == Some(Placement::SmtSibling));
+        check!(run.load == Some(Load::Memory));
+        check!(run.load_threads == Some(2));
+        let config = run.config(1);
+        check!(config.rounds == 20);
+        check!(config.seed == 7);
+        check!(config.interrupt_percent == 80);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        let error = |json| Suite::parse(json).unwrap_err().to_string();
+        check!(error(r#"{ "runs": [] }"#) == "the suite has no runs");
+        check!(
+            error(
+                r#"{ "runs": [{ "backend": "condvar", "timeslice": "1ms" }, { "backend": "foo", "timeslice": "1ms" }] }"#
+            ) == "run 2: unknown or not compiled-in backend `foo`"
+        );
+        check!(
+            error(
+                r#"{ "runs": [{ "backend": "condvar", "timeslice": "1ms", "interrupt_percent": 101 }] }"#
+            ) == "run 1: interrupt_percent 101 is above 100"
+        );
+        check!(
+            error(
+                r#"{ "runs": [{ "backend": "condvar", "timeslice": "1ms", "placement": "far" }] }"#
+            ) == "run 1: unknown placement `far`"
+        );
+        // Typos are not silently ignored.
+        check!(
+            Suite::parse(
+                r#"{ "runs": [{ "backend": "condvar", "timeslice": "1ms", "round": 5 }] }"#
+            )
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,231 @@
+//! Automatic timeslice sweep over a logarithmic range. See [`SweepRange`].
+
+use crate::units::{ParseDurationError, format_duration, parse_duration};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter, Write};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// An inclusive range of timeslices, e.g. `100us..100ms`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SweepRange {
+    pub from: Duration,
+    pub to: Duration,
+}
+
+/// Error when parsing a [`SweepRange`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseSweepRangeError {
+    /// The range is not of the form `<from>..<to>`.
+    MissingSeparator,
+    /// One of the bounds is not a valid duration.
+    InvalidDuration(ParseDurationError),
+    /// The lower bound is zero or larger than the upper bound.
+    InvalidBounds,
+}
+
+impl Display for ParseSweepRangeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingSeparator => write!(f, "expected a range of the form <from>..<to>"),
+            Self::InvalidDuration(e) => write!(f, "{e}"),
+            Self::InvalidBounds => write!(f, "expected 0 < from <= to"),
+        }
+    }
+}
+
+impl Error for ParseSweepRangeError {}
+
+impl FromStr for SweepRange {
+    type Err = ParseSweepRangeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (from, to) = s
+            .split_once("..")
+            .ok_or(ParseSweepRangeError::MissingSeparator)?;
+        let from = parse_duration(from).map_err(ParseSweepRangeError::InvalidDuration)?;
+        let to = parse_duration(to).map_err(ParseSweepRangeError::InvalidDuration)?;
+        if from.is_zero() || from > to {
+            return Err(ParseSweepRangeError::InvalidBounds);
+        }
+        Ok(Self { from, to })
+    }
+}
+
+impl SweepRange {
+    /// Returns the timeslices of the range on a logarithmic scale with
+    /// `steps_per_decade` steps per power of ten.
+    ///
+    /// The values are rounded to two significant digits to keep them readable.
+    /// Both bounds are always part of the result.
+    #[must_use]
+    pub fn timeslices(&self, steps_per_decade: usize) -> Vec<Duration> {
+        let steps_per_decade = steps_per_decade.max(1) as f64;
+        let from = self.from.as_nanos() as f64;
+        let to = self.to.as_nanos() as f64;
+
+        let mut timeslices = vec![self.from];
+        for step in 1.. {
+            let value = from * 10_f64.powf(step as f64 / steps_per_decade);
+            // Tolerance against floating point errors for the upper bound.
+            if value >= to * 0.999 {
+                break;
+            }
+            let value = Duration::from_nanos(round_significant(value, 2) as u64);
+            if timeslices.last() != Some(&value) {
+                timeslices.push(value);
+            }
+        }
+        if timeslices.last() != Some(&self.to) {
+            timeslices.push(self.to);
+        }
+        timeslices
+    }
+}
+
+fn round_significant(value: f64, digits: i32) -> f64 {
+    let magnitude = 10_f64.powi(value.log10().floor() as i32 - digits + 1);
+    (value / magnitude).round() * magnitude
+}
+
+/// The result of a single backend for a single timeslice of the sweep.
+#[derive(Debug, Clone)]
+pub struct SweepPoint {
+    pub backend: &'static str,
+    pub timeslice: Duration,
+    pub interrupted_mean_delay: Duration,
+    pub timeouted_mean_delay: Duration,
+}
+
+/// Renders the sweep results as table with one row per timeslice and one
+/// column per backend.
+#[must_use]
+pub fn render_table(points: &[SweepPoint]) -> String {
+    let backends = backends_in_order(points);
+    let timeslices = timeslices_in_order(points);
+    let mut out = String::new();
+
+    let _ = write!(out, "| {:>10} | {:<11} |", "Timeslice", "Delay");
+    for backend in &backends {
+        let _ = write!(out, " {backend:>12} |");
+    }
+    out.push('\n');
+    let _ = write!(out, "|{:-<12}|{:-<13}|", "", "");
+    for _ in &backends {
+        let _ = write!(out, "{:-<14}|", "");
+    }
+    out.push('\n');
+
+    for timeslice in timeslices {
+        for (i, label) in ["interrupted", "timeouted"].into_iter().enumerate() {
+            // Only the first row of each timeslice carries its label.
+            let timeslice_label = if i == 0 {
+                format_duration(timeslice)
+            } else {
+                String::new()
+            };
+            let _ = write!(out, "| {timeslice_label:>10} | {label:<11} |");
+            for backend in &backends {
+                let delay = points
+                    .iter()
+                    .find(|p| p.backend == *backend && p.timeslice == timeslice)
+                    .map_or_else(
+                        || "-".to_string(),
+                        |p| {
+                            format_duration(if i == 0 {
+                                p.interrupted_mean_delay
+                            } else {
+                                p.timeouted_mean_delay
+                            })
+                        },
+                    );
+                let _ = write!(out, " {delay:>12} |");
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Renders the mean delay of interrupted sleeps over the timeslice as a
+/// horizontal bar chart, one block per backend.
+#[must_use]
+pub fn render_chart(points: &[SweepPoint]) -> String {
+    const WIDTH: usize = 50;
+    let max = points
+        .iter()
+        .map(|p| p.interrupted_mean_delay)
+        .max()
+        .unwrap_or_default()
+        .max(Duration::from_nanos(1));
+    let mut out = String::new();
+
+    for backend in backends_in_order(points) {
+        let _ = writeln!(out, "{backend}: interrupted mean delay");
+        for point in points.iter().filter(|p| p.backend == backend) {
+            let len = (point.interrupted_mean_delay.as_nanos() * WIDTH as u128
+                / max.as_nanos()) as usize;
+            let _ = writeln!(
+                out,
+                "  {:>8} |{:<WIDTH$}| {}",
+                format_duration(point.timeslice),
+                "#".repeat(len),
+                format_duration(point.interrupted_mean_delay),
+            );
+        }
+    }
+    out
+}
+
+fn backends_in_order(points: &[SweepPoint]) -> Vec<&'static str> {
+    let mut backends = Vec::new();
+    for point in points {
+        if !backends.contains(&point.backend) {
+            backends.push(point.backend);
+        }
+    }
+    backends
+}
+
+fn timeslices_in_order(points: &[SweepPoint]) -> Vec<Duration> {
+    let mut timeslices = points.iter().map(|p| p.timeslice).collect::<Vec<_>>();
+    timeslices.sort();
+    timeslices.dedup();
+    timeslices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert2::check;
+
+    #[test]
+    fn test_parse_range() {
+        check!(
+            "100us..100ms".parse()
+                == Ok(SweepRange {
+                    from: Duration::from_micros(100),
+                    to: Duration::from_millis(100),
+                })
+        );
+        check!("100us".parse::<SweepRange>() == Err(ParseSweepRangeError::MissingSeparator));
+        check!("1ms..100us".parse::<SweepRange>() == Err(ParseSweepRangeError::InvalidBounds));
+    }
+
+    #[test]
+    fn test_timeslices() {
+        let range = "100us..100ms".parse::<SweepRange>().unwrap();
+        let micros = range
+            .timeslices(3)
+            .iter()
+            .map(Duration::as_micros)
+            .collect::<Vec<_>>();
+        let expected = [
+            100, 220, 460, 1000, 2200, 4600, 10000, 22000, 46000, 100_000,
+        ];
+        check!(micros == expected);
+
+        let range = "1ms..1ms".parse::<SweepRange>().unwrap();
+        check!(range.timeslices(4) == [Duration::from_millis(1)]);
+    }
+}
@@ -1,5 +1,13 @@
+use crate::Handshake;
 use core::hint;
-use core::sync::atomic::{AtomicUsize, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use core::time::Duration;
+use std::thread;
+use std::time::Instant;
+
+/// The first thread checks its deadline only every this many spins, so that
+/// reading the clock doesn't delay noticing the second thread.
+const DEADLINE_CHECK_INTERVAL: u32 = 1024;
 
 /// A minimal busy-wait barrier for exactly **two threads**.
 ///
@@ -11,6 +19,10 @@ pub struct NoDelayBarrier {
     arrived: AtomicUsize,
     // arrival count for the current epoch (0, 1, 2, ...)
     epoch: AtomicUsize,
+    // whether the first thread yields instead of spinning, see `set_handshake`
+    yielding: AtomicBool,
+    // how long the first thread waits in nanoseconds, 0 means forever
+    timeout: AtomicU64,
 }
 
 impl NoDelayBarrier {
@@ -20,12 +32,30 @@ impl NoDelayBarrier {
         Self {
             epoch: AtomicUsize::new(0),
             arrived: AtomicUsize::new(0),
+            yielding: AtomicBool::new(false),
+            timeout: AtomicU64::new(0),
         }
     }
 
+    /// Configures how the threads wait for each other from now on, see
+    /// [`Handshake`]. Backends forward [`Waker::set_handshake`] to the
+    /// barrier of their ACK.
+    ///
+    /// [`Waker::set_handshake`]: crate::Waker::set_handshake
+    pub fn set_handshake(&self, handshake: Handshake) {
+        self.yielding.store(handshake.yielding, Ordering::Relaxed);
+        let timeout = handshake.timeout.map_or(0, |timeout| {
+            u64::try_from(timeout.as_nanos()).unwrap_or(u64::MAX).max(1)
+        });
+        self.timeout.store(timeout, Ordering::Relaxed);
+    }
+
     /// Wait until both threads have reached this point.
     /// - The *first* thread spins until the second arrives.
     /// - The *second* thread resets `arrived` and bumps `epoch` to release the first.
+    ///
+    /// With a [`Handshake::timeout`], the first thread gives up after it. Its
+    /// arrival stays counted, so a late second thread still passes.
     pub fn wait(&self) {
         // Remember which epoch we are trying to synchronize in
         let my_epoch = self.epoch.load(Ordering::Acquire);
@@ -39,8 +69,24 @@ impl NoDelayBarrier {
             self.epoch.fetch_add(1, Ordering::Release);
         } else {
             // spin until epoch changes (second thread has arrived)
+            let yielding = self.yielding.load(Ordering::Relaxed);
+            let timeout = self.timeout.load(Ordering::Relaxed);
+            let deadline = (timeout != 0)
+                .then(|| Instant::now().checked_add(Duration::from_nanos(timeout)))
+                .flatten();
+            let mut spins = 0_u32;
             while self.epoch.load(Ordering::Acquire) == my_epoch {
-                hint::spin_loop();
+                if yielding {
+                    thread::yield_now();
+                } else {
+                    hint::spin_loop();
+                }
+                spins = spins.wrapping_add(1);
+                if spins.is_multiple_of(DEADLINE_CHECK_INTERVAL)
+                    && deadline.is_some_and(|deadline| Instant::now() >= deadline)
+                {
+                    return;
+                }
             }
         }
     }
@@ -93,6 +139,46 @@ mod tests {
         t2.join().unwrap();
     }
 
+    #[test]
+    fn two_threads_meet_while_yielding() {
+        let barrier = Arc::new(NoDelayBarrier::new());
+        barrier.set_handshake(Handshake {
+            yielding: true,
+            ..Handshake::default()
+        });
+        let rounds = 1000;
+
+        let b1 = barrier.clone();
+        let t1 = thread::spawn(move || {
+            for _ in 0..rounds {
+                b1.wait();
+            }
+        });
+        for _ in 0..rounds {
+            barrier.wait();
+        }
+        t1.join().unwrap();
+    }
+
+    #[test]
+    fn waiter_gives_up_after_timeout() {
+        let barrier = NoDelayBarrier::new();
+        barrier.set_handshake(Handshake {
+            timeout: Some(Duration::from_millis(50)),
+            ..Handshake::default()
+        });
+
+        let start = Instant::now();
+        // Nobody else arrives.
+        barrier.wait();
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        // The late thread passes alone.
+        let start = Instant::now();
+        barrier.wait();
+        assert!(start.elapsed() < Duration::from_millis(50));
+    }
+
     #[test]
     fn unblock_releases_waiter() {
         let barrier = Arc::new(NoDelayBarrier::new());
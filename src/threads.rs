@@ -0,0 +1,164 @@
+//! Names and kernel ids of the threads of a run, for correlation with
+//! external tools. See [`ThreadIds`].
+//!
+//! Profilers and tracers such as `perf sched`, ftrace, or magic-trace show
+//! threads by their name and TID. During a run, the control thread is named
+//! `waker:<backend>` and the sleeper thread `sleeper:<backend>`, and their
+//! TIDs are recorded in the results.
+
+use crate::priority::current_tid;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use std::thread::JoinHandle;
+
+/// Maximum length of a thread name on Linux, without the terminating NUL.
+const MAX_NAME_LEN: usize = 15;
+
+/// The process and the kernel's ids of the threads of a run. The TIDs are
+/// `None` if not supported on this platform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThreadIds {
+    pub pid: u32,
+    /// The control thread, which issues the wake-ups.
+    pub waker: Option<i32>,
+    pub sleeper: Option<i32>,
+}
+
+impl ThreadIds {
+    /// Returns the ids of the calling thread as waker and of `sleeper`.
+    #[must_use]
+    pub fn with_sleeper(sleeper: Option<i32>) -> Self {
+        Self {
+            pid: std::process::id(),
+            waker: current_tid(),
+            sleeper,
+        }
+    }
+}
+
+impl Display for ThreadIds {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let tid = |tid: Option<i32>| tid.map_or_else(|| "-".to_string(), |tid| tid.to_string());
+        write!(
+            f,
+            "PID {}, waker TID {}, sleeper TID {}",
+            self.pid,
+            tid(self.waker),
+            tid(self.sleeper)
+        )
+    }
+}
+
+/// Returns the name of a thread of `role` during a run of `backend`,
+/// truncated to what Linux supports, e.g., `sleeper:parking`.
+#[must_use]
+pub fn name(role: &str, backend: &str) -> String {
+    let mut name = format!("{role}:{backend}");
+    while name.len() > MAX_NAME_LEN {
+        name.pop();
+    }
+    name
+}
+
+/// Restores the previous name of the calling thread when dropped. See
+/// [`rename_current`].
+#[derive(Debug)]
+pub struct Renamed {
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    previous: Option<Vec<u8>>,
+}
+
+impl Drop for Renamed {
+    fn drop(&mut self) {
+        #[cfg(target_os = "linux")]
+        if let Some(previous) = self.previous.take() {
+            let _ = set_current_name(&previous);
+        }
+    }
+}
+
+/// Names the calling thread `name` until the returned guard is dropped.
+/// Thread names are informational, so this does nothing on other platforms
+/// than Linux or if it fails.
+#[must_use]
+pub fn rename_current(name: &str) -> Renamed {
+    #[cfg(target_os = "linux")]
+    {
+        let mut previous = [0_u8; MAX_NAME_LEN + 1];
+        // SAFETY: PR_GET_NAME writes at most 16 bytes to the buffer.
+        let ret = unsafe { libc::prctl(libc::PR_GET_NAME, previous.as_mut_ptr()) };
+        if ret != 0 || set_current_name(name.as_bytes()).is_none() {
+            return Renamed { previous: None };
+        }
+        let len = previous
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(previous.len());
+        Renamed {
+            previous: Some(previous[..len].to_vec()),
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = name;
+        Renamed { previous: None }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_current_name(name: &[u8]) -> Option<()> {
+    let name = std::ffi::CString::new(name).ok()?;
+    // SAFETY: The name is NUL-terminated; longer names are truncated.
+    let ret = unsafe { libc::prctl(libc::PR_SET_NAME, name.as_ptr()) };
+    (ret == 0).then_some(())
+}
+
+/// Names another thread `name` for the rest of its life. Like
+/// [`rename_current`], this is best effort.
+pub fn rename<T>(thread: &JoinHandle<T>, name: &str) {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::thread::JoinHandleExt;
+
+        let Ok(name) = std::ffi::CString::new(name) else {
+            return;
+        };
+        // SAFETY: The thread is alive as long as the handle exists, and the
+        // name is NUL-terminated and at most 16 bytes long.
+        unsafe { libc::pthread_setname_np(thread.as_pthread_t(), name.as_ptr()) };
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (thread, name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert2::check;
+    use std::thread;
+
+    #[test]
+    fn test_name() {
+        check!(name("waker", "futex") == "waker:futex");
+        check!(name("sleeper", "parking_lot") == "sleeper:parking");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_rename_current() {
+        let comm = || std::fs::read_to_string("/proc/thread-self/comm").unwrap();
+        thread::Builder::new()
+            .name("before".to_string())
+            .spawn(move || {
+                let renamed = rename_current("waker:futex");
+                check!(comm() == "waker:futex\n");
+                drop(renamed);
+                check!(comm() == "before\n");
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+}
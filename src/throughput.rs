@@ -0,0 +1,124 @@
+//! Sustained wake-up throughput in a closed loop. See [`measure`].
+//!
+//! The other benchmarks leave the sleeper idle between the wake-ups, which
+//! shows the latency of a single wake-up. Here, the sleeper goes back to
+//! sleep immediately and the waker wakes it again as soon as the previous
+//! wake-up was acknowledged. Backends with a low one-shot latency may still
+//! sustain few wake-ups per second, e.g., because of several syscalls per
+//! wake-up.
+
+use crate::stats::{mean, percentile};
+use crate::{Sleeper, Waker, WakeupReason, measure as measure_timing};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Timeout of each sleep. In the closed loop, it only expires if a wake-up
+/// is lost or after the end of the measurement.
+const SLEEP_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Result of [`measure`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Throughput {
+    /// Number of wake-ups issued.
+    pub wakes: usize,
+    /// Duration of the measurement.
+    pub elapsed: Duration,
+    /// Delays of the wake-ups that found the sleeper asleep, sorted. The
+    /// others were issued before the sleeper went back to sleep and have no
+    /// meaningful delay.
+    pub delays: Vec<Duration>,
+    /// Number of sleeps that timed out during the measurement.
+    pub timeouts: usize,
+}
+
+impl Throughput {
+    /// Returns the wake-ups per second.
+    #[must_use]
+    pub fn per_second(&self) -> f64 {
+        self.wakes as f64 / self.elapsed.as_secs_f64()
+    }
+
+    /// Returns the `p`-th percentile (`0.0..=100.0`) of the delays.
+    #[must_use]
+    pub fn delay(&self, p: f64) -> Duration {
+        percentile(&self.delays, p)
+    }
+
+    #[must_use]
+    pub fn mean_delay(&self) -> Duration {
+        mean(&self.delays)
+    }
+}
+
+/// Wakes the sleeper in a closed loop for `duration`.
+///
+/// # Panics
+///
+/// Panics if the sleeper thread panics.
+pub fn measure<S: Sleeper<Instant> + Send + 'static>(
+    sleeper: S,
+    waker: &impl Waker,
+    duration: Duration,
+) -> Throughput {
+    let should_stop = Arc::new(AtomicBool::new(false));
+    let handle = {
+        let should_stop = should_stop.clone();
+        thread::spawn(move || {
+            let mut delays = Vec::new();
+            let mut timeouts = 0;
+            while !should_stop.load(Ordering::Relaxed) {
+                let begin = Instant::now();
+                let reason = sleeper.sleep_interruptible(SLEEP_TIMEOUT);
+                let end = Instant::now();
+                if reason == WakeupReason::Timeout {
+                    // The last sleep times out after the end.
+                    if !should_stop.load(Ordering::Relaxed) {
+                        timeouts += 1;
+                    }
+                } else if let Some(timing) = measure_timing(&reason, begin, end, SLEEP_TIMEOUT) {
+                    delays.push(timing.delay);
+                }
+            }
+            (delays, timeouts)
+        })
+    };
+
+    let begin = Instant::now();
+    let mut wakes = 0;
+    while begin.elapsed() < duration {
+        waker.wake();
+        wakes += 1;
+    }
+    let elapsed = begin.elapsed();
+    should_stop.store(true, Ordering::Relaxed);
+
+    let (mut delays, timeouts) = handle.join().expect("sleeper thread should not panic");
+    delays.sort_unstable();
+    Throughput {
+        wakes,
+        elapsed,
+        delays,
+        timeouts,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::condvar;
+    use assert2::check;
+
+    #[test]
+    fn test_measure() {
+        let (sleeper, waker) = condvar::new_pair();
+        let throughput = measure(sleeper, &waker, Duration::from_millis(50));
+
+        check!(throughput.wakes > 0);
+        check!(throughput.elapsed >= Duration::from_millis(50));
+        check!(throughput.delays.len() <= throughput.wakes);
+        check!(throughput.delays.is_sorted());
+        check!(throughput.per_second() > 0.0);
+    }
+}
@@ -0,0 +1,360 @@
+//! The CPU topology of the machine and the placement of the waker and the
+//! sleeper on it. See [`Topology`] and [`Placement`].
+//!
+//! Where the two threads run matters: a wake-up on the same core is cheap, a
+//! wake-up across a last-level cache (LLC) or a NUMA node moves cache lines
+//! and inter-processor interrupts over a longer distance.
+
+use crate::cpu;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::path::Path;
+
+/// The CPU topology in sysfs.
+const SYSFS_CPU: &str = "/sys/devices/system/cpu";
+
+/// A logical CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cpu {
+    pub id: u32,
+    pub package: u32,
+    /// The physical core within the package. SMT siblings share it.
+    pub core: u32,
+    /// The lowest id of the CPUs sharing the L3 cache, if known.
+    pub llc: Option<u32>,
+    /// The NUMA node.
+    pub node: u32,
+}
+
+impl Cpu {
+    fn same_core(&self, other: &Self) -> bool {
+        (self.package, self.core) == (other.package, other.core)
+    }
+
+    fn same_llc(&self, other: &Self) -> Option<bool> {
+        Some(self.llc? == other.llc?)
+    }
+}
+
+/// The CPUs the process may run on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Topology {
+    cpus: Vec<Cpu>,
+}
+
+impl Topology {
+    /// Reads the topology of the online CPUs from sysfs and drops those the
+    /// process may not run on. Returns `None` if it is not available, e.g.,
+    /// on other platforms than Linux.
+    #[must_use]
+    pub fn read() -> Option<Self> {
+        let mut topology = Self::read_at(Path::new(SYSFS_CPU))?;
+        let allowed = cpu::allowed()?;
+        topology.cpus.retain(|cpu| allowed.contains(&cpu.id));
+        (!topology.cpus.is_empty()).then_some(topology)
+    }
+
+    fn read_at(root: &Path) -> Option<Self> {
+        let online = fs::read_to_string(root.join("online")).ok()?;
+        let cpus = parse_cpu_list(&online)?
+            .into_iter()
+            .map(|id| read_cpu(root, id))
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self { cpus })
+    }
+
+    #[must_use]
+    pub fn cpus(&self) -> &[Cpu] {
+        &self.cpus
+    }
+
+    /// Returns the number of physical cores.
+    #[must_use]
+    pub fn cores(&self) -> usize {
+        self.count_distinct(|cpu| Some((cpu.package, cpu.core)))
+    }
+
+    /// Returns the number of L3 caches, zero if unknown.
+    #[must_use]
+    pub fn llcs(&self) -> usize {
+        self.count_distinct(|cpu| cpu.llc)
+    }
+
+    /// Returns the number of NUMA nodes.
+    #[must_use]
+    pub fn nodes(&self) -> usize {
+        self.count_distinct(|cpu| Some(cpu.node))
+    }
+
+    fn count_distinct<T: Ord>(&self, key: impl Fn(&Cpu) -> Option<T>) -> usize {
+        let mut keys = self.cpus.iter().filter_map(key).collect::<Vec<_>>();
+        keys.sort_unstable();
+        keys.dedup();
+        keys.len()
+    }
+
+    /// Picks two CPUs for the placement, or `None` if the machine has no such
+    /// pair.
+    ///
+    /// CPU 0 is only picked if there is no other choice, as it typically
+    /// handles more interrupts and housekeeping work than the others.
+    #[must_use]
+    pub fn pick(&self, placement: Placement) -> Option<CpuPair> {
+        let mut candidates = self.cpus.clone();
+        candidates.sort_by_key(|cpu| (cpu.id == 0, cpu.id));
+        if placement == Placement::SameCpu {
+            let cpu = candidates.first()?.id;
+            return Some(CpuPair {
+                waker: cpu,
+                sleeper: cpu,
+            });
+        }
+        candidates.iter().find_map(|waker| {
+            candidates
+                .iter()
+                .find(|sleeper| sleeper.id != waker.id && placement.matches(waker, sleeper))
+                .map(|sleeper| CpuPair {
+                    waker: waker.id,
+                    sleeper: sleeper.id,
+                })
+        })
+    }
+}
+
+fn read_cpu(root: &Path, id: u32) -> Option<Cpu> {
+    let dir = root.join(format!("cpu{id}"));
+    let read_number = |path: &str| -> Option<u32> {
+        fs::read_to_string(dir.join(path)).ok()?.trim().parse().ok()
+    };
+    Some(Cpu {
+        id,
+        package: read_number("topology/physical_package_id")?,
+        core: read_number("topology/core_id")?,
+        llc: read_llc(&dir),
+        // Without NUMA support, there are no node links.
+        node: fs::read_dir(&dir)
+            .ok()?
+            .filter_map(Result::ok)
+            .find_map(|entry| {
+                entry
+                    .file_name()
+                    .to_str()?
+                    .strip_prefix("node")?
+                    .parse()
+                    .ok()
+            })
+            .unwrap_or(0),
+    })
+}
+
+/// Returns the lowest id of the CPUs sharing the L3 cache of the CPU.
+fn read_llc(cpu_dir: &Path) -> Option<u32> {
+    fs::read_dir(cpu_dir.join("cache"))
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .find(|index| fs::read_to_string(index.join("level")).is_ok_and(|l| l.trim() == "3"))
+        .and_then(|index| fs::read_to_string(index.join("shared_cpu_list")).ok())
+        .and_then(|list| parse_cpu_list(&list)?.into_iter().min())
+}
+
+/// Parses a CPU list of the kernel, e.g., `0-3,8,10-11`.
+fn parse_cpu_list(list: &str) -> Option<Vec<u32>> {
+    let mut cpus = Vec::new();
+    for range in list.trim().split(',').filter(|range| !range.is_empty()) {
+        match range.split_once('-') {
+            Some((first, last)) => cpus.extend(first.parse::<u32>().ok()?..=last.parse().ok()?),
+            None => cpus.push(range.parse().ok()?),
+        }
+    }
+    Some(cpus)
+}
+
+/// Where the waker and the sleeper run relative to each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    /// Both threads on the same logical CPU. The threads yield the CPU
+    /// instead of spinning while they wait for each other.
+    SameCpu,
+    /// On the two hardware threads of one core.
+    SmtSibling,
+    /// On different cores that share the L3 cache.
+    SameLlc,
+    /// On different L3 caches of one NUMA node.
+    CrossLlc,
+    /// On different NUMA nodes.
+    CrossNode,
+}
+
+impl Placement {
+    /// All placements.
+    pub const ALL: &[Self] = &[
+        Self::SameCpu,
+        Self::SmtSibling,
+        Self::SameLlc,
+        Self::CrossLlc,
+        Self::CrossNode,
+    ];
+
+    /// Returns the name, as used on the command line.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::SameCpu => "same-cpu",
+            Self::SmtSibling => "smt-sibling",
+            Self::SameLlc => "same-llc",
+            Self::CrossLlc => "cross-llc",
+            Self::CrossNode => "cross-node",
+        }
+    }
+
+    /// Returns a short human-readable description.
+    #[must_use]
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::SameCpu => "waker and sleeper on the same logical CPU",
+            Self::SmtSibling => "on the two hardware threads of one core",
+            Self::SameLlc => "on different cores that share the L3 cache",
+            Self::CrossLlc => "on different L3 caches of one NUMA node",
+            Self::CrossNode => "on different NUMA nodes",
+        }
+    }
+
+    /// Looks up a placement by its [name](Self::name).
+    #[must_use]
+    pub fn find(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|p| p.name() == name)
+    }
+
+    /// Whether two different CPUs have this placement.
+    fn matches(self, a: &Cpu, b: &Cpu) -> bool {
+        match self {
+            Self::SameCpu => a.id == b.id,
+            Self::SmtSibling => a.same_core(b),
+            Self::SameLlc => !a.same_core(b) && a.same_llc(b) == Some(true),
+            Self::CrossLlc => a.node == b.node && a.same_llc(b) == Some(false),
+            Self::CrossNode => a.node != b.node,
+        }
+    }
+}
+
+/// The CPUs the waker and the sleeper are pinned to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CpuPair {
+    pub waker: u32,
+    pub sleeper: u32,
+}
+
+impl Display for CpuPair {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "waker on CPU {}, sleeper on CPU {}",
+            self.waker, self.sleeper
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert2::check;
+
+    #[test]
+    fn test_parse_cpu_list() {
+        check!(parse_cpu_list("0-3,8,10-11\n") == Some(vec![0, 1, 2, 3, 8, 10, 11]));
+        check!(parse_cpu_list("5") == Some(vec![5]));
+        check!(parse_cpu_list("") == Some(vec![]));
+        check!(parse_cpu_list("a-b").is_none());
+    }
+
+    #[test]
+    fn test_find() {
+        for placement in Placement::ALL {
+            check!(Placement::find(placement.name()) == Some(*placement));
+        }
+        check!(Placement::find("unknown").is_none());
+    }
+
+    /// Two NUMA nodes with two L3 caches each; each L3 cache has one core
+    /// with two hardware threads. CPU n and n + 8 are SMT siblings.
+    fn write_fake_sysfs(root: &Path) {
+        fs::create_dir_all(root).unwrap();
+        fs::write(root.join("online"), "0-15\n").unwrap();
+        for id in 0..16_u32 {
+            let core = id % 8;
+            let llc = core;
+            let node = core / 4;
+            let dir = root.join(format!("cpu{id}"));
+            fs::create_dir_all(dir.join("topology")).unwrap();
+            fs::create_dir_all(dir.join(format!("node{node}"))).unwrap();
+            fs::write(dir.join("topology/physical_package_id"), "0\n").unwrap();
+            fs::write(dir.join("topology/core_id"), format!("{core}\n")).unwrap();
+            for (index, level, shared) in [
+                ("index0", "1", format!("{id}")),
+                ("index3", "3", format!("{llc},{}", llc + 8)),
+            ] {
+                let cache = dir.join("cache").join(index);
+                fs::create_dir_all(&cache).unwrap();
+                fs::write(cache.join("level"), format!("{level}\n")).unwrap();
+                fs::write(cache.join("shared_cpu_list"), format!("{shared}\n")).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_read_at_and_pick() {
+        let root = std::env::temp_dir().join(format!("topology-{}", std::process::id()));
+        write_fake_sysfs(&root);
+        let topology = Topology::read_at(&root).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        check!(topology.cpus().len() == 16);
+        check!(
+            topology.cpus()[9]
+                == Cpu {
+                    id: 9,
+                    package: 0,
+                    core: 1,
+                    llc: Some(1),
+                    node: 0,
+                }
+        );
+        check!(topology.cores() == 8);
+        check!(topology.llcs() == 8);
+        check!(topology.nodes() == 2);
+
+        let pick = |placement| topology.pick(placement).unwrap();
+        check!(
+            pick(Placement::SameCpu)
+                == CpuPair {
+                    waker: 1,
+                    sleeper: 1
+                }
+        );
+        check!(
+            pick(Placement::SmtSibling)
+                == CpuPair {
+                    waker: 1,
+                    sleeper: 9
+                }
+        );
+        // Each L3 cache has only one core.
+        check!(topology.pick(Placement::SameLlc).is_none());
+        check!(
+            pick(Placement::CrossLlc)
+                == CpuPair {
+                    waker: 1,
+                    sleeper: 2
+                }
+        );
+        check!(
+            pick(Placement::CrossNode)
+                == CpuPair {
+                    waker: 1,
+                    sleeper: 4
+                }
+        );
+    }
+}
@@ -0,0 +1,35 @@
+//! Optional instrumentation with [`tracing`](https://docs.rs/tracing), enabled
+//! by the `trace` feature.
+//!
+//! The runner, the [`SleeperThread`], and the backends emit trace-level spans
+//! and events for each round: sleep start, wake issued, wake observed, and the
+//! ACK of the wake-up. A timeline of individual slow rounds can then be
+//! inspected, e.g., with Perfetto (see `bench --trace`).
+//!
+//! Without the feature, the macros expand to nothing and the measurements are
+//! not influenced at all.
+//!
+//! [`SleeperThread`]: crate::sleeper_thread::SleeperThread
+
+/// Emits a trace-level event. Takes the same arguments as [`tracing::trace!`].
+///
+/// [`tracing::trace!`]: https://docs.rs/tracing/latest/tracing/macro.trace.html
+macro_rules! event {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "trace")]
+        ::tracing::trace!($($arg)*);
+    };
+}
+
+/// Enters a trace-level span until the end of the current scope. Takes the
+/// same arguments as [`tracing::trace_span!`].
+///
+/// [`tracing::trace_span!`]: https://docs.rs/tracing/latest/tracing/macro.trace_span.html
+macro_rules! span {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "trace")]
+        let _span = ::tracing::trace_span!($($arg)*).entered();
+    };
+}
+
+pub(crate) use {event, span};
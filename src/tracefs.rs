@@ -0,0 +1,151 @@
+//! Markers in the kernel trace, for correlation with external tools, see
+//! [`TraceMarker`], and captures of the scheduler events, see
+//! [`SchedTrace`].
+//!
+//! Writes to the ftrace `trace_marker` file show up in `trace-cmd` and
+//! ftrace captures and as `ftrace:print` events in `perf`, interleaved with
+//! the scheduler events of the same moment. Linux only; typically requires
+//! root.
+
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Mount points of the tracefs: the current one and the one of older kernels.
+const TRACEFS_DIRS: [&str; 2] = ["/sys/kernel/tracing", "/sys/kernel/debug/tracing"];
+
+/// The events recorded by [`SchedTrace`].
+const SCHED_EVENTS: [&str; 3] = [
+    "sched/sched_switch",
+    "sched/sched_wakeup",
+    "sched/sched_migrate_task",
+];
+
+/// Size of the ring buffer of each CPU in KiB. It bounds the capture window
+/// of [`SchedTrace`], to a few hundred milliseconds on a busy CPU.
+const BUFFER_SIZE_KB: u32 = 4096;
+
+/// Returns the mount point of the tracefs, if any.
+#[must_use]
+pub fn dir() -> Option<PathBuf> {
+    TRACEFS_DIRS
+        .iter()
+        .map(Path::new)
+        .find(|dir| dir.join("trace_marker").exists())
+        .map(Path::to_path_buf)
+}
+
+/// The open `trace_marker` file.
+#[derive(Debug)]
+pub struct TraceMarker {
+    file: File,
+}
+
+impl TraceMarker {
+    /// Opens the `trace_marker` file.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the tracefs is not mounted or not writable.
+    pub fn open() -> io::Result<Self> {
+        let dir = dir().ok_or_else(not_mounted)?;
+        let file = OpenOptions::new()
+            .write(true)
+            .open(dir.join("trace_marker"))?;
+        Ok(Self { file })
+    }
+
+    /// Writes a marker. Each marker is a single write, so it can't be torn
+    /// apart. Errors are ignored, as markers are informational.
+    pub fn mark(&self, args: fmt::Arguments<'_>) {
+        let message = format!("benchmark-interruptible-sleep: {args}\n");
+        let _ = (&self.file).write(message.as_bytes());
+    }
+}
+
+/// Records the scheduler events of all CPUs in the ftrace ring buffer until
+/// dropped.
+///
+/// The buffer keeps the most recent events; [`SchedTrace::save`] copies
+/// them. The previous settings of the tracefs are restored when dropped.
+#[derive(Debug)]
+pub struct SchedTrace {
+    dir: PathBuf,
+    /// The changed files of the tracefs and their previous values.
+    restore: Vec<(PathBuf, String)>,
+}
+
+impl SchedTrace {
+    /// Enables the scheduler events and clears the ring buffer.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the tracefs is not mounted or not writable, e.g., without
+    /// root.
+    pub fn start() -> io::Result<Self> {
+        let dir = dir().ok_or_else(not_mounted)?;
+        let mut trace = Self {
+            dir,
+            restore: Vec::new(),
+        };
+        for event in SCHED_EVENTS {
+            trace.set(&format!("events/{event}/enable"), "1")?;
+        }
+        trace.set("buffer_size_kb", &BUFFER_SIZE_KB.to_string())?;
+        trace.set("tracing_on", "1")?;
+        trace.clear()?;
+        Ok(trace)
+    }
+
+    /// Copies the ring buffer to `path` in the text format of ftrace and
+    /// clears it, so the next capture doesn't repeat the events.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the buffer can't be read or `path` can't be written.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut trace = File::open(self.dir.join("trace"))?;
+        let mut file = File::create(path)?;
+        io::copy(&mut trace, &mut file)?;
+        self.clear()
+    }
+
+    /// Discards the events in the ring buffer.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the buffer is not writable.
+    pub fn clear(&self) -> io::Result<()> {
+        OpenOptions::new()
+            .write(true)
+            .truncate(true)
+            .open(self.dir.join("trace"))
+            .map(drop)
+    }
+
+    fn set(&mut self, file: &str, value: &str) -> io::Result<()> {
+        let path = self.dir.join(file);
+        let previous = fs::read_to_string(&path)?;
+        fs::write(&path, value)?;
+        // E.g., `7 (expanded: 1408)` for a buffer that was never used.
+        let previous = previous.split_whitespace().next().unwrap_or_default();
+        self.restore.push((path, previous.to_string()));
+        Ok(())
+    }
+}
+
+impl Drop for SchedTrace {
+    fn drop(&mut self) {
+        for (path, previous) in self.restore.drain(..).rev() {
+            let _ = fs::write(path, previous);
+        }
+    }
+}
+
+fn not_mounted() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        "the tracefs is not mounted at /sys/kernel/tracing",
+    )
+}
@@ -0,0 +1,125 @@
+//! Parsing and formatting of human-readable durations, such as `100us` or
+//! `2.5ms`.
+
+use serde::{Deserialize, Deserializer, Serializer};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
+
+/// Error of [`parse_duration`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseDurationError(String);
+
+impl Display for ParseDurationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid duration '{}', expected a number followed by ns, us, µs, ms, or s",
+            self.0
+        )
+    }
+}
+
+impl Error for ParseDurationError {}
+
+/// Parses a duration consisting of a (fractional) number and a unit.
+///
+/// Supported units: `ns`, `us`, `µs`, `ms`, `s`.
+pub fn parse_duration(input: &str) -> Result<Duration, ParseDurationError> {
+    let err = || ParseDurationError(input.to_string());
+    let input_trimmed = input.trim();
+    let unit_begin = input_trimmed
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .ok_or_else(err)?;
+    let (number, unit) = input_trimmed.split_at(unit_begin);
+    let number = number.parse::<f64>().map_err(|_| err())?;
+    let nanos_per_unit = match unit.trim() {
+        "ns" => 1.0,
+        "us" | "µs" => 1e3,
+        "ms" => 1e6,
+        "s" => 1e9,
+        _ => return Err(err()),
+    };
+    Ok(Duration::from_nanos((number * nanos_per_unit).round() as u64))
+}
+
+/// Formats a duration with the largest unit that keeps the value >= 1.
+///
+/// The output is the inverse of [`parse_duration`], e.g. `250µs` or `2.5ms`.
+#[must_use]
+pub fn format_duration(duration: Duration) -> String {
+    let nanos = duration.as_nanos();
+    let (value, unit) = if nanos < 1_000 {
+        return format!("{nanos}ns");
+    } else if nanos < 1_000_000 {
+        (nanos as f64 / 1e3, "µs")
+    } else if nanos < 1_000_000_000 {
+        (nanos as f64 / 1e6, "ms")
+    } else {
+        (nanos as f64 / 1e9, "s")
+    };
+    let value = format!("{value:.2}");
+    let value = value.trim_end_matches('0').trim_end_matches('.');
+    format!("{value}{unit}")
+}
+
+/// Serializes a duration as integer nanoseconds, for `#[serde(serialize_with)]`.
+pub(crate) fn serialize_nanos<S: Serializer>(
+    duration: &Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX))
+}
+
+/// Deserializes a duration from integer nanoseconds, for
+/// `#[serde(deserialize_with)]`.
+pub(crate) fn deserialize_nanos<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Duration, D::Error> {
+    u64::deserialize(deserializer).map(Duration::from_nanos)
+}
+
+/// Like [`serialize_nanos`] but `None` is serialized as `null`.
+pub(crate) fn serialize_optional_nanos<S: Serializer>(
+    duration: &Option<Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match duration {
+        Some(duration) => serialize_nanos(duration, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Like [`deserialize_nanos`] but `null` is deserialized as `None`.
+pub(crate) fn deserialize_optional_nanos<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    Option::<u64>::deserialize(deserializer).map(|nanos| nanos.map(Duration::from_nanos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert2::check;
+
+    #[test]
+    fn test_parse_duration() {
+        check!(parse_duration("100us") == Ok(Duration::from_micros(100)));
+        check!(parse_duration("100µs") == Ok(Duration::from_micros(100)));
+        check!(parse_duration("2.5ms") == Ok(Duration::from_micros(2500)));
+        check!(parse_duration("1s") == Ok(Duration::from_secs(1)));
+        check!(parse_duration("42ns") == Ok(Duration::from_nanos(42)));
+        check!(parse_duration("100").is_err());
+        check!(parse_duration("ms").is_err());
+        check!(parse_duration("10 parsecs").is_err());
+    }
+
+    #[test]
+    fn test_format_duration() {
+        check!(format_duration(Duration::from_nanos(42)) == "42ns");
+        check!(format_duration(Duration::from_micros(100)) == "100µs");
+        check!(format_duration(Duration::from_micros(2500)) == "2.5ms");
+        check!(format_duration(Duration::from_millis(100)) == "100ms");
+        check!(format_duration(Duration::from_secs(2)) == "2s");
+    }
+}
@@ -0,0 +1,239 @@
+//! Deterministic virtual time for tests.
+//!
+//! Provides the [`VirtualClock`] and a mock [`Sleeper`] and [`Waker`] pair
+//! whose timeouts are driven by that clock. Time only advances when the
+//! control thread sleeps on the [`VirtualClock`]. Hence, every measurement
+//! taken with it is exact and a run of the harness finishes instantly,
+//! independent of the load of the machine.
+
+use crate::clock::Clock;
+use crate::synchronization::NoDelayBarrier;
+use crate::trace;
+use crate::{Handshake, Sleeper, Waker, WakeupReason};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Default)]
+struct ClockState {
+    // virtual time that passed since `ClockShared::base`
+    elapsed: Duration,
+    // number of virtual sleepers currently blocked
+    sleeping: usize,
+}
+
+#[derive(Debug)]
+struct ClockShared {
+    base: Instant,
+    state: Mutex<ClockState>,
+    // notified whenever the time advances, a sleeper blocks, or a wake-up
+    // is issued
+    condvar: Condvar,
+}
+
+/// A [`Clock`] whose time only advances when someone sleeps on it.
+///
+/// [`Clock::sleep_until`] first waits until at least one [`VirtualSleeper`] of
+/// this clock is blocked and then advances the time instantly. This guarantees
+/// that the time never advances under a sleeper that has not yet started to
+/// wait, which would otherwise be a race between the control and the sleeper
+/// thread.
+///
+/// Cloning the clock creates a new handle to the same virtual time.
+#[derive(Debug, Clone)]
+pub struct VirtualClock {
+    shared: Arc<ClockShared>,
+}
+
+impl VirtualClock {
+    /// Creates a new virtual clock starting at the current point in time.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            shared: Arc::new(ClockShared {
+                base: Instant::now(),
+                state: Mutex::new(ClockState::default()),
+                condvar: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Advances the virtual time immediately, without waiting for a sleeper.
+    pub fn advance(&self, duration: Duration) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.elapsed += duration;
+        drop(state);
+        self.shared.condvar.notify_all();
+    }
+}
+
+impl Default for VirtualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Instant {
+        let state = self.shared.state.lock().unwrap();
+        self.shared.base + state.elapsed
+    }
+
+    fn sleep_until(&self, deadline: Instant) {
+        let mut state = self.shared.state.lock().unwrap();
+        while state.sleeping == 0 {
+            state = self.shared.condvar.wait(state).unwrap();
+        }
+        state.elapsed = state
+            .elapsed
+            .max(deadline.saturating_duration_since(self.shared.base));
+        drop(state);
+        self.shared.condvar.notify_all();
+    }
+}
+
+#[derive(Debug)]
+pub struct VirtualSleeper {
+    clock: VirtualClock,
+    wake_call_instant: Arc<Mutex<Option<Instant>>>,
+    // Helper to synchronize sleep_interruptible() and wake()
+    synchronization_point: Arc<NoDelayBarrier>,
+}
+
+#[derive(Debug)]
+pub struct VirtualWaker {
+    clock: VirtualClock,
+    wake_call_instant: Arc<Mutex<Option<Instant>>>,
+    // Helper to synchronize sleep_interruptible() and wake()
+    synchronization_point: Arc<NoDelayBarrier>,
+}
+
+/// Creates a new pair whose timeouts are driven by the given clock.
+#[must_use]
+pub fn new_pair(clock: &VirtualClock) -> (VirtualSleeper, VirtualWaker) {
+    let wake_call_instant = Arc::new(Mutex::new(None));
+    let synchronization_point = Arc::new(NoDelayBarrier::new());
+
+    let sleeper = VirtualSleeper {
+        clock: clock.clone(),
+        wake_call_instant: wake_call_instant.clone(),
+        synchronization_point: synchronization_point.clone(),
+    };
+    let waker = VirtualWaker {
+        clock: clock.clone(),
+        wake_call_instant,
+        synchronization_point,
+    };
+
+    (sleeper, waker)
+}
+
+impl VirtualSleeper {
+    /// Sleeps until the virtual time returned by `deadline`, which gets the
+    /// current virtual time.
+    fn sleep_with(&self, deadline: impl FnOnce(Duration) -> Duration) -> WakeupReason {
+        let shared = &self.clock.shared;
+        let mut state = shared.state.lock().unwrap();
+        let deadline = deadline(state.elapsed);
+        state.sleeping += 1;
+        shared.condvar.notify_all();
+
+        let wakeup_reason = loop {
+            let wake_call_instant = self.wake_call_instant.lock().unwrap().take();
+            if let Some(wake_call_instant) = wake_call_instant {
+                break WakeupReason::Interrupted { wake_call_instant };
+            }
+            if state.elapsed >= deadline {
+                break WakeupReason::Timeout;
+            }
+            state = shared.condvar.wait(state).unwrap();
+        };
+
+        state.sleeping -= 1;
+        drop(state);
+
+        if matches!(wakeup_reason, WakeupReason::Interrupted { .. }) {
+            // Unblock Waker::wake()
+            self.synchronization_point.wait();
+            trace::event!(backend = "virtual", "ack sent");
+        }
+
+        wakeup_reason
+    }
+}
+
+impl Sleeper<Instant> for VirtualSleeper {
+    fn sleep_interruptible(&self, sleep_duration: Duration) -> WakeupReason {
+        self.sleep_with(|elapsed| elapsed + sleep_duration)
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> WakeupReason {
+        let deadline = deadline.saturating_duration_since(self.clock.shared.base);
+        self.sleep_with(|_| deadline)
+    }
+}
+
+impl Waker for VirtualWaker {
+    fn wake(&self) {
+        *self.wake_call_instant.lock().unwrap() = Some(self.clock.now());
+
+        // Taking the lock guarantees that the sleeper is either not yet
+        // checking for a wake-up or already waiting for the notification.
+        let state = self.clock.shared.state.lock().unwrap();
+        self.clock.shared.condvar.notify_all();
+        drop(state);
+
+        // Wait for Sleeper to ACK
+        self.synchronization_point.wait();
+        trace::event!(backend = "virtual", "ack received");
+    }
+
+    /// Notifies the condvar without a wake-up call.
+    fn wake_spurious(&self) -> bool {
+        let state = self.clock.shared.state.lock().unwrap();
+        self.clock.shared.condvar.notify_all();
+        drop(state);
+        true
+    }
+
+    fn set_handshake(&self, handshake: Handshake) {
+        self.synchronization_point.set_handshake(handshake);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert2::check;
+    use std::thread;
+
+    #[test]
+    fn timeout_is_driven_by_clock() {
+        let clock = VirtualClock::new();
+        let (sleeper, _waker) = new_pair(&clock);
+        let begin = clock.now();
+
+        let thread = thread::spawn(move || sleeper.sleep_interruptible(Duration::from_secs(3600)));
+        clock.sleep(Duration::from_secs(3600));
+
+        check!(thread.join().unwrap() == WakeupReason::Timeout);
+        check!(clock.now() - begin == Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn wake_interrupts_sleep() {
+        let clock = VirtualClock::new();
+        let (sleeper, waker) = new_pair(&clock);
+        let begin = clock.now();
+
+        let thread = thread::spawn(move || sleeper.sleep_interruptible(Duration::from_secs(3600)));
+        clock.sleep(Duration::from_secs(42));
+        waker.wake();
+
+        check!(
+            thread.join().unwrap()
+                == WakeupReason::Interrupted {
+                    wake_call_instant: begin + Duration::from_secs(42)
+                }
+        );
+    }
+}
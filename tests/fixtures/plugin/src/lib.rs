@@ -0,0 +1,13 @@
+//! A plugin with the `Condvar` backend under the name `fixture`, see the
+//! `plugin` module of `benchmark-interruptible-sleep`. Built and loaded by
+//! `tests/plugin.rs`.
+
+use benchmark_interruptible_sleep::backends::{Backend, condvar};
+
+#[unsafe(no_mangle)]
+pub fn benchmark_interruptible_sleep_backends() -> Vec<Backend> {
+    vec![Backend::new("fixture", "the Condvar backend", || {
+        let (sleeper, waker) = condvar::new_pair();
+        (Box::new(sleeper), Box::new(waker))
+    })]
+}
@@ -0,0 +1,49 @@
+//! Loads the plugin of `tests/fixtures/plugin`, see the `plugin` module.
+
+#![cfg(feature = "plugins")]
+
+use assert2::check;
+use benchmark_interruptible_sleep::plugin;
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Builds the plugin with the same compiler and returns the path of the
+/// library.
+fn build_plugin() -> PathBuf {
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("plugin");
+    let status = Command::new(env!("CARGO"))
+        .arg("build")
+        .arg("--manifest-path")
+        .arg(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/plugin/Cargo.toml"))
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .status()
+        .expect("should run cargo");
+    check!(status.success());
+    target_dir.join("debug").join(format!(
+        "{DLL_PREFIX}benchmark_interruptible_sleep_plugin_fixture{DLL_SUFFIX}"
+    ))
+}
+
+#[test]
+fn test_plugin() {
+    let library = build_plugin();
+    let backends = plugin::load(&library).unwrap();
+    check!(backends.len() == 1);
+    check!(backends[0].name == "fixture");
+
+    // The backends of the plugin can be selected by name.
+    let output = Command::new(env!("CARGO_BIN_EXE_benchmark-interruptible-sleep"))
+        .args(["conformance", "--backend", "fixture", "--plugin"])
+        .arg(&library)
+        .output()
+        .expect("should run the binary");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    check!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    check!(stdout.contains("CONFORMANCE: fixture Sleeper"));
+}